@@ -11,8 +11,10 @@
 // FIXME: #13994: port to the sized deallocation API when available
 // FIXME: #13996: mark the `allocate` and `reallocate` return value as `noalias` and `nonnull`
 
+use core::atomics;
 use core::intrinsics::{abort, cttz32};
-use core::option::{None, Option};
+use core::mem;
+use core::option::{Some, None, Option};
 use core::ptr::{RawPtr, mut_null, null};
 use libc::{c_char, c_int, c_void, size_t};
 
@@ -40,6 +42,34 @@ extern {}
 #[inline(always)]
 fn mallocx_align(a: uint) -> c_int { unsafe { cttz32(a as u32) as c_int } }
 
+// The function to tell about allocations, as set by `set_hook`, or 0.
+static mut HOOK: atomics::AtomicUint = atomics::INIT_ATOMIC_UINT;
+
+#[inline(always)]
+fn notify(allocated: uint, freed: uint) {
+    let hook = unsafe { HOOK.load(atomics::Relaxed) };
+    if hook != 0 {
+        unsafe { mem::transmute::<uint, fn(uint, uint)>(hook)(allocated, freed) }
+    }
+}
+
+/// Set a function to be called with the number of bytes allocated and freed
+/// by each call to the functions of this module, returning the previous one.
+///
+/// The sizes are the ones given by the callers. The function is called from
+/// whichever thread is allocating, and must not allocate itself.
+#[unstable]
+pub fn set_hook(hook: Option<fn(uint, uint)>) -> Option<fn(uint, uint)> {
+    let hook = match hook {
+        Some(hook) => unsafe { mem::transmute::<fn(uint, uint), uint>(hook) },
+        None => 0,
+    };
+    match unsafe { HOOK.swap(hook, atomics::SeqCst) } {
+        0 => None,
+        prev => Some(unsafe { mem::transmute::<uint, fn(uint, uint)>(prev) }),
+    }
+}
+
 /// Return a pointer to `size` bytes of memory.
 ///
 /// Behavior is undefined if the requested size is 0 or the alignment is not a power of 2. The
@@ -50,6 +80,7 @@ pub unsafe fn allocate(size: uint, align: uint) -> *mut u8 {
     if ptr.is_null() {
         abort()
     }
+    notify(size, 0);
     ptr
 }
 
@@ -62,12 +93,12 @@ pub unsafe fn allocate(size: uint, align: uint) -> *mut u8 {
 /// allocation referenced by `ptr`. The `old_size` parameter may also be the value returned by
 /// `usable_size` for the requested size.
 #[inline]
-#[allow(unused_variable)] // for the parameter names in the documentation
 pub unsafe fn reallocate(ptr: *mut u8, size: uint, align: uint, old_size: uint) -> *mut u8 {
     let ptr = je_rallocx(ptr as *mut c_void, size as size_t, mallocx_align(align)) as *mut u8;
     if ptr.is_null() {
         abort()
     }
+    notify(size, old_size);
     ptr
 }
 
@@ -82,9 +113,13 @@ pub unsafe fn reallocate(ptr: *mut u8, size: uint, align: uint, old_size: uint)
 /// create the allocation referenced by `ptr`. The `old_size` parameter may be
 /// any value in range_inclusive(requested_size, usable_size).
 #[inline]
-#[allow(unused_variable)] // for the parameter names in the documentation
 pub unsafe fn reallocate_inplace(ptr: *mut u8, size: uint, align: uint, old_size: uint) -> bool {
-    je_xallocx(ptr as *mut c_void, size as size_t, 0, mallocx_align(align)) == size as size_t
+    if je_xallocx(ptr as *mut c_void, size as size_t, 0, mallocx_align(align)) == size as size_t {
+        notify(size, old_size);
+        true
+    } else {
+        false
+    }
 }
 
 /// Deallocate the memory referenced by `ptr`.
@@ -95,9 +130,9 @@ pub unsafe fn reallocate_inplace(ptr: *mut u8, size: uint, align: uint, old_size
 /// allocation referenced by `ptr`. The `size` parameter may also be the value returned by
 /// `usable_size` for the requested size.
 #[inline]
-#[allow(unused_variable)] // for the parameter names in the documentation
 pub unsafe fn deallocate(ptr: *mut u8, size: uint, align: uint) {
-    je_dallocx(ptr as *mut c_void, mallocx_align(align))
+    je_dallocx(ptr as *mut c_void, mallocx_align(align));
+    notify(0, size);
 }
 
 /// Return the usable size of an allocation created with the specified the `size` and `align`.
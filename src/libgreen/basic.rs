@@ -226,6 +226,7 @@ mod test {
         SchedPool::new(PoolConfig {
             threads: 1,
             event_loop_factory: basic::event_loop,
+            deadlock_hook: None,
         })
     }
 
@@ -256,6 +257,7 @@ mod test {
         let mut pool = SchedPool::new(PoolConfig {
             threads: 2,
             event_loop_factory: basic::event_loop,
+            deadlock_hook: None,
         });
 
         for _ in range(0, 20) {
@@ -226,6 +226,7 @@ use std::sync::atomics::{SeqCst, AtomicUint, INIT_ATOMIC_UINT};
 use std::sync::deque;
 use std::task::TaskOpts;
 
+use registry::{Registry, TaskInfo};
 use sched::{Shutdown, Scheduler, SchedHandle, TaskFromFriend, NewNeighbor};
 use sleeper_list::SleeperList;
 use stack::StackPool;
@@ -238,6 +239,7 @@ mod message_queue;
 pub mod basic;
 pub mod context;
 pub mod coroutine;
+pub mod registry;
 pub mod sched;
 pub mod sleeper_list;
 pub mod stack;
@@ -342,6 +344,20 @@ pub struct PoolConfig {
     /// A factory function used to create new event loops. If this is not
     /// specified then the default event loop factory is used.
     pub event_loop_factory: fn() -> Box<rtio::EventLoop:Send>,
+    /// A function to call with the tasks of the pool when all of them are
+    /// blocked on channels. Deadlocks are not looked for if this is `None`,
+    /// which is the default.
+    ///
+    /// This is a heuristic rather than a proof of a deadlock: tasks outside of
+    /// the pool may still hold the other ends of the channels, and wake up the
+    /// tasks which were reported.
+    ///
+    /// The hook is called on a scheduler which has run out of work, outside
+    /// of any task. It must therefore not fail, as there is no task to unwind
+    /// and the whole process is aborted instead; this includes failed
+    /// assertions. It also shouldn't block, or use anything else requiring a
+    /// task.
+    pub deadlock_hook: Option<fn(&[TaskInfo])>,
 }
 
 impl PoolConfig {
@@ -351,6 +367,7 @@ impl PoolConfig {
         PoolConfig {
             threads: rt::default_sched_threads(),
             event_loop_factory: basic::event_loop,
+            deadlock_hook: None,
         }
     }
 }
@@ -374,10 +391,12 @@ pub struct SchedPool {
 /// This is an internal state shared among a pool of schedulers. This is used to
 /// keep track of how many tasks are currently running in the pool and then
 /// sending on a channel once the entire pool has been drained of all tasks.
+/// It also holds the registry of the tasks of the pool.
 #[deriving(Clone)]
 struct TaskState {
     cnt: Arc<AtomicUint>,
     done: Sender<()>,
+    registry: Registry,
 }
 
 impl SchedPool {
@@ -390,12 +409,13 @@ impl SchedPool {
 
         let PoolConfig {
             threads: nscheds,
-            event_loop_factory: factory
+            event_loop_factory: factory,
+            deadlock_hook: deadlock_hook,
         } = config;
         assert!(nscheds > 0);
 
         // The pool of schedulers that will be returned from this function
-        let (p, state) = TaskState::new();
+        let (p, state) = TaskState::new(deadlock_hook);
         let mut pool = SchedPool {
             threads: vec![],
             handles: vec![],
@@ -446,7 +466,15 @@ impl SchedPool {
     /// scheduler created by `spawn_sched` (and possibly pin it to that
     /// scheduler).
     pub fn task(&mut self, opts: TaskOpts, f: proc():Send) -> Box<GreenTask> {
-        GreenTask::configure(&mut self.stack_pool, opts, f)
+        let mut task = GreenTask::configure(&mut self.stack_pool, opts, f);
+        task.register(&self.task_state.registry);
+        task
+    }
+
+    /// Returns a handle to the registry of the live tasks in this pool, which
+    /// can be used to list them or dump them for debugging.
+    pub fn registry(&self) -> Registry {
+        self.task_state.registry.clone()
     }
 
     /// Spawns a new task into this pool of schedulers, using the specified
@@ -533,11 +561,12 @@ impl SchedPool {
 }
 
 impl TaskState {
-    fn new() -> (Receiver<()>, TaskState) {
+    fn new(deadlock_hook: Option<fn(&[TaskInfo])>) -> (Receiver<()>, TaskState) {
         let (tx, rx) = channel();
         (rx, TaskState {
             cnt: Arc::new(AtomicUint::new(0)),
             done: tx,
+            registry: Registry::new(deadlock_hook),
         })
     }
 
@@ -0,0 +1,332 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A registry of the live tasks in a pool of schedulers.
+//!
+//! Every task in a pool has an entry in the pool's registry from the time it's
+//! spawned until it exits, which records what the task is currently doing.
+//! The registry can be listed or dumped at any time through the handle
+//! returned by `SchedPool::registry`. Nothing dumps it on its own, and in
+//! particular no signal handler is installed: a program which wants a dump on
+//! `SIGUSR1`, say, has to spawn a task listening for the signal with
+//! `std::io::signal::Listener` which then calls `Registry::dump`.
+//!
+//! The registry is also used to look for deadlocks. Whenever a scheduler runs
+//! out of work it checks whether every task in the pool is blocked on a
+//! channel, in which case none of them can be woken up by another task in the
+//! pool, and reports this to the `deadlock_hook` of the pool's configuration.
+//! This is only a heuristic: the registry doesn't know who holds the other
+//! ends of the channels, and tasks outside of the pool (native tasks or tasks
+//! in other pools) can still wake up such tasks. A report is therefore only
+//! conclusive if the tasks in the pool only communicate among themselves.
+
+use alloc::arc::Arc;
+use std::fmt;
+use std::io::{IoResult, Writer};
+use std::rt::task::{BlockedOn, OnChannel, OnTimer, OnIo, OnMutex, OnUnknown};
+use std::str::SendStr;
+use std::sync::atomics::{AtomicUint, SeqCst};
+use std::ty::Unsafe;
+use std::unstable::mutex::NativeMutex;
+
+/// What a task in a pool is currently doing
+#[deriving(Clone, Eq)]
+pub enum Status {
+    /// The task is waiting to be run by a scheduler
+    Runnable,
+    /// The task is running on a scheduler
+    Running,
+    /// The task is waiting to be woken up
+    Blocked(BlockedOn),
+}
+
+/// A snapshot of the entry of a task in a registry
+#[deriving(Clone)]
+pub struct TaskInfo {
+    /// The identifier of the task, unique within its pool
+    pub id: uint,
+    /// The name of the task, if it had one when it was spawned
+    pub name: Option<SendStr>,
+    /// What the task was doing when the snapshot was taken
+    pub status: Status,
+}
+
+/// A handle to the registry of a pool of schedulers
+#[deriving(Clone)]
+pub struct Registry {
+    inner: Arc<Unsafe<Inner>>,
+}
+
+struct Inner {
+    lock: NativeMutex,
+    tasks: Vec<Arc<Entry>>,
+    next_id: uint,
+    hook: Option<fn(&[TaskInfo])>,
+    // The identifiers and states of the tasks when a deadlock was last
+    // reported, sorted by identifier, so that the same deadlock is only
+    // reported once.
+    last_report: Vec<(uint, uint)>,
+}
+
+/// The entry of a task in a registry, which is owned by the task.
+pub struct Entry {
+    id: uint,
+    name: Option<SendStr>,
+    // The status of the task in the low bits, and the number of times it has
+    // changed in the high bits. This is only modified by whoever owns the task
+    // at the time, but may be read by anyone.
+    state: AtomicUint,
+}
+
+static STATUS_BITS: uint = 3;
+
+impl Registry {
+    /// Creates a new empty registry, which reports deadlocks to `hook`.
+    pub fn new(hook: Option<fn(&[TaskInfo])>) -> Registry {
+        Registry {
+            inner: Arc::new(Unsafe::new(Inner {
+                lock: unsafe { NativeMutex::new() },
+                tasks: Vec::new(),
+                next_id: 0,
+                hook: hook,
+                last_report: Vec::new(),
+            })),
+        }
+    }
+
+    /// Adds a new runnable task to the registry, returning its entry.
+    pub fn register(&self, name: Option<SendStr>) -> Arc<Entry> {
+        unsafe {
+            let inner = self.inner.get();
+            let _g = (*inner).lock.lock();
+            let entry = Arc::new(Entry {
+                id: (*inner).next_id,
+                name: name,
+                state: AtomicUint::new(encode(Runnable)),
+            });
+            (*inner).next_id += 1;
+            (*inner).tasks.push(entry.clone());
+            entry
+        }
+    }
+
+    /// Removes the entry of a task which is exiting from the registry.
+    pub fn unregister(&self, entry: &Entry) {
+        unsafe {
+            let inner = self.inner.get();
+            let _g = (*inner).lock.lock();
+            match (*inner).tasks.iter().position(|e| e.id == entry.id) {
+                Some(i) => { (*inner).tasks.swap_remove(i); }
+                None => {}
+            }
+        }
+    }
+
+    /// Returns a snapshot of all of the tasks in the registry.
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        unsafe {
+            let inner = self.inner.get();
+            let _g = (*inner).lock.lock();
+            (*inner).tasks.iter().map(|e| e.info()).collect()
+        }
+    }
+
+    /// Writes a description of each of the tasks in the registry to `w`, one
+    /// per line.
+    pub fn dump(&self, w: &mut Writer) -> IoResult<()> {
+        for task in self.tasks().iter() {
+            try!(writeln!(w, "{}", *task));
+        }
+        Ok(())
+    }
+
+    /// Checks whether all of the tasks in the registry are blocked on
+    /// channels, and reports them to the deadlock hook if they are and they
+    /// haven't already been reported in the same states.
+    pub fn check_deadlock(&self) {
+        let hook = match unsafe { (*self.inner.get()).hook } {
+            Some(hook) => hook,
+            None => return,
+        };
+        let tasks = unsafe {
+            let inner = self.inner.get();
+            let _g = (*inner).lock.lock();
+            if (*inner).tasks.len() == 0 { return }
+
+            // The states are read twice, and if they didn't change in between
+            // then they were all current at the time of the second read. With
+            // a single read, a task could be seen as blocked just before
+            // another wakes it up and blocks in turn.
+            let mut before: Vec<(uint, uint)> = (*inner).tasks.iter().map(|e| {
+                (e.id, e.state.load(SeqCst))
+            }).collect();
+            if !before.iter().all(|&(_, s)| decode(s) == Blocked(OnChannel)) {
+                return
+            }
+            let unchanged = (*inner).tasks.iter().zip(before.iter()).all(|(e, &(_, s))| {
+                e.state.load(SeqCst) == s
+            });
+            if !unchanged { return }
+
+            // Each state includes the number of times it changed, so the tasks
+            // are still stuck in the same place if they are all unchanged.
+            before.sort();
+            if before == (*inner).last_report { return }
+            (*inner).last_report = before;
+            (*inner).tasks.iter().map(|e| e.info()).collect::<Vec<TaskInfo>>()
+        };
+        hook(tasks.as_slice());
+    }
+}
+
+impl Entry {
+    /// Returns the identifier of the task, unique within its pool.
+    pub fn id(&self) -> uint { self.id }
+
+    /// Returns what the task is currently doing.
+    pub fn status(&self) -> Status {
+        decode(self.state.load(SeqCst))
+    }
+
+    /// Records what the task is now doing. This may only be called by whoever
+    /// owns the task.
+    pub fn set_status(&self, status: Status) {
+        let prev = self.state.load(SeqCst);
+        let changes = (prev >> STATUS_BITS) + 1;
+        self.state.store(changes << STATUS_BITS | encode(status), SeqCst);
+    }
+
+    fn info(&self) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            name: self.name.clone(),
+            status: self.status(),
+        }
+    }
+}
+
+fn encode(status: Status) -> uint {
+    match status {
+        Runnable => 0,
+        Running => 1,
+        Blocked(OnChannel) => 2,
+        Blocked(OnTimer) => 3,
+        Blocked(OnIo) => 4,
+        Blocked(OnMutex) => 5,
+        Blocked(OnUnknown) => 6,
+    }
+}
+
+fn decode(state: uint) -> Status {
+    match state & ((1 << STATUS_BITS) - 1) {
+        0 => Runnable,
+        1 => Running,
+        2 => Blocked(OnChannel),
+        3 => Blocked(OnTimer),
+        4 => Blocked(OnIo),
+        5 => Blocked(OnMutex),
+        _ => Blocked(OnUnknown),
+    }
+}
+
+impl fmt::Show for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Runnable => write!(f, "runnable"),
+            Running => write!(f, "running"),
+            Blocked(OnChannel) => write!(f, "blocked on a channel"),
+            Blocked(OnTimer) => write!(f, "blocked on a timer"),
+            Blocked(OnIo) => write!(f, "blocked on I/O"),
+            Blocked(OnMutex) => write!(f, "blocked on a mutex"),
+            Blocked(OnUnknown) => write!(f, "blocked"),
+        }
+    }
+}
+
+impl fmt::Show for TaskInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.name.as_ref().map(|n| n.as_slice());
+        write!(f, "task {} '{}': {}", self.id, name.unwrap_or("<unnamed>"),
+               self.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rt::task::{OnChannel, OnIo};
+    use super::{Registry, TaskInfo, Runnable, Running, Blocked};
+
+    static mut REPORTS: uint = 0;
+
+    fn hook(tasks: &[TaskInfo]) {
+        if tasks.len() == 2 {
+            unsafe { REPORTS += 1; }
+        }
+    }
+
+    #[test]
+    fn status() {
+        let registry = Registry::new(None);
+        let entry = registry.register(None);
+        assert!(entry.status() == Runnable);
+        entry.set_status(Running);
+        assert!(entry.status() == Running);
+        entry.set_status(Blocked(OnIo));
+        assert!(entry.status() == Blocked(OnIo));
+    }
+
+    #[test]
+    fn list() {
+        let registry = Registry::new(None);
+        let a = registry.register(Some("a".into_maybe_owned()));
+        let b = registry.register(None);
+        b.set_status(Blocked(OnChannel));
+        let tasks = registry.tasks();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks.get(0).to_str(), "task 0 'a': runnable".to_owned());
+        assert_eq!(tasks.get(1).to_str(),
+                   "task 1 '<unnamed>': blocked on a channel".to_owned());
+        registry.unregister(&*a);
+        assert_eq!(registry.tasks().len(), 1);
+    }
+
+    #[test]
+    fn deadlock() {
+        let registry = Registry::new(Some(hook));
+        let a = registry.register(None);
+        let b = registry.register(None);
+        a.set_status(Blocked(OnChannel));
+        b.set_status(Blocked(OnIo));
+        registry.check_deadlock();
+        assert_eq!(unsafe { REPORTS }, 0);
+
+        b.set_status(Blocked(OnChannel));
+        registry.check_deadlock();
+        assert_eq!(unsafe { REPORTS }, 1);
+
+        // The same deadlock is only reported once
+        registry.check_deadlock();
+        assert_eq!(unsafe { REPORTS }, 1);
+
+        // ... even once an unrelated task has come and gone
+        let c = registry.register(None);
+        registry.check_deadlock();
+        registry.unregister(&*c);
+        registry.check_deadlock();
+        assert_eq!(unsafe { REPORTS }, 1);
+
+        // but it is reported again once the tasks have been woken up and got
+        // stuck once more
+        a.set_status(Running);
+        a.set_status(Blocked(OnChannel));
+        registry.check_deadlock();
+        assert_eq!(unsafe { REPORTS }, 2);
+    }
+}
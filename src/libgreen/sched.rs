@@ -11,6 +11,7 @@
 use std::mem;
 use std::rt::local::Local;
 use std::rt::rtio::{RemoteCallback, PausableIdleCallback, Callback, EventLoop};
+use std::rt::stats;
 use std::rt::task::BlockedTask;
 use std::rt::task::Task;
 use std::sync::deque;
@@ -22,6 +23,7 @@ use rand::{XorShiftRng, Rng, Rand};
 use TaskState;
 use context::Context;
 use coroutine::Coroutine;
+use registry::{Runnable, Running};
 use sleeper_list::SleeperList;
 use stack::StackPool;
 use task::{TypeSched, GreenTask, HomeSched, AnySched};
@@ -323,7 +325,10 @@ impl Scheduler {
             return stask.put_with_sched(sched);
         }
 
-        // If we got here then there was no work to do.
+        // If we got here then there was no work to do, which is when a
+        // deadlock among the tasks of the pool may have just happened.
+        sched.task_state.registry.check_deadlock();
+
         // Generate a SchedHandle and push it to the sleeper list so
         // somebody can wake us up later.
         if !sched.sleepy && !sched.no_sleep {
@@ -617,6 +622,22 @@ impl Scheduler {
                                -> Box<GreenTask> {
         let f_opaque = ClosureConverter::from_fn(f);
 
+        // Tasks keeping statistics are only charged for the time they
+        // actually spend running.
+        match current_task.task {
+            Some(ref task) => stats::suspend(&**task),
+            None => {}
+        }
+        match next_task.task {
+            Some(ref task) => stats::resume(&**task),
+            None => {}
+        }
+
+        // The task being switched away from is runnable until the cleanup job
+        // records that it blocked, if that's why it's being switched away from.
+        current_task.set_status(Runnable);
+        next_task.set_status(Running);
+
         let current_task_dupe = &*current_task as *GreenTask;
 
         // The current task is placed inside an enum with the cleanup
@@ -802,6 +823,10 @@ impl Scheduler {
         let _cur = self.change_task_context(cur, stask, |sched, mut dead_task| {
             let coroutine = dead_task.coroutine.take_unwrap();
             coroutine.recycle(&mut sched.stack_pool);
+            match dead_task.entry.take() {
+                Some(entry) => sched.task_state.registry.unregister(&*entry),
+                None => {}
+            }
             sched.task_state.decrement();
         });
         fail!("should never return!");
@@ -1034,6 +1059,7 @@ mod test {
         SchedPool::new(PoolConfig {
             threads: 1,
             event_loop_factory: basic::event_loop,
+            deadlock_hook: None,
         })
     }
 
@@ -1146,7 +1172,7 @@ mod test {
             let (normal_worker, normal_stealer) = pool.deque();
             let (special_worker, special_stealer) = pool.deque();
             let queues = vec![normal_stealer, special_stealer];
-            let (_p, state) = TaskState::new();
+            let (_p, state) = TaskState::new(None);
 
             // Our normal scheduler
             let mut normal_sched = box Scheduler::new(
@@ -1278,6 +1304,7 @@ mod test {
         let mut pool = SchedPool::new(PoolConfig {
             threads: 2,
             event_loop_factory: rustuv::event_loop,
+            deadlock_hook: None,
         });
 
         // This is a regression test that when there are no schedulable tasks in
@@ -1429,6 +1456,7 @@ mod test {
         let mut pool = SchedPool::new(PoolConfig {
             threads: 2, // this must be > 1
             event_loop_factory: basic::event_loop,
+            deadlock_hook: None,
         });
         pool.spawn(TaskOpts::new(), proc() {
             let (tx, rx) = channel();
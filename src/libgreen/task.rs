@@ -18,6 +18,7 @@
 //! contains the rust task itself in order to juggle around ownership of the
 //! values.
 
+use alloc::arc::Arc;
 use std::any::Any;
 use std::mem;
 use std::raw;
@@ -26,12 +27,14 @@ use std::rt::env;
 use std::rt::local::Local;
 use std::rt::rtio;
 use std::rt::stack;
+use std::rt::stats;
 use std::rt::task::{Task, BlockedTask, SendMessage};
 use std::task::TaskOpts;
 use std::unstable::mutex::NativeMutex;
 
 use context::Context;
 use coroutine::Coroutine;
+use registry::{Registry, Entry, Status, Runnable, Running, Blocked};
 use sched::{Scheduler, SchedHandle, RunOnce};
 use stack::StackPool;
 
@@ -66,6 +69,10 @@ pub struct GreenTask {
     /// schedulers.
     pub pool_id: uint,
 
+    /// The entry of this task in the registry of its pool. This is set when
+    /// the task is spawned into a pool, or when it starts running otherwise.
+    pub entry: Option<Arc<Entry>>,
+
     // See the comments in the scheduler about why this is necessary
     pub nasty_deschedule_lock: NativeMutex,
 }
@@ -106,6 +113,11 @@ extern fn bootstrap_green_task(task: uint, code: *(), env: *()) -> ! {
         sched.task_state.increment();
         sched.pool_id
     };
+    if task.entry.is_none() {
+        let registry = task.sched.get_ref().task_state.registry.clone();
+        task.register(&registry);
+    }
+    task.set_status(Running);
 
     // Convert our green task to a libstd task and then execute the code
     // requested. This is the "try/catch" block for this green task and
@@ -161,6 +173,7 @@ impl GreenTask {
                      task_type: TaskType) -> Box<GreenTask> {
         box GreenTask {
             pool_id: 0,
+            entry: None,
             coroutine: coroutine,
             task_type: task_type,
             sched: None,
@@ -178,7 +191,7 @@ impl GreenTask {
                      f: proc():Send) -> Box<GreenTask> {
         let TaskOpts {
             notify_chan, name, stack_size,
-            stderr, stdout,
+            stderr, stdout, failure_hook, stats: keep_stats,
         } = opts;
 
         let mut green = GreenTask::new(pool, stack_size, f);
@@ -187,6 +200,8 @@ impl GreenTask {
             task.name = name;
             task.stderr = stderr;
             task.stdout = stdout;
+            task.failure_hook = failure_hook;
+            if keep_stats { stats::enable(&mut **task) }
             match notify_chan {
                 Some(chan) => {
                     task.death.on_exit = Some(SendMessage(chan));
@@ -263,6 +278,22 @@ impl GreenTask {
         }
     }
 
+    /// Adds this task to `registry`, unless it's already in a registry.
+    pub fn register(&mut self, registry: &Registry) {
+        if self.entry.is_some() { return }
+        let name = self.task.as_ref().and_then(|t| t.name.clone());
+        self.entry = Some(registry.register(name));
+    }
+
+    /// Records what this task is doing in the registry of its pool, if it's in
+    /// one.
+    pub fn set_status(&self, status: Status) {
+        match self.entry {
+            Some(ref entry) => entry.set_status(status),
+            None => {}
+        }
+    }
+
     // Unsafe functions for transferring ownership of this GreenTask across
     // context switches
 
@@ -347,6 +378,7 @@ impl Runtime for GreenTask {
 
     fn deschedule(mut ~self, times: uint, cur_task: Box<Task>,
                   f: |BlockedTask| -> Result<(), BlockedTask>) {
+        let status = Blocked(cur_task.blocked_on);
         self.put_task(cur_task);
         let mut sched = self.sched.take_unwrap();
 
@@ -366,25 +398,36 @@ impl Runtime for GreenTask {
         // the task while the cleanup job is running). In order to get around
         // this for now, we invoke the scheduler directly with the converted
         // Task => GreenTask structure.
+        //
+        // The task is recorded as blocked right before `f` hands it off, as
+        // it's still runnable as far as anyone else can tell until then. Its
+        // entry can't go away before it's woken up.
+        let entry = self.entry.as_ref().map(|e| &**e as *Entry);
         if times == 1 {
             sched.deschedule_running_task_and_then(self, |sched, task| {
+                entry.map(|e| unsafe { (*e).set_status(status) });
                 match f(task) {
                     Ok(()) => {}
                     Err(t) => {
                         t.wake().map(|t| {
-                            sched.enqueue_task(GreenTask::convert(t))
+                            let t = GreenTask::convert(t);
+                            t.set_status(Runnable);
+                            sched.enqueue_task(t)
                         });
                     }
                 }
             });
         } else {
             sched.deschedule_running_task_and_then(self, |sched, task| {
+                entry.map(|e| unsafe { (*e).set_status(status) });
                 for task in task.make_selectable(times) {
                     match f(task) {
                         Ok(()) => {},
                         Err(task) => {
                             task.wake().map(|t| {
-                                sched.enqueue_task(GreenTask::convert(t))
+                                let t = GreenTask::convert(t);
+                                t.set_status(Runnable);
+                                sched.enqueue_task(t)
                             });
                             break
                         }
@@ -395,6 +438,7 @@ impl Runtime for GreenTask {
     }
 
     fn reawaken(mut ~self, to_wake: Box<Task>) {
+        self.set_status(Runnable);
         self.put_task(to_wake);
         assert!(self.sched.is_none());
 
@@ -458,7 +502,8 @@ impl Runtime for GreenTask {
         //
         // Upon returning, our task is back in TLS and we're good to return.
         let mut sched = self.sched.take_unwrap();
-        let sibling = GreenTask::configure(&mut sched.stack_pool, opts, f);
+        let mut sibling = GreenTask::configure(&mut sched.stack_pool, opts, f);
+        sibling.register(&sched.task_state.registry);
         sched.run_task(self, sibling)
     }
 
@@ -488,17 +533,20 @@ impl Runtime for GreenTask {
 #[cfg(test)]
 mod tests {
     use std::rt::local::Local;
-    use std::rt::task::Task;
+    use std::rt::task::{Task, OnChannel};
+    use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
     use std::task;
     use std::task::TaskOpts;
 
     use super::super::{PoolConfig, SchedPool};
     use super::GreenTask;
+    use registry::{TaskInfo, Blocked};
 
     fn spawn_opts(opts: TaskOpts, f: proc():Send) {
         let mut pool = SchedPool::new(PoolConfig {
             threads: 1,
             event_loop_factory: ::rustuv::event_loop,
+            deadlock_hook: None,
         });
         pool.spawn(opts, f);
         pool.shutdown();
@@ -590,4 +638,72 @@ mod tests {
         });
         rx.recv();
     }
+
+    #[test]
+    fn registry() {
+        let mut pool = SchedPool::new(PoolConfig {
+            threads: 1,
+            event_loop_factory: ::rustuv::event_loop,
+            deadlock_hook: None,
+        });
+        let registry = pool.registry();
+        let (tx, rx) = channel::<()>();
+        let mut opts = TaskOpts::new();
+        opts.name = Some("waiting".into_maybe_owned());
+        pool.spawn(opts, proc() { rx.recv() });
+
+        loop {
+            let tasks = registry.tasks();
+            if tasks.len() == 1 && tasks.get(0).status == Blocked(OnChannel) {
+                assert_eq!(tasks.get(0).to_str(),
+                           "task 0 'waiting': blocked on a channel".to_owned());
+                break
+            }
+            task::deschedule();
+        }
+        tx.send(());
+        pool.shutdown();
+        assert_eq!(registry.tasks().len(), 0);
+    }
+
+    static mut DEADLOCKS: AtomicUint = INIT_ATOMIC_UINT;
+
+    // This runs outside of any task, where a failed assertion would abort the
+    // whole process, so the tasks are checked by the test instead.
+    fn deadlocked(tasks: &[TaskInfo]) {
+        if tasks.len() == 2 {
+            unsafe { DEADLOCKS.fetch_add(1, SeqCst); }
+        }
+    }
+
+    // Waits for a message from the task holding the sender of `rx`, which is
+    // waiting for a message on `_tx` in turn. `stop` only exists so that the
+    // test can end.
+    fn wait_for_other(rx: Receiver<()>, _tx: Sender<()>, stop: Receiver<()>) {
+        select! (
+            _ = rx.recv_opt() => {},
+            _ = stop.recv_opt() => {}
+        )
+    }
+
+    #[test]
+    fn deadlock() {
+        let mut pool = SchedPool::new(PoolConfig {
+            threads: 1,
+            event_loop_factory: ::rustuv::event_loop,
+            deadlock_hook: Some(deadlocked),
+        });
+        let (tx1, rx1) = channel::<()>();
+        let (tx2, rx2) = channel::<()>();
+        let (stop1, stop_rx1) = channel::<()>();
+        let (stop2, stop_rx2) = channel::<()>();
+        pool.spawn(TaskOpts::new(), proc() wait_for_other(rx1, tx2, stop_rx1));
+        pool.spawn(TaskOpts::new(), proc() wait_for_other(rx2, tx1, stop_rx2));
+
+        while unsafe { DEADLOCKS.load(SeqCst) } == 0 {
+            task::deschedule();
+        }
+        drop((stop1, stop2));
+        pool.shutdown();
+    }
 }
@@ -90,6 +90,7 @@ pub use types::common::posix88::{DIR, dirent_t};
 pub use types::os::common::posix01::{timeval};
 pub use types::os::common::bsd44::{addrinfo, in_addr, in6_addr, sockaddr_storage};
 pub use types::os::common::bsd44::{ip_mreq, ip6_mreq, sockaddr, sockaddr_un};
+pub use types::os::common::bsd44::linger;
 pub use types::os::common::bsd44::{sa_family_t, sockaddr_in, sockaddr_in6, socklen_t};
 pub use types::os::arch::c95::{c_char, c_double, c_float, c_int, c_uint};
 pub use types::os::arch::c95::{c_long, c_short, c_uchar, c_ulong, wchar_t};
@@ -119,6 +120,7 @@ pub use consts::os::bsd44::{SO_REUSEADDR, SO_BROADCAST, SHUT_WR, IP_MULTICAST_LO
 pub use consts::os::bsd44::{IP_ADD_MEMBERSHIP, IP_DROP_MEMBERSHIP};
 pub use consts::os::bsd44::{IPV6_ADD_MEMBERSHIP, IPV6_DROP_MEMBERSHIP};
 pub use consts::os::bsd44::{IP_MULTICAST_TTL, IP_TTL, SHUT_RD};
+pub use consts::os::bsd44::{SO_SNDBUF, SO_RCVBUF, SO_LINGER, IPV6_V6ONLY};
 
 pub use funcs::c95::ctype::{isalnum, isalpha, iscntrl, isdigit};
 pub use funcs::c95::ctype::{islower, isprint, ispunct, isspace};
@@ -175,6 +177,7 @@ pub use funcs::bsd43::{shutdown};
 #[cfg(unix)] pub use consts::os::posix88::{SIGTERM, SIGKILL, SIGPIPE, PROT_NONE};
 #[cfg(unix)] pub use consts::os::posix01::{SIG_IGN};
 #[cfg(unix)] pub use consts::os::bsd44::{AF_UNIX};
+#[cfg(unix)] pub use consts::os::bsd44::{LOCK_SH, LOCK_EX, LOCK_NB, LOCK_UN};
 
 #[cfg(unix)] pub use types::os::common::posix01::{pthread_t, timespec, timezone};
 
@@ -182,7 +185,7 @@ pub use funcs::bsd43::{shutdown};
 #[cfg(unix)] pub use types::os::arch::posix01::{pthread_attr_t};
 #[cfg(unix)] pub use types::os::arch::posix01::{stat, utimbuf};
 #[cfg(unix)] pub use funcs::posix88::unistd::{sysconf, setgid, setsid, setuid, pread, pwrite};
-#[cfg(unix)] pub use funcs::posix88::unistd::{getgid, getuid};
+#[cfg(unix)] pub use funcs::posix88::unistd::{getgid, getuid, setpgid};
 #[cfg(unix)] pub use funcs::posix88::unistd::{_PC_NAME_MAX, utime, nanosleep, pathconf, link};
 #[cfg(unix)] pub use funcs::posix88::unistd::{chown};
 #[cfg(unix)] pub use funcs::posix88::mman::{mmap, munmap, mprotect};
@@ -191,6 +194,7 @@ pub use funcs::bsd43::{shutdown};
 #[cfg(unix)] pub use funcs::posix01::stat_::{lstat};
 #[cfg(unix)] pub use funcs::posix01::unistd::{fsync, ftruncate};
 #[cfg(unix)] pub use funcs::posix01::unistd::{readlink, symlink};
+#[cfg(unix)] pub use funcs::bsd44::{flock};
 
 #[cfg(windows)] pub use consts::os::c95::{WSAECONNREFUSED, WSAECONNRESET, WSAEACCES};
 #[cfg(windows)] pub use consts::os::c95::{WSAEWOULDBLOCK, WSAENOTCONN, WSAECONNABORTED};
@@ -204,12 +208,15 @@ pub use funcs::bsd43::{shutdown};
 #[cfg(windows)] pub use consts::os::extra::{FILE_MAP_READ, FILE_MAP_WRITE, FILE_MAP_EXECUTE};
 #[cfg(windows)] pub use consts::os::extra::{ERROR_ALREADY_EXISTS, ERROR_NO_DATA};
 #[cfg(windows)] pub use consts::os::extra::{ERROR_FILE_NOT_FOUND, ERROR_INVALID_NAME};
+#[cfg(windows)] pub use consts::os::extra::{ERROR_NO_MORE_FILES};
+#[cfg(windows)] pub use consts::os::extra::{FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_REPARSE_POINT};
 #[cfg(windows)] pub use consts::os::extra::{ERROR_BROKEN_PIPE, ERROR_INVALID_FUNCTION};
 #[cfg(windows)] pub use consts::os::extra::{TRUE, FALSE, INFINITE};
 #[cfg(windows)] pub use consts::os::extra::{PROCESS_TERMINATE, PROCESS_QUERY_INFORMATION};
 #[cfg(windows)] pub use consts::os::extra::{STILL_ACTIVE, DETACHED_PROCESS};
 #[cfg(windows)] pub use consts::os::extra::{CREATE_NEW_PROCESS_GROUP, CREATE_UNICODE_ENVIRONMENT};
 #[cfg(windows)] pub use consts::os::extra::{FILE_BEGIN, FILE_END, FILE_CURRENT};
+#[cfg(windows)] pub use consts::os::extra::{LOCKFILE_FAIL_IMMEDIATELY, LOCKFILE_EXCLUSIVE_LOCK};
 #[cfg(windows)] pub use consts::os::extra::{FILE_GENERIC_READ, FILE_GENERIC_WRITE};
 #[cfg(windows)] pub use consts::os::extra::{FILE_SHARE_READ, FILE_SHARE_WRITE, FILE_SHARE_DELETE};
 #[cfg(windows)] pub use consts::os::extra::{TRUNCATE_EXISTING, CREATE_ALWAYS, OPEN_EXISTING};
@@ -226,7 +233,7 @@ pub use funcs::bsd43::{shutdown};
 #[cfg(windows)] pub use consts::os::extra::{FILE_WRITE_ATTRIBUTES, FILE_READ_ATTRIBUTES};
 #[cfg(windows)] pub use consts::os::extra::{ERROR_PIPE_BUSY, ERROR_IO_PENDING};
 #[cfg(windows)] pub use consts::os::extra::{ERROR_PIPE_CONNECTED, WAIT_OBJECT_0};
-#[cfg(windows)] pub use consts::os::extra::{ERROR_NOT_FOUND};
+#[cfg(windows)] pub use consts::os::extra::{ERROR_NOT_FOUND, ERROR_LOCK_VIOLATION};
 #[cfg(windows)] pub use consts::os::extra::{ERROR_OPERATION_ABORTED};
 #[cfg(windows)] pub use types::os::common::bsd44::{SOCKET};
 #[cfg(windows)] pub use types::os::common::posix01::{stat, utimbuf};
@@ -258,6 +265,7 @@ pub use funcs::bsd43::{shutdown};
 #[cfg(windows)] pub use funcs::extra::kernel32::{GetOverlappedResult, ConnectNamedPipe};
 #[cfg(windows)] pub use funcs::extra::kernel32::{DisconnectNamedPipe, OpenProcess};
 #[cfg(windows)] pub use funcs::extra::kernel32::{MoveFileExW, VirtualProtect};
+#[cfg(windows)] pub use funcs::extra::kernel32::{LockFileEx, UnlockFileEx};
 #[cfg(windows)] pub use funcs::extra::msvcrt::{get_osfhandle, open_osfhandle};
 
 #[cfg(target_os = "linux")] #[cfg(target_os = "android")] #[cfg(target_os = "freebsd")]
@@ -424,6 +432,10 @@ pub mod types {
                     pub ipv6mr_multiaddr: in6_addr,
                     pub ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    pub l_onoff: c_int,
+                    pub l_linger: c_int,
+                }
                 pub struct addrinfo {
                     pub ai_flags: c_int,
                     pub ai_family: c_int,
@@ -813,6 +825,10 @@ pub mod types {
                     pub ipv6mr_multiaddr: in6_addr,
                     pub ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    pub l_onoff: c_int,
+                    pub l_linger: c_int,
+                }
                 pub struct addrinfo {
                     pub ai_flags: c_int,
                     pub ai_family: c_int,
@@ -1011,6 +1027,10 @@ pub mod types {
                     pub ipv6mr_multiaddr: in6_addr,
                     pub ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    pub l_onoff: u16,
+                    pub l_linger: u16,
+                }
                 pub struct addrinfo {
                     pub ai_flags: c_int,
                     pub ai_family: c_int,
@@ -1366,6 +1386,10 @@ pub mod types {
                     pub ipv6mr_multiaddr: in6_addr,
                     pub ipv6mr_interface: c_uint,
                 }
+                pub struct linger {
+                    pub l_onoff: c_int,
+                    pub l_linger: c_int,
+                }
                 pub struct addrinfo {
                     pub ai_flags: c_int,
                     pub ai_family: c_int,
@@ -1722,6 +1746,10 @@ pub mod consts {
             pub static SO_BROADCAST: c_int = 32;
             pub static SO_REUSEADDR: c_int = 4;
             pub static SO_ERROR: c_int = 0x1007;
+            pub static SO_SNDBUF: c_int = 0x1001;
+            pub static SO_RCVBUF: c_int = 0x1002;
+            pub static SO_LINGER: c_int = 0x0080;
+            pub static IPV6_V6ONLY: c_int = 27;
 
             pub static SHUT_RD: c_int = 0;
             pub static SHUT_WR: c_int = 1;
@@ -1743,9 +1771,11 @@ pub mod consts {
             pub static ERROR_FILE_NOT_FOUND: c_int = 2;
             pub static ERROR_ACCESS_DENIED: c_int = 5;
             pub static ERROR_INVALID_HANDLE : c_int = 6;
+            pub static ERROR_NO_MORE_FILES: c_int = 18;
             pub static ERROR_BROKEN_PIPE: c_int = 109;
             pub static ERROR_DISK_FULL : c_int = 112;
             pub static ERROR_CALL_NOT_IMPLEMENTED : c_int = 120;
+            pub static ERROR_LOCK_VIOLATION: c_int = 33;
             pub static ERROR_INSUFFICIENT_BUFFER : c_int = 122;
             pub static ERROR_INVALID_NAME : c_int = 123;
             pub static ERROR_ALREADY_EXISTS : c_int = 183;
@@ -1931,6 +1961,9 @@ pub mod consts {
             pub static FILE_CURRENT: DWORD = 1;
             pub static FILE_END: DWORD = 2;
 
+            pub static LOCKFILE_FAIL_IMMEDIATELY: DWORD = 0x00000001;
+            pub static LOCKFILE_EXCLUSIVE_LOCK: DWORD = 0x00000002;
+
             pub static MAX_PROTOCOL_CHAIN: DWORD = 7;
             pub static WSAPROTOCOL_LEN: DWORD = 255;
             pub static INVALID_SOCKET: DWORD = !0;
@@ -2513,10 +2546,20 @@ pub mod consts {
             pub static SO_BROADCAST: c_int = 6;
             pub static SO_REUSEADDR: c_int = 2;
             pub static SO_ERROR: c_int = 4;
+            pub static SO_REUSEPORT: c_int = 15;
+            pub static SO_SNDBUF: c_int = 7;
+            pub static SO_RCVBUF: c_int = 8;
+            pub static SO_LINGER: c_int = 13;
+            pub static IPV6_V6ONLY: c_int = 26;
 
             pub static SHUT_RD: c_int = 0;
             pub static SHUT_WR: c_int = 1;
             pub static SHUT_RDWR: c_int = 2;
+
+            pub static LOCK_SH: c_int = 1;
+            pub static LOCK_EX: c_int = 2;
+            pub static LOCK_NB: c_int = 4;
+            pub static LOCK_UN: c_int = 8;
         }
         #[cfg(target_arch = "x86")]
         #[cfg(target_arch = "x86_64")]
@@ -2970,10 +3013,20 @@ pub mod consts {
             pub static SO_BROADCAST: c_int = 0x0020;
             pub static SO_REUSEADDR: c_int = 0x0004;
             pub static SO_ERROR: c_int = 0x1007;
+            pub static SO_REUSEPORT: c_int = 0x0200;
+            pub static SO_SNDBUF: c_int = 0x1001;
+            pub static SO_RCVBUF: c_int = 0x1002;
+            pub static SO_LINGER: c_int = 0x0080;
+            pub static IPV6_V6ONLY: c_int = 27;
 
             pub static SHUT_RD: c_int = 0;
             pub static SHUT_WR: c_int = 1;
             pub static SHUT_RDWR: c_int = 2;
+
+            pub static LOCK_SH: c_int = 1;
+            pub static LOCK_EX: c_int = 2;
+            pub static LOCK_NB: c_int = 4;
+            pub static LOCK_UN: c_int = 8;
         }
         pub mod extra {
             use types::os::arch::c95::c_int;
@@ -3355,10 +3408,20 @@ pub mod consts {
             pub static SO_BROADCAST: c_int = 0x0020;
             pub static SO_REUSEADDR: c_int = 0x0004;
             pub static SO_ERROR: c_int = 0x1007;
+            pub static SO_REUSEPORT: c_int = 0x0200;
+            pub static SO_SNDBUF: c_int = 0x1001;
+            pub static SO_RCVBUF: c_int = 0x1002;
+            pub static SO_LINGER: c_int = 0x0080;
+            pub static IPV6_V6ONLY: c_int = 27;
 
             pub static SHUT_RD: c_int = 0;
             pub static SHUT_WR: c_int = 1;
             pub static SHUT_RDWR: c_int = 2;
+
+            pub static LOCK_SH: c_int = 1;
+            pub static LOCK_EX: c_int = 2;
+            pub static LOCK_NB: c_int = 4;
+            pub static LOCK_UN: c_int = 8;
         }
         pub mod extra {
             use types::os::arch::c95::c_int;
@@ -4124,6 +4187,7 @@ pub mod funcs {
                                    sizep: *mut size_t)
                                    -> c_int;
             pub fn getdtablesize() -> c_int;
+            pub fn flock(fd: c_int, operation: c_int) -> c_int;
             pub fn madvise(addr: *c_void, len: size_t, advice: c_int)
                            -> c_int;
             pub fn mincore(addr: *c_void, len: size_t, vec: *c_uchar)
@@ -4140,6 +4204,7 @@ pub mod funcs {
 
         extern {
             pub fn getdtablesize() -> c_int;
+            pub fn flock(fd: c_int, operation: c_int) -> c_int;
             pub fn madvise(addr: *c_void, len: size_t, advice: c_int)
                            -> c_int;
             pub fn mincore(addr: *c_void, len: size_t, vec: *c_uchar)
@@ -4324,6 +4389,17 @@ pub mod funcs {
                                         lpNewFilePointer: PLARGE_INTEGER,
                                         dwMoveMethod: DWORD) -> BOOL;
                 pub fn SetEndOfFile(hFile: HANDLE) -> BOOL;
+                pub fn LockFileEx(hFile: HANDLE,
+                                  dwFlags: DWORD,
+                                  dwReserved: DWORD,
+                                  nNumberOfBytesToLockLow: DWORD,
+                                  nNumberOfBytesToLockHigh: DWORD,
+                                  lpOverlapped: LPOVERLAPPED) -> BOOL;
+                pub fn UnlockFileEx(hFile: HANDLE,
+                                    dwReserved: DWORD,
+                                    nNumberOfBytesToUnlockLow: DWORD,
+                                    nNumberOfBytesToUnlockHigh: DWORD,
+                                    lpOverlapped: LPOVERLAPPED) -> BOOL;
 
                 pub fn GetSystemTimeAsFileTime(
                             lpSystemTimeAsFileTime: LPFILETIME);
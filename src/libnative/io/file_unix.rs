@@ -17,6 +17,7 @@ use std::c_str::CString;
 use std::io::IoError;
 use std::io;
 use std::mem;
+use std::os;
 use std::rt::rtio;
 
 use io::{IoResult, retry, keep_going};
@@ -170,6 +171,23 @@ impl rtio::RtioFileStream for FileDesc {
             _ => Err(super::last_error()),
         }
     }
+
+    fn lock(&mut self, exclusive: bool, wait: bool) -> IoResult<bool> {
+        let mut op = if exclusive {libc::LOCK_EX} else {libc::LOCK_SH};
+        if !wait { op |= libc::LOCK_NB; }
+        match retry(|| unsafe { libc::flock(self.fd(), op) }) {
+            0 => Ok(true),
+            _ if !wait && os::errno() as int == libc::EWOULDBLOCK as int => {
+                Ok(false)
+            }
+            _ => Err(super::last_error()),
+        }
+    }
+    fn unlock(&mut self) -> IoResult<()> {
+        super::mkerr_libc(retry(|| unsafe {
+            libc::flock(self.fd(), libc::LOCK_UN)
+        }))
+    }
 }
 
 impl rtio::RtioPipe for FileDesc {
@@ -196,6 +214,9 @@ impl rtio::RtioPipe for FileDesc {
     fn set_timeout(&mut self, _t: Option<u64>) {}
     fn set_read_timeout(&mut self, _t: Option<u64>) {}
     fn set_write_timeout(&mut self, _t: Option<u64>) {}
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd()))
+    }
 }
 
 impl rtio::RtioTTY for FileDesc {
@@ -325,6 +346,13 @@ impl rtio::RtioFileStream for CFile {
     fn fstat(&mut self) -> IoResult<io::FileStat> {
         self.flush().and_then(|()| self.fd.fstat())
     }
+
+    fn lock(&mut self, exclusive: bool, wait: bool) -> IoResult<bool> {
+        self.flush().and_then(|()| self.fd.lock(exclusive, wait))
+    }
+    fn unlock(&mut self) -> IoResult<()> {
+        self.flush().and_then(|()| self.fd.unlock())
+    }
 }
 
 impl Drop for CFile {
@@ -374,11 +402,6 @@ pub fn readdir(p: &CString) -> IoResult<Vec<Path>> {
         }).map(|path| root.join(path)).collect()
     }
 
-    extern {
-        fn rust_dirent_t_size() -> libc::c_int;
-        fn rust_list_dir_val(ptr: *mut dirent_t) -> *libc::c_char;
-    }
-
     let size = unsafe { rust_dirent_t_size() };
     let mut buf = Vec::<u8>::with_capacity(size as uint);
     let ptr = buf.as_mut_slice().as_mut_ptr() as *mut dirent_t;
@@ -402,6 +425,74 @@ pub fn readdir(p: &CString) -> IoResult<Vec<Path>> {
     }
 }
 
+/// A directory opened for reading one entry at a time.
+pub struct Directory {
+    dir: *libc::DIR,
+    buf: Vec<u8>,
+}
+
+pub fn opendir(p: &CString) -> IoResult<Directory> {
+    let size = unsafe { rust_dirent_t_size() };
+    let dir = p.with_ref(|buf| unsafe { libc::opendir(buf) });
+    if dir.is_null() {
+        Err(super::last_error())
+    } else {
+        Ok(Directory { dir: dir, buf: Vec::with_capacity(size as uint) })
+    }
+}
+
+impl rtio::RtioDirectory for Directory {
+    fn next_entry(&mut self) -> IoResult<Option<rtio::DirEntry>> {
+        let ptr = self.buf.as_mut_slice().as_mut_ptr() as *mut libc::dirent_t;
+        loop {
+            let mut entry_ptr = 0 as *mut libc::dirent_t;
+            match unsafe { libc::readdir_r(self.dir, ptr, &mut entry_ptr) } {
+                0 => {}
+                n => return Err(IoError::from_errno(n as uint, false)),
+            }
+            if entry_ptr.is_null() { return Ok(None) }
+
+            let name = unsafe {
+                CString::new(rust_list_dir_val(entry_ptr), false)
+            };
+            let name = name.as_bytes_no_nul();
+            if name == bytes!(".") || name == bytes!("..") { continue }
+            return Ok(Some(rtio::DirEntry {
+                name: Vec::from_slice(name),
+                kind: dirent_kind(unsafe { rust_dirent_type(entry_ptr) }),
+                inode: unsafe { rust_dirent_ino(entry_ptr) },
+            }))
+        }
+    }
+}
+
+impl Drop for Directory {
+    fn drop(&mut self) {
+        unsafe { let _ = libc::closedir(self.dir); }
+    }
+}
+
+fn dirent_kind(d_type: c_int) -> Option<io::FileType> {
+    // The DT_* values are the same on every unix we support.
+    match d_type {
+        1 => Some(io::TypeNamedPipe),
+        2 => Some(io::TypeUnknown), // character device
+        4 => Some(io::TypeDirectory),
+        6 => Some(io::TypeBlockSpecial),
+        8 => Some(io::TypeFile),
+        10 => Some(io::TypeSymlink),
+        12 => Some(io::TypeUnknown), // socket
+        _ => None,
+    }
+}
+
+extern {
+    fn rust_dirent_t_size() -> libc::c_int;
+    fn rust_list_dir_val(ptr: *mut libc::dirent_t) -> *libc::c_char;
+    fn rust_dirent_type(ptr: *mut libc::dirent_t) -> libc::c_int;
+    fn rust_dirent_ino(ptr: *mut libc::dirent_t) -> u64;
+}
+
 pub fn unlink(p: &CString) -> IoResult<()> {
     super::mkerr_libc(retry(|| unsafe { libc::unlink(p.with_ref(|p| p)) }))
 }
@@ -17,6 +17,7 @@ use std::c_str::CString;
 use std::io::IoError;
 use std::io;
 use std::mem;
+use std::os;
 use std::os::win32::{as_utf16_p, fill_utf16_buf_and_decode};
 use std::ptr;
 use std::rt::rtio;
@@ -201,6 +202,30 @@ impl rtio::RtioFileStream for FileDesc {
             _ => Err(super::last_error()),
         }
     }
+
+    fn lock(&mut self, exclusive: bool, wait: bool) -> IoResult<bool> {
+        let mut flags = 0;
+        if exclusive { flags |= libc::LOCKFILE_EXCLUSIVE_LOCK; }
+        if !wait { flags |= libc::LOCKFILE_FAIL_IMMEDIATELY; }
+        // Lock the largest possible range, which covers the whole file no
+        // matter how it grows.
+        let mut overlap: libc::OVERLAPPED = unsafe { mem::zeroed() };
+        match unsafe {
+            libc::LockFileEx(self.handle(), flags, 0, !0, !0, &mut overlap)
+        } {
+            0 if !wait && os::errno() == libc::ERROR_LOCK_VIOLATION as uint => {
+                Ok(false)
+            }
+            0 => Err(super::last_error()),
+            _ => Ok(true),
+        }
+    }
+    fn unlock(&mut self) -> IoResult<()> {
+        let mut overlap: libc::OVERLAPPED = unsafe { mem::zeroed() };
+        super::mkerr_winbool(unsafe {
+            libc::UnlockFileEx(self.handle(), 0, !0, !0, &mut overlap)
+        })
+    }
 }
 
 impl rtio::RtioPipe for FileDesc {
@@ -353,10 +378,6 @@ pub fn readdir(p: &CString) -> IoResult<Vec<Path>> {
         }).map(|path| root.join(path)).collect()
     }
 
-    extern {
-        fn rust_list_dir_wfd_size() -> libc::size_t;
-        fn rust_list_dir_wfd_fp_buf(wfd: *libc::c_void) -> *u16;
-    }
     let star = Path::new(unsafe {
         CString::new(p.with_ref(|p| p), false)
     }).join("*");
@@ -389,6 +410,93 @@ pub fn readdir(p: &CString) -> IoResult<Vec<Path>> {
     })
 }
 
+/// A directory opened for reading one entry at a time.
+pub struct Directory {
+    handle: libc::HANDLE,
+    wfd: *mut c_void,
+    // whether `wfd` holds an entry which hasn't been returned yet
+    pending: bool,
+}
+
+pub fn opendir(p: &CString) -> IoResult<Directory> {
+    use std::rt::libc_heap::malloc_raw;
+
+    let star = Path::new(unsafe {
+        CString::new(p.with_ref(|p| p), false)
+    }).join("*");
+    as_utf16_p(star.as_str().unwrap(), |path_ptr| unsafe {
+        let wfd = malloc_raw(rust_list_dir_wfd_size() as uint);
+        let handle = libc::FindFirstFileW(path_ptr, wfd as libc::HANDLE);
+        if handle as libc::c_int == libc::INVALID_HANDLE_VALUE {
+            let err = super::last_error();
+            libc::free(wfd as *mut c_void);
+            Err(err)
+        } else {
+            Ok(Directory { handle: handle, wfd: wfd as *mut c_void, pending: true })
+        }
+    })
+}
+
+impl rtio::RtioDirectory for Directory {
+    fn next_entry(&mut self) -> IoResult<Option<rtio::DirEntry>> {
+        loop {
+            if !self.pending {
+                let ret = unsafe {
+                    libc::FindNextFileW(self.handle, self.wfd as libc::HANDLE)
+                };
+                if ret == 0 {
+                    return match os::errno() as c_int {
+                        libc::ERROR_NO_MORE_FILES => Ok(None),
+                        _ => Err(super::last_error()),
+                    }
+                }
+            }
+            self.pending = false;
+
+            let name = unsafe {
+                let fp_buf = rust_list_dir_wfd_fp_buf(self.wfd as *c_void);
+                let fp_vec = vec::raw::from_buf(fp_buf,
+                                                libc::wcslen(fp_buf) as uint);
+                let fp_trimmed = str::truncate_utf16_at_nul(fp_vec.as_slice());
+                str::from_utf16(fp_trimmed)
+                    .expect("rust_list_dir_wfd_fp_buf returned invalid UTF-16")
+            };
+            if name.as_slice() == "." || name.as_slice() == ".." { continue }
+
+            let attrs = unsafe {
+                rust_list_dir_wfd_attributes(self.wfd as *c_void)
+            };
+            let kind = if attrs & libc::FILE_ATTRIBUTE_REPARSE_POINT != 0 {
+                io::TypeSymlink
+            } else if attrs & libc::FILE_ATTRIBUTE_DIRECTORY != 0 {
+                io::TypeDirectory
+            } else {
+                io::TypeFile
+            };
+            return Ok(Some(rtio::DirEntry {
+                name: Vec::from_slice(name.as_bytes()),
+                kind: Some(kind),
+                inode: 0,
+            }))
+        }
+    }
+}
+
+impl Drop for Directory {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = libc::FindClose(self.handle);
+            libc::free(self.wfd);
+        }
+    }
+}
+
+extern {
+    fn rust_list_dir_wfd_size() -> libc::size_t;
+    fn rust_list_dir_wfd_fp_buf(wfd: *libc::c_void) -> *u16;
+    fn rust_list_dir_wfd_attributes(wfd: *libc::c_void) -> libc::DWORD;
+}
+
 pub fn unlink(p: &CString) -> IoResult<()> {
     super::mkerr_winbool(unsafe {
         as_utf16_p(p.as_str().unwrap(), |buf| {
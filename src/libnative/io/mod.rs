@@ -33,6 +33,7 @@ use std::rt::rtio;
 use std::rt::rtio::{RtioTcpStream, RtioTcpListener, RtioUdpSocket};
 use std::rt::rtio::{RtioUnixListener, RtioPipe, RtioFileStream, RtioProcess};
 use std::rt::rtio::{RtioSignal, RtioTTY, CloseBehavior, RtioTimer, ProcessConfig};
+use std::rt::rtio::{RtioSelector, SocketConfig, RtioUnixDatagram, RtioDirectory};
 use ai = std::io::net::addrinfo;
 
 // Local re-exports
@@ -73,6 +74,11 @@ pub mod pipe;
 #[path = "pipe_win32.rs"]
 pub mod pipe;
 
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+#[path = "poll_epoll.rs"]
+pub mod poll;
+
 #[cfg(unix)]    #[path = "c_unix.rs"]  mod c;
 #[cfg(windows)] #[path = "c_win32.rs"] mod c;
 
@@ -164,21 +170,21 @@ impl IoFactory {
 
 impl rtio::IoFactory for IoFactory {
     // networking
-    fn tcp_connect(&mut self, addr: SocketAddr,
+    fn tcp_connect(&mut self, addr: SocketAddr, cfg: &SocketConfig,
                    timeout: Option<u64>) -> IoResult<Box<RtioTcpStream:Send>> {
-        net::TcpStream::connect(addr, timeout).map(|s| {
+        net::TcpStream::connect(addr, cfg, timeout).map(|s| {
             box s as Box<RtioTcpStream:Send>
         })
     }
-    fn tcp_bind(&mut self, addr: SocketAddr)
+    fn tcp_bind(&mut self, addr: SocketAddr, cfg: &SocketConfig)
                 -> IoResult<Box<RtioTcpListener:Send>> {
-        net::TcpListener::bind(addr).map(|s| {
+        net::TcpListener::bind(addr, cfg).map(|s| {
             box s as Box<RtioTcpListener:Send>
         })
     }
-    fn udp_bind(&mut self, addr: SocketAddr)
+    fn udp_bind(&mut self, addr: SocketAddr, cfg: &SocketConfig)
                 -> IoResult<Box<RtioUdpSocket:Send>> {
-        net::UdpSocket::bind(addr).map(|u| box u as Box<RtioUdpSocket:Send>)
+        net::UdpSocket::bind(addr, cfg).map(|u| box u as Box<RtioUdpSocket:Send>)
     }
     fn unix_bind(&mut self, path: &CString)
                  -> IoResult<Box<RtioUnixListener:Send>> {
@@ -192,6 +198,20 @@ impl rtio::IoFactory for IoFactory {
             box s as Box<RtioPipe:Send>
         })
     }
+    #[cfg(unix)]
+    fn unix_datagram(&mut self, path: Option<&CString>)
+                     -> IoResult<Box<RtioUnixDatagram:Send>> {
+        let socket = match path {
+            Some(path) => pipe::UnixDatagram::bind(path),
+            None => pipe::UnixDatagram::unbound(),
+        };
+        socket.map(|s| box s as Box<RtioUnixDatagram:Send>)
+    }
+    #[cfg(windows)]
+    fn unix_datagram(&mut self, _path: Option<&CString>)
+                     -> IoResult<Box<RtioUnixDatagram:Send>> {
+        Err(unimpl())
+    }
     fn get_host_addresses(&mut self, host: Option<&str>, servname: Option<&str>,
                           hint: Option<ai::Hint>) -> IoResult<Vec<ai::Info>> {
         addrinfo::GetAddrInfoRequest::run(host, servname, hint)
@@ -233,6 +253,10 @@ impl rtio::IoFactory for IoFactory {
     fn fs_readdir(&mut self, path: &CString, _flags: c_int) -> IoResult<Vec<Path>> {
         file::readdir(path)
     }
+    fn fs_opendir(&mut self, path: &CString)
+                  -> IoResult<Box<RtioDirectory:Send>> {
+        file::opendir(path).map(|d| box d as Box<RtioDirectory:Send>)
+    }
     fn fs_lstat(&mut self, path: &CString) -> IoResult<io::FileStat> {
         file::lstat(path)
     }
@@ -289,4 +313,14 @@ impl rtio::IoFactory for IoFactory {
               -> IoResult<Box<RtioSignal:Send>> {
         Err(unimpl())
     }
+
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    fn selector(&mut self) -> IoResult<Box<RtioSelector:Send>> {
+        poll::Selector::new().map(|s| box s as Box<RtioSelector:Send>)
+    }
+    #[cfg(not(target_os = "linux"), not(target_os = "android"))]
+    fn selector(&mut self) -> IoResult<Box<RtioSelector:Send>> {
+        Err(unimpl())
+    }
 }
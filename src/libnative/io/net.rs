@@ -117,6 +117,64 @@ fn setsockopt<T>(fd: sock_t, opt: libc::c_int, val: libc::c_int,
     }
 }
 
+// Applies the options of `cfg` which have to be set before a socket is bound.
+fn configure(fd: sock_t, addr: ip::SocketAddr,
+             cfg: &rtio::SocketConfig) -> IoResult<()> {
+    if cfg.reuse_address {
+        try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR,
+                        1 as libc::c_int));
+    }
+    if cfg.reuse_port {
+        try!(reuse_port(fd));
+    }
+    match (cfg.only_v6, addr.ip) {
+        (Some(only), ip::Ipv6Addr(..)) => {
+            try!(setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY,
+                            only as libc::c_int));
+        }
+        _ => {}
+    }
+    match cfg.send_buffer_size {
+        Some(size) => try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_SNDBUF,
+                                      size as libc::c_int)),
+        None => {}
+    }
+    match cfg.recv_buffer_size {
+        Some(size) => try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF,
+                                      size as libc::c_int)),
+        None => {}
+    }
+    match cfg.linger {
+        Some(secs) => {
+            try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER,
+                            linger(secs)));
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn linger(secs: uint) -> libc::linger {
+    libc::linger { l_onoff: 1, l_linger: secs as libc::c_int }
+}
+
+#[cfg(windows)]
+fn linger(secs: uint) -> libc::linger {
+    libc::linger { l_onoff: 1, l_linger: secs as u16 }
+}
+
+#[cfg(unix)]
+fn reuse_port(fd: sock_t) -> IoResult<()> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::consts::os::bsd44::SO_REUSEPORT,
+               1 as libc::c_int)
+}
+
+#[cfg(windows)]
+fn reuse_port(_fd: sock_t) -> IoResult<()> {
+    Err(super::unimpl())
+}
+
 pub fn getsockopt<T: Copy>(fd: sock_t, opt: libc::c_int,
                            val: libc::c_int) -> IoResult<T> {
     unsafe {
@@ -257,10 +315,25 @@ impl Inner {
 }
 
 impl TcpStream {
-    pub fn connect(addr: ip::SocketAddr,
+    pub fn connect(addr: ip::SocketAddr, cfg: &rtio::SocketConfig,
                    timeout: Option<u64>) -> IoResult<TcpStream> {
         let fd = try!(socket(addr, libc::SOCK_STREAM));
         let ret = TcpStream::new(Inner::new(fd));
+        try!(configure(fd, addr, cfg));
+
+        match cfg.local_addr {
+            Some(local) => {
+                let (local, len) = addr_to_sockaddr(local);
+                let localp = &local as *_ as *libc::sockaddr;
+                match unsafe {
+                    libc::bind(fd, localp, len as libc::socklen_t)
+                } {
+                    -1 => return Err(last_error()),
+                    _ => {}
+                }
+            }
+            None => {}
+        }
 
         let (addr, len) = addr_to_sockaddr(addr);
         let addrp = &addr as *_ as *libc::sockaddr;
@@ -413,6 +486,9 @@ impl rtio::RtioSocket for TcpStream {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd(), libc::getsockname)
     }
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd() as libc::c_int))
+    }
 }
 
 impl Drop for Inner {
@@ -432,25 +508,20 @@ impl<'a> Drop for Guard<'a> {
 
 pub struct TcpListener {
     inner: Inner,
+    backlog: uint,
 }
 
 impl TcpListener {
-    pub fn bind(addr: ip::SocketAddr) -> IoResult<TcpListener> {
+    pub fn bind(addr: ip::SocketAddr,
+                cfg: &rtio::SocketConfig) -> IoResult<TcpListener> {
         let fd = try!(socket(addr, libc::SOCK_STREAM));
-        let ret = TcpListener { inner: Inner::new(fd) };
+        let ret = TcpListener { inner: Inner::new(fd), backlog: cfg.backlog };
+        try!(configure(fd, addr, cfg));
 
         let (addr, len) = addr_to_sockaddr(addr);
         let addrp = &addr as *_ as *libc::sockaddr;
         let len = len as libc::socklen_t;
 
-        // On platforms with Berkeley-derived sockets, this allows
-        // to quickly rebind a socket, without needing to wait for
-        // the OS to clean up the previous one.
-        if cfg!(unix) {
-            try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR,
-                            1 as libc::c_int));
-        }
-
         match unsafe { libc::bind(fd, addrp, len) } {
             -1 => Err(last_error()),
             _ => Ok(ret),
@@ -469,7 +540,8 @@ impl TcpListener {
 
 impl rtio::RtioTcpListener for TcpListener {
     fn listen(~self) -> IoResult<Box<rtio::RtioTcpAcceptor:Send>> {
-        self.native_listen(128).map(|a| {
+        let backlog = self.backlog as int;
+        self.native_listen(backlog).map(|a| {
             box a as Box<rtio::RtioTcpAcceptor:Send>
         })
     }
@@ -479,6 +551,9 @@ impl rtio::RtioSocket for TcpListener {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd(), libc::getsockname)
     }
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd() as libc::c_int))
+    }
 }
 
 pub struct TcpAcceptor {
@@ -514,6 +589,9 @@ impl rtio::RtioSocket for TcpAcceptor {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd(), libc::getsockname)
     }
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd() as libc::c_int))
+    }
 }
 
 impl rtio::RtioTcpAcceptor for TcpAcceptor {
@@ -539,13 +617,15 @@ pub struct UdpSocket {
 }
 
 impl UdpSocket {
-    pub fn bind(addr: ip::SocketAddr) -> IoResult<UdpSocket> {
+    pub fn bind(addr: ip::SocketAddr,
+                cfg: &rtio::SocketConfig) -> IoResult<UdpSocket> {
         let fd = try!(socket(addr, libc::SOCK_DGRAM));
         let ret = UdpSocket {
             inner: Arc::new(Inner::new(fd)),
             read_deadline: 0,
             write_deadline: 0,
         };
+        try!(configure(fd, addr, cfg));
 
         let (addr, len) = addr_to_sockaddr(addr);
         let addrp = &addr as *_ as *libc::sockaddr;
@@ -608,6 +688,9 @@ impl rtio::RtioSocket for UdpSocket {
     fn socket_name(&mut self) -> IoResult<ip::SocketAddr> {
         sockname(self.fd(), libc::getsockname)
     }
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd() as libc::c_int))
+    }
 }
 
 #[cfg(windows)] type msglen_t = libc::c_int;
@@ -10,9 +10,11 @@
 
 use alloc::arc::Arc;
 use libc;
-use std::c_str::CString;
+use std::c_str::{CString, ToCStr};
+use std::cmp;
 use std::intrinsics;
 use std::io;
+use std::io::net::unix::Credentials;
 use std::mem;
 use std::rt::rtio;
 use std::unstable::mutex;
@@ -70,6 +72,38 @@ impl Drop for Inner {
     fn drop(&mut self) { unsafe { let _ = libc::close(self.fd); } }
 }
 
+fn sockaddr_to_path(storage: &libc::sockaddr_storage,
+                    len: uint) -> Option<CString> {
+    let s: &libc::sockaddr_un = unsafe { mem::transmute(storage) };
+    let family = mem::size_of::<libc::sa_family_t>();
+    if len <= family { return None }
+    let len = cmp::min(len - family, s.sun_path.len());
+    let path = s.sun_path.slice_to(len).iter()
+                         .take_while(|c| **c != 0)
+                         .map(|c| *c as u8)
+                         .collect::<Vec<u8>>();
+    // unnamed sockets (and the abstract namespace on linux) have no path
+    if path.len() == 0 { None } else { Some(path.as_slice().to_c_str()) }
+}
+
+mod rustrt {
+    use libc;
+
+    extern {
+        pub fn rust_send_fds(sock: libc::c_int, buf: *libc::c_void,
+                             len: libc::size_t, fds: *libc::c_int,
+                             nfds: libc::size_t,
+                             flags: libc::c_int) -> libc::ssize_t;
+        pub fn rust_recv_fds(sock: libc::c_int, buf: *mut libc::c_void,
+                             len: libc::size_t, fds: *mut libc::c_int,
+                             nfds: *mut libc::size_t,
+                             flags: libc::c_int) -> libc::ssize_t;
+        pub fn rust_peer_cred(sock: libc::c_int, pid: *mut libc::pid_t,
+                              uid: *mut libc::uid_t,
+                              gid: *mut libc::gid_t) -> libc::c_int;
+    }
+}
+
 fn connect(addr: &CString, ty: libc::c_int,
            timeout: Option<u64>) -> IoResult<Inner> {
     let (addr, len) = try!(addr_to_sockaddr_un(addr));
@@ -196,6 +230,167 @@ impl rtio::RtioPipe for UnixStream {
     fn set_write_timeout(&mut self, timeout: Option<u64>) {
         self.write_deadline = timeout.map(|a| ::io::timer::now() + a).unwrap_or(0);
     }
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd()))
+    }
+
+    fn send_fds(&mut self, buf: &[u8], fds: &[libc::c_int]) -> IoResult<()> {
+        let ret = retry(|| unsafe {
+            rustrt::rust_send_fds(self.fd(),
+                                  buf.as_ptr() as *libc::c_void,
+                                  buf.len() as libc::size_t,
+                                  fds.as_ptr(),
+                                  fds.len() as libc::size_t,
+                                  0) as libc::c_int
+        });
+        match ret {
+            -1 => Err(super::last_error()),
+            n if (n as uint) < buf.len() => self.write(buf.slice_from(n as uint)),
+            _ => Ok(()),
+        }
+    }
+
+    fn recv_fds(&mut self, buf: &mut [u8],
+                max_fds: uint) -> IoResult<(uint, Vec<libc::c_int>)> {
+        let mut fds: Vec<libc::c_int> = Vec::with_capacity(max_fds);
+        let mut nfds = max_fds as libc::size_t;
+        let ret = retry(|| unsafe {
+            nfds = max_fds as libc::size_t;
+            rustrt::rust_recv_fds(self.fd(),
+                                  buf.as_mut_ptr() as *mut libc::c_void,
+                                  buf.len() as libc::size_t,
+                                  fds.as_mut_ptr(),
+                                  &mut nfds,
+                                  0) as libc::c_int
+        });
+        match ret {
+            -1 => Err(super::last_error()),
+            0 if buf.len() > 0 => Err(io::standard_error(io::EndOfFile)),
+            n => {
+                unsafe { fds.set_len(nfds as uint); }
+                Ok((n as uint, fds))
+            }
+        }
+    }
+
+    fn peer_cred(&mut self) -> IoResult<Credentials> {
+        let mut pid = 0;
+        let mut uid = 0;
+        let mut gid = 0;
+        match unsafe {
+            rustrt::rust_peer_cred(self.fd(), &mut pid, &mut uid, &mut gid)
+        } {
+            -1 => Err(super::last_error()),
+            _ => Ok(Credentials {
+                pid: if pid == -1 {None} else {Some(pid)},
+                uid: uid as uint,
+                gid: gid as uint,
+            }),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Unix Datagrams
+////////////////////////////////////////////////////////////////////////////////
+
+struct DatagramInner {
+    inner: Inner,
+    path: Option<CString>,
+}
+
+impl Drop for DatagramInner {
+    fn drop(&mut self) {
+        // See the comment in UnixListener's destructor, the path is unlinked
+        // before the descriptor is closed.
+        match self.path {
+            Some(ref path) => unsafe {
+                let _ = libc::unlink(path.with_ref(|p| p));
+            },
+            None => {}
+        }
+    }
+}
+
+pub struct UnixDatagram {
+    inner: Arc<DatagramInner>,
+}
+
+impl UnixDatagram {
+    pub fn bind(addr: &CString) -> IoResult<UnixDatagram> {
+        bind(addr, libc::SOCK_DGRAM).map(|inner| {
+            UnixDatagram::new(inner, Some(addr.clone()))
+        })
+    }
+
+    pub fn unbound() -> IoResult<UnixDatagram> {
+        unix_socket(libc::SOCK_DGRAM).map(|fd| {
+            UnixDatagram::new(Inner::new(fd), None)
+        })
+    }
+
+    fn new(inner: Inner, path: Option<CString>) -> UnixDatagram {
+        UnixDatagram {
+            inner: Arc::new(DatagramInner { inner: inner, path: path }),
+        }
+    }
+
+    fn fd(&self) -> fd_t { self.inner.inner.fd }
+}
+
+impl rtio::RtioUnixDatagram for UnixDatagram {
+    fn recv_from(&mut self,
+                 buf: &mut [u8]) -> IoResult<(uint, Option<CString>)> {
+        let mut storage: libc::sockaddr_storage = unsafe { intrinsics::init() };
+        let storagep = &mut storage as *mut _ as *mut libc::sockaddr;
+        let mut addrlen: libc::socklen_t =
+                mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        let ret = retry(|| unsafe {
+            libc::recvfrom(self.fd(),
+                           buf.as_mut_ptr() as *mut libc::c_void,
+                           buf.len() as libc::size_t,
+                           0,
+                           storagep,
+                           &mut addrlen) as libc::c_int
+        });
+        match ret {
+            -1 => Err(super::last_error()),
+            n => Ok((n as uint, sockaddr_to_path(&storage, addrlen as uint))),
+        }
+    }
+
+    fn send_to(&mut self, buf: &[u8], dst: &CString) -> IoResult<()> {
+        let (dst, len) = try!(addr_to_sockaddr_un(dst));
+        let dstp = &dst as *_ as *libc::sockaddr;
+        let ret = retry(|| unsafe {
+            libc::sendto(self.fd(),
+                         buf.as_ptr() as *libc::c_void,
+                         buf.len() as libc::size_t,
+                         0,
+                         dstp,
+                         len as libc::socklen_t) as libc::c_int
+        });
+        match ret {
+            -1 => Err(super::last_error()),
+            n if n as uint != buf.len() => {
+                Err(io::IoError {
+                    kind: io::ShortWrite(n as uint),
+                    desc: "couldn't send entire datagram",
+                    detail: None,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn clone(&self) -> Box<rtio::RtioUnixDatagram:Send> {
+        box UnixDatagram { inner: self.inner.clone() }
+            as Box<rtio::RtioUnixDatagram:Send>
+    }
+
+    fn event_source(&self) -> IoResult<rtio::EventSource> {
+        Ok(rtio::FdSource(self.fd()))
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
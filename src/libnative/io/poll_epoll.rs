@@ -0,0 +1,214 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Readiness selectors implemented with epoll
+//!
+//! Each registration is added to the epoll set with its token as the event's
+//! data, so the events returned by `epoll_wait` map straight back to tokens.
+//! The selector also remembers the descriptor and interest of each token,
+//! both to be able to remove or modify it later and to drain timer
+//! notification pipes when they are reported.
+//!
+//! epoll is level-triggered by default, which is what `std::io::poll`
+//! promises, so nothing else needs to be done to keep a ready descriptor
+//! reported until it's been drained.
+
+use libc;
+use std::io;
+use std::io::poll::{Event, Interest, Readable, Writable};
+use std::os;
+use std::rt::rtio;
+
+use io::{IoResult, retry, last_error};
+use io::c;
+use io::file::FileDesc;
+use io::timer;
+
+struct Registration {
+    token: uint,
+    fd: libc::c_int,
+    interest: Interest,
+    timer: bool,
+}
+
+pub struct Selector {
+    epfd: FileDesc,
+    registered: Vec<Registration>,
+}
+
+impl Selector {
+    pub fn new() -> IoResult<Selector> {
+        let fd = unsafe { imp::epoll_create(1) };
+        if fd == -1 { return Err(last_error()) }
+        let epfd = FileDesc::new(fd, true);
+        let _ = unsafe { c::ioctl(fd, c::FIOCLEX) };
+        Ok(Selector { epfd: epfd, registered: Vec::new() })
+    }
+
+    fn ctl(&self, op: libc::c_int, fd: libc::c_int, token: uint,
+           interest: Interest) -> IoResult<()> {
+        let mut events = 0;
+        if interest.contains(Readable) { events |= imp::EPOLLIN }
+        if interest.contains(Writable) { events |= imp::EPOLLOUT }
+        let mut event = imp::epoll_event { events: events, data: token as u64 };
+        match retry(|| unsafe {
+            imp::epoll_ctl(self.epfd.fd(), op, fd, &mut event)
+        }) {
+            0 => Ok(()),
+            _ => Err(last_error()),
+        }
+    }
+
+    fn position(&self, token: uint) -> Option<uint> {
+        self.registered.iter().position(|r| r.token == token)
+    }
+}
+
+impl rtio::RtioSelector for Selector {
+    fn register(&mut self, source: rtio::EventSource, token: uint,
+                interest: Interest) -> IoResult<()> {
+        let (fd, is_timer) = match source {
+            rtio::FdSource(fd) => (fd, false),
+            rtio::TimerSource(fd) => (fd, true),
+        };
+        let reg = Registration {
+            token: token, fd: fd, interest: interest, timer: is_timer,
+        };
+        match self.position(token) {
+            Some(i) if self.registered.get(i).fd == fd => {
+                try!(self.ctl(imp::EPOLL_CTL_MOD, fd, token, interest));
+                *self.registered.get_mut(i) = reg;
+            }
+            Some(i) => {
+                try!(self.ctl(imp::EPOLL_CTL_ADD, fd, token, interest));
+                let old = self.registered.get(i).fd;
+                let _ = self.ctl(imp::EPOLL_CTL_DEL, old, token, interest);
+                *self.registered.get_mut(i) = reg;
+            }
+            None => {
+                try!(self.ctl(imp::EPOLL_CTL_ADD, fd, token, interest));
+                self.registered.push(reg);
+            }
+        }
+        Ok(())
+    }
+
+    fn deregister(&mut self, token: uint) -> IoResult<()> {
+        let i = match self.position(token) {
+            Some(i) => i,
+            None => return Err(io::standard_error(io::InvalidInput)),
+        };
+        let reg = self.registered.swap_remove(i).unwrap();
+        self.ctl(imp::EPOLL_CTL_DEL, reg.fd, token, reg.interest)
+    }
+
+    fn select(&mut self, timeout_ms: Option<u64>) -> IoResult<Vec<Event>> {
+        let max = if self.registered.len() == 0 {1} else {self.registered.len()};
+        let mut events: Vec<imp::epoll_event> = Vec::with_capacity(max);
+        let deadline = timeout_ms.map(|ms| timer::now() + ms);
+
+        let n;
+        loop {
+            let timeout = match deadline {
+                None => -1,
+                Some(deadline) => {
+                    let now = timer::now();
+                    if deadline > now {(deadline - now) as libc::c_int} else {0}
+                }
+            };
+            match unsafe {
+                imp::epoll_wait(self.epfd.fd(), events.as_mut_ptr(),
+                                max as libc::c_int, timeout)
+            } {
+                -1 if os::errno() == libc::EINTR as int => {}
+                -1 => return Err(last_error()),
+                ret => { n = ret as uint; break }
+            }
+        }
+        unsafe { events.set_len(n); }
+
+        let mut ret = Vec::with_capacity(n);
+        for event in events.iter() {
+            let token = event.data as uint;
+            let reg = match self.position(token) {
+                Some(i) => self.registered.get(i),
+                None => continue,
+            };
+
+            // Errors and hangups are reported as whatever the registration
+            // was interested in, so the next operation sees the error.
+            let mut readiness = Interest::empty();
+            if event.events & (imp::EPOLLIN | imp::EPOLLERR | imp::EPOLLHUP) != 0 {
+                readiness.insert(Readable);
+            }
+            if event.events & (imp::EPOLLOUT | imp::EPOLLERR | imp::EPOLLHUP) != 0 {
+                readiness.insert(Writable);
+            }
+            readiness = readiness & reg.interest;
+            if readiness.is_empty() { continue }
+
+            if reg.timer { drain(reg.fd) }
+            ret.push(Event { token: token, readiness: readiness });
+        }
+        Ok(ret)
+    }
+}
+
+// Timer notification pipes are nonblocking, so this reads until the pipe is
+// empty.
+fn drain(fd: libc::c_int) {
+    let mut buf = [0u8, ..64];
+    loop {
+        let n = retry(|| unsafe {
+            libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void,
+                       buf.len() as libc::size_t) as libc::c_int
+        });
+        if n <= 0 { break }
+    }
+}
+
+#[allow(dead_code)]
+mod imp {
+    use libc;
+
+    pub static EPOLL_CTL_ADD: libc::c_int = 1;
+    pub static EPOLL_CTL_DEL: libc::c_int = 2;
+    pub static EPOLL_CTL_MOD: libc::c_int = 3;
+
+    pub static EPOLLIN: u32 = 0x001;
+    pub static EPOLLOUT: u32 = 0x004;
+    pub static EPOLLERR: u32 = 0x008;
+    pub static EPOLLHUP: u32 = 0x010;
+
+    #[cfg(target_arch = "x86_64")]
+    #[packed]
+    pub struct epoll_event {
+        pub events: u32,
+        pub data: u64,
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    pub struct epoll_event {
+        pub events: u32,
+        pub data: u64,
+    }
+
+    extern {
+        pub fn epoll_create(size: libc::c_int) -> libc::c_int;
+        pub fn epoll_ctl(epfd: libc::c_int,
+                         op: libc::c_int,
+                         fd: libc::c_int,
+                         event: *mut epoll_event) -> libc::c_int;
+        pub fn epoll_wait(epfd: libc::c_int,
+                          events: *mut epoll_event,
+                          maxevents: libc::c_int,
+                          timeout: libc::c_int) -> libc::c_int;
+    }
+}
@@ -270,6 +270,13 @@ fn spawn_process_os(cfg: ProcessConfig, in_fd: c_int, out_fd: c_int, err_fd: c_i
             detail: None,
         })
     }
+    if cfg.pgroup.is_some() || cfg.setsid || cfg.controlling_tty {
+        return Err(io::IoError {
+            kind: io::IoUnavailable,
+            desc: "unsupported process group/session requested on windows",
+            detail: None,
+        })
+    }
 
     unsafe {
         let mut si = zeroed_startupinfo();
@@ -459,8 +466,10 @@ fn spawn_process_os(cfg: ProcessConfig, in_fd: c_int, out_fd: c_int, err_fd: c_i
     use io::c;
 
     mod rustrt {
+        use libc::c_int;
         extern {
             pub fn rust_unset_sigprocmask();
+            pub fn rust_set_controlling_tty(fd: c_int) -> c_int;
         }
     }
 
@@ -496,6 +505,15 @@ fn spawn_process_os(cfg: ProcessConfig, in_fd: c_int, out_fd: c_int, err_fd: c_i
                 fail!("failure in fork: {}", os::last_os_error());
             } else if pid > 0 {
                 drop(output);
+                // The child joins its process group itself, but doing it here
+                // as well means the group exists as soon as spawn returns. This
+                // fails harmlessly if the child has already exec'd.
+                match cfg.pgroup {
+                    Some(pgid) if !cfg.setsid && !cfg.detach => {
+                        let _ = libc::setpgid(pid, pgid);
+                    }
+                    _ => {}
+                }
                 let mut bytes = [0, ..4];
                 return match input.inner_read(bytes) {
                     Ok(4) => {
@@ -619,6 +637,22 @@ fn spawn_process_os(cfg: ProcessConfig, in_fd: c_int, out_fd: c_int, err_fd: c_i
                 // error, but ignore it anyway.
                 let _ = libc::setsid();
             }
+            if cfg.setsid && !cfg.detach && libc::setsid() == -1 {
+                fail(&mut output);
+            }
+            if cfg.controlling_tty &&
+               rustrt::rust_set_controlling_tty(0) == -1 {
+                fail(&mut output);
+            }
+            // A session leader already leads its own group, and can't move.
+            match cfg.pgroup {
+                Some(pgid) if !cfg.setsid && !cfg.detach => {
+                    if libc::setpgid(0, pgid) != 0 {
+                        fail(&mut output);
+                    }
+                }
+                _ => {}
+            }
             if !dirp.is_null() && chdir(dirp) == -1 {
                 fail(&mut output);
             }
@@ -58,20 +58,26 @@ use io::IoResult;
 use io::c;
 use io::file::FileDesc;
 use io::helper_thread::Helper;
+use io::util;
 
 helper_init!(static mut HELPER: Helper<Req>)
 
 pub struct Timer {
     id: uint,
     inner: Option<Box<Inner>>,
+    // The read and write ends of the pipe used to make this timer selectable,
+    // created the first time it's registered with a selector.
+    notify: Option<(FileDesc, FileDesc)>,
 }
 
 struct Inner {
     tx: Option<Sender<()>>,
+    cb: Option<Box<rtio::Callback:Send>>,
     interval: u64,
     repeat: bool,
     target: u64,
     id: uint,
+    notify: Option<libc::c_int>,
 }
 
 #[allow(visible_private_types)]
@@ -119,8 +125,28 @@ fn helper(input: libc::c_int, messages: Receiver<Req>, _: ()) {
         let mut timer = match active.shift() {
             Some(timer) => timer, None => return
         };
+        match timer.cb.take() {
+            Some(mut cb) => {
+                cb.call();
+                dead.push((timer.id, timer));
+                return
+            }
+            None => {}
+        }
         let tx = timer.tx.take_unwrap();
-        if tx.send_opt(()).is_ok() && timer.repeat {
+        let sent = tx.send_opt(()).is_ok();
+        if sent {
+            // The pipe is nonblocking, so if nobody has drained it for a
+            // while this just drops the notification.
+            match timer.notify {
+                Some(fd) => unsafe {
+                    let buf = [0u8];
+                    let _ = libc::write(fd, buf.as_ptr() as *libc::c_void, 1);
+                },
+                None => {}
+            }
+        }
+        if sent && timer.repeat {
             timer.tx = Some(tx);
             timer.target += timer.interval;
             insert(timer, active);
@@ -210,11 +236,14 @@ impl Timer {
             id: id,
             inner: Some(box Inner {
                 tx: None,
+                cb: None,
                 interval: 0,
                 target: 0,
                 repeat: false,
                 id: id,
-            })
+                notify: None,
+            }),
+            notify: None,
         })
     }
 
@@ -246,6 +275,7 @@ impl rtio::RtioTimer for Timer {
     fn sleep(&mut self, msecs: u64) {
         let mut inner = self.inner();
         inner.tx = None; // cancel any previous request
+        inner.cb = None;
         self.inner = Some(inner);
 
         Timer::sleep(msecs);
@@ -258,6 +288,7 @@ impl rtio::RtioTimer for Timer {
         let (tx, rx) = channel();
         inner.repeat = false;
         inner.tx = Some(tx);
+        inner.cb = None;
         inner.interval = msecs;
         inner.target = now + msecs;
 
@@ -272,12 +303,49 @@ impl rtio::RtioTimer for Timer {
         let (tx, rx) = channel();
         inner.repeat = true;
         inner.tx = Some(tx);
+        inner.cb = None;
         inner.interval = msecs;
         inner.target = now + msecs;
 
         unsafe { HELPER.send(NewTimer(inner)); }
         return rx;
     }
+
+    fn oneshot_callback(&mut self, msecs: u64, cb: Box<rtio::Callback:Send>) {
+        let now = now();
+        let mut inner = self.inner();
+
+        inner.repeat = false;
+        inner.tx = None;
+        inner.cb = Some(cb);
+        inner.interval = msecs;
+        inner.target = now + msecs;
+
+        unsafe { HELPER.send(NewTimer(inner)); }
+    }
+
+    fn event_source(&mut self) -> IoResult<rtio::EventSource> {
+        match self.notify {
+            Some((ref reader, _)) => return Ok(rtio::TimerSource(reader.fd())),
+            None => {}
+        }
+        let os::Pipe { input, out } = os::pipe();
+        let reader = FileDesc::new(input, true);
+        let writer = FileDesc::new(out, true);
+        try!(util::set_nonblocking(input, true));
+        try!(util::set_nonblocking(out, true));
+
+        // Any pending firing needs to know about the pipe as well
+        let mut inner = self.inner();
+        inner.notify = Some(out);
+        if inner.tx.is_some() || inner.cb.is_some() {
+            unsafe { HELPER.send(NewTimer(inner)); }
+        } else {
+            self.inner = Some(inner);
+        }
+        self.notify = Some((reader, writer));
+        Ok(rtio::TimerSource(input))
+    }
 }
 
 impl Drop for Timer {
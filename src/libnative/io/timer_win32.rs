@@ -36,10 +36,18 @@ pub struct Timer {
 }
 
 pub enum Req {
-    NewTimer(libc::HANDLE, Sender<()>, bool),
+    NewTimer(libc::HANDLE, Action),
     RemoveTimer(libc::HANDLE, Sender<()>),
 }
 
+pub enum Action {
+    // Send on the channel when the timer fires, removing the timer afterwards
+    // if the flag is set.
+    Notify(Sender<()>, bool),
+    // Invoke the callback the first time the timer fires.
+    Invoke(Box<rtio::Callback:Send>),
+}
+
 fn helper(input: libc::HANDLE, messages: Receiver<Req>, _: ()) {
     let mut objs = vec![input];
     let mut chans = vec![];
@@ -55,9 +63,9 @@ fn helper(input: libc::HANDLE, messages: Receiver<Req>, _: ()) {
         if idx == 0 {
             loop {
                 match messages.try_recv() {
-                    Ok(NewTimer(obj, c, one)) => {
+                    Ok(NewTimer(obj, action)) => {
                         objs.push(obj);
-                        chans.push((c, one));
+                        chans.push(action);
                     }
                     Ok(RemoveTimer(obj, c)) => {
                         c.send(());
@@ -79,8 +87,9 @@ fn helper(input: libc::HANDLE, messages: Receiver<Req>, _: ()) {
             }
         } else {
             let remove = {
-                match chans.get(idx as uint - 1) {
-                    &(ref c, oneshot) => c.send_opt(()).is_err() || oneshot
+                match chans.get_mut(idx as uint - 1) {
+                    &Notify(ref c, oneshot) => c.send_opt(()).is_err() || oneshot,
+                    &Invoke(ref mut cb) => { cb.call(); true }
                 }
             };
             if remove {
@@ -159,7 +168,7 @@ impl rtio::RtioTimer for Timer {
                                   ptr::mut_null(), 0)
         }, 1);
 
-        unsafe { HELPER.send(NewTimer(self.obj, tx, true)) }
+        unsafe { HELPER.send(NewTimer(self.obj, Notify(tx, true))) }
         self.on_worker = true;
         return rx;
     }
@@ -175,11 +184,25 @@ impl rtio::RtioTimer for Timer {
                                   ptr::null(), ptr::mut_null(), 0)
         }, 1);
 
-        unsafe { HELPER.send(NewTimer(self.obj, tx, false)) }
+        unsafe { HELPER.send(NewTimer(self.obj, Notify(tx, false))) }
         self.on_worker = true;
 
         return rx;
     }
+
+    fn oneshot_callback(&mut self, msecs: u64, cb: Box<rtio::Callback:Send>) {
+        self.remove();
+
+        // see above for the calculation
+        let due = -(msecs as i64 * 10000) as libc::LARGE_INTEGER;
+        assert_eq!(unsafe {
+            imp::SetWaitableTimer(self.obj, &due, 0, ptr::null(),
+                                  ptr::mut_null(), 0)
+        }, 1);
+
+        unsafe { HELPER.send(NewTimer(self.obj, Invoke(cb))) }
+        self.on_worker = true;
+    }
 }
 
 impl Drop for Timer {
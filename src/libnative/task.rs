@@ -21,6 +21,7 @@ use std::rt::env;
 use std::rt::local::Local;
 use std::rt::rtio;
 use std::rt::stack;
+use std::rt::stats;
 use std::rt::task::{Task, BlockedTask, SendMessage};
 use std::rt::thread::Thread;
 use std::rt;
@@ -59,13 +60,15 @@ pub fn spawn(f: proc():Send) {
 pub fn spawn_opts(opts: TaskOpts, f: proc():Send) {
     let TaskOpts {
         notify_chan, name, stack_size,
-        stderr, stdout,
+        stderr, stdout, failure_hook, stats: keep_stats,
     } = opts;
 
     let mut task = box Task::new();
     task.name = name;
     task.stderr = stderr;
     task.stdout = stdout;
+    task.failure_hook = failure_hook;
+    if keep_stats { stats::enable(&mut *task) }
     match notify_chan {
         Some(chan) => { task.death.on_exit = Some(SendMessage(chan)); }
         None => {}
@@ -99,6 +102,7 @@ pub fn spawn_opts(opts: TaskOpts, f: proc():Send) {
         let mut f = Some(f);
         let mut task = task;
         task.put_runtime(ops);
+        stats::resume(&*task);
         let t = task.run(|| { f.take_unwrap()() });
         drop(t);
         bookkeeping::decrement();
@@ -184,6 +188,7 @@ impl rt::Runtime for Ops {
                   f: |BlockedTask| -> Result<(), BlockedTask>) {
         let me = &mut *self as *mut Ops;
         cur_task.put_runtime(self);
+        stats::suspend(&*cur_task);
 
         unsafe {
             let cur_task_dupe = &*cur_task as *Task;
@@ -231,6 +236,7 @@ impl rt::Runtime for Ops {
             // re-acquire ownership of the task
             cur_task = mem::transmute(cur_task_dupe);
         }
+        stats::resume(&*cur_task);
 
         // put the task back in TLS, and everything is as it once was.
         Local::put(cur_task);
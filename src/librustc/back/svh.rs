@@ -280,6 +280,9 @@ mod svh_visitor {
             // just syntactic artifacts, expanded away by time of SVH.
             ExprForLoop(..)          => unreachable!(),
             ExprMac(..)              => unreachable!(),
+
+            // only present in crates that failed to parse.
+            ExprError                => unreachable!(),
         }
     }
 
@@ -70,6 +70,13 @@ pub fn compile_input(sess: Session,
         let (outputs, expanded_crate, ast_map) = {
             let krate = phase_1_parse_input(&sess, cfg, input);
             if stop_after_phase_1(&sess) { return; }
+            if sess.parse_recovery() {
+                // Set aside the syntax errors the parser recovered from so
+                // that expansion and resolution, which stop at the first
+                // sign of trouble, still run and report their own errors.
+                // They are put back once resolution is done.
+                sess.diagnostic().handler().defer_errors();
+            }
             let outputs = build_output_filenames(input,
                                                  outdir,
                                                  output,
@@ -84,7 +91,11 @@ pub fn compile_input(sess: Session,
         };
         write_out_deps(&sess, input, &outputs, &expanded_crate);
 
-        if stop_after_phase_2(&sess) { return; }
+        if stop_after_phase_2(&sess) {
+            sess.diagnostic().handler().restore_deferred_errors();
+            sess.abort_if_errors();
+            return;
+        }
 
         let analysis = phase_3_run_analysis_passes(sess, &expanded_crate, ast_map);
         if stop_after_phase_3(&analysis.ty_cx.sess) { return; }
@@ -275,6 +286,11 @@ pub fn phase_3_run_analysis_passes(sess: Session,
     // Discard MTWT tables that aren't required past resolution.
     syntax::ext::mtwt::clear_tables();
 
+    // A crate with syntax errors gets no further than resolution: the
+    // placeholders error recovery left in it can't be type checked.
+    sess.diagnostic().handler().restore_deferred_errors();
+    sess.abort_if_errors();
+
     let named_region_map = time(time_passes, "lifetime resolution", (),
                                 |_| middle::resolve_lifetime::krate(&sess, krate));
 
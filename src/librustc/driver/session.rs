@@ -171,6 +171,9 @@ impl Session {
     pub fn show_span(&self) -> bool {
         self.debugging_opt(config::SHOW_SPAN)
     }
+    pub fn parse_recovery(&self) -> bool {
+        self.debugging_opt(config::PARSE_RECOVERY)
+    }
     pub fn sysroot<'a>(&'a self) -> &'a Path {
         match self.opts.maybe_sysroot {
             Some (ref sysroot) => sysroot,
@@ -209,6 +212,7 @@ pub fn build_session_(sopts: config::Options,
                       -> Session {
     let target_cfg = config::build_target_config(&sopts);
     let p_s = parse::new_parse_sess_special_handler(span_diagnostic);
+    p_s.error_recovery.set(sopts.debugging_opts & config::PARSE_RECOVERY != 0);
     let default_sysroot = match sopts.maybe_sysroot {
         Some(_) => None,
         None => Some(filesearch::get_or_default_sysroot())
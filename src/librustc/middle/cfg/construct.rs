@@ -421,6 +421,7 @@ impl<'a> CFGBuilder<'a> {
             }
 
             ast::ExprMac(..) |
+            ast::ExprError |
             ast::ExprInlineAsm(..) |
             ast::ExprFnBlock(..) |
             ast::ExprProc(..) |
@@ -621,6 +621,10 @@ impl<'a, 'b, O:DataFlowOperator> PropagationContext<'a, 'b, O> {
             ast::ExprMac(..) => {
                 self.tcx().sess.span_bug(expr.span, "unexpanded macro");
             }
+
+            ast::ExprError => {
+                self.tcx().sess.span_bug(expr.span, "unrecovered parse error");
+            }
         }
 
         self.dfcx.apply_gen_kill(expr.id, in_out);
@@ -394,6 +394,12 @@ impl<'d,'t,TYPER:mc::Typer> ExprUseVisitor<'d,'t,TYPER> {
                     expr.span,
                     "macro expression remains after expansion");
             }
+
+            ast::ExprError => {
+                self.tcx().sess.span_bug(
+                    expr.span,
+                    "error expression remains after parsing");
+            }
         }
     }
 
@@ -519,6 +519,7 @@ fn visit_expr(ir: &mut IrMaps, expr: &Expr) {
         visit::walk_expr(ir, expr, ());
       }
       ExprForLoop(..) => fail!("non-desugared expr_for_loop"),
+      ExprError => fail!("unrecovered parse error"),
       ExprBinary(op, _, _) if ast_util::lazy_binop(op) => {
         ir.add_live_node_for_node(expr.id, ExprNode(expr.span));
         visit::walk_expr(ir, expr, ());
@@ -1213,6 +1214,10 @@ impl<'a> Liveness<'a> {
           ExprMac(..) => {
             self.ir.tcx.sess.span_bug(expr.span, "unexpanded macro");
           }
+
+          ExprError => {
+            self.ir.tcx.sess.span_bug(expr.span, "unrecovered parse error");
+          }
         }
     }
 
@@ -1446,7 +1451,8 @@ fn check_expr(this: &mut Liveness, expr: &Expr) {
       ExprBox(..) => {
         visit::walk_expr(this, expr, ());
       }
-      ExprForLoop(..) => fail!("non-desugared expr_for_loop")
+      ExprForLoop(..) => fail!("non-desugared expr_for_loop"),
+      ExprError => fail!("unrecovered parse error")
     }
 }
 
@@ -483,7 +483,8 @@ impl<'t,TYPER:Typer> MemCategorizationContext<'t,TYPER> {
             Ok(self.cat_rvalue_node(expr.id(), expr.span(), expr_ty))
           }
 
-          ast::ExprForLoop(..) => fail!("non-desugared expr_for_loop")
+          ast::ExprForLoop(..) => fail!("non-desugared expr_for_loop"),
+          ast::ExprError => fail!("unrecovered parse error")
         }
     }
 
@@ -2738,6 +2738,11 @@ fn populate_scope_map(cx: &CrateContext,
                                               Found unexpanded macro.");
             }
 
+            ast::ExprError => {
+                cx.sess().span_bug(exp.span, "debuginfo::populate_scope_map() - \
+                                              Found error expression.");
+            }
+
             ast::ExprLoop(block, _) |
             ast::ExprBlock(block)   => {
                 with_new_scope(cx,
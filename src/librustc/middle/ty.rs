@@ -3141,6 +3141,7 @@ pub fn expr_kind(tcx: &ctxt, expr: &ast::Expr) -> ExprKind {
         }
 
         ast::ExprForLoop(..) => fail!("non-desugared expr_for_loop"),
+        ast::ExprError => fail!("unrecovered parse error"),
 
         ast::ExprLit(_) | // Note: LitStr is carved out above
         ast::ExprUnary(..) |
@@ -2949,6 +2949,7 @@ fn check_expr_with_unifier(fcx: &FnCtxt,
           fcx.write_nil(id);
       }
       ast::ExprMac(_) => tcx.sess.bug("unexpanded macro"),
+      ast::ExprError => tcx.sess.span_bug(expr.span, "unrecovered parse error"),
       ast::ExprBreak(_) => { fcx.write_bot(id); }
       ast::ExprAgain(_) => { fcx.write_bot(id); }
       ast::ExprRet(expr_opt) => {
@@ -19,6 +19,7 @@ use syntax::attr::AttributeMethods;
 use syntax::codemap::Pos;
 use syntax::parse::token::InternedString;
 use syntax::parse::token;
+use syntax::print::pprust;
 
 use rustc::back::link;
 use rustc::driver::driver;
@@ -35,7 +36,7 @@ use visit_ast;
 
 /// A stable identifier to the particular version of JSON output.
 /// Increment this when the `Crate` and related structures change.
-pub static SCHEMA_VERSION: &'static str = "0.8.2";
+pub static SCHEMA_VERSION: &'static str = "0.8.3";
 
 pub trait Clean<T> {
     fn clean(&self) -> T;
@@ -1671,6 +1672,8 @@ fn resolve_def(id: ast::NodeId) -> Option<ast::DefId> {
 #[deriving(Clone, Encodable, Decodable)]
 pub struct Macro {
     pub source: StrBuf,
+    /// The matcher of each of the macro's arms, as written in the source
+    pub matchers: Vec<StrBuf>,
 }
 
 impl Clean<Item> for doctree::Macro {
@@ -1683,7 +1686,35 @@ impl Clean<Item> for doctree::Macro {
             def_id: ast_util::local_def(self.id),
             inner: MacroItem(Macro {
                 source: self.where.to_src(),
+                matchers: macro_matchers(self.tts.as_slice()),
             }),
         }
     }
 }
+
+/// Finds the matchers in the body of a `macro_rules!` definition, which is a
+/// sequence of `(matcher) => (transcriber)` arms separated by semicolons.
+fn macro_matchers(tts: &[ast::TokenTree]) -> Vec<StrBuf> {
+    let mut matchers = Vec::new();
+    for (i, tt) in tts.iter().enumerate() {
+        match (tt, tts.get(i + 1)) {
+            (&ast::TTDelim(ref delim), Some(&ast::TTTok(_, token::FAT_ARROW))) => {
+                // The delimiters are the first and last token trees, so the
+                // matcher can be taken from the source as it was written.
+                let src = match (delim.iter().next(), delim.iter().last()) {
+                    (Some(&ast::TTTok(lo, _)), Some(&ast::TTTok(hi, _))) => {
+                        syntax::codemap::mk_sp(lo.lo, hi.hi).to_src()
+                    }
+                    _ => StrBuf::new(),
+                };
+                matchers.push(if src.len() > 0 {
+                    src
+                } else {
+                    pprust::tts_to_str(delim.as_slice())
+                });
+            }
+            _ => {}
+        }
+    }
+    matchers
+}
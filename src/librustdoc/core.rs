@@ -25,6 +25,7 @@ use collections::{HashMap, HashSet};
 use visit_ast::RustdocVisitor;
 use clean;
 use clean::Clean;
+use xref;
 
 pub enum MaybeTyped {
     Typed(middle::ty::ctxt),
@@ -58,6 +59,7 @@ pub struct CrateAnalysis {
     pub external_paths: ExternalPaths,
     pub external_traits: RefCell<Option<HashMap<ast::DefId, clean::Trait>>>,
     pub external_typarams: RefCell<Option<HashMap<ast::DefId, StrBuf>>>,
+    pub xrefs: RefCell<Option<xref::CrossReferences>>,
 }
 
 /// Parses, resolves, and typechecks the given crate
@@ -102,6 +104,7 @@ fn get_ast_and_resolve(cpath: &Path, libs: HashSet<Path>, cfgs: Vec<StrBuf>)
     let driver::driver::CrateAnalysis {
         exported_items, public_items, ty_cx, ..
     } = phase_3_run_analysis_passes(sess, &krate, ast_map);
+    let xrefs = xref::collect(&ty_cx, &krate);
 
     debug!("crate: {:?}", krate);
     (DocContext {
@@ -117,6 +120,7 @@ fn get_ast_and_resolve(cpath: &Path, libs: HashSet<Path>, cfgs: Vec<StrBuf>)
         external_paths: RefCell::new(None),
         external_traits: RefCell::new(None),
         external_typarams: RefCell::new(None),
+        xrefs: RefCell::new(Some(xrefs)),
     })
 }
 
@@ -0,0 +1,282 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Documentation coverage reports (`rustdoc --show-coverage`).
+//!
+//! The report is computed from the cleaned crate after the passes have run,
+//! so with the default passes it only covers items which are visible from
+//! outside the crate. Every module, function, struct, enum, variant, trait,
+//! method, static, typedef and macro is counted, except for the methods of
+//! trait impls, which are documented by the trait.
+
+use collections::TreeMap;
+use serialize::json;
+use serialize::json::{Json, ToJson};
+use std::io;
+
+use clean;
+
+/// The kinds of items counted by the report, in the order they're printed.
+static KINDS: &'static [&'static str] = &[
+    "modules", "fns", "structs", "enums", "variants", "traits", "methods",
+    "statics", "typedefs", "macros",
+];
+
+#[deriving(Clone)]
+pub struct Counts {
+    /// Number of items counted
+    pub total: uint,
+    /// Number of those which have any documentation
+    pub documented: uint,
+    /// Number of those whose documentation includes a code example
+    pub examples: uint,
+}
+
+impl Counts {
+    fn new() -> Counts { Counts { total: 0, documented: 0, examples: 0 } }
+
+    fn add(&mut self, other: &Counts) {
+        self.total += other.total;
+        self.documented += other.documented;
+        self.examples += other.examples;
+    }
+
+    /// The percentage of items which are documented. An empty set of items is
+    /// considered fully documented.
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.documented as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+impl ToJson for Counts {
+    fn to_json(&self) -> Json {
+        let mut obj = box TreeMap::new();
+        obj.insert("total".to_strbuf(), self.total.to_json());
+        obj.insert("documented".to_strbuf(), self.documented.to_json());
+        obj.insert("examples".to_strbuf(), self.examples.to_json());
+        obj.insert("percentage".to_strbuf(), self.percentage().to_json());
+        json::Object(obj)
+    }
+}
+
+/// Coverage of a single module, not including its submodules.
+pub struct ModuleCoverage {
+    pub kinds: TreeMap<&'static str, Counts>,
+}
+
+impl ModuleCoverage {
+    fn new() -> ModuleCoverage { ModuleCoverage { kinds: TreeMap::new() } }
+
+    pub fn total(&self) -> Counts {
+        let mut c = Counts::new();
+        for (_, counts) in self.kinds.iter() {
+            c.add(counts);
+        }
+        c
+    }
+}
+
+/// The coverage of a crate, keyed by the fully qualified module path.
+pub struct Coverage {
+    pub modules: TreeMap<StrBuf, ModuleCoverage>,
+}
+
+impl Coverage {
+    /// Totals for each kind of item, over the whole crate.
+    pub fn kinds(&self) -> TreeMap<&'static str, Counts> {
+        let mut kinds = TreeMap::new();
+        for (_, m) in self.modules.iter() {
+            for (kind, counts) in m.kinds.iter() {
+                kinds.find_or_insert(*kind, Counts::new()).add(counts);
+            }
+        }
+        kinds
+    }
+
+    pub fn total(&self) -> Counts {
+        let mut c = Counts::new();
+        for (_, m) in self.modules.iter() {
+            c.add(&m.total());
+        }
+        c
+    }
+
+    /// Prints the report as a table, one row per module followed by one row
+    /// per kind of item.
+    pub fn write_table(&self, w: &mut Writer) -> io::IoResult<()> {
+        let width = self.modules.iter().map(|(name, _)| name.len())
+                        .chain(KINDS.iter().map(|k| k.len()))
+                        .max().unwrap_or(0);
+        try!(header(w, "module", width));
+        for (name, m) in self.modules.iter() {
+            try!(row(w, name.as_slice(), width, &m.total()));
+        }
+        try!(w.write_str("\n"));
+        try!(header(w, "kind", width));
+        let kinds = self.kinds();
+        for kind in KINDS.iter() {
+            match kinds.find(kind) {
+                Some(counts) => try!(row(w, *kind, width, counts)),
+                None => {}
+            }
+        }
+        try!(w.write_str("\n"));
+        row(w, "total", width, &self.total())
+    }
+}
+
+impl ToJson for Coverage {
+    fn to_json(&self) -> Json {
+        let kinds_json = |kinds: &TreeMap<&'static str, Counts>| {
+            let mut obj = box TreeMap::new();
+            for (kind, counts) in kinds.iter() {
+                obj.insert(kind.to_strbuf(), counts.to_json());
+            }
+            json::Object(obj)
+        };
+        let mut modules = box TreeMap::new();
+        for (name, m) in self.modules.iter() {
+            let mut obj = box TreeMap::new();
+            obj.insert("total".to_strbuf(), m.total().to_json());
+            obj.insert("kinds".to_strbuf(), kinds_json(&m.kinds));
+            modules.insert(name.clone(), json::Object(obj));
+        }
+        let mut obj = box TreeMap::new();
+        obj.insert("modules".to_strbuf(), json::Object(modules));
+        obj.insert("kinds".to_strbuf(), kinds_json(&self.kinds()));
+        obj.insert("total".to_strbuf(), self.total().to_json());
+        json::Object(obj)
+    }
+}
+
+fn header(w: &mut Writer, title: &str, width: uint) -> io::IoResult<()> {
+    writeln!(w, "{:<5$s} {:>10s} {:>6s} {:>8s} {:>8s}",
+             title, "documented", "total", "percent", "examples", width)
+}
+
+fn row(w: &mut Writer, name: &str, width: uint,
+       c: &Counts) -> io::IoResult<()> {
+    writeln!(w, "{:<5$s} {:>10u} {:>6u} {:>7.1f}% {:>8u}",
+             name, c.documented, c.total, c.percentage(), c.examples, width)
+}
+
+/// Computes the documentation coverage of `krate`.
+pub fn measure(krate: &clean::Crate) -> Coverage {
+    let mut cov = Coverage { modules: TreeMap::new() };
+    match krate.module {
+        Some(ref m) => {
+            let mut path = vec!(krate.name.clone());
+            count(&mut cov, &mut path, m, false);
+        }
+        None => {}
+    }
+    cov
+}
+
+fn count(cov: &mut Coverage, path: &mut Vec<StrBuf>, item: &clean::Item,
+         in_trait_impl: bool) {
+    let kind = match item.inner {
+        clean::ModuleItem(ref m) if !m.is_crate => Some("modules"),
+        clean::FunctionItem(..) | clean::ForeignFunctionItem(..) => Some("fns"),
+        clean::StructItem(..) => Some("structs"),
+        clean::EnumItem(..) => Some("enums"),
+        clean::VariantItem(..) => Some("variants"),
+        clean::TraitItem(..) => Some("traits"),
+        clean::TyMethodItem(..) => Some("methods"),
+        clean::MethodItem(..) if !in_trait_impl => Some("methods"),
+        clean::StaticItem(..) | clean::ForeignStaticItem(..) => Some("statics"),
+        clean::TypedefItem(..) => Some("typedefs"),
+        clean::MacroItem(..) => Some("macros"),
+        _ => None,
+    };
+
+    // A module is counted as an item of its parent, so the crate root isn't
+    // counted at all.
+    match kind {
+        Some(kind) => {
+            let counts = cov.modules.find_or_insert(path.connect("::").to_strbuf(),
+                                                    ModuleCoverage::new())
+                            .kinds.find_or_insert(kind, Counts::new());
+            counts.total += 1;
+            match item.doc_value() {
+                Some(doc) if !doc.trim().is_empty() => {
+                    counts.documented += 1;
+                    if has_example(doc) {
+                        counts.examples += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        None => {}
+    }
+
+    match item.inner {
+        clean::ModuleItem(ref m) => {
+            if !m.is_crate {
+                path.push(item.name.clone().unwrap_or(StrBuf::new()));
+            }
+            for i in m.items.iter() {
+                count(cov, path, i, false);
+            }
+            if !m.is_crate {
+                path.pop();
+            }
+        }
+        clean::EnumItem(ref e) => {
+            for v in e.variants.iter() {
+                count(cov, path, v, false);
+            }
+        }
+        clean::TraitItem(ref t) => {
+            for m in t.methods.iter() {
+                count(cov, path, m.item(), false);
+            }
+        }
+        clean::ImplItem(ref i) => {
+            for m in i.methods.iter() {
+                count(cov, path, m, i.trait_.is_some());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns whether a doc string contains a fenced code block.
+fn has_example(doc: &str) -> bool {
+    doc.lines().any(|line| {
+        let line = line.trim_left();
+        line.starts_with("```") || line.starts_with("~~~")
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_example, Counts};
+
+    #[test]
+    fn examples() {
+        assert!(has_example("Adds one.\n\n```\nassert_eq!(add_one(1), 2);\n```"));
+        assert!(has_example("  ~~~ {.rust}\nfoo()\n  ~~~"));
+        assert!(!has_example("Adds one to `x`."));
+    }
+
+    #[test]
+    fn percentage() {
+        let c = Counts { total: 4, documented: 3, examples: 0 };
+        assert_eq!(c.percentage(), 75.0);
+        let empty = Counts { total: 0, documented: 0, examples: 0 };
+        assert_eq!(empty.percentage(), 100.0);
+    }
+}
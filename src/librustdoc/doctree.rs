@@ -168,6 +168,8 @@ pub struct Macro {
     pub id: ast::NodeId,
     pub attrs: Vec<ast::Attribute>,
     pub where: Span,
+    /// The body of the `macro_rules!` definition
+    pub tts: Vec<ast::TokenTree>,
 }
 
 pub fn struct_type_from_def(sd: &ast::StructDef) -> StructType {
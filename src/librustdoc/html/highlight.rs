@@ -18,14 +18,32 @@ use std::io;
 
 use syntax::parse;
 use syntax::parse::lexer;
-use syntax::codemap::{BytePos, Span};
+use syntax::codemap::{BytePos, Pos, Span};
 
 use html::escape::Escape;
 
 use t = syntax::parse::token;
 
+/// A link to place on an identifier of the highlighted source.
+pub struct Link {
+    /// Byte offset of the start of the path the identifier ends.
+    pub lo: uint,
+    /// Byte offset of the end of that path.
+    pub hi: uint,
+    /// The identifier to link.
+    pub name: StrBuf,
+    pub url: StrBuf,
+}
+
 /// Highlights some source code, returning the HTML output.
 pub fn highlight(src: &str, class: Option<&str>) -> StrBuf {
+    highlight_with_links(src, class, &[])
+}
+
+/// Highlights some source code like `highlight`, also turning identifiers
+/// into links as described by `links`, which must be sorted by position.
+pub fn highlight_with_links(src: &str, class: Option<&str>,
+                            links: &[Link]) -> StrBuf {
     debug!("highlighting: ================\n{}\n==============", src);
     let sess = parse::new_parse_sess();
     let fm = parse::string_to_filemap(&sess,
@@ -36,6 +54,7 @@ pub fn highlight(src: &str, class: Option<&str>) -> StrBuf {
     doit(&sess,
          lexer::new_string_reader(&sess.span_diagnostic, fm),
          class,
+         links,
          &mut out).unwrap();
     str::from_utf8_lossy(out.unwrap().as_slice()).to_strbuf()
 }
@@ -48,7 +67,7 @@ pub fn highlight(src: &str, class: Option<&str>) -> StrBuf {
 /// not from the tokens themselves, in order to stay true to the original
 /// source.
 fn doit(sess: &parse::ParseSess, mut lexer: lexer::StringReader, class: Option<&str>,
-        out: &mut Writer) -> io::IoResult<()> {
+        links: &[Link], out: &mut Writer) -> io::IoResult<()> {
     use syntax::parse::lexer::Reader;
 
     try!(write!(out, "<pre class='rust {}'>\n", class.unwrap_or("")));
@@ -56,6 +75,10 @@ fn doit(sess: &parse::ParseSess, mut lexer: lexer::StringReader, class: Option<&
     let mut is_attribute = false;
     let mut is_macro = false;
     let mut is_macro_nonterminal = false;
+    // Links whose path has started but whose identifier hasn't been seen yet.
+    // Paths nest (`Vec<Vec<T>>`), so there may be several.
+    let mut pending: Vec<&Link> = Vec::new();
+    let mut next_link = 0;
     loop {
         let next = lexer.next_token();
         let test = if next.tok == t::EOF {lexer.pos} else {next.sp.lo};
@@ -170,14 +193,38 @@ fn doit(sess: &parse::ParseSess, mut lexer: lexer::StringReader, class: Option<&
             t::UNDERSCORE | t::EOF | t::INTERPOLATED(..) => "",
         };
 
+        let link = match next.tok {
+            t::IDENT(ident, _) if !t::is_any_keyword(&next.tok) => {
+                let pos = next.sp.lo.to_uint();
+                while next_link < links.len() && links[next_link].lo <= pos {
+                    pending.push(&links[next_link]);
+                    next_link += 1;
+                }
+                pending.retain(|l| l.hi > pos);
+                let name = t::get_ident(ident);
+                match pending.iter().position(|l| l.name.as_slice() == name.get()) {
+                    Some(i) => pending.remove(i),
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+
         // as mentioned above, use the original source code instead of
         // stringifying this token
         let snip = sess.span_diagnostic.cm.span_to_snippet(next.sp).unwrap();
-        if klass == "" {
-            try!(write!(out, "{}", Escape(snip.as_slice())));
-        } else {
-            try!(write!(out, "<span class='{}'>{}</span>", klass,
-                          Escape(snip.as_slice())));
+        match link {
+            Some(link) => {
+                try!(write!(out, "<a class='{}' href='{}'>{}</a>", klass,
+                              link.url, Escape(snip.as_slice())));
+            }
+            None if klass == "" => {
+                try!(write!(out, "{}", Escape(snip.as_slice())));
+            }
+            None => {
+                try!(write!(out, "<span class='{}'>{}</span>", klass,
+                              Escape(snip.as_slice())));
+            }
         }
     }
 
@@ -278,8 +278,8 @@ pub fn find_testable_code(doc: &str, tests: &mut ::test::Collector) {
                     lang: *hoedown_buffer, opaque: *mut libc::c_void) {
         unsafe {
             if text.is_null() { return }
-            let (should_fail, no_run, ignore, notrust) = if lang.is_null() {
-                (false, false, false, false)
+            let (should_fail, no_run, ignore, notrust, compile_fail) = if lang.is_null() {
+                (false, false, false, false, false)
             } else {
                 slice::raw::buf_as_slice((*lang).data,
                                        (*lang).size as uint, |lang| {
@@ -287,7 +287,8 @@ pub fn find_testable_code(doc: &str, tests: &mut ::test::Collector) {
                     (s.contains("should_fail"),
                      s.contains("no_run"),
                      s.contains("ignore"),
-                     s.contains("notrust"))
+                     s.contains("notrust"),
+                     s.contains("compile_fail"))
                 })
             };
             if notrust { return }
@@ -299,7 +300,8 @@ pub fn find_testable_code(doc: &str, tests: &mut ::test::Collector) {
                     stripped_filtered_line(l).unwrap_or(l)
                 });
                 let text = lines.collect::<Vec<&str>>().connect("\n");
-                tests.add_test(text.to_strbuf(), should_fail, no_run, ignore);
+                tests.add_test(text.to_strbuf(), should_fail, no_run, ignore,
+                               compile_fail);
             })
         }
     }
@@ -58,6 +58,7 @@ use html::item_type;
 use html::layout;
 use html::markdown::Markdown;
 use html::markdown;
+use xref;
 
 /// Major driving force in all rustdoc rendering. This contains information
 /// about where in the tree-like hierarchy rendering is occurring and controls
@@ -174,6 +175,9 @@ pub struct Cache {
 /// Helper struct to render all source code to HTML pages
 struct SourceCollector<'a> {
     cx: &'a mut Context,
+    cache: &'a Cache,
+    /// Where the paths in the source refer to
+    xrefs: &'a xref::CrossReferences,
 
     /// Processed source-file paths
     seen: HashSet<StrBuf>,
@@ -182,8 +186,9 @@ struct SourceCollector<'a> {
 }
 
 /// Wrapper struct to render the source code of a file. This will do things like
-/// adding line numbers to the left-hand side.
-struct Source<'a>(&'a str);
+/// adding line numbers to the left-hand side, and linking identifiers to their
+/// definitions.
+struct Source<'a>(&'a str, &'a [highlight::Link]);
 
 // Helper structs for rendering items/sidebars and carrying along contextual
 // information
@@ -199,6 +204,7 @@ struct IndexItem {
     path: StrBuf,
     desc: StrBuf,
     parent: Option<ast::NodeId>,
+    def_id: ast::DefId,
 }
 
 // TLS keys used to carry information around during rendering.
@@ -206,8 +212,12 @@ struct IndexItem {
 local_data_key!(pub cache_key: Arc<Cache>)
 local_data_key!(pub current_location_key: Vec<StrBuf> )
 
-/// Generates the documentation for `crate` into the directory `dst`
-pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
+/// Generates the documentation for `crate` into the directory `dst`.
+///
+/// `extern_urls` maps names of external crates to the root URL of their
+/// documentation, for crates not documented into `dst` as well.
+pub fn run(mut krate: clean::Crate, dst: Path,
+           extern_urls: HashMap<StrBuf, StrBuf>) -> io::IoResult<()> {
     let mut cx = Context {
         dst: dst,
         current: Vec::new(),
@@ -281,6 +291,8 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
             a.external_typarams.borrow_mut().take_unwrap()
         }).unwrap_or(HashMap::new()),
     };
+    let xrefs = analysis.as_ref().and_then(|a| a.xrefs.borrow_mut().take());
+    let xrefs = xrefs.unwrap_or(xref::CrossReferences::new());
     cache.stack.push(krate.name.clone());
     krate = cache.fold_crate(krate);
 
@@ -303,6 +315,7 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
                                                          .to_strbuf(),
                         desc: shorter(item.doc_value()).to_strbuf(),
                         parent: Some(pid),
+                        def_id: item.def_id,
                     });
                 },
                 None => {}
@@ -370,6 +383,34 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
                         short, *fqp.last().unwrap()));
         }
 
+        // Where each item is referred to in the crate's source, as a list of
+        // `[item index, [[source page, line], ...]]`.
+        try!(write!(&mut w, r#"],"refs":["#));
+        if cx.include_sources {
+            let uses = xrefs.uses();
+            let mut first = true;
+            for (i, item) in cache.search_index.iter().enumerate() {
+                let places = match uses.find(&item.def_id) {
+                    Some(places) => places,
+                    None => continue,
+                };
+                if !first {
+                    try!(write!(&mut w, ","));
+                }
+                first = false;
+                try!(write!(&mut w, "[{},[", i));
+                for (j, &(ref file, line)) in places.iter().enumerate() {
+                    if j > 0 {
+                        try!(write!(&mut w, ","));
+                    }
+                    let page = format_strbuf!("src/{}/{}", krate.name,
+                                              source_page(file.as_slice()));
+                    try!(write!(&mut w, "[{},{}]", page.to_json().to_str(), line));
+                }
+                try!(write!(&mut w, "]]"));
+            }
+        }
+
         try!(write!(&mut w, r"]\};"));
 
         str::from_utf8(w.unwrap().as_slice()).unwrap().to_owned()
@@ -380,7 +421,9 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
     // operation with respect to all other rustdocs running around.
     {
         try!(mkdir(&cx.dst));
-        let _lock = ::flock::Lock::new(&cx.dst.join(".lock"));
+        let mut lock = try!(File::open_mode(&cx.dst.join(".lock"), io::Open,
+                                            io::ReadWrite));
+        try!(lock.lock_exclusive());
 
         // Add all the static files. These may already exist, but we just
         // overwrite them anyway to make sure that they're fresh and up-to-date.
@@ -417,13 +460,16 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
             return Ok(ret);
         }
 
-        // Update the search index
+        // Update the search index. All crates documented into this directory
+        // share it; keep it sorted by crate so that it comes out the same no
+        // matter which order the crates are documented in.
         let dst = cx.dst.join("search-index.js");
-        let all_indexes = try!(collect(&dst, krate.name.as_slice(),
-                                       "searchIndex"));
+        let mut all_indexes = try!(collect(&dst, krate.name.as_slice(),
+                                           "searchIndex"));
+        all_indexes.push(index.to_strbuf());
+        all_indexes.sort();
         let mut w = try!(File::create(&dst));
         try!(writeln!(&mut w, r"var searchIndex = \{\};"));
-        try!(writeln!(&mut w, "{}", index));
         for index in all_indexes.iter() {
             try!(writeln!(&mut w, "{}", *index));
         }
@@ -482,6 +528,13 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
         }
     }
 
+    for &(n, ref e) in krate.externs.iter() {
+        cache.extern_locations.insert(n, extern_location(e, &cx.dst,
+                                                         &extern_urls));
+        let did = ast::DefId { krate: n, node: ast::CRATE_NODE_ID };
+        cache.paths.insert(did, (Vec::new(), item_type::Module));
+    }
+
     // Render all source files (this may turn into a giant no-op)
     {
         info!("emitting source files");
@@ -493,16 +546,12 @@ pub fn run(mut krate: clean::Crate, dst: Path) -> io::IoResult<()> {
             dst: dst,
             seen: HashSet::new(),
             cx: &mut cx,
+            cache: &cache,
+            xrefs: &xrefs,
         };
         krate = folder.fold_crate(krate);
     }
 
-    for &(n, ref e) in krate.externs.iter() {
-        cache.extern_locations.insert(n, extern_location(e, &cx.dst));
-        let did = ast::DefId { krate: n, node: ast::CRATE_NODE_ID };
-        cache.paths.insert(did, (Vec::new(), item_type::Module));
-    }
-
     // And finally render the whole crate's documentation
     cx.krate(krate, cache)
 }
@@ -540,15 +589,40 @@ fn clean_srcpath(src: &[u8], f: |&str|) {
     }
 }
 
+/// The path of the rendered source of `filename` within the crate's source
+/// directory.
+fn source_page(filename: &str) -> StrBuf {
+    let mut path = Vec::new();
+    clean_srcpath(filename.as_bytes(), |component| {
+        path.push(component.to_owned());
+    });
+    format_strbuf!("{}.html", path.connect("/"))
+}
+
 /// Attempts to find where an external crate is located, given that we're
 /// rendering in to the specified source destination.
-fn extern_location(e: &clean::ExternalCrate, dst: &Path) -> ExternalLocation {
+fn extern_location(e: &clean::ExternalCrate, dst: &Path,
+                   extern_urls: &HashMap<StrBuf, StrBuf>) -> ExternalLocation {
+    fn remote(url: &str) -> ExternalLocation {
+        if url.ends_with("/") {
+            Remote(url.to_strbuf())
+        } else {
+            Remote(format_strbuf!("{}/", url))
+        }
+    }
+
     // See if there's documentation generated into the local directory
     let local_location = dst.join(e.name.as_slice());
     if local_location.is_dir() {
         return Local;
     }
 
+    // Next, see if we were told where it lives on the command line
+    match extern_urls.find(&e.name) {
+        Some(url) => return remote(url.as_slice()),
+        None => {}
+    }
+
     // Failing that, see if there's an attribute specifying where to find this
     // external crate
     for attr in e.attrs.iter() {
@@ -558,10 +632,7 @@ fn extern_location(e: &clean::ExternalCrate, dst: &Path) -> ExternalLocation {
                     match *attr {
                         clean::NameValue(ref x, ref s)
                                 if "html_root_url" == x.as_slice() => {
-                            if s.as_slice().ends_with("/") {
-                                return Remote(s.to_strbuf());
-                            }
-                            return Remote(format_strbuf!("{}/", s));
+                            return remote(s.as_slice());
                         }
                         _ => {}
                     }
@@ -644,6 +715,20 @@ impl<'a> SourceCollector<'a> {
                  .append(bytes!(".html")));
         let mut w = BufferedWriter::new(try!(File::create(&cur)));
 
+        let links = match self.xrefs.refs.find(&filename.to_strbuf()) {
+            Some(refs) => refs.iter().filter_map(|r| {
+                self.def_href(r.def, root_path.as_slice()).map(|url| {
+                    highlight::Link {
+                        lo: r.lo,
+                        hi: r.hi,
+                        name: r.name.clone(),
+                        url: url,
+                    }
+                })
+            }).collect(),
+            None => Vec::new(),
+        };
+
         let title = format!("{} -- source", cur.filename_display());
         let page = layout::Page {
             title: title,
@@ -651,10 +736,46 @@ impl<'a> SourceCollector<'a> {
             root_path: root_path.as_slice(),
         };
         try!(layout::render(&mut w as &mut Writer, &self.cx.layout,
-                            &page, &(""), &Source(contents)));
+                            &page, &(""), &Source(contents, links.as_slice())));
         try!(w.flush());
         return Ok(());
     }
+
+    /// The URL, relative to the documentation root `root`, of the definition
+    /// `did` refers to: its source if it's defined in this crate, or else its
+    /// documentation if that can be found.
+    fn def_href(&self, did: ast::DefId, root: &str) -> Option<StrBuf> {
+        if ast_util::is_local(did) {
+            return self.xrefs.defs.find(&did).map(|&(ref file, line)| {
+                format_strbuf!("{}src/{}/{}\\#{}", root, self.cx.layout.krate,
+                               source_page(file.as_slice()), line)
+            })
+        }
+        let &(ref fqp, shortty) = match self.cache.paths.find(&did) {
+            Some(path) if path.ref0().len() > 0 => path,
+            _ => return None,
+        };
+        let mut url = match self.cache.extern_locations.find(&did.krate) {
+            Some(&Remote(ref s)) => s.clone(),
+            Some(&Local) => root.to_strbuf(),
+            Some(&Unknown) | None => return None,
+        };
+        for component in fqp.slice_to(fqp.len() - 1).iter() {
+            url.push_str(component.as_slice());
+            url.push_str("/");
+        }
+        match shortty {
+            item_type::Module => {
+                url.push_str(fqp.last().unwrap().as_slice());
+                url.push_str("/index.html");
+            }
+            _ => {
+                url.push_str(format!("{}.{}.html", shortty.to_static_str(),
+                                     *fqp.last().unwrap()));
+            }
+        }
+        Some(url)
+    }
 }
 
 impl DocFolder for Cache {
@@ -760,6 +881,7 @@ impl DocFolder for Cache {
                             path: path.connect("::").to_strbuf(),
                             desc: shorter(item.doc_value()).to_strbuf(),
                             parent: parent,
+                            def_id: item.def_id,
                         });
                     }
                     (Some(parent), None) if !self.privmod => {
@@ -1039,10 +1161,6 @@ impl<'a> Item<'a> {
     }
 
     fn link(&self) -> StrBuf {
-        let mut path = Vec::new();
-        clean_srcpath(self.item.source.filename.as_bytes(), |component| {
-            path.push(component.to_owned());
-        });
         let href = if self.item.source.loline == self.item.source.hiline {
             format_strbuf!("{}", self.item.source.loline)
         } else {
@@ -1050,10 +1168,10 @@ impl<'a> Item<'a> {
                            self.item.source.loline,
                            self.item.source.hiline)
         };
-        format_strbuf!("{root}src/{krate}/{path}.html\\#{href}",
+        format_strbuf!("{root}src/{krate}/{page}\\#{href}",
                        root = self.cx.root_path,
                        krate = self.cx.layout.krate,
-                       path = path.connect("/"),
+                       page = source_page(self.item.source.filename.as_slice()),
                        href = href)
     }
 }
@@ -1868,7 +1986,7 @@ fn build_sidebar(m: &clean::Module) -> HashMap<StrBuf, Vec<StrBuf>> {
 
 impl<'a> fmt::Show for Source<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let Source(s) = *self;
+        let Source(s, links) = *self;
         let lines = s.lines().len();
         let mut cols = 0;
         let mut tmp = lines;
@@ -1881,13 +1999,31 @@ impl<'a> fmt::Show for Source<'a> {
             try!(write!(fmt, "<span id='{0:u}'>{0:1$u}</span>\n", i, cols));
         }
         try!(write!(fmt, "</pre>"));
-        try!(write!(fmt, "{}", highlight::highlight(s.as_slice(), None)));
+        try!(write!(fmt, "{}", highlight::highlight_with_links(s.as_slice(),
+                                                               None, links)));
         Ok(())
     }
 }
 
 fn item_macro(w: &mut fmt::Formatter, it: &clean::Item,
               t: &clean::Macro) -> fmt::Result {
-    try!(w.write(highlight::highlight(t.source.as_slice(), Some("macro")).as_bytes()));
+    // Show what the macro can be invoked with rather than its whole
+    // definition, which is a click away in the source view.
+    let src = if t.matchers.len() == 0 {
+        t.source.clone()
+    } else {
+        let name = it.name.get_ref().as_slice();
+        let mut src = StrBuf::from_str("macro_rules! ");
+        src.push_str(name.slice_to(name.len() - 1));
+        src.push_str(" {\n");
+        for matcher in t.matchers.iter() {
+            src.push_str("    ");
+            src.push_str(matcher.as_slice());
+            src.push_str(" => { ... };\n");
+        }
+        src.push_str("}");
+        src
+    };
+    try!(w.write(highlight::highlight(src.as_slice(), Some("macro")).as_bytes()));
     document(w, it)
 }
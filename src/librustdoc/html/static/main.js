@@ -448,7 +448,18 @@
                     }
 
                     output += '</td><td><span class="desc">' + item.desc +
-                        '</span></td></tr>';
+                        '</span>';
+                    if (item.refs !== undefined) {
+                        output += '<span class="refs">Referenced from ' +
+                            item.refs.map(function(place) {
+                                var file = place[0].replace(/^src\/[^\/]*\//, '')
+                                                   .replace(/\.html$/, '');
+                                return '<a href="' + rootPath + place[0] + '#' +
+                                    place[1] + '">' + file + ':' + place[1] +
+                                    '</a>';
+                            }).join(', ') + '</span>';
+                    }
+                    output += '</td></tr>';
                 });
             } else {
                 output += 'No results :( <a href="https://duckduckgo.com/?q=' +
@@ -551,6 +562,11 @@
                 // an array of [(Number) item type,
                 //              (String) name]
                 var paths = rawSearchIndex[crate].paths;
+                // an array of [(Number) index into `items`,
+                //              [[(String) source page, (Number) line], ...]],
+                // which is missing from indices written by older versions
+                var refs = rawSearchIndex[crate].refs || [];
+                var start = searchIndex.length;
 
                 // convert `paths` into an object form
                 var len = paths.length;
@@ -581,6 +597,11 @@
                     }
                     lastPath = row.path;
                 }
+
+                // attach the places referring to each item
+                for (var i = 0; i < refs.length; i += 1) {
+                    searchIndex[start + refs[i][0]].refs = refs[i][1];
+                }
             }
             return searchWords;
         }
@@ -0,0 +1,764 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The documented JSON description of a crate's API.
+//!
+//! Unlike the `"crate"` field of rustdoc's JSON output, which is a raw
+//! `Encodable` dump of `clean::Crate` and changes whenever that structure
+//! does, the `"api"` field is built by this module and follows the format
+//! described here. Any incompatible change to it bumps `FORMAT_VERSION`.
+//!
+//! The top level is an object:
+//!
+//! ```notrust
+//! {
+//!   "format_version": 1,
+//!   "name": "mycrate",
+//!   "root": "0:0",
+//!   "externs": { "1": { "name": "std", "attrs": [ ... ] }, ... },
+//!   "index": { "<id>": <item>, ... },
+//!   "paths": { "<id>": { "path": ["std", "vec", "Vec"], "kind": "struct" } },
+//!   "implementors": { "<trait id>": ["<impl id>", ...] },
+//!   "impls": { "<type id>": ["<impl id>", ...] }
+//! }
+//! ```
+//!
+//! An id is a string of the form `"<crate number>:<node id>"`, where crate
+//! number 0 is the crate being documented and the others are the keys of
+//! `"externs"`. Ids are only stable within a single run of rustdoc.
+//!
+//! `"index"` holds every documented item of the crate, flattened: modules
+//! refer to their items, structs to their fields, enums to their variants,
+//! traits and impls to their methods, all by id. `"paths"` maps each module,
+//! struct, enum, trait, function, static, typedef and macro (local or
+//! external) to its fully qualified path. `"implementors"` and `"impls"`
+//! record, for every trait and every struct or enum, the impls that refer to
+//! it.
+//!
+//! Each item is an object with the fields `"id"`, `"name"` (a string or
+//! `null`), `"kind"`, `"visibility"` (`"public"`, `"inherited"` or `null`),
+//! `"docs"` (the doc comment, or `null`), `"attrs"`, `"span"` and `"inner"`.
+//! `"kind"` is one of `module`, `struct`, `enum`, `variant`, `struct_field`,
+//! `function`, `foreign_function`, `typedef`, `static`, `foreign_static`,
+//! `trait`, `ty_method`, `method`, `impl` and `macro`, and selects the shape
+//! of `"inner"`; see the `*_json` functions below for each.
+//!
+//! Types, generic bounds, attributes and self types are objects tagged by a
+//! `"kind"` field as well. Function signatures are `{"inputs": [{"name",
+//! "type"}], "output": <type>, "diverges": bool, "attrs": [...]}` and
+//! generics are `{"lifetimes": [...], "type_params": [{"id", "name",
+//! "bounds"}]}`. Spans are `{"filename", "lo": {"line", "col"}, "hi":
+//! {"line", "col"}}` with 1-based lines and 0-based columns.
+
+use collections::{HashMap, TreeMap};
+use serialize::json;
+use serialize::json::{Json, ToJson};
+use syntax::ast;
+use syntax::ast_util;
+
+use clean;
+use doctree;
+
+/// The version of the format documented above.
+pub static FORMAT_VERSION: uint = 1;
+
+/// Builds the JSON description of `krate`.
+///
+/// `external_paths` are the paths to items of other crates collected while
+/// cleaning the crate; they are unavailable when the crate was read back from
+/// JSON, in which case only local paths are reported.
+pub fn krate(krate: &clean::Crate,
+             external_paths: Option<&HashMap<ast::DefId,
+                                             (Vec<StrBuf>, clean::TypeKind)>>)
+             -> Json {
+    let mut cx = Context {
+        index: TreeMap::new(),
+        paths: TreeMap::new(),
+        implementors: TreeMap::new(),
+        impls: TreeMap::new(),
+        typarams: HashMap::new(),
+        stack: vec!(krate.name.clone()),
+    };
+    match external_paths {
+        Some(paths) => {
+            for (did, &(ref fqp, kind)) in paths.iter() {
+                cx.paths.insert(id_str(*did), path_json(fqp, kind_name(kind)));
+            }
+        }
+        None => {}
+    }
+    match krate.module {
+        Some(ref m) => cx.collect_typarams(m),
+        None => {}
+    }
+    let root = match krate.module {
+        Some(ref m) => {
+            cx.item(m);
+            id(m.def_id)
+        }
+        None => json::Null,
+    };
+
+    let mut externs = TreeMap::new();
+    for &(cnum, ref e) in krate.externs.iter() {
+        externs.insert(format_strbuf!("{}", cnum), Object::new()
+            .set("name", e.name.to_json())
+            .set("attrs", list(e.attrs.iter(), attr_json))
+            .build());
+    }
+
+    Object::new()
+        .set("format_version", FORMAT_VERSION.to_json())
+        .set("name", krate.name.to_json())
+        .set("root", root)
+        .set("externs", json::Object(box externs))
+        .set("index", json::Object(box cx.index))
+        .set("paths", json::Object(box cx.paths))
+        .set("implementors", id_lists(cx.implementors))
+        .set("impls", id_lists(cx.impls))
+        .build()
+}
+
+struct Context {
+    index: TreeMap<StrBuf, Json>,
+    paths: TreeMap<StrBuf, Json>,
+    implementors: TreeMap<StrBuf, Vec<Json>>,
+    impls: TreeMap<StrBuf, Vec<Json>>,
+    /// Names of the type parameters of the crate, so `Generic` types can be
+    /// reported by name.
+    typarams: HashMap<ast::DefId, StrBuf>,
+    /// Names of the modules enclosing the item being visited.
+    stack: Vec<StrBuf>,
+}
+
+impl Context {
+    fn collect_typarams(&mut self, item: &clean::Item) {
+        {
+            let generics = match item.inner {
+                clean::StructItem(ref s) => Some(&s.generics),
+                clean::EnumItem(ref e) => Some(&e.generics),
+                clean::FunctionItem(ref f) |
+                clean::ForeignFunctionItem(ref f) => Some(&f.generics),
+                clean::TypedefItem(ref t) => Some(&t.generics),
+                clean::TraitItem(ref t) => Some(&t.generics),
+                clean::ImplItem(ref i) => Some(&i.generics),
+                clean::TyMethodItem(ref m) => Some(&m.generics),
+                clean::MethodItem(ref m) => Some(&m.generics),
+                _ => None,
+            };
+            match generics {
+                Some(g) => {
+                    for tp in g.type_params.iter() {
+                        self.typarams.insert(tp.did, tp.name.clone());
+                    }
+                }
+                None => {}
+            }
+        }
+        for child in children(item).move_iter() {
+            self.collect_typarams(child);
+        }
+    }
+
+    /// Adds `item` and everything nested within it to the index.
+    fn item(&mut self, item: &clean::Item) {
+        let kind = item_kind(&item.inner);
+        let pushed = match item.inner {
+            clean::ModuleItem(ref m) if !m.is_crate => {
+                self.stack.push(item.name.clone().unwrap_or(StrBuf::new()));
+                true
+            }
+            _ => false,
+        };
+        match (kind, &item.name) {
+            ("module", _) => {
+                let fqp = self.stack.clone();
+                self.paths.insert(id_str(item.def_id), path_json(&fqp, kind));
+            }
+            ("struct", &Some(ref name)) | ("enum", &Some(ref name)) |
+            ("trait", &Some(ref name)) | ("function", &Some(ref name)) |
+            ("static", &Some(ref name)) | ("typedef", &Some(ref name)) |
+            ("macro", &Some(ref name)) => {
+                let mut fqp = self.stack.clone();
+                fqp.push(name.clone());
+                self.paths.insert(id_str(item.def_id), path_json(&fqp, kind));
+            }
+            _ => {}
+        }
+
+        match item.inner {
+            clean::ImplItem(ref i) => {
+                let impl_id = id(item.def_id);
+                match i.trait_ {
+                    Some(clean::ResolvedPath { did, .. }) => {
+                        self.implementors.find_or_insert(id_str(did), Vec::new())
+                                         .push(impl_id.clone());
+                    }
+                    _ => {}
+                }
+                match i.for_ {
+                    clean::ResolvedPath { did, .. } => {
+                        self.impls.find_or_insert(id_str(did), Vec::new())
+                                  .push(impl_id);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        let inner = self.inner_json(&item.inner);
+        self.index.insert(id_str(item.def_id), Object::new()
+            .set("id", id(item.def_id))
+            .set("name", item.name.to_json())
+            .set("kind", string(kind))
+            .set("visibility", match item.visibility {
+                Some(ast::Public) => string("public"),
+                Some(ast::Inherited) => string("inherited"),
+                None => json::Null,
+            })
+            .set("docs", item.doc_value().map(|s| s.to_strbuf()).to_json())
+            .set("attrs", list(item.attrs.iter(), attr_json))
+            .set("span", span_json(&item.source))
+            .set("inner", inner)
+            .build());
+
+        for child in children(item).move_iter() {
+            self.item(child);
+        }
+        if pushed {
+            self.stack.pop();
+        }
+    }
+
+    fn inner_json(&self, inner: &clean::ItemEnum) -> Json {
+        match *inner {
+            clean::ModuleItem(ref m) => {
+                let items = m.items.iter().filter(|i| !is_view_item(*i));
+                let imports = m.items.iter().filter_map(|i| {
+                    match i.inner {
+                        clean::ViewItemItem(ref v) => Some(v),
+                        _ => None,
+                    }
+                });
+                Object::new()
+                    .set("is_crate", m.is_crate.to_json())
+                    .set("items", list(items, |i| id(i.def_id)))
+                    .set("imports", list(imports, |v| view_item_json(v)))
+                    .build()
+            }
+            clean::StructItem(ref s) => {
+                Object::new()
+                    .set("struct_type", struct_type_json(s.struct_type))
+                    .set("generics", self.generics_json(&s.generics))
+                    .set("fields", list(s.fields.iter(), |i| id(i.def_id)))
+                    .set("fields_stripped", s.fields_stripped.to_json())
+                    .build()
+            }
+            clean::EnumItem(ref e) => {
+                Object::new()
+                    .set("generics", self.generics_json(&e.generics))
+                    .set("variants", list(e.variants.iter(), |i| id(i.def_id)))
+                    .set("variants_stripped", e.variants_stripped.to_json())
+                    .build()
+            }
+            clean::VariantItem(ref v) => {
+                match v.kind {
+                    clean::CLikeVariant => {
+                        Object::new().set("kind", string("c_like")).build()
+                    }
+                    clean::TupleVariant(ref tys) => {
+                        Object::new()
+                            .set("kind", string("tuple"))
+                            .set("types", list(tys.iter(), |t| self.type_json(t)))
+                            .build()
+                    }
+                    clean::StructVariant(ref s) => {
+                        Object::new()
+                            .set("kind", string("struct"))
+                            .set("struct_type", struct_type_json(s.struct_type))
+                            .set("fields", list(s.fields.iter(), |i| id(i.def_id)))
+                            .set("fields_stripped", s.fields_stripped.to_json())
+                            .build()
+                    }
+                }
+            }
+            clean::StructFieldItem(ref f) => {
+                Object::new().set("type", match *f {
+                    clean::TypedStructField(ref t) => self.type_json(t),
+                    clean::HiddenStructField => json::Null,
+                }).build()
+            }
+            clean::FunctionItem(ref f) | clean::ForeignFunctionItem(ref f) => {
+                Object::new()
+                    .set("fn_style", fn_style_json(f.fn_style))
+                    .set("generics", self.generics_json(&f.generics))
+                    .set("decl", self.decl_json(&f.decl))
+                    .build()
+            }
+            clean::TypedefItem(ref t) => {
+                Object::new()
+                    .set("type", self.type_json(&t.type_))
+                    .set("generics", self.generics_json(&t.generics))
+                    .build()
+            }
+            clean::StaticItem(ref s) | clean::ForeignStaticItem(ref s) => {
+                Object::new()
+                    .set("type", self.type_json(&s.type_))
+                    .set("mutable", mutable(s.mutability))
+                    .set("expr", s.expr.to_json())
+                    .build()
+            }
+            clean::TraitItem(ref t) => {
+                let required = t.methods.iter().filter_map(|m| {
+                    match *m { clean::Required(ref i) => Some(i), _ => None }
+                });
+                let provided = t.methods.iter().filter_map(|m| {
+                    match *m { clean::Provided(ref i) => Some(i), _ => None }
+                });
+                Object::new()
+                    .set("generics", self.generics_json(&t.generics))
+                    .set("parents", list(t.parents.iter(), |t| self.type_json(t)))
+                    .set("required", list(required, |i| id(i.def_id)))
+                    .set("provided", list(provided, |i| id(i.def_id)))
+                    .build()
+            }
+            clean::TyMethodItem(clean::TyMethod {
+                fn_style, ref decl, ref generics, ref self_
+            }) |
+            clean::MethodItem(clean::Method {
+                fn_style, ref decl, ref generics, ref self_
+            }) => {
+                Object::new()
+                    .set("fn_style", fn_style_json(fn_style))
+                    .set("self", self.self_json(self_))
+                    .set("generics", self.generics_json(generics))
+                    .set("decl", self.decl_json(decl))
+                    .build()
+            }
+            clean::ImplItem(ref i) => {
+                Object::new()
+                    .set("generics", self.generics_json(&i.generics))
+                    .set("trait", match i.trait_ {
+                        Some(ref t) => self.type_json(t),
+                        None => json::Null,
+                    })
+                    .set("for", self.type_json(&i.for_))
+                    .set("methods", list(i.methods.iter(), |i| id(i.def_id)))
+                    .set("derived", i.derived.to_json())
+                    .build()
+            }
+            clean::MacroItem(ref m) => {
+                Object::new()
+                    .set("source", m.source.to_json())
+                    .set("matchers", m.matchers.to_json())
+                    .build()
+            }
+            clean::ViewItemItem(ref v) => view_item_json(v),
+        }
+    }
+
+    fn type_json(&self, t: &clean::Type) -> Json {
+        let o = Object::new();
+        let o = match *t {
+            clean::ResolvedPath { ref path, ref typarams, did } => {
+                o.set("kind", string("resolved_path"))
+                 .set("id", id(did))
+                 .set("path", self.path_json(path))
+                 .set("bounds", match *typarams {
+                     Some(ref b) => list(b.iter(), |b| self.bound_json(b)),
+                     None => json::Null,
+                 })
+            }
+            clean::TyParamBinder(node) => {
+                o.set("kind", string("ty_param_binder"))
+                 .set("node", node.to_json())
+            }
+            clean::Generic(did) => {
+                o.set("kind", string("generic"))
+                 .set("id", id(did))
+                 .set("name", self.typarams.find(&did).map(|s| s.clone())
+                                               .to_json())
+            }
+            clean::Self(did) => {
+                o.set("kind", string("self")).set("id", id(did))
+            }
+            clean::Primitive(prim) => {
+                o.set("kind", string("primitive"))
+                 .set("name", prim_name(prim).to_json())
+            }
+            clean::Closure(ref decl, ref lifetime) => {
+                o.set("kind", string("closure"))
+                 .set("decl", self.closure_json(*decl))
+                 .set("lifetime", lifetime_json(lifetime))
+            }
+            clean::Proc(ref decl) => {
+                o.set("kind", string("proc"))
+                 .set("decl", self.closure_json(*decl))
+            }
+            clean::BareFunction(ref f) => {
+                o.set("kind", string("bare_function"))
+                 .set("fn_style", fn_style_json(f.fn_style))
+                 .set("generics", self.generics_json(&f.generics))
+                 .set("decl", self.decl_json(&f.decl))
+                 .set("abi", f.abi.to_json())
+            }
+            clean::Tuple(ref tys) => {
+                o.set("kind", string("tuple"))
+                 .set("types", list(tys.iter(), |t| self.type_json(t)))
+            }
+            clean::Vector(ref t) => {
+                o.set("kind", string("vector"))
+                 .set("type", self.type_json(*t))
+            }
+            clean::FixedVector(ref t, ref size) => {
+                o.set("kind", string("fixed_vector"))
+                 .set("type", self.type_json(*t))
+                 .set("size", size.to_json())
+            }
+            clean::String => o.set("kind", string("string")),
+            clean::Bool => o.set("kind", string("bool")),
+            clean::Unit => o.set("kind", string("unit")),
+            clean::Bottom => o.set("kind", string("bottom")),
+            clean::Unique(ref t) => {
+                o.set("kind", string("unique"))
+                 .set("type", self.type_json(*t))
+            }
+            clean::Managed(ref t) => {
+                o.set("kind", string("managed"))
+                 .set("type", self.type_json(*t))
+            }
+            clean::RawPointer(m, ref t) => {
+                o.set("kind", string("raw_pointer"))
+                 .set("mutable", mutable(m))
+                 .set("type", self.type_json(*t))
+            }
+            clean::BorrowedRef { ref lifetime, mutability, ref type_ } => {
+                o.set("kind", string("borrowed_ref"))
+                 .set("lifetime", lifetime_json(lifetime))
+                 .set("mutable", mutable(mutability))
+                 .set("type", self.type_json(*type_))
+            }
+        };
+        o.build()
+    }
+
+    fn path_json(&self, path: &clean::Path) -> Json {
+        Object::new()
+            .set("global", path.global.to_json())
+            .set("segments", list(path.segments.iter(), |s| {
+                Object::new()
+                    .set("name", s.name.to_json())
+                    .set("lifetimes", list(s.lifetimes.iter(),
+                                           |l| string(l.get_ref())))
+                    .set("types", list(s.types.iter(), |t| self.type_json(t)))
+                    .build()
+            }))
+            .build()
+    }
+
+    fn bound_json(&self, bound: &clean::TyParamBound) -> Json {
+        match *bound {
+            clean::RegionBound => {
+                Object::new().set("kind", string("region")).build()
+            }
+            clean::TraitBound(ref t) => {
+                Object::new()
+                    .set("kind", string("trait"))
+                    .set("trait", self.type_json(t))
+                    .build()
+            }
+        }
+    }
+
+    fn generics_json(&self, g: &clean::Generics) -> Json {
+        Object::new()
+            .set("lifetimes", list(g.lifetimes.iter(), |l| string(l.get_ref())))
+            .set("type_params", list(g.type_params.iter(), |tp| {
+                Object::new()
+                    .set("id", id(tp.did))
+                    .set("name", tp.name.to_json())
+                    .set("bounds", list(tp.bounds.iter(),
+                                        |b| self.bound_json(b)))
+                    .build()
+            }))
+            .build()
+    }
+
+    fn decl_json(&self, decl: &clean::FnDecl) -> Json {
+        Object::new()
+            .set("inputs", list(decl.inputs.values.iter(), |a| {
+                Object::new()
+                    .set("name", a.name.to_json())
+                    .set("type", self.type_json(&a.type_))
+                    .build()
+            }))
+            .set("output", self.type_json(&decl.output))
+            .set("diverges", match decl.cf {
+                clean::NoReturn => true,
+                clean::Return => false,
+            }.to_json())
+            .set("attrs", list(decl.attrs.iter(), attr_json))
+            .build()
+    }
+
+    fn closure_json(&self, c: &clean::ClosureDecl) -> Json {
+        Object::new()
+            .set("lifetimes", list(c.lifetimes.iter(), |l| string(l.get_ref())))
+            .set("once", match c.onceness {
+                ast::Once => true,
+                ast::Many => false,
+            }.to_json())
+            .set("fn_style", fn_style_json(c.fn_style))
+            .set("bounds", list(c.bounds.iter(), |b| self.bound_json(b)))
+            .set("decl", self.decl_json(&c.decl))
+            .build()
+    }
+
+    fn self_json(&self, s: &clean::SelfTy) -> Json {
+        let o = Object::new();
+        let o = match *s {
+            clean::SelfStatic => o.set("kind", string("static")),
+            clean::SelfValue => o.set("kind", string("value")),
+            clean::SelfOwned => o.set("kind", string("owned")),
+            clean::SelfBorrowed(ref lifetime, m) => {
+                o.set("kind", string("borrowed"))
+                 .set("lifetime", lifetime_json(lifetime))
+                 .set("mutable", mutable(m))
+            }
+        };
+        o.build()
+    }
+}
+
+/// A small builder for JSON objects.
+struct Object {
+    map: TreeMap<StrBuf, Json>,
+}
+
+impl Object {
+    fn new() -> Object { Object { map: TreeMap::new() } }
+
+    fn set(mut self, key: &str, value: Json) -> Object {
+        self.map.insert(key.to_strbuf(), value);
+        self
+    }
+
+    fn build(self) -> Json { json::Object(box self.map) }
+}
+
+fn list<T, I: Iterator<T>>(mut it: I, f: |T| -> Json) -> Json {
+    json::List(it.map(|x| f(x)).collect())
+}
+
+fn id_str(did: ast::DefId) -> StrBuf {
+    format_strbuf!("{}:{}", did.krate, did.node)
+}
+
+fn id(did: ast::DefId) -> Json { json::String(id_str(did)) }
+
+fn string(s: &str) -> Json { json::String(s.to_strbuf()) }
+
+fn id_lists(map: TreeMap<StrBuf, Vec<Json>>) -> Json {
+    let mut ret = TreeMap::new();
+    for (k, v) in map.move_iter() {
+        ret.insert(k, json::List(v));
+    }
+    json::Object(box ret)
+}
+
+fn path_json(fqp: &Vec<StrBuf>, kind: &str) -> Json {
+    Object::new()
+        .set("path", list(fqp.iter(), |s| s.to_json()))
+        .set("kind", string(kind))
+        .build()
+}
+
+fn is_view_item(item: &clean::Item) -> bool {
+    match item.inner { clean::ViewItemItem(..) => true, _ => false }
+}
+
+/// The items nested directly within `item` which get their own index entry.
+fn children<'a>(item: &'a clean::Item) -> Vec<&'a clean::Item> {
+    match item.inner {
+        clean::ModuleItem(ref m) => {
+            m.items.iter().filter(|i| !is_view_item(*i)).collect()
+        }
+        clean::StructItem(ref s) => s.fields.iter().collect(),
+        clean::EnumItem(ref e) => e.variants.iter().collect(),
+        clean::VariantItem(clean::Variant {
+            kind: clean::StructVariant(ref s)
+        }) => s.fields.iter().collect(),
+        clean::TraitItem(ref t) => t.methods.iter().map(|m| m.item()).collect(),
+        clean::ImplItem(ref i) => i.methods.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn item_kind(inner: &clean::ItemEnum) -> &'static str {
+    match *inner {
+        clean::ModuleItem(..) => "module",
+        clean::StructItem(..) => "struct",
+        clean::EnumItem(..) => "enum",
+        clean::VariantItem(..) => "variant",
+        clean::StructFieldItem(..) => "struct_field",
+        clean::FunctionItem(..) => "function",
+        clean::ForeignFunctionItem(..) => "foreign_function",
+        clean::TypedefItem(..) => "typedef",
+        clean::StaticItem(..) => "static",
+        clean::ForeignStaticItem(..) => "foreign_static",
+        clean::TraitItem(..) => "trait",
+        clean::TyMethodItem(..) => "ty_method",
+        clean::MethodItem(..) => "method",
+        clean::ImplItem(..) => "impl",
+        clean::MacroItem(..) => "macro",
+        clean::ViewItemItem(..) => "view_item",
+    }
+}
+
+fn kind_name(kind: clean::TypeKind) -> &'static str {
+    match kind {
+        clean::TypeEnum => "enum",
+        clean::TypeFunction => "function",
+        clean::TypeModule => "module",
+        clean::TypeStatic => "static",
+        clean::TypeStruct => "struct",
+        clean::TypeTrait => "trait",
+        clean::TypeVariant => "variant",
+    }
+}
+
+fn prim_name(prim: ast::PrimTy) -> StrBuf {
+    match prim {
+        ast::TyInt(t) => ast_util::int_ty_to_str(t, None, ast_util::AutoSuffix),
+        ast::TyUint(t) => ast_util::uint_ty_to_str(t, None, ast_util::AutoSuffix),
+        ast::TyFloat(t) => ast_util::float_ty_to_str(t),
+        ast::TyStr => "str".to_strbuf(),
+        ast::TyBool => "bool".to_strbuf(),
+        ast::TyChar => "char".to_strbuf(),
+    }
+}
+
+fn struct_type_json(t: doctree::StructType) -> Json {
+    string(match t {
+        doctree::Plain => "plain",
+        doctree::Tuple => "tuple",
+        doctree::Newtype => "newtype",
+        doctree::Unit => "unit",
+    })
+}
+
+fn fn_style_json(style: ast::FnStyle) -> Json {
+    string(match style {
+        ast::UnsafeFn => "unsafe",
+        ast::NormalFn => "normal",
+    })
+}
+
+fn mutable(m: clean::Mutability) -> Json {
+    json::Boolean(match m {
+        clean::Mutable => true,
+        clean::Immutable => false,
+    })
+}
+
+fn lifetime_json(l: &Option<clean::Lifetime>) -> Json {
+    match *l {
+        Some(ref l) => string(l.get_ref()),
+        None => json::Null,
+    }
+}
+
+fn span_json(sp: &clean::Span) -> Json {
+    let pos = |line: uint, col: uint| {
+        Object::new()
+            .set("line", line.to_json())
+            .set("col", col.to_json())
+            .build()
+    };
+    Object::new()
+        .set("filename", sp.filename.to_json())
+        .set("lo", pos(sp.loline, sp.locol))
+        .set("hi", pos(sp.hiline, sp.hicol))
+        .build()
+}
+
+fn attr_json(attr: &clean::Attribute) -> Json {
+    match *attr {
+        clean::Word(ref name) => {
+            Object::new()
+                .set("kind", string("word"))
+                .set("name", name.to_json())
+                .build()
+        }
+        clean::List(ref name, ref attrs) => {
+            Object::new()
+                .set("kind", string("list"))
+                .set("name", name.to_json())
+                .set("items", list(attrs.iter(), attr_json))
+                .build()
+        }
+        clean::NameValue(ref name, ref value) => {
+            Object::new()
+                .set("kind", string("name_value"))
+                .set("name", name.to_json())
+                .set("value", value.to_json())
+                .build()
+        }
+    }
+}
+
+fn view_item_json(v: &clean::ViewItem) -> Json {
+    let source = |s: &clean::ImportSource| {
+        let segments = s.path.segments.iter().map(|s| s.name.to_json());
+        Object::new()
+            .set("path", json::List(segments.collect()))
+            .set("global", s.path.global.to_json())
+            .set("id", match s.did { Some(did) => id(did), None => json::Null })
+            .build()
+    };
+    match v.inner {
+        clean::ExternCrate(ref name, ref path, _) => {
+            Object::new()
+                .set("kind", string("extern_crate"))
+                .set("name", name.to_json())
+                .set("source", path.to_json())
+                .build()
+        }
+        clean::Import(clean::SimpleImport(ref name, ref src)) => {
+            Object::new()
+                .set("kind", string("simple"))
+                .set("name", name.to_json())
+                .set("source", source(src))
+                .build()
+        }
+        clean::Import(clean::GlobImport(ref src)) => {
+            Object::new()
+                .set("kind", string("glob"))
+                .set("source", source(src))
+                .build()
+        }
+        clean::Import(clean::ImportList(ref src, ref names)) => {
+            Object::new()
+                .set("kind", string("list"))
+                .set("source", source(src))
+                .set("names", list(names.iter(), |n| {
+                    Object::new()
+                        .set("name", n.name.to_json())
+                        .set("id", match n.source {
+                            Some(did) => id(did),
+                            None => json::Null,
+                        })
+                        .build()
+                }))
+                .build()
+        }
+    }
+}
@@ -31,13 +31,16 @@ extern crate libc;
 use std::io;
 use std::io::{File, MemWriter};
 use std::str;
+use collections::HashMap;
 use serialize::{json, Decodable, Encodable};
+use serialize::json::ToJson;
 
 // reexported from `clean` so it can be easily updated with the mod itself
 pub use clean::SCHEMA_VERSION;
 
 pub mod clean;
 pub mod core;
+pub mod coverage;
 pub mod doctree;
 pub mod fold;
 pub mod html {
@@ -50,12 +53,13 @@ pub mod html {
     pub mod render;
     pub mod toc;
 }
+pub mod json_api;
 pub mod markdown;
 pub mod passes;
 pub mod plugins;
 pub mod visit_ast;
 pub mod test;
-mod flock;
+pub mod xref;
 
 type Pass = (&'static str,                                      // name
              fn(clean::Crate) -> plugins::PluginResult,         // fn
@@ -126,7 +130,17 @@ pub fn opts() -> Vec<getopts::OptGroup> {
         optmulti("", "markdown-after-content",
                  "files to include inline between the content and </body> of a rendered \
                  Markdown file",
-                 "FILES")
+                 "FILES"),
+        optmulti("", "extern-html-root-url",
+                 "base URL to use when linking to the documentation of an external crate",
+                 "CRATE=URL"),
+        optflag("", "show-coverage",
+                "print the percentage of items which are documented instead \
+                 of generating documentation (as json with `-w json`)"),
+        optopt("", "coverage-threshold",
+               "fail if less than this percentage of items are documented \
+                (implies --show-coverage)",
+               "PERCENT")
     )
 }
 
@@ -175,6 +189,14 @@ pub fn main_args(args: &[StrBuf]) -> int {
     let output = matches.opt_str("o").map(|s| Path::new(s));
     let cfgs = matches.opt_strs("cfg");
 
+    let extern_urls = match parse_extern_html_roots(&matches) {
+        Ok(urls) => urls,
+        Err(s) => {
+            println!("{}", s);
+            return 1;
+        }
+    };
+
     match (should_test, markdown_input) {
         (true, true) => {
             return markdown::test(input,
@@ -214,11 +236,17 @@ pub fn main_args(args: &[StrBuf]) -> int {
         }
     };
 
+    if matches.opt_present("show-coverage") ||
+       matches.opt_present("coverage-threshold") {
+        return show_coverage(&krate, &matches);
+    }
+
     info!("going to format");
     let started = time::precise_time_ns();
     match matches.opt_str("w").as_ref().map(|s| s.as_slice()) {
         Some("html") | None => {
-            match html::render::run(krate, output.unwrap_or(Path::new("doc"))) {
+            match html::render::run(krate, output.unwrap_or(Path::new("doc")),
+                                    extern_urls) {
                 Ok(()) => {}
                 Err(e) => fail!("failed to generate documentation: {}", e),
             }
@@ -240,6 +268,64 @@ pub fn main_args(args: &[StrBuf]) -> int {
     return 0;
 }
 
+/// Prints the documentation coverage of `krate`, returning a failing exit
+/// status if it falls short of `--coverage-threshold`.
+fn show_coverage(krate: &clean::Crate, matches: &getopts::Matches) -> int {
+    let threshold = match matches.opt_str("coverage-threshold") {
+        Some(s) => match from_str::<f64>(s.as_slice()) {
+            Some(t) => Some(t),
+            None => {
+                println!("invalid --coverage-threshold: {}", s);
+                return 1;
+            }
+        },
+        None => None,
+    };
+
+    let cov = coverage::measure(krate);
+    let mut stdout = io::stdout();
+    let res = match matches.opt_str("w").as_ref().map(|s| s.as_slice()) {
+        Some("json") => cov.to_json().to_writer(&mut stdout),
+        _ => cov.write_table(&mut stdout),
+    };
+    match res {
+        Ok(()) => {}
+        Err(e) => fail!("failed to write coverage report: {}", e),
+    }
+
+    let percentage = cov.total().percentage();
+    match threshold {
+        Some(t) if percentage < t => {
+            // stdout may hold the json report, so complain on stderr.
+            let _ = writeln!(&mut io::stderr(),
+                             "documentation coverage {:.1f}% is below the \
+                              threshold of {:.1f}%", percentage, t);
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Extracts the `--extern-html-root-url` arguments, returning a map from crate
+/// names to the URLs their documentation lives at.
+fn parse_extern_html_roots(matches: &getopts::Matches)
+                           -> Result<HashMap<StrBuf, StrBuf>, StrBuf> {
+    let mut externs = HashMap::new();
+    for arg in matches.opt_strs("extern-html-root-url").iter() {
+        let mut parts = arg.as_slice().splitn('=', 1);
+        let name = parts.next().unwrap();
+        let url = match parts.next() {
+            Some(url) if name.len() > 0 && url.len() > 0 => url,
+            _ => {
+                return Err(format_strbuf!("--extern-html-root-url expects \
+                                           CRATE=URL, found `{}`", arg))
+            }
+        };
+        externs.insert(name.to_strbuf(), url.to_strbuf());
+    }
+    Ok(externs)
+}
+
 /// Looks inside the command line arguments to extract the relevant input format
 /// and files and then generates the necessary rustdoc output for formatting.
 fn acquire_input(input: &str,
@@ -400,6 +486,7 @@ fn json_output(krate: clean::Crate, res: Vec<plugins::PluginJson> ,
     // {
     //   "schema": version,
     //   "crate": { parsed crate ... },
+    //   "api": { documented, versioned description of the crate ... },
     //   "plugins": { output of plugins ... }
     // }
     let mut json = box collections::TreeMap::new();
@@ -430,7 +517,18 @@ fn json_output(krate: clean::Crate, res: Vec<plugins::PluginJson> ,
         Err(e) => fail!("Rust generated JSON is invalid: {:?}", e)
     };
 
+    let api_json = {
+        let analysis = analysiskey.get();
+        let paths = analysis.as_ref().map(|a| a.external_paths.borrow());
+        let paths = match paths {
+            Some(ref p) => p.as_ref(),
+            None => None,
+        };
+        json_api::krate(&krate, paths)
+    };
+
     json.insert("crate".to_strbuf(), crate_json);
+    json.insert("api".to_strbuf(), api_json);
     json.insert("plugins".to_strbuf(), json::Object(plugins_json));
 
     let mut file = try!(File::create(&dst));
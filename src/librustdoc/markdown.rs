@@ -13,7 +13,6 @@ use std::{str, io};
 use std::strbuf::StrBuf;
 
 use getopts;
-use testing;
 
 use html::escape::Escape;
 use html::markdown::{MarkdownWithToc, find_testable_code, reset_headers};
@@ -170,12 +169,10 @@ pub fn render(input: &str, mut output: Path, matches: &getopts::Matches) -> int
 }
 
 /// Run any tests/code examples in the markdown file `input`.
-pub fn test(input: &str, libs: HashSet<Path>, mut test_args: Vec<StrBuf>) -> int {
+pub fn test(input: &str, libs: HashSet<Path>, test_args: Vec<StrBuf>) -> int {
     let input_str = load_or_return!(input, 1, 2);
 
     let mut collector = Collector::new(input.to_strbuf(), libs, true, true);
     find_testable_code(input_str.as_slice(), &mut collector);
-    test_args.unshift("rustdoctest".to_strbuf());
-    testing::test_main(test_args.as_slice(), collector.tests);
-    0
+    collector.run_tests(test_args)
 }
@@ -12,12 +12,16 @@ use std::cell::RefCell;
 use std::char;
 use std::io;
 use std::io::{Command, TempDir};
+use std::io::process;
+use std::mem;
 use std::os;
 use std::str;
 use std::strbuf::StrBuf;
+use std::task;
 use std::unstable::dynamic_lib::DynamicLibrary;
 
 use collections::{HashSet, HashMap};
+use libc;
 use testing;
 use rustc::back::link;
 use rustc::driver::config;
@@ -40,7 +44,7 @@ use visit_ast::RustdocVisitor;
 pub fn run(input: &str,
            cfgs: Vec<StrBuf>,
            libs: HashSet<Path>,
-           mut test_args: Vec<StrBuf>)
+           test_args: Vec<StrBuf>)
            -> int {
     let input_path = Path::new(input);
     let input = driver::FileInput(input_path.clone());
@@ -92,31 +96,17 @@ pub fn run(input: &str,
                                        false,
                                        false);
     collector.fold_crate(krate);
-
-    test_args.unshift("rustdoctest".to_strbuf());
-
-    testing::test_main(test_args.as_slice(),
-                       collector.tests.move_iter().collect());
-    0
+    collector.run_tests(test_args)
 }
 
 fn runtest(test: &str, cratename: &str, libs: HashSet<Path>, should_fail: bool,
-           no_run: bool, loose_feature_gating: bool) {
-    let test = maketest(test, cratename, loose_feature_gating);
-    let input = driver::StrInput(test.to_strbuf());
+           no_run: bool, compile_fail: bool, loose_feature_gating: bool) {
+    let expected_errors = expected_errors(test);
+    let test = maketest(test, cratename, loose_feature_gating, !compile_fail);
 
-    let sessopts = config::Options {
-        maybe_sysroot: Some(os::self_exe_path().unwrap().dir_path()),
-        addl_lib_search_paths: RefCell::new(libs),
-        crate_types: vec!(config::CrateTypeExecutable),
-        output_types: vec!(link::OutputTypeExe),
-        no_trans: no_run,
-        cg: config::CodegenOptions {
-            prefer_dynamic: true,
-            .. config::basic_codegen_options()
-        },
-        ..config::basic_options().clone()
-    };
+    if compile_fail {
+        return check_compile_fail(test, libs, expected_errors);
+    }
 
     // Shuffle around a few input and output handles here. We're going to pass
     // an explicit handle into rustc to collect output messages, but we also
@@ -138,51 +128,15 @@ fn runtest(test: &str, cratename: &str, libs: HashSet<Path>, should_fail: bool,
         let mut err = old.unwrap_or(box io::stderr() as Box<Writer:Send>);
         io::util::copy(&mut p, &mut err).unwrap();
     });
-    let emitter = diagnostic::EmitterWriter::new(box w2);
 
     // Compile the code
-    let codemap = CodeMap::new();
-    let diagnostic_handler = diagnostic::mk_handler(box emitter);
-    let span_diagnostic_handler =
-        diagnostic::mk_span_handler(diagnostic_handler, codemap);
-
-    let sess = session::build_session_(sessopts,
-                                      None,
-                                      span_diagnostic_handler);
-
-    let outdir = TempDir::new("rustdoctest").expect("rustdoc needs a tempdir");
-    let out = Some(outdir.path().clone());
-    let cfg = config::build_configuration(&sess);
-    let libdir = sess.target_filesearch().get_lib_path();
-    driver::compile_input(sess, cfg, &input, &out, &None);
+    let (outdir, libdir) = compile(test, libs, no_run, box w2);
 
     if no_run { return }
 
     // Run the code!
-    //
-    // We're careful to prepend the *target* dylib search path to the child's
-    // environment to ensure that the target loads the right libraries at
-    // runtime. It would be a sad day if the *host* libraries were loaded as a
-    // mistake.
     let exe = outdir.path().join("rust_out");
-    let env = {
-        let mut path = DynamicLibrary::search_path();
-        path.insert(0, libdir.clone());
-
-        // Remove the previous dylib search path var
-        let var = DynamicLibrary::envvar();
-        let mut env: Vec<(~str,~str)> = os::env().move_iter().collect();
-        match env.iter().position(|&(ref k, _)| k.as_slice() == var) {
-            Some(i) => { env.remove(i); }
-            None => {}
-        };
-
-        // Add the new dylib search path var
-        let newpath = DynamicLibrary::create_path(path.as_slice());
-        env.push((var.to_owned(),
-                  str::from_utf8(newpath.as_slice()).unwrap().to_owned()));
-        env
-    };
+    let env = test_env(&libdir);
     match Command::new(exe).env(env.as_slice()).output() {
         Err(e) => fail!("couldn't run the test: {}{}", e,
                         if e.kind == io::PermissionDenied {
@@ -199,12 +153,208 @@ fn runtest(test: &str, cratename: &str, libs: HashSet<Path>, should_fail: bool,
     }
 }
 
-fn maketest(s: &str, cratename: &str, loose_feature_gating: bool) -> StrBuf {
+/// Checks that `prog` fails to compile, with diagnostics mentioning each of
+/// `expected_errors`.
+fn check_compile_fail(prog: StrBuf, libs: HashSet<Path>,
+                      expected_errors: Vec<StrBuf>) {
+    // Both the diagnostics and the message of the compiler's failure end up
+    // in `output`.
+    let (tx, rx) = channel();
+    let w1 = io::ChanWriter::new(tx);
+    let w2 = w1.clone();
+    let res = task::try(proc() {
+        io::stdio::set_stderr(box w1);
+        compile(prog, libs, true, box w2);
+    });
+    let output = io::ChanReader::new(rx).read_to_str().unwrap();
+
+    if res.is_ok() {
+        fail!("test compiled successfully when it should have failed");
+    }
+    for expected in expected_errors.iter() {
+        if !output.contains(expected.as_slice()) {
+            fail!("expected error `{}` was not reported; the compiler \
+                   said:\n{}", expected, output);
+        }
+    }
+}
+
+/// The diagnostics a `compile_fail` example expects, as given by its
+/// `//~ ERROR <message>` comments.
+fn expected_errors(test: &str) -> Vec<StrBuf> {
+    static MARKER: &'static str = "//~ ERROR";
+    test.lines().filter_map(|line| {
+        line.find_str(MARKER).map(|i| {
+            line.slice_from(i + MARKER.len()).trim().to_strbuf()
+        })
+    }).filter(|msg| msg.len() > 0).collect()
+}
+
+/// Compiles `prog` into an executable named `rust_out` in a fresh temporary
+/// directory, failing the task if it doesn't compile. Compiler diagnostics
+/// are written to `err`. Returns the directory along with the target library
+/// path the executable needs at runtime.
+fn compile(prog: StrBuf, libs: HashSet<Path>, no_run: bool,
+           err: Box<Writer:Send>) -> (TempDir, Path) {
+    let input = driver::StrInput(prog);
+
+    let sessopts = config::Options {
+        maybe_sysroot: Some(os::self_exe_path().unwrap().dir_path()),
+        addl_lib_search_paths: RefCell::new(libs),
+        crate_types: vec!(config::CrateTypeExecutable),
+        output_types: vec!(link::OutputTypeExe),
+        no_trans: no_run,
+        cg: config::CodegenOptions {
+            prefer_dynamic: true,
+            .. config::basic_codegen_options()
+        },
+        ..config::basic_options().clone()
+    };
+
+    let emitter = diagnostic::EmitterWriter::new(err);
+    let codemap = CodeMap::new();
+    let diagnostic_handler = diagnostic::mk_handler(box emitter);
+    let span_diagnostic_handler =
+        diagnostic::mk_span_handler(diagnostic_handler, codemap);
+
+    let sess = session::build_session_(sessopts,
+                                      None,
+                                      span_diagnostic_handler);
+
+    let outdir = TempDir::new("rustdoctest").expect("rustdoc needs a tempdir");
+    let out = Some(outdir.path().clone());
+    let cfg = config::build_configuration(&sess);
+    let libdir = sess.target_filesearch().get_lib_path();
+    driver::compile_input(sess, cfg, &input, &out, &None);
+    (outdir, libdir)
+}
+
+/// The environment to run a compiled test in.
+///
+/// We're careful to prepend the *target* dylib search path to the child's
+/// environment to ensure that the target loads the right libraries at
+/// runtime. It would be a sad day if the *host* libraries were loaded as a
+/// mistake.
+fn test_env(libdir: &Path) -> Vec<(~str, ~str)> {
+    let mut path = DynamicLibrary::search_path();
+    path.insert(0, libdir.clone());
+
+    // Remove the previous dylib search path var
+    let var = DynamicLibrary::envvar();
+    let mut env: Vec<(~str,~str)> = os::env().move_iter().collect();
+    match env.iter().position(|&(ref k, _)| k.as_slice() == var) {
+        Some(i) => { env.remove(i); }
+        None => {}
+    };
+
+    // Add the new dylib search path var
+    let newpath = DynamicLibrary::create_path(path.as_slice());
+    env.push((var.to_owned(),
+              str::from_utf8(newpath.as_slice()).unwrap().to_owned()));
+    env
+}
+
+/// Compiles the merged examples into a single test harness and runs it,
+/// passing `test_args` along to it. Returns whether all of the examples
+/// passed, or `None` if the harness failed to compile.
+fn run_merged(tests: &[MergedTest], cratename: &str, libs: HashSet<Path>,
+              loose_feature_gating: bool, test_args: &[StrBuf]) -> Option<bool> {
+    let prog = make_merged_test(tests, cratename, loose_feature_gating);
+    debug!("Merged doctests:\n{}", prog);
+
+    // The diagnostics of a harness which doesn't compile are thrown away:
+    // every example is then compiled on its own, which attributes the errors
+    // to the examples causing them.
+    let compiled = task::try(proc() {
+        io::stdio::set_stderr(box io::util::NullWriter);
+        compile(prog, libs, false, box io::util::NullWriter)
+    });
+    let (outdir, libdir) = match compiled {
+        Ok(pair) => pair,
+        Err(..) => return None,
+    };
+
+    let exe = outdir.path().join("rust_out");
+    let env = test_env(&libdir);
+    let args = test_args.iter().map(|s| s.as_slice()).collect::<Vec<&str>>();
+    match Command::new(exe).args(args.as_slice())
+                           .env(env.as_slice())
+                           .stdout(process::InheritFd(libc::STDOUT_FILENO))
+                           .stderr(process::InheritFd(libc::STDERR_FILENO))
+                           .status() {
+        Ok(status) => Some(status.success()),
+        Err(e) => fail!("couldn't run the merged doctests: {}", e),
+    }
+}
+
+/// Whether an example has to be compiled as a crate of its own rather than
+/// merged with the others: it defines `main` itself, uses crate attributes
+/// such as `#![feature]`, or links to crates.
+fn needs_isolation(s: &str) -> bool {
+    s.contains("fn main") || s.contains("#![") || s.contains("extern crate")
+}
+
+/// Generates a crate running every example of `tests` as a libtest test. Each
+/// example becomes the `run` function of a module of its own.
+fn make_merged_test(tests: &[MergedTest], cratename: &str,
+                    loose_feature_gating: bool) -> StrBuf {
     let mut prog = StrBuf::from_str(r"
 #![deny(warnings)]
 #![allow(unused_variable, dead_assignment, unused_mut, attribute_usage, dead_code)]
 ");
 
+    if loose_feature_gating {
+        // FIXME #12773: see `maketest`
+        prog.push_str("#![feature(macro_rules, globs, struct_variant, managed_boxes) ]\n");
+    }
+
+    prog.push_str("extern crate test;\n");
+
+    for (i, t) in tests.iter().enumerate() {
+        prog.push_str(format!("mod __doctest_{} {{\n", i));
+        // Items of the crate root aren't visible from within the module, so
+        // the crate being documented is linked in each module using it.
+        if t.code.as_slice().contains(cratename) {
+            prog.push_str(format!("extern crate {};\n", cratename));
+        }
+        prog.push_str("pub fn run() {\n");
+        prog.push_str(t.code.as_slice());
+        prog.push_str("\n}\n}\n");
+    }
+
+    prog.push_str("fn main() {\n    let tests = vec!(\n");
+    for (i, t) in tests.iter().enumerate() {
+        prog.push_str(format!(
+"        test::TestDescAndFn {{
+            desc: test::TestDesc {{
+                name: test::StaticTestName(\"{name}\"),
+                ignore: false,
+                should_fail: {should_fail},
+            }},
+            testfn: test::StaticTestFn(__doctest_{i}::run),
+        }},\n",
+            name = t.name.as_slice().escape_default(),
+            should_fail = t.should_fail,
+            i = i));
+    }
+    prog.push_str("    );\n");
+    prog.push_str("    let args = std::os::args().iter().map(|a| a.to_strbuf())\n");
+    prog.push_str("                              .collect::<Vec<StrBuf>>();\n");
+    prog.push_str("    test::test_main(args.as_slice(), tests);\n}\n");
+
+    return prog
+}
+
+fn maketest(s: &str, cratename: &str, loose_feature_gating: bool,
+            deny_warnings: bool) -> StrBuf {
+    let mut prog = StrBuf::from_str("\n");
+    // A compile_fail example has to fail because of an error, not a lint.
+    if deny_warnings {
+        prog.push_str("#![deny(warnings)]\n");
+    }
+    prog.push_str("#![allow(unused_variable, dead_assignment, unused_mut, attribute_usage, \
+                   dead_code)]\n");
+
     if loose_feature_gating {
         // FIXME #12773: avoid inserting these when the tutorial & manual
         // etc. have been updated to not use them so prolifically.
@@ -227,8 +377,17 @@ fn maketest(s: &str, cratename: &str, loose_feature_gating: bool) -> StrBuf {
     return prog
 }
 
+/// An example which is compiled together with the other examples of the
+/// crate rather than on its own.
+struct MergedTest {
+    name: StrBuf,
+    code: StrBuf,
+    should_fail: bool,
+}
+
 pub struct Collector {
     pub tests: Vec<testing::TestDescAndFn>,
+    merged: Vec<MergedTest>,
     names: Vec<StrBuf>,
     libs: HashSet<Path>,
     cnt: uint,
@@ -244,6 +403,7 @@ impl Collector {
                use_headers: bool, loose_feature_gating: bool) -> Collector {
         Collector {
             tests: Vec::new(),
+            merged: Vec::new(),
             names: Vec::new(),
             libs: libs,
             cnt: 0,
@@ -255,7 +415,8 @@ impl Collector {
         }
     }
 
-    pub fn add_test(&mut self, test: StrBuf, should_fail: bool, no_run: bool, should_ignore: bool) {
+    pub fn add_test(&mut self, test: StrBuf, should_fail: bool, no_run: bool,
+                    should_ignore: bool, compile_fail: bool) {
         let name = if self.use_headers {
             let s = self.current_header.as_ref().map(|s| s.as_slice()).unwrap_or("");
             format_strbuf!("{}_{}", s, self.cnt)
@@ -263,6 +424,23 @@ impl Collector {
             format_strbuf!("{}_{}", self.names.connect("::"), self.cnt)
         };
         self.cnt += 1;
+        if !no_run && !should_ignore && !compile_fail &&
+           !needs_isolation(test.as_slice()) {
+            debug!("Merging test {}: {}", name, test);
+            self.merged.push(MergedTest {
+                name: name,
+                code: test,
+                should_fail: should_fail,
+            });
+            return
+        }
+        self.add_isolated_test(name, test, should_fail, no_run, should_ignore,
+                               compile_fail);
+    }
+
+    fn add_isolated_test(&mut self, name: StrBuf, test: StrBuf,
+                         should_fail: bool, no_run: bool, should_ignore: bool,
+                         compile_fail: bool) {
         let libs = self.libs.clone();
         let cratename = self.cratename.to_owned();
         let loose_feature_gating = self.loose_feature_gating;
@@ -279,11 +457,47 @@ impl Collector {
                         libs,
                         should_fail,
                         no_run,
+                        compile_fail,
                         loose_feature_gating);
             }),
         });
     }
 
+    /// Runs all of the collected tests, returning the exit status.
+    ///
+    /// The examples which can be merged are compiled into a single harness
+    /// and run first; if that harness doesn't compile they are run one by
+    /// one along with the others instead.
+    pub fn run_tests(mut self, mut test_args: Vec<StrBuf>) -> int {
+        let merged = mem::replace(&mut self.merged, Vec::new());
+        let result = if merged.len() > 0 {
+            run_merged(merged.as_slice(), self.cratename.as_slice(),
+                       self.libs.clone(), self.loose_feature_gating,
+                       test_args.as_slice())
+        } else {
+            None
+        };
+        let status = match result {
+            Some(true) => 0,
+            Some(false) => 101,
+            None => {
+                for t in merged.move_iter() {
+                    self.add_isolated_test(t.name, t.code, t.should_fail,
+                                           false, false, false);
+                }
+                0
+            }
+        };
+
+        // Don't report on zero remaining tests after the merged harness ran.
+        if result.is_none() || self.tests.len() > 0 {
+            test_args.unshift("rustdoctest".to_strbuf());
+            testing::test_main(test_args.as_slice(),
+                               self.tests.move_iter().collect());
+        }
+        status
+    }
+
     pub fn register_header(&mut self, name: &str, level: u32) {
         if self.use_headers && level == 1 {
             // we use these headings as test names, so it's good if
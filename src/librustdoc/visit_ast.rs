@@ -301,12 +301,18 @@ impl<'a> RustdocVisitor<'a> {
             ast::ItemForeignMod(ref fm) => {
                 om.foreigns.push(fm.clone());
             }
-            ast::ItemMac(ref _m) => {
+            // Only the definitions of exported macros are left after
+            // expansion.
+            ast::ItemMac(ref m) => {
+                let tts = match m.node {
+                    ast::MacInvocTT(_, ref tts, _) => tts.clone(),
+                };
                 om.macros.push(Macro {
                     id: item.id,
                     attrs: item.attrs.iter().map(|x| *x).collect(),
                     name: item.ident,
                     where: item.span,
+                    tts: tts,
                 })
             }
         }
@@ -0,0 +1,135 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Cross-reference information for the source view.
+//!
+//! While the typed context is still around, every path in the crate which the
+//! resolver could resolve is recorded along with the definition it refers to.
+//! The HTML renderer uses this to link identifiers in the rendered source to
+//! their definitions, and to list the references to each item in the search
+//! index.
+
+use collections::HashMap;
+use rustc::middle::ty;
+use syntax::ast;
+use syntax::ast_util;
+use syntax::codemap::{Pos, Span};
+use syntax::parse::token;
+use syntax::visit;
+use syntax::visit::Visitor;
+
+/// A path in the source referring to a definition.
+pub struct Reference {
+    /// Byte offset of the start of the path in its file.
+    pub lo: uint,
+    /// Byte offset of the end of the path in its file.
+    pub hi: uint,
+    /// The line the path is on.
+    pub line: uint,
+    /// The last segment of the path, the identifier naming the definition.
+    pub name: StrBuf,
+    pub def: ast::DefId,
+}
+
+pub struct CrossReferences {
+    /// The references in each source file, sorted by position.
+    pub refs: HashMap<StrBuf, Vec<Reference>>,
+    /// The file and line of each local definition which is referred to.
+    pub defs: HashMap<ast::DefId, (StrBuf, uint)>,
+}
+
+impl CrossReferences {
+    pub fn new() -> CrossReferences {
+        CrossReferences { refs: HashMap::new(), defs: HashMap::new() }
+    }
+
+    /// The file and line of every reference to each definition.
+    pub fn uses(&self) -> HashMap<ast::DefId, Vec<(StrBuf, uint)>> {
+        let mut uses = HashMap::new();
+        for (file, refs) in self.refs.iter() {
+            for r in refs.iter() {
+                uses.find_or_insert(r.def, Vec::new())
+                    .push((file.clone(), r.line));
+            }
+        }
+        uses
+    }
+}
+
+/// Collects the cross references of `krate`.
+pub fn collect(tcx: &ty::ctxt, krate: &ast::Crate) -> CrossReferences {
+    let mut v = Collector { tcx: tcx, xrefs: CrossReferences::new() };
+    visit::walk_crate(&mut v, krate, ());
+    for (_, refs) in v.xrefs.refs.mut_iter() {
+        refs.sort_by(|a, b| a.lo.cmp(&b.lo));
+    }
+    v.xrefs
+}
+
+struct Collector<'a> {
+    tcx: &'a ty::ctxt,
+    xrefs: CrossReferences,
+}
+
+impl<'a> Collector<'a> {
+    fn record(&mut self, path: &ast::Path, id: ast::NodeId) {
+        // Paths produced by macro expansion point into the macro's invocation
+        // or definition rather than at anything written there.
+        if path.span.expn_info.is_some() { return }
+
+        let def = match self.tcx.def_map.borrow().find(&id) {
+            Some(&def) => def,
+            None => return,
+        };
+        let did = match def {
+            ast::DefPrimTy(..) | ast::DefSelfTy(..) | ast::DefRegion(..) |
+            ast::DefTyParamBinder(..) | ast::DefLabel(..) => return,
+            def => ast_util::def_id_of_def(def),
+        };
+
+        if ast_util::is_local(did) && !self.xrefs.defs.contains_key(&did) {
+            match self.tcx.map.opt_span(did.node) {
+                Some(sp) => {
+                    let (file, line, _) = self.position(sp);
+                    self.xrefs.defs.insert(did, (file, line));
+                }
+                None => {}
+            }
+        }
+
+        let (file, line, lo) = self.position(path.span);
+        let hi = lo + (path.span.hi - path.span.lo).to_uint();
+        let name = match path.segments.last() {
+            Some(seg) => token::get_ident(seg.identifier).get().to_strbuf(),
+            None => return,
+        };
+        self.xrefs.refs.find_or_insert(file, Vec::new()).push(Reference {
+            lo: lo,
+            hi: hi,
+            line: line,
+            name: name,
+            def: did,
+        });
+    }
+
+    /// The file, line and byte offset within the file of the start of `sp`.
+    fn position(&self, sp: Span) -> (StrBuf, uint, uint) {
+        let loc = self.tcx.sess.codemap().lookup_char_pos(sp.lo);
+        let offset = (sp.lo - loc.file.start_pos).to_uint();
+        (loc.file.name.clone(), loc.line, offset)
+    }
+}
+
+impl<'a> Visitor<()> for Collector<'a> {
+    fn visit_path(&mut self, path: &ast::Path, id: ast::NodeId, _: ()) {
+        self.record(path, id);
+        visit::walk_path(self, path, ());
+    }
+}
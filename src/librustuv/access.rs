@@ -17,7 +17,7 @@
 use alloc::arc::Arc;
 use std::mem;
 use std::rt::local::Local;
-use std::rt::task::{BlockedTask, Task};
+use std::rt::task::{BlockedTask, Task, OnIo};
 use std::ty::Unsafe;
 
 use homing::HomingMissile;
@@ -57,7 +57,7 @@ impl Access {
 
         if inner.held {
             let t: Box<Task> = Local::take();
-            t.deschedule(1, |task| {
+            t.deschedule_on(OnIo, 1, |task| {
                 inner.queue.push((task, token));
                 Ok(())
             });
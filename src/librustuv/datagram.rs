@@ -0,0 +1,178 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Unix datagram sockets
+//!
+//! libuv has no support for these, so the socket is created and used directly,
+//! and the task only waits on the event loop (through a poll handle) when a
+//! call would block.
+
+use alloc::arc::Arc;
+use libc::{c_int, c_void};
+use libc;
+use std::c_str::{CString, ToCStr};
+use std::cmp;
+use std::io::IoError;
+use std::io::poll::{Readable, Writable};
+use std::io;
+use std::mem;
+use std::rt::rtio;
+
+use homing::{HomingIO, HomeHandle};
+use poll;
+use uvio::UvIoFactory;
+use Loop;
+
+pub struct UnixDatagramWatcher {
+    inner: Arc<Inner>,
+    home: HomeHandle,
+    loop_: Loop,
+}
+
+struct Inner {
+    fd: c_int,
+    path: Option<CString>,
+}
+
+impl UnixDatagramWatcher {
+    pub fn new(io: &mut UvIoFactory, path: Option<&CString>)
+               -> Result<UnixDatagramWatcher, IoError> {
+        let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_DGRAM, 0) };
+        if fd == -1 { return Err(IoError::last_error()) }
+        poll::set_nonblocking(fd);
+        let mut inner = Inner { fd: fd, path: None };
+
+        match path {
+            Some(path) => {
+                let (addr, len) = try!(addr_to_sockaddr_un(path));
+                let addrp = &addr as *_ as *libc::sockaddr;
+                match unsafe {
+                    libc::bind(fd, addrp, len as libc::socklen_t)
+                } {
+                    -1 => return Err(IoError::last_error()),
+                    _ => inner.path = Some(path.clone()),
+                }
+            }
+            None => {}
+        }
+
+        Ok(UnixDatagramWatcher {
+            inner: Arc::new(inner),
+            home: io.make_handle(),
+            loop_: Loop::wrap(io.uv_loop()),
+        })
+    }
+}
+
+impl HomingIO for UnixDatagramWatcher {
+    fn home<'r>(&'r mut self) -> &'r mut HomeHandle { &mut self.home }
+}
+
+impl rtio::RtioUnixDatagram for UnixDatagramWatcher {
+    fn recv_from(&mut self, buf: &mut [u8])
+                 -> Result<(uint, Option<CString>), IoError> {
+        let _m = self.fire_homing_missile();
+        let fd = self.inner.fd;
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let storagep = &mut storage as *mut _ as *mut libc::sockaddr;
+        let mut addrlen = 0;
+        let n = try!(poll::nonblocking(&self.loop_, fd, Readable, || unsafe {
+            addrlen = mem::size_of::<libc::sockaddr_storage>() as
+                            libc::socklen_t;
+            libc::recvfrom(fd, buf.as_mut_ptr() as *mut c_void,
+                           buf.len() as libc::size_t, 0,
+                           storagep, &mut addrlen)
+        }));
+        Ok((n, sockaddr_to_path(&storage, addrlen as uint)))
+    }
+
+    fn send_to(&mut self, buf: &[u8], dst: &CString) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        let fd = self.inner.fd;
+        let (dst, len) = try!(addr_to_sockaddr_un(dst));
+        let dstp = &dst as *_ as *libc::sockaddr;
+        let n = try!(poll::nonblocking(&self.loop_, fd, Writable, || unsafe {
+            libc::sendto(fd, buf.as_ptr() as *c_void,
+                         buf.len() as libc::size_t, 0,
+                         dstp, len as libc::socklen_t)
+        }));
+        if n != buf.len() {
+            return Err(IoError {
+                kind: io::ShortWrite(n),
+                desc: "couldn't send entire datagram",
+                detail: None,
+            })
+        }
+        Ok(())
+    }
+
+    fn clone(&self) -> Box<rtio::RtioUnixDatagram:Send> {
+        box UnixDatagramWatcher {
+            inner: self.inner.clone(),
+            home: self.home.clone(),
+            loop_: Loop::wrap(self.loop_.handle),
+        } as Box<rtio::RtioUnixDatagram:Send>
+    }
+
+    fn event_source(&self) -> Result<rtio::EventSource, IoError> {
+        Ok(rtio::FdSource(self.inner.fd))
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Unlink the path before closing the socket so someone else's socket
+        // bound in between isn't removed.
+        match self.path {
+            Some(ref path) => unsafe {
+                let _ = libc::unlink(path.with_ref(|p| p));
+            },
+            None => {}
+        }
+        unsafe { let _ = libc::close(self.fd); }
+    }
+}
+
+fn addr_to_sockaddr_un(addr: &CString)
+                       -> Result<(libc::sockaddr_storage, uint), IoError> {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let s: &mut libc::sockaddr_un = unsafe { mem::transmute(&mut storage) };
+
+    let len = addr.len();
+    if len > s.sun_path.len() - 1 {
+        return Err(IoError {
+            kind: io::InvalidInput,
+            desc: "path must be smaller than SUN_LEN",
+            detail: None,
+        })
+    }
+    s.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (slot, value) in s.sun_path.mut_iter().zip(addr.iter()) {
+        *slot = value;
+    }
+
+    // count the null terminator
+    let len = mem::size_of::<libc::sa_family_t>() + len + 1;
+    Ok((storage, len))
+}
+
+fn sockaddr_to_path(storage: &libc::sockaddr_storage,
+                    len: uint) -> Option<CString> {
+    let s: &libc::sockaddr_un = unsafe { mem::transmute(storage) };
+    let family = mem::size_of::<libc::sa_family_t>();
+    if len <= family { return None }
+    let len = cmp::min(len - family, s.sun_path.len());
+    let path = s.sun_path.slice_to(len).iter()
+                         .take_while(|c| **c != 0)
+                         .map(|c| *c as u8)
+                         .collect::<Vec<u8>>();
+    // unnamed sockets (and the abstract namespace on linux) have no path
+    if path.len() == 0 { None } else { Some(path.as_slice().to_c_str()) }
+}
@@ -12,14 +12,18 @@ use libc::{c_int, c_char, c_void, ssize_t};
 use libc;
 use std::c_str::CString;
 use std::c_str;
+use std::cmp;
 use std::io::{FileStat, IoError};
 use std::io;
 use std::mem;
+use std::os;
 use std::rt::rtio;
+use std::rt::rtio::RtioTimer;
 use std::rt::task::BlockedTask;
 
 use homing::{HomingIO, HomeHandle};
 use super::{Loop, UvError, uv_error_to_io_error, wait_until_woken_after, wakeup};
+use timer::TimerWatcher;
 use uvio::UvIoFactory;
 use uvll;
 
@@ -469,6 +473,277 @@ impl rtio::RtioFileStream for FileWatcher {
         let _m = self.fire_homing_missile();
         FsRequest::fstat(&self.loop_, self.fd).map_err(uv_error_to_io_error)
     }
+
+    fn lock(&mut self, exclusive: bool, wait: bool) -> Result<bool, IoError> {
+        let _m = self.fire_homing_missile();
+        let fd = self.fd;
+        // A contended lock could hold a threadpool thread for arbitrarily long
+        // (and starve everything else queued on the pool), so instead of a
+        // blocking lock we retry the non-blocking one, sleeping on a timer
+        // with exponential backoff in between attempts.
+        let mut res = os_lock(fd, exclusive, false);
+        if wait && res == Ok(false) {
+            let mut timer = TimerWatcher::new_home(&self.loop_,
+                                                   self.home().clone());
+            let mut delay = 1;
+            while res == Ok(false) {
+                timer.sleep(delay);
+                delay = cmp::min(delay * 2, 100);
+                res = os_lock(fd, exclusive, false);
+            }
+        }
+        res.map_err(|errno| IoError::from_errno(errno, true))
+    }
+
+    fn unlock(&mut self) -> Result<(), IoError> {
+        let _m = self.fire_homing_missile();
+        os_unlock(self.fd).map_err(|errno| IoError::from_errno(errno, true))
+    }
+}
+
+/// A directory being read in batches on the threadpool.
+#[cfg(unix)]
+pub struct Directory {
+    dir: *libc::DIR,
+    buf: Vec<u8>,
+    // the rest of the current batch, in reverse order
+    entries: Vec<rtio::DirEntry>,
+    error: Option<uint>,
+    done: bool,
+    loop_: Loop,
+    home: HomeHandle,
+}
+
+#[cfg(unix)]
+static DIR_BATCH: uint = 64;
+
+#[cfg(unix)]
+impl Directory {
+    pub fn open(io: &mut UvIoFactory, path: &CString)
+                -> Result<Directory, IoError> {
+        let loop_ = Loop::wrap(io.uv_loop());
+        let mut res = Ok(0 as *libc::DIR);
+        on_threadpool(&loop_, || {
+            let dir = path.with_ref(|p| unsafe { libc::opendir(p) });
+            res = if dir.is_null() {Err(os::errno() as uint)} else {Ok(dir)};
+        });
+        let dir = try!(res.map_err(|errno| IoError::from_errno(errno, true)));
+        let size = unsafe { rust_dirent_t_size() };
+        Ok(Directory {
+            dir: dir,
+            buf: Vec::with_capacity(size as uint),
+            entries: Vec::new(),
+            error: None,
+            done: false,
+            loop_: loop_,
+            home: io.make_handle(),
+        })
+    }
+
+    // Runs on the threadpool. Reads are stopped at the first error, which is
+    // reported once the entries read before it have been handed out.
+    fn read_batch(&mut self) {
+        let ptr = self.buf.as_mut_slice().as_mut_ptr() as *mut libc::dirent_t;
+        while self.entries.len() < DIR_BATCH {
+            let mut entry_ptr = 0 as *mut libc::dirent_t;
+            match unsafe { libc::readdir_r(self.dir, ptr, &mut entry_ptr) } {
+                0 => {}
+                n => { self.error = Some(n as uint); self.done = true; break }
+            }
+            if entry_ptr.is_null() { self.done = true; break }
+
+            let name = unsafe {
+                CString::new(rust_list_dir_val(entry_ptr), false)
+            };
+            let name = name.as_bytes_no_nul();
+            if name == bytes!(".") || name == bytes!("..") { continue }
+            self.entries.push(rtio::DirEntry {
+                name: Vec::from_slice(name),
+                kind: dirent_kind(unsafe { rust_dirent_type(entry_ptr) }),
+                inode: unsafe { rust_dirent_ino(entry_ptr) },
+            });
+        }
+        self.entries.reverse();
+    }
+}
+
+#[cfg(unix)]
+impl HomingIO for Directory {
+    fn home<'r>(&'r mut self) -> &'r mut HomeHandle { &mut self.home }
+}
+
+#[cfg(unix)]
+impl rtio::RtioDirectory for Directory {
+    fn next_entry(&mut self) -> Result<Option<rtio::DirEntry>, IoError> {
+        if self.entries.len() == 0 && !self.done {
+            let _m = self.fire_homing_missile();
+            let loop_ = Loop::wrap(self.loop_.handle);
+            on_threadpool(&loop_, || self.read_batch());
+        }
+        match self.entries.pop() {
+            Some(entry) => Ok(Some(entry)),
+            None => match self.error.take() {
+                Some(errno) => Err(IoError::from_errno(errno, true)),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Directory {
+    fn drop(&mut self) {
+        unsafe { let _ = libc::closedir(self.dir); }
+    }
+}
+
+#[cfg(unix)]
+fn dirent_kind(d_type: c_int) -> Option<io::FileType> {
+    // The DT_* values are the same on every unix we support.
+    match d_type {
+        1 => Some(io::TypeNamedPipe),
+        2 => Some(io::TypeUnknown), // character device
+        4 => Some(io::TypeDirectory),
+        6 => Some(io::TypeBlockSpecial),
+        8 => Some(io::TypeFile),
+        10 => Some(io::TypeSymlink),
+        12 => Some(io::TypeUnknown), // socket
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+extern {
+    fn rust_dirent_t_size() -> c_int;
+    fn rust_list_dir_val(ptr: *mut libc::dirent_t) -> *c_char;
+    fn rust_dirent_type(ptr: *mut libc::dirent_t) -> c_int;
+    fn rust_dirent_ino(ptr: *mut libc::dirent_t) -> u64;
+}
+
+/// libuv can only read a whole directory at once on windows, and without
+/// the type of each entry, so that's what this does.
+#[cfg(windows)]
+pub struct Directory {
+    // the remaining entries, in reverse order
+    paths: Vec<Path>,
+}
+
+#[cfg(windows)]
+impl Directory {
+    pub fn open(io: &mut UvIoFactory, path: &CString)
+                -> Result<Directory, IoError> {
+        let loop_ = Loop::wrap(io.uv_loop());
+        let mut paths = try!(FsRequest::readdir(&loop_, path, 0)
+                                       .map_err(uv_error_to_io_error));
+        paths.reverse();
+        Ok(Directory { paths: paths })
+    }
+}
+
+#[cfg(windows)]
+impl rtio::RtioDirectory for Directory {
+    fn next_entry(&mut self) -> Result<Option<rtio::DirEntry>, IoError> {
+        Ok(self.paths.pop().map(|path| {
+            rtio::DirEntry {
+                name: Vec::from_slice(path.filename().unwrap_or(&[])),
+                kind: None,
+                inode: 0,
+            }
+        }))
+    }
+}
+
+struct ThreadpoolWork<'a> {
+    f: ||:'a,
+    slot: Option<BlockedTask>,
+}
+
+/// Runs `f` on libuv's threadpool, blocking the current task (but not the
+/// event loop) until it has finished. `f` runs on another thread, so it must
+/// not touch the task or the event loop.
+fn on_threadpool(loop_: &Loop, f: ||) {
+    let mut work = ThreadpoolWork { f: f, slot: None };
+    let slot = &mut work.slot as *mut Option<BlockedTask>;
+    wait_until_woken_after(slot, loop_, || unsafe {
+        let req = uvll::malloc_req(uvll::UV_WORK);
+        uvll::set_data_for_req(req, &work);
+        assert_eq!(uvll::uv_queue_work(loop_.handle, req, work_cb,
+                                       after_work_cb), 0);
+    });
+
+    extern fn work_cb(req: *uvll::uv_work_t) {
+        let work: &mut ThreadpoolWork = unsafe {
+            mem::transmute(uvll::get_data_for_req(req))
+        };
+        (work.f)();
+    }
+
+    extern fn after_work_cb(req: *uvll::uv_work_t, _status: c_int) {
+        let work: &mut ThreadpoolWork = unsafe {
+            mem::transmute(uvll::get_data_for_req(req))
+        };
+        unsafe { uvll::free_req(req) }
+        wakeup(&mut work.slot);
+    }
+}
+
+#[cfg(unix)]
+fn os_lock(fd: c_int, exclusive: bool, wait: bool) -> Result<bool, uint> {
+    let mut op = if exclusive {libc::LOCK_EX} else {libc::LOCK_SH};
+    if !wait { op |= libc::LOCK_NB; }
+    loop {
+        match unsafe { libc::flock(fd, op) } {
+            0 => return Ok(true),
+            _ => match os::errno() as c_int {
+                libc::EINTR => {}
+                libc::EWOULDBLOCK if !wait => return Ok(false),
+                n => return Err(n as uint),
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn os_unlock(fd: c_int) -> Result<(), uint> {
+    loop {
+        match unsafe { libc::flock(fd, libc::LOCK_UN) } {
+            0 => return Ok(()),
+            _ => match os::errno() as c_int {
+                libc::EINTR => {}
+                n => return Err(n as uint),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+fn os_lock(fd: c_int, exclusive: bool, wait: bool) -> Result<bool, uint> {
+    let mut flags = 0;
+    if exclusive { flags |= libc::LOCKFILE_EXCLUSIVE_LOCK; }
+    if !wait { flags |= libc::LOCKFILE_FAIL_IMMEDIATELY; }
+    let mut overlap: libc::OVERLAPPED = unsafe { mem::zeroed() };
+    match unsafe {
+        let handle = libc::get_osfhandle(fd) as libc::HANDLE;
+        libc::LockFileEx(handle, flags, 0, !0, !0, &mut overlap)
+    } {
+        0 => match os::errno() {
+            n if !wait && n == libc::ERROR_LOCK_VIOLATION as uint => Ok(false),
+            n => Err(n),
+        },
+        _ => Ok(true),
+    }
+}
+
+#[cfg(windows)]
+fn os_unlock(fd: c_int) -> Result<(), uint> {
+    let mut overlap: libc::OVERLAPPED = unsafe { mem::zeroed() };
+    match unsafe {
+        let handle = libc::get_osfhandle(fd) as libc::HANDLE;
+        libc::UnlockFileEx(handle, 0, !0, !0, &mut overlap)
+    } {
+        0 => Err(os::errno()),
+        _ => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -36,7 +36,7 @@
 use std::mem;
 use std::rt::local::Local;
 use std::rt::rtio::LocalIo;
-use std::rt::task::{Task, BlockedTask};
+use std::rt::task::{Task, BlockedTask, OnIo};
 
 use ForbidUnwind;
 use queue::{Queue, QueuePool};
@@ -101,7 +101,7 @@ pub trait HomingIO {
         // this event loop as long as we avoid the scheduler).
         if cur_loop_id != destination {
             let cur_task: Box<Task> = Local::take();
-            cur_task.deschedule(1, |task| {
+            cur_task.deschedule_on(OnIo, 1, |task| {
                 self.home().send(task);
                 Ok(())
             });
@@ -152,6 +152,7 @@ mod test {
     use green::sched;
     use green::{SchedPool, PoolConfig};
     use std::rt::rtio::RtioUdpSocket;
+    use std::default::Default;
     use std::io::test::next_test_ip4;
     use std::task::TaskOpts;
 
@@ -168,10 +169,11 @@ mod test {
         let mut pool = SchedPool::new(PoolConfig {
             threads: 1,
             event_loop_factory: ::event_loop,
+            deadlock_hook: None,
         });
 
         pool.spawn(TaskOpts::new(), proc() {
-            let listener = UdpWatcher::bind(local_loop(), next_test_ip4());
+            let listener = UdpWatcher::bind(local_loop(), next_test_ip4(), &Default::default());
             tx.send(listener.unwrap());
         });
 
@@ -189,14 +191,15 @@ mod test {
         let mut pool = SchedPool::new(PoolConfig {
             threads: 1,
             event_loop_factory: ::event_loop,
+            deadlock_hook: None,
         });
 
         pool.spawn(TaskOpts::new(), proc() {
             let addr1 = next_test_ip4();
             let addr2 = next_test_ip4();
-            let listener = UdpWatcher::bind(local_loop(), addr2);
+            let listener = UdpWatcher::bind(local_loop(), addr2, &Default::default());
             tx.send((listener.unwrap(), addr1));
-            let mut listener = UdpWatcher::bind(local_loop(), addr1).unwrap();
+            let mut listener = UdpWatcher::bind(local_loop(), addr1, &Default::default()).unwrap();
             listener.sendto([1, 2, 3, 4], addr2).unwrap();
         });
 
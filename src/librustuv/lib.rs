@@ -57,7 +57,7 @@ use std::ptr::null;
 use std::ptr;
 use std::rt::local::Local;
 use std::rt::rtio;
-use std::rt::task::{BlockedTask, Task};
+use std::rt::task::{BlockedTask, Task, BlockedOn, OnIo};
 use std::str::raw::from_c_str;
 use std::str;
 use std::task;
@@ -67,6 +67,7 @@ pub use self::file::{FsRequest, FileWatcher};
 pub use self::idle::IdleWatcher;
 pub use self::net::{TcpWatcher, TcpListener, TcpAcceptor, UdpWatcher};
 pub use self::pipe::{PipeWatcher, PipeListener, PipeAcceptor};
+pub use self::poll::SelectorWatcher;
 pub use self::process::Process;
 pub use self::signal::SignalWatcher;
 pub use self::timer::TimerWatcher;
@@ -101,6 +102,9 @@ pub mod async;
 pub mod addrinfo;
 pub mod process;
 pub mod pipe;
+#[cfg(unix)]
+pub mod datagram;
+pub mod poll;
 pub mod tty;
 pub mod signal;
 pub mod stream;
@@ -241,12 +245,19 @@ impl Drop for ForbidUnwind {
 fn wait_until_woken_after(slot: *mut Option<BlockedTask>,
                           loop_: &Loop,
                           f: ||) {
+    wait_until_woken_on(slot, loop_, OnIo, f)
+}
+
+fn wait_until_woken_on(slot: *mut Option<BlockedTask>,
+                       loop_: &Loop,
+                       on: BlockedOn,
+                       f: ||) {
     let _f = ForbidUnwind::new("wait_until_woken_after");
     unsafe {
         assert!((*slot).is_none());
         let task: Box<Task> = Local::take();
         loop_.modify_blockers(1);
-        task.deschedule(1, |task| {
+        task.deschedule_on(on, 1, |task| {
             *slot = Some(task);
             f();
             Ok(())
@@ -14,11 +14,13 @@ use std::io;
 use std::io::IoError;
 use std::io::net::ip;
 use std::mem;
+use std::os;
 use std::ptr;
 use std::rt::rtio;
 use std::rt::task::BlockedTask;
 
 use homing::{HomingIO, HomeHandle};
+use poll;
 use rc::Refcount;
 use stream::StreamWatcher;
 use super::{Loop, Request, UvError, Buf, status_to_io_result,
@@ -145,6 +147,128 @@ fn socket_name(sk: SocketNameKind,
     }
 }
 
+// Creates the socket of a TCP or UDP handle by hand if `cfg` asks for options
+// which libuv can't set itself before the socket is bound. Otherwise libuv
+// creates the socket lazily when the handle is bound or connected.
+fn configured_socket(addr: ip::SocketAddr, ty: c_int,
+                     cfg: &rtio::SocketConfig)
+                     -> Result<Option<uvll::uv_os_sock_t>, UvError> {
+    if !cfg.reuse_port && cfg.only_v6.is_none() &&
+       cfg.send_buffer_size.is_none() && cfg.recv_buffer_size.is_none() &&
+       cfg.linger.is_none() {
+        return Ok(None)
+    }
+
+    let fam = match addr.ip {
+        ip::Ipv4Addr(..) => libc::AF_INET,
+        ip::Ipv6Addr(..) => libc::AF_INET6,
+    };
+    let fd = unsafe { libc::socket(fam, ty, 0) };
+    if fd == -1 as uvll::uv_os_sock_t { return Err(last_uv_error()) }
+
+    match configure(fd, addr, cfg) {
+        Ok(()) => {
+            set_nonblocking(fd);
+            Ok(Some(fd))
+        }
+        Err(e) => { close_socket(fd); Err(e) }
+    }
+}
+
+fn configure(fd: uvll::uv_os_sock_t, addr: ip::SocketAddr,
+             cfg: &rtio::SocketConfig) -> Result<(), UvError> {
+    if cfg.reuse_address {
+        try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR,
+                        1 as c_int));
+    }
+    if cfg.reuse_port {
+        try!(reuse_port(fd));
+    }
+    match (cfg.only_v6, addr.ip) {
+        (Some(only), ip::Ipv6Addr(..)) => {
+            try!(setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY,
+                            only as c_int));
+        }
+        _ => {}
+    }
+    match cfg.send_buffer_size {
+        Some(size) => try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_SNDBUF,
+                                      size as c_int)),
+        None => {}
+    }
+    match cfg.recv_buffer_size {
+        Some(size) => try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF,
+                                      size as c_int)),
+        None => {}
+    }
+    match cfg.linger {
+        Some(secs) => try!(setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER,
+                                      linger(secs))),
+        None => {}
+    }
+    Ok(())
+}
+
+// Hands a socket created by `configured_socket` over to its handle.
+fn open_socket(fd: Option<uvll::uv_os_sock_t>,
+               open: |uvll::uv_os_sock_t| -> c_int) -> Result<(), UvError> {
+    match fd {
+        Some(fd) => match open(fd) {
+            0 => Ok(()),
+            n => { close_socket(fd); Err(UvError(n)) }
+        },
+        None => Ok(()),
+    }
+}
+
+fn setsockopt<T>(fd: uvll::uv_os_sock_t, level: c_int, opt: c_int,
+                 payload: T) -> Result<(), UvError> {
+    let ret = unsafe {
+        libc::setsockopt(fd, level, opt, &payload as *T as *c_void,
+                         mem::size_of::<T>() as libc::socklen_t)
+    };
+    if ret != 0 { Err(last_uv_error()) } else { Ok(()) }
+}
+
+#[cfg(unix)]
+fn linger(secs: uint) -> libc::linger {
+    libc::linger { l_onoff: 1, l_linger: secs as c_int }
+}
+
+#[cfg(windows)]
+fn linger(secs: uint) -> libc::linger {
+    libc::linger { l_onoff: 1, l_linger: secs as u16 }
+}
+
+#[cfg(unix)]
+fn reuse_port(fd: uvll::uv_os_sock_t) -> Result<(), UvError> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::consts::os::bsd44::SO_REUSEPORT,
+               1 as c_int)
+}
+
+#[cfg(windows)]
+fn reuse_port(_fd: uvll::uv_os_sock_t) -> Result<(), UvError> {
+    Err(UvError(uvll::UNKNOWN))
+}
+
+// libuv's errors are negated errno values on unix. Windows has no such
+// mapping available, so errors from the socket API aren't more specific.
+#[cfg(unix)]
+fn last_uv_error() -> UvError { UvError(-(os::errno() as c_int)) }
+#[cfg(windows)]
+fn last_uv_error() -> UvError { UvError(uvll::UNKNOWN) }
+
+#[cfg(unix)]
+fn set_nonblocking(fd: uvll::uv_os_sock_t) { poll::set_nonblocking(fd) }
+// uv_tcp_open and uv_udp_open make sockets nonblocking on windows.
+#[cfg(windows)]
+fn set_nonblocking(_fd: uvll::uv_os_sock_t) {}
+
+#[cfg(unix)]
+fn close_socket(fd: uvll::uv_os_sock_t) { unsafe { libc::close(fd); } }
+#[cfg(windows)]
+fn close_socket(fd: uvll::uv_os_sock_t) { unsafe { libc::closesocket(fd); } }
+
 ////////////////////////////////////////////////////////////////////////////////
 /// TCP implementation
 ////////////////////////////////////////////////////////////////////////////////
@@ -167,6 +291,7 @@ pub struct TcpListener {
     home: HomeHandle,
     handle: *uvll::uv_pipe_t,
     closing_task: Option<BlockedTask>,
+    backlog: c_int,
     outgoing: Sender<Result<Box<rtio::RtioTcpStream:Send>, IoError>>,
     incoming: Receiver<Result<Box<rtio::RtioTcpStream:Send>, IoError>>,
 }
@@ -201,8 +326,24 @@ impl TcpWatcher {
 
     pub fn connect(io: &mut UvIoFactory,
                    address: ip::SocketAddr,
+                   cfg: &rtio::SocketConfig,
                    timeout: Option<u64>) -> Result<TcpWatcher, UvError> {
         let tcp = TcpWatcher::new(io);
+        let fd = try!(configured_socket(address, libc::SOCK_STREAM, cfg));
+        try!(open_socket(fd, |fd| unsafe {
+            uvll::uv_tcp_open(tcp.handle, fd)
+        }));
+        match cfg.local_addr {
+            Some(local) => {
+                let (local, _len) = addr_to_sockaddr(local);
+                let local_p = &local as *_ as *libc::sockaddr;
+                match unsafe { uvll::uv_tcp_bind(tcp.handle, local_p) } {
+                    0 => {}
+                    n => return Err(UvError(n)),
+                }
+            }
+            None => {}
+        }
         let cx = ConnectCtx { status: -1, task: None, timer: None };
         let (addr, _len) = addr_to_sockaddr(address);
         let addr_p = &addr as *_ as *libc::sockaddr;
@@ -221,6 +362,9 @@ impl rtio::RtioSocket for TcpWatcher {
         let _m = self.fire_homing_missile();
         socket_name(Tcp, self.handle)
     }
+    fn event_source(&self) -> Result<rtio::EventSource, IoError> {
+        poll::event_source(self.handle)
+    }
 }
 
 impl rtio::RtioTcpStream for TcpWatcher {
@@ -349,7 +493,8 @@ impl Drop for TcpWatcher {
 // TCP listeners (unbound servers)
 
 impl TcpListener {
-    pub fn bind(io: &mut UvIoFactory, address: ip::SocketAddr)
+    pub fn bind(io: &mut UvIoFactory, address: ip::SocketAddr,
+                cfg: &rtio::SocketConfig)
                 -> Result<Box<TcpListener>, UvError> {
         let handle = unsafe { uvll::malloc_handle(uvll::UV_TCP) };
         assert_eq!(unsafe {
@@ -360,9 +505,12 @@ impl TcpListener {
             home: io.make_handle(),
             handle: handle,
             closing_task: None,
+            backlog: cfg.backlog as c_int,
             outgoing: tx,
             incoming: rx,
         };
+        let fd = try!(configured_socket(address, libc::SOCK_STREAM, cfg));
+        try!(open_socket(fd, |fd| unsafe { uvll::uv_tcp_open(l.handle, fd) }));
         let (addr, _len) = addr_to_sockaddr(address);
         let res = unsafe {
             let addr_p = &addr as *libc::sockaddr_storage;
@@ -388,6 +536,9 @@ impl rtio::RtioSocket for TcpListener {
         let _m = self.fire_homing_missile();
         socket_name(Tcp, self.handle)
     }
+    fn event_source(&self) -> Result<rtio::EventSource, IoError> {
+        poll::event_source(self.handle)
+    }
 }
 
 impl rtio::RtioTcpListener for TcpListener {
@@ -399,8 +550,10 @@ impl rtio::RtioTcpListener for TcpListener {
         };
 
         let _m = acceptor.fire_homing_missile();
-        // FIXME: the 128 backlog should be configurable
-        match unsafe { uvll::uv_listen(acceptor.listener.handle, 128, listen_cb) } {
+        let backlog = acceptor.listener.backlog;
+        match unsafe {
+            uvll::uv_listen(acceptor.listener.handle, backlog, listen_cb)
+        } {
             0 => Ok(acceptor as Box<rtio::RtioTcpAcceptor:Send>),
             n => Err(uv_error_to_io_error(UvError(n))),
         }
@@ -501,8 +654,8 @@ struct UdpSendCtx {
 }
 
 impl UdpWatcher {
-    pub fn bind(io: &mut UvIoFactory, address: ip::SocketAddr)
-                -> Result<UdpWatcher, UvError> {
+    pub fn bind(io: &mut UvIoFactory, address: ip::SocketAddr,
+                cfg: &rtio::SocketConfig) -> Result<UdpWatcher, UvError> {
         let udp = UdpWatcher {
             handle: unsafe { uvll::malloc_handle(uvll::UV_UDP) },
             home: io.make_handle(),
@@ -514,10 +667,13 @@ impl UdpWatcher {
         assert_eq!(unsafe {
             uvll::uv_udp_init(io.uv_loop(), udp.handle)
         }, 0);
+        let fd = try!(configured_socket(address, libc::SOCK_DGRAM, cfg));
+        try!(open_socket(fd, |fd| unsafe { uvll::uv_udp_open(udp.handle, fd) }));
+        let flags = if cfg.reuse_address {uvll::UV_UDP_REUSEADDR} else {0};
         let (addr, _len) = addr_to_sockaddr(address);
         let result = unsafe {
             let addr_p = &addr as *libc::sockaddr_storage;
-            uvll::uv_udp_bind(udp.handle, addr_p as *libc::sockaddr, 0u32)
+            uvll::uv_udp_bind(udp.handle, addr_p as *libc::sockaddr, flags)
         };
         return match result {
             0 => Ok(udp),
@@ -539,6 +695,9 @@ impl rtio::RtioSocket for UdpWatcher {
         let _m = self.fire_homing_missile();
         socket_name(Udp, self.handle)
     }
+    fn event_source(&self) -> Result<rtio::EventSource, IoError> {
+        poll::event_source(self.handle)
+    }
 }
 
 impl rtio::RtioUdpSocket for UdpWatcher {
@@ -842,6 +1001,7 @@ pub fn shutdown(handle: *uvll::uv_stream_t, loop_: &Loop) -> Result<(), IoError>
 mod test {
     use std::rt::rtio::{RtioTcpStream, RtioTcpListener, RtioTcpAcceptor,
                         RtioUdpSocket};
+    use std::default::Default;
     use std::io::test::{next_test_ip4, next_test_ip6};
 
     use super::{UdpWatcher, TcpWatcher, TcpListener};
@@ -849,7 +1009,7 @@ mod test {
 
     #[test]
     fn connect_close_ip4() {
-        match TcpWatcher::connect(local_loop(), next_test_ip4(), None) {
+        match TcpWatcher::connect(local_loop(), next_test_ip4(), &Default::default(), None) {
             Ok(..) => fail!(),
             Err(e) => assert_eq!(e.name(), "ECONNREFUSED".to_strbuf()),
         }
@@ -857,7 +1017,7 @@ mod test {
 
     #[test]
     fn connect_close_ip6() {
-        match TcpWatcher::connect(local_loop(), next_test_ip6(), None) {
+        match TcpWatcher::connect(local_loop(), next_test_ip6(), &Default::default(), None) {
             Ok(..) => fail!(),
             Err(e) => assert_eq!(e.name(), "ECONNREFUSED".to_strbuf()),
         }
@@ -865,7 +1025,7 @@ mod test {
 
     #[test]
     fn udp_bind_close_ip4() {
-        match UdpWatcher::bind(local_loop(), next_test_ip4()) {
+        match UdpWatcher::bind(local_loop(), next_test_ip4(), &Default::default()) {
             Ok(..) => {}
             Err(..) => fail!()
         }
@@ -873,7 +1033,7 @@ mod test {
 
     #[test]
     fn udp_bind_close_ip6() {
-        match UdpWatcher::bind(local_loop(), next_test_ip6()) {
+        match UdpWatcher::bind(local_loop(), next_test_ip6(), &Default::default()) {
             Ok(..) => {}
             Err(..) => fail!()
         }
@@ -885,7 +1045,7 @@ mod test {
         let addr = next_test_ip4();
 
         spawn(proc() {
-            let w = match TcpListener::bind(local_loop(), addr) {
+            let w = match TcpListener::bind(local_loop(), addr, &Default::default()) {
                 Ok(w) => w, Err(e) => fail!("{:?}", e)
             };
             let mut w = match w.listen() {
@@ -907,7 +1067,7 @@ mod test {
         });
 
         rx.recv();
-        let mut w = match TcpWatcher::connect(local_loop(), addr, None) {
+        let mut w = match TcpWatcher::connect(local_loop(), addr, &Default::default(), None) {
             Ok(w) => w, Err(e) => fail!("{:?}", e)
         };
         match w.write([1, 2, 3, 4, 5, 6, 7, 8, 9, 10]) {
@@ -921,7 +1081,7 @@ mod test {
         let addr = next_test_ip6();
 
         spawn(proc() {
-            let w = match TcpListener::bind(local_loop(), addr) {
+            let w = match TcpListener::bind(local_loop(), addr, &Default::default()) {
                 Ok(w) => w, Err(e) => fail!("{:?}", e)
             };
             let mut w = match w.listen() {
@@ -943,7 +1103,7 @@ mod test {
         });
 
         rx.recv();
-        let mut w = match TcpWatcher::connect(local_loop(), addr, None) {
+        let mut w = match TcpWatcher::connect(local_loop(), addr, &Default::default(), None) {
             Ok(w) => w, Err(e) => fail!("{:?}", e)
         };
         match w.write([1, 2, 3, 4, 5, 6, 7, 8, 9, 10]) {
@@ -958,7 +1118,7 @@ mod test {
         let server = next_test_ip4();
 
         spawn(proc() {
-            match UdpWatcher::bind(local_loop(), server) {
+            match UdpWatcher::bind(local_loop(), server, &Default::default()) {
                 Ok(mut w) => {
                     tx.send(());
                     let mut buf = [0u8, ..10];
@@ -975,7 +1135,7 @@ mod test {
         });
 
         rx.recv();
-        let mut w = match UdpWatcher::bind(local_loop(), client) {
+        let mut w = match UdpWatcher::bind(local_loop(), client, &Default::default()) {
             Ok(w) => w, Err(e) => fail!("{:?}", e)
         };
         match w.sendto([1, 2, 3, 4, 5, 6, 7, 8, 9, 10], server) {
@@ -990,7 +1150,7 @@ mod test {
         let server = next_test_ip6();
 
         spawn(proc() {
-            match UdpWatcher::bind(local_loop(), server) {
+            match UdpWatcher::bind(local_loop(), server, &Default::default()) {
                 Ok(mut w) => {
                     tx.send(());
                     let mut buf = [0u8, ..10];
@@ -1007,7 +1167,7 @@ mod test {
         });
 
         rx.recv();
-        let mut w = match UdpWatcher::bind(local_loop(), client) {
+        let mut w = match UdpWatcher::bind(local_loop(), client, &Default::default()) {
             Ok(w) => w, Err(e) => fail!("{:?}", e)
         };
         match w.sendto([1, 2, 3, 4, 5, 6, 7, 8, 9, 10], server) {
@@ -1022,7 +1182,7 @@ mod test {
         let (tx, rx) = channel();
 
         spawn(proc() {
-            let listener = TcpListener::bind(local_loop(), addr).unwrap();
+            let listener = TcpListener::bind(local_loop(), addr, &Default::default()).unwrap();
             let mut acceptor = listener.listen().unwrap();
             tx.send(());
             let mut stream = acceptor.accept().unwrap();
@@ -1036,7 +1196,8 @@ mod test {
         });
 
         rx.recv();
-        let mut stream = TcpWatcher::connect(local_loop(), addr, None).unwrap();
+        let mut stream = TcpWatcher::connect(local_loop(), addr,
+                                             &Default::default(), None).unwrap();
         let mut buf = [0, .. 2048];
         let mut total_bytes_read = 0;
         while total_bytes_read < MAX {
@@ -1057,13 +1218,14 @@ mod test {
         let (tx, rx) = channel();
 
         spawn(proc() {
-            let mut client = UdpWatcher::bind(local_loop(), client_addr).unwrap();
+            let mut client = UdpWatcher::bind(local_loop(), client_addr,
+                                              &Default::default()).unwrap();
             rx.recv();
             assert!(client.sendto([1], server_addr).is_ok());
             assert!(client.sendto([2], server_addr).is_ok());
         });
 
-        let mut server = UdpWatcher::bind(local_loop(), server_addr).unwrap();
+        let mut server = UdpWatcher::bind(local_loop(), server_addr, &Default::default()).unwrap();
         tx.send(());
         let mut buf1 = [0];
         let mut buf2 = [0];
@@ -1090,8 +1252,8 @@ mod test {
 
         spawn(proc() {
             let l = local_loop();
-            let mut server_out = UdpWatcher::bind(l, server_out_addr).unwrap();
-            let mut server_in = UdpWatcher::bind(l, server_in_addr).unwrap();
+            let mut server_out = UdpWatcher::bind(l, server_out_addr, &Default::default()).unwrap();
+            let mut server_in = UdpWatcher::bind(l, server_in_addr, &Default::default()).unwrap();
             let (tx, rx) = (tx2, rx1);
             tx.send(());
             rx.recv();
@@ -1113,8 +1275,8 @@ mod test {
         });
 
         let l = local_loop();
-        let mut client_out = UdpWatcher::bind(l, client_out_addr).unwrap();
-        let mut client_in = UdpWatcher::bind(l, client_in_addr).unwrap();
+        let mut client_out = UdpWatcher::bind(l, client_out_addr, &Default::default()).unwrap();
+        let mut client_in = UdpWatcher::bind(l, client_in_addr, &Default::default()).unwrap();
         let (tx, rx) = (tx1, rx2);
         rx.recv();
         tx.send(());
@@ -1144,7 +1306,8 @@ mod test {
 
         spawn(proc() {
             let rx = rx.recv();
-            let mut stream = TcpWatcher::connect(local_loop(), addr, None).unwrap();
+            let mut stream = TcpWatcher::connect(local_loop(), addr,
+                                                 &Default::default(), None).unwrap();
             stream.write([0, 1, 2, 3, 4, 5, 6, 7]).unwrap();
             stream.write([0, 1, 2, 3, 4, 5, 6, 7]).unwrap();
             rx.recv();
@@ -1153,7 +1316,7 @@ mod test {
             rx.recv();
         });
 
-        let listener = TcpListener::bind(local_loop(), addr).unwrap();
+        let listener = TcpListener::bind(local_loop(), addr, &Default::default()).unwrap();
         let mut acceptor = listener.listen().unwrap();
         let (tx2, rx2) = channel();
         tx.send(rx2);
@@ -1185,7 +1348,7 @@ mod test {
         let addr = next_test_ip4();
 
         spawn(proc() {
-            let listener = TcpListener::bind(local_loop(), addr).unwrap();
+            let listener = TcpListener::bind(local_loop(), addr, &Default::default()).unwrap();
             let mut acceptor = listener.listen().unwrap();
             let mut stream = acceptor.accept().unwrap();
             let mut buf = [0, .. 2048];
@@ -1196,9 +1359,9 @@ mod test {
             }
         });
 
-        let mut stream = TcpWatcher::connect(local_loop(), addr, None);
+        let mut stream = TcpWatcher::connect(local_loop(), addr, &Default::default(), None);
         while stream.is_err() {
-            stream = TcpWatcher::connect(local_loop(), addr, None);
+            stream = TcpWatcher::connect(local_loop(), addr, &Default::default(), None);
         }
         stream.unwrap().write([0, 1, 2, 3, 4, 5, 6, 7]).unwrap();
     }
@@ -1206,7 +1369,7 @@ mod test {
     #[should_fail] #[test]
     fn tcp_listener_fail_cleanup() {
         let addr = next_test_ip4();
-        let w = TcpListener::bind(local_loop(), addr).unwrap();
+        let w = TcpListener::bind(local_loop(), addr, &Default::default()).unwrap();
         let _w = w.listen().unwrap();
         fail!();
     }
@@ -1217,20 +1380,20 @@ mod test {
         let addr = next_test_ip4();
 
         spawn(proc() {
-            let w = TcpListener::bind(local_loop(), addr).unwrap();
+            let w = TcpListener::bind(local_loop(), addr, &Default::default()).unwrap();
             let mut w = w.listen().unwrap();
             tx.send(());
             drop(w.accept().unwrap());
         });
         rx.recv();
-        let _w = TcpWatcher::connect(local_loop(), addr, None).unwrap();
+        let _w = TcpWatcher::connect(local_loop(), addr, &Default::default(), None).unwrap();
         fail!();
     }
 
     #[should_fail] #[test]
     fn udp_listener_fail_cleanup() {
         let addr = next_test_ip4();
-        let _w = UdpWatcher::bind(local_loop(), addr).unwrap();
+        let _w = UdpWatcher::bind(local_loop(), addr, &Default::default()).unwrap();
         fail!();
     }
 
@@ -1243,7 +1406,7 @@ mod test {
         // the original task will force a homing operation back to this
         // scheduler.
         spawn(proc() {
-            let w = UdpWatcher::bind(local_loop(), addr).unwrap();
+            let w = UdpWatcher::bind(local_loop(), addr, &Default::default()).unwrap();
             tx.send(w);
         });
 
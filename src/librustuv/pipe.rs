@@ -11,13 +11,17 @@
 use libc;
 use std::c_str::CString;
 use std::io::IoError;
+use std::io::net::unix::Credentials;
+use std::io::poll::{Readable, Writable};
 use std::io;
 use std::mem;
+use std::rt::rtio;
 use std::rt::rtio::{RtioPipe, RtioUnixListener, RtioUnixAcceptor};
 use std::rt::task::BlockedTask;
 
 use homing::{HomingIO, HomeHandle};
 use net;
+use poll;
 use rc::Refcount;
 use stream::StreamWatcher;
 use super::{Loop, UvError, UvHandle, uv_error_to_io_error};
@@ -103,6 +107,13 @@ impl PipeWatcher {
 
     pub fn handle(&self) -> *uvll::uv_pipe_t { self.stream.handle }
 
+    #[cfg(unix)]
+    fn fileno(&self) -> Result<libc::c_int, IoError> {
+        match try!(poll::event_source(self.handle())) {
+            rtio::FdSource(fd) | rtio::TimerSource(fd) => Ok(fd),
+        }
+    }
+
     // Unwraps the underlying uv pipe. This cancels destruction of the pipe and
     // allows the pipe to get moved elsewhere
     fn unwrap(mut self) -> *uvll::uv_pipe_t {
@@ -201,6 +212,98 @@ impl RtioPipe for PipeWatcher {
             stream.cancel_write()
         }
     }
+
+    fn event_source(&self) -> Result<rtio::EventSource, IoError> {
+        poll::event_source(self.handle())
+    }
+
+    // libuv can only pass its own handles over pipes, so descriptors are
+    // passed by calling sendmsg and recvmsg on the pipe's descriptor.
+
+    #[cfg(unix)]
+    fn send_fds(&mut self, buf: &[u8],
+                fds: &[libc::c_int]) -> Result<(), IoError> {
+        let m = self.fire_homing_missile();
+        let fd = try!(self.fileno());
+        let loop_ = self.uv_loop();
+        let guard = try!(self.write_access.grant(m));
+        let n = try!(poll::nonblocking(&loop_, fd, Writable, || unsafe {
+            rustrt::rust_send_fds(fd, buf.as_ptr() as *libc::c_void,
+                                  buf.len() as libc::size_t,
+                                  fds.as_ptr(), fds.len() as libc::size_t, 0)
+        }));
+        if n < buf.len() {
+            try!(self.stream.write(buf.slice_from(n), guard.can_timeout)
+                            .map_err(uv_error_to_io_error));
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn recv_fds(&mut self, buf: &mut [u8], max_fds: uint)
+                -> Result<(uint, Vec<libc::c_int>), IoError> {
+        let m = self.fire_homing_missile();
+        let fd = try!(self.fileno());
+        let loop_ = self.uv_loop();
+        let guard = try!(self.read_access.grant(m));
+
+        // see comments in close_read about this check
+        if guard.access.is_closed() {
+            return Err(io::standard_error(io::EndOfFile))
+        }
+
+        let mut fds: Vec<libc::c_int> = Vec::with_capacity(max_fds);
+        let mut nfds = 0;
+        let n = try!(poll::nonblocking(&loop_, fd, Readable, || unsafe {
+            nfds = max_fds as libc::size_t;
+            rustrt::rust_recv_fds(fd, buf.as_mut_ptr() as *mut libc::c_void,
+                                  buf.len() as libc::size_t,
+                                  fds.as_mut_ptr(), &mut nfds, 0)
+        }));
+        if n == 0 && buf.len() > 0 {
+            return Err(io::standard_error(io::EndOfFile))
+        }
+        unsafe { fds.set_len(nfds as uint); }
+        Ok((n, fds))
+    }
+
+    #[cfg(unix)]
+    fn peer_cred(&mut self) -> Result<Credentials, IoError> {
+        let _m = self.fire_homing_missile();
+        let fd = try!(self.fileno());
+        let mut pid = 0;
+        let mut uid = 0;
+        let mut gid = 0;
+        match unsafe {
+            rustrt::rust_peer_cred(fd, &mut pid, &mut uid, &mut gid)
+        } {
+            -1 => Err(IoError::last_error()),
+            _ => Ok(Credentials {
+                pid: if pid == -1 {None} else {Some(pid)},
+                uid: uid as uint,
+                gid: gid as uint,
+            }),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod rustrt {
+    use libc;
+
+    extern {
+        pub fn rust_send_fds(sock: libc::c_int, buf: *libc::c_void,
+                             len: libc::size_t, fds: *libc::c_int,
+                             nfds: libc::size_t,
+                             flags: libc::c_int) -> libc::ssize_t;
+        pub fn rust_recv_fds(sock: libc::c_int, buf: *mut libc::c_void,
+                             len: libc::size_t, fds: *mut libc::c_int,
+                             nfds: *mut libc::size_t,
+                             flags: libc::c_int) -> libc::ssize_t;
+        pub fn rust_peer_cred(sock: libc::c_int, pid: *mut libc::pid_t,
+                              uid: *mut libc::uid_t,
+                              gid: *mut libc::gid_t) -> libc::c_int;
+    }
 }
 
 impl HomingIO for PipeWatcher {
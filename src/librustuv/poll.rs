@@ -0,0 +1,341 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Readiness selectors on top of uv_poll_t
+//!
+//! Every registration gets its own poll handle, but the handles are only
+//! started while a task is blocked in `select`. libuv reports a ready
+//! descriptor on every turn of the loop, so leaving them running would spin
+//! the event loop for as long as something was ready but nobody was asking.
+//!
+//! Note that libuv doesn't allow two active watchers on the same descriptor,
+//! so an object can't be reading or writing through libuv at the same time as
+//! the selector is waiting on it. This is fine for the usual pattern of
+//! selecting and then performing I/O, but means that listening TCP sockets,
+//! which libuv is always accepting from, can't be registered at all.
+
+use libc::c_int;
+use libc;
+use std::io::IoResult;
+use std::io::poll::{Event, Interest, Readable, Writable};
+use std::io;
+use std::mem;
+use std::rt::rtio;
+use std::rt::task::BlockedTask;
+
+use homing::{HomingIO, HomeHandle};
+use timer::TimerWatcher;
+use uvio::UvIoFactory;
+use uvll;
+use {Loop, UvError, UvHandle, uv_error_to_io_error};
+use {wait_until_woken_after, wakeup};
+
+pub struct SelectorWatcher {
+    home: HomeHandle,
+    loop_: Loop,
+    registered: Vec<Box<PollWatcher>>,
+    timer: Option<Box<TimerWatcher>>,
+    blocker: Option<BlockedTask>,
+}
+
+struct PollWatcher {
+    handle: *uvll::uv_poll_t,
+    token: uint,
+    fd: c_int,
+    interest: Interest,
+    timer: bool,
+    ready: Interest,
+    // The blocker of the selector which is currently waiting on this handle.
+    blocker: *mut Option<BlockedTask>,
+}
+
+impl SelectorWatcher {
+    pub fn new(io: &mut UvIoFactory) -> Box<SelectorWatcher> {
+        box SelectorWatcher {
+            home: io.make_handle(),
+            loop_: Loop::wrap(io.uv_loop()),
+            registered: Vec::new(),
+            timer: None,
+            blocker: None,
+        }
+    }
+
+    fn position(&self, token: uint) -> Option<uint> {
+        self.registered.iter().position(|w| w.token == token)
+    }
+
+    fn stop(&mut self) {
+        for w in self.registered.iter() {
+            unsafe { uvll::uv_poll_stop(w.handle); }
+        }
+        match self.timer {
+            Some(ref mut t) => t.stop(),
+            None => {}
+        }
+    }
+}
+
+impl PollWatcher {
+    fn new(loop_: &Loop, fd: c_int, token: uint, interest: Interest,
+           timer: bool) -> Result<Box<PollWatcher>, UvError> {
+        let handle = UvHandle::alloc(None::<PollWatcher>, uvll::UV_POLL);
+        match unsafe { uvll::uv_poll_init(loop_.handle, handle, fd) } {
+            0 => {}
+            n => {
+                unsafe { uvll::free_handle(handle) }
+                return Err(UvError(n))
+            }
+        }
+        let w = box PollWatcher {
+            handle: handle,
+            token: token,
+            fd: fd,
+            interest: interest,
+            timer: timer,
+            ready: Interest::empty(),
+            blocker: 0 as *mut Option<BlockedTask>,
+        };
+        Ok(w.install())
+    }
+
+    fn events(&self) -> c_int {
+        let mut events = 0;
+        if self.interest.contains(Readable) { events |= uvll::UV_READABLE }
+        if self.interest.contains(Writable) { events |= uvll::UV_WRITABLE }
+        events
+    }
+}
+
+impl HomingIO for SelectorWatcher {
+    fn home<'r>(&'r mut self) -> &'r mut HomeHandle { &mut self.home }
+}
+
+impl UvHandle<uvll::uv_poll_t> for PollWatcher {
+    fn uv_handle(&self) -> *uvll::uv_poll_t { self.handle }
+}
+
+impl rtio::RtioSelector for SelectorWatcher {
+    fn register(&mut self, source: rtio::EventSource, token: uint,
+                interest: Interest) -> IoResult<()> {
+        let _m = self.fire_homing_missile();
+        let (fd, is_timer) = match source {
+            rtio::FdSource(fd) => (fd, false),
+            rtio::TimerSource(fd) => (fd, true),
+        };
+        match self.position(token) {
+            Some(i) if self.registered.get(i).fd == fd => {
+                let w = self.registered.get_mut(i);
+                w.interest = interest;
+                w.timer = is_timer;
+                return Ok(())
+            }
+            Some(i) => {
+                let mut old = self.registered.swap_remove(i).unwrap();
+                old.close();
+            }
+            None => {}
+        }
+        let w = try!(PollWatcher::new(&self.loop_, fd, token, interest,
+                                      is_timer).map_err(uv_error_to_io_error));
+        self.registered.push(w);
+        Ok(())
+    }
+
+    fn deregister(&mut self, token: uint) -> IoResult<()> {
+        let _m = self.fire_homing_missile();
+        match self.position(token) {
+            Some(i) => {
+                let mut w = self.registered.swap_remove(i).unwrap();
+                w.close();
+                Ok(())
+            }
+            None => Err(io::standard_error(io::InvalidInput)),
+        }
+    }
+
+    fn select(&mut self, timeout_ms: Option<u64>) -> IoResult<Vec<Event>> {
+        let _m = self.fire_homing_missile();
+        let blocker = &mut self.blocker as *mut Option<BlockedTask>;
+        for w in self.registered.mut_iter() {
+            w.ready = Interest::empty();
+            w.blocker = blocker;
+            match unsafe { uvll::uv_poll_start(w.handle, w.events(), poll_cb) } {
+                0 => {}
+                n => {
+                    self.stop();
+                    return Err(uv_error_to_io_error(UvError(n)))
+                }
+            }
+        }
+        match timeout_ms {
+            Some(ms) => {
+                if self.timer.is_none() {
+                    let t = box TimerWatcher::new_home(&self.loop_,
+                                                       self.home.clone());
+                    self.timer = Some(t);
+                }
+                let timer = self.timer.get_mut_ref();
+                unsafe { timer.set_data(blocker as *Option<BlockedTask>) }
+                timer.start(timer_cb, ms, 0);
+            }
+            None => {}
+        }
+
+        wait_until_woken_after(blocker, &self.loop_, || {});
+        self.stop();
+
+        let mut ret = Vec::new();
+        for w in self.registered.iter() {
+            if w.ready.is_empty() { continue }
+            if w.timer { drain(w.fd) }
+            ret.push(Event { token: w.token, readiness: w.ready });
+        }
+        Ok(ret)
+    }
+}
+
+extern fn poll_cb(handle: *uvll::uv_poll_t, status: c_int, events: c_int) {
+    let w: &mut PollWatcher = unsafe { UvHandle::from_uv_handle(&handle) };
+
+    // On error, report whatever was asked for so that the next operation on
+    // the object sees the error.
+    if status < 0 {
+        w.ready = w.interest;
+    } else {
+        if events & uvll::UV_READABLE != 0 { w.ready.insert(Readable) }
+        if events & uvll::UV_WRITABLE != 0 { w.ready.insert(Writable) }
+        w.ready = w.ready & w.interest;
+    }
+    if w.ready.is_empty() { return }
+
+    // Other handles may have already woken up the selector during this turn
+    // of the loop, in which case this one just adds its readiness.
+    let slot: &mut Option<BlockedTask> = unsafe { &mut *w.blocker };
+    if slot.is_some() { wakeup(slot) }
+}
+
+extern fn timer_cb(handle: *uvll::uv_timer_t) {
+    let slot: &mut Option<BlockedTask> = unsafe {
+        mem::transmute(uvll::get_data_for_uv_handle(handle))
+    };
+    if slot.is_some() { wakeup(slot) }
+}
+
+/// Blocks the current task until `fd` is ready for `interest`. This has to be
+/// called from the home of whatever owns the descriptor.
+pub fn wait_fd(loop_: &Loop, fd: c_int,
+               interest: Interest) -> Result<(), UvError> {
+    let mut w = try!(PollWatcher::new(loop_, fd, 0, interest, false));
+    let mut blocker = None;
+    w.blocker = &mut blocker as *mut Option<BlockedTask>;
+    let ret = match unsafe { uvll::uv_poll_start(w.handle, w.events(), poll_cb) } {
+        0 => {
+            wait_until_woken_after(w.blocker, loop_, || {});
+            Ok(())
+        }
+        n => Err(UvError(n)),
+    };
+    w.close();
+    ret
+}
+
+/// Performs a system call on a nonblocking descriptor which libuv doesn't
+/// provide, waiting for `interest` on the descriptor whenever the call would
+/// block.
+#[cfg(unix)]
+pub fn nonblocking(loop_: &Loop, fd: c_int, interest: Interest,
+                   f: || -> libc::ssize_t) -> IoResult<uint> {
+    use std::os;
+
+    loop {
+        match f() {
+            -1 => {
+                let errno = os::errno() as c_int;
+                if errno == libc::EINTR { continue }
+                if errno != libc::EAGAIN && errno != libc::EWOULDBLOCK {
+                    return Err(io::IoError::last_error())
+                }
+                try!(wait_fd(loop_, fd, interest).map_err(uv_error_to_io_error));
+            }
+            n => return Ok(n as uint),
+        }
+    }
+}
+
+/// The source to register for a libuv handle which wraps a descriptor.
+pub fn event_source<T>(handle: *T) -> IoResult<rtio::EventSource> {
+    let mut fd = -1;
+    match unsafe { uvll::uv_fileno(handle as *uvll::uv_handle_t, &mut fd) } {
+        0 => Ok(rtio::FdSource(fd)),
+        n => Err(uv_error_to_io_error(UvError(n))),
+    }
+}
+
+/// Creates the nonblocking pipe which makes a timer selectable, returning the
+/// read and write ends.
+#[cfg(unix)]
+pub fn notify_pipe() -> IoResult<(c_int, c_int)> {
+    let mut fds = [0 as c_int, ..2];
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return Err(io::IoError::last_error())
+        }
+    }
+    for fd in fds.iter() {
+        set_nonblocking(*fd);
+    }
+    Ok((fds[0], fds[1]))
+}
+
+/// Puts a descriptor which wasn't created by libuv into nonblocking mode.
+#[cfg(unix)]
+pub fn set_nonblocking(fd: c_int) {
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    static O_NONBLOCK: c_int = 0x800;
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    static O_NONBLOCK: c_int = 0x4;
+    static F_SETFL: c_int = 4;
+
+    unsafe { libc::fcntl(fd, F_SETFL, O_NONBLOCK); }
+}
+
+#[cfg(windows)]
+pub fn notify_pipe() -> IoResult<(c_int, c_int)> {
+    Err(io::standard_error(io::IoUnavailable))
+}
+
+// Timer notification pipes are nonblocking, so this reads until the pipe is
+// empty.
+#[cfg(unix)]
+fn drain(fd: c_int) {
+    let mut buf = [0u8, ..64];
+    loop {
+        let n = unsafe {
+            libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void,
+                       buf.len() as libc::size_t)
+        };
+        if n <= 0 { break }
+    }
+}
+
+#[cfg(windows)]
+fn drain(_fd: c_int) {}
+
+impl Drop for SelectorWatcher {
+    fn drop(&mut self) {
+        let _m = self.fire_homing_missile();
+        for w in self.registered.mut_iter() {
+            w.close();
+        }
+        self.timer = None;
+    }
+}
@@ -77,7 +77,8 @@ impl Process {
                 if cfg.gid.is_some() {
                     flags |= uvll::PROCESS_SETGID;
                 }
-                if cfg.detach {
+                // On unix a detached child calls setsid.
+                if cfg.detach || cfg.setsid {
                     flags |= uvll::PROCESS_DETACHED;
                 }
                 let options = uvll::uv_process_options_t {
@@ -8,12 +8,16 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use libc;
+use std::io::IoResult;
 use std::mem;
+use std::rt::rtio;
 use std::rt::rtio::RtioTimer;
-use std::rt::task::BlockedTask;
+use std::rt::task::{BlockedTask, OnTimer};
 
 use homing::{HomeHandle, HomingIO};
-use super::{UvHandle, ForbidUnwind, ForbidSwitch, wait_until_woken_after, Loop};
+use poll;
+use super::{UvHandle, ForbidUnwind, ForbidSwitch, wait_until_woken_on, Loop};
 use uvio::UvIoFactory;
 use uvll;
 
@@ -23,12 +27,15 @@ pub struct TimerWatcher {
     action: Option<NextAction>,
     blocker: Option<BlockedTask>,
     id: uint, // see comments in timer_cb
+    // read and write ends of the pipe which makes the timer selectable
+    notify: Option<(libc::c_int, libc::c_int)>,
 }
 
 pub enum NextAction {
     WakeTask,
     SendOnce(Sender<()>),
     SendMany(Sender<()>, uint),
+    CallOnce(Box<rtio::Callback:Send>),
 }
 
 impl TimerWatcher {
@@ -47,6 +54,7 @@ impl TimerWatcher {
             blocker: None,
             home: home,
             id: 0,
+            notify: None,
         }
     }
 
@@ -97,7 +105,7 @@ impl RtioTimer for TimerWatcher {
         let _f = ForbidUnwind::new("timer");
 
         self.action = Some(WakeTask);
-        wait_until_woken_after(&mut self.blocker, &self.uv_loop(), || {
+        wait_until_woken_on(&mut self.blocker, &self.uv_loop(), OnTimer, || {
             self.start(timer_cb, msecs, 0);
         });
         self.stop();
@@ -134,18 +142,50 @@ impl RtioTimer for TimerWatcher {
 
         return rx;
     }
+
+    fn oneshot_callback(&mut self, msecs: u64, cb: Box<rtio::Callback:Send>) {
+        // similarly to the destructor, we must drop the previous action outside
+        // of the homing missile
+        let _prev_action = {
+            let _m = self.fire_homing_missile();
+            self.id += 1;
+            self.stop();
+            self.start(timer_cb, msecs, 0);
+            mem::replace(&mut self.action, Some(CallOnce(cb)))
+        };
+    }
+
+    fn event_source(&mut self) -> IoResult<rtio::EventSource> {
+        let _m = self.fire_homing_missile();
+        if self.notify.is_none() {
+            self.notify = Some(try!(poll::notify_pipe()));
+        }
+        let (reader, _) = self.notify.unwrap();
+        Ok(rtio::TimerSource(reader))
+    }
 }
 
 extern fn timer_cb(handle: *uvll::uv_timer_t) {
     let _f = ForbidSwitch::new("timer callback can't switch");
     let timer: &mut TimerWatcher = unsafe { UvHandle::from_uv_handle(&handle) };
 
+    // If nobody has drained the (nonblocking) pipe for a while, this just
+    // drops the notification.
+    match timer.notify {
+        Some((_, writer)) => unsafe {
+            let buf = [0u8];
+            let _ = libc::write(writer, buf.as_ptr() as *libc::c_void, 1);
+        },
+        None => {}
+    }
+
     match timer.action.take_unwrap() {
         WakeTask => {
             let task = timer.blocker.take_unwrap();
             let _ = task.wake().map(|t| t.reawaken());
         }
         SendOnce(chan) => { let _ = chan.send_opt(()); }
+        CallOnce(mut cb) => cb.call(),
         SendMany(chan, id) => {
             let _ = chan.send_opt(());
 
@@ -175,6 +215,13 @@ impl Drop for TimerWatcher {
             let _m = self.fire_homing_missile();
             self.stop();
             self.close();
+            match self.notify.take() {
+                Some((reader, writer)) => unsafe {
+                    libc::close(reader);
+                    libc::close(writer);
+                },
+                None => {}
+            }
             self.action.take()
         };
     }
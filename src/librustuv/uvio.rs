@@ -33,12 +33,14 @@ use super::{uv_error_to_io_error, Loop};
 
 use addrinfo::GetAddrInfoRequest;
 use async::AsyncWatcher;
-use file::{FsRequest, FileWatcher};
+#[cfg(unix)] use datagram::UnixDatagramWatcher;
+use file::{FsRequest, FileWatcher, Directory};
 use queue::QueuePool;
 use homing::HomeHandle;
 use idle::IdleWatcher;
 use net::{TcpWatcher, TcpListener, UdpWatcher};
 use pipe::{PipeWatcher, PipeListener};
+use poll::SelectorWatcher;
 use process::Process;
 use signal::SignalWatcher;
 use timer::TimerWatcher;
@@ -148,25 +150,26 @@ impl IoFactory for UvIoFactory {
     // Connect to an address and return a new stream
     // NB: This blocks the task waiting on the connection.
     // It would probably be better to return a future
-    fn tcp_connect(&mut self, addr: SocketAddr, timeout: Option<u64>)
+    fn tcp_connect(&mut self, addr: SocketAddr, cfg: &rtio::SocketConfig,
+                   timeout: Option<u64>)
                    -> Result<Box<rtio::RtioTcpStream:Send>, IoError> {
-        match TcpWatcher::connect(self, addr, timeout) {
+        match TcpWatcher::connect(self, addr, cfg, timeout) {
             Ok(t) => Ok(box t as Box<rtio::RtioTcpStream:Send>),
             Err(e) => Err(uv_error_to_io_error(e)),
         }
     }
 
-    fn tcp_bind(&mut self, addr: SocketAddr)
+    fn tcp_bind(&mut self, addr: SocketAddr, cfg: &rtio::SocketConfig)
                 -> Result<Box<rtio::RtioTcpListener:Send>, IoError> {
-        match TcpListener::bind(self, addr) {
+        match TcpListener::bind(self, addr, cfg) {
             Ok(t) => Ok(t as Box<rtio::RtioTcpListener:Send>),
             Err(e) => Err(uv_error_to_io_error(e)),
         }
     }
 
-    fn udp_bind(&mut self, addr: SocketAddr)
+    fn udp_bind(&mut self, addr: SocketAddr, cfg: &rtio::SocketConfig)
                 -> Result<Box<rtio::RtioUdpSocket:Send>, IoError> {
-        match UdpWatcher::bind(self, addr) {
+        match UdpWatcher::bind(self, addr, cfg) {
             Ok(u) => Ok(box u as Box<rtio::RtioUdpSocket:Send>),
             Err(e) => Err(uv_error_to_io_error(e)),
         }
@@ -246,6 +249,12 @@ impl IoFactory for UvIoFactory {
         let r = FsRequest::readdir(&self.loop_, path, flags);
         r.map_err(uv_error_to_io_error)
     }
+    fn fs_opendir(&mut self, path: &CString)
+                  -> Result<Box<rtio::RtioDirectory:Send>, IoError> {
+        Directory::open(self, path).map(|d| {
+            box d as Box<rtio::RtioDirectory:Send>
+        })
+    }
     fn fs_link(&mut self, src: &CString, dst: &CString) -> Result<(), IoError> {
         let r = FsRequest::link(&self.loop_, src, dst);
         r.map_err(uv_error_to_io_error)
@@ -274,6 +283,16 @@ impl IoFactory for UvIoFactory {
                        Vec<Option<Box<rtio::RtioPipe:Send>>>),
                       IoError>
     {
+        // libuv runs nothing of ours in the child between fork and exec, so
+        // there's nowhere to join an existing group or take a terminal.
+        if cfg.pgroup.is_some() || cfg.controlling_tty ||
+           (cfg!(windows) && cfg.setsid) {
+            return Err(IoError {
+                kind: io::IoUnavailable,
+                desc: "process groups and terminals are unsupported by libuv",
+                detail: None,
+            })
+        }
         match Process::spawn(self, cfg) {
             Ok((p, io)) => {
                 Ok((p as Box<rtio::RtioProcess:Send>,
@@ -328,4 +347,21 @@ impl IoFactory for UvIoFactory {
             Err(e) => Err(uv_error_to_io_error(e)),
         }
     }
+
+    #[cfg(unix)]
+    fn unix_datagram(&mut self, path: Option<&CString>)
+                     -> Result<Box<rtio::RtioUnixDatagram:Send>, IoError> {
+        UnixDatagramWatcher::new(self, path).map(|s| {
+            box s as Box<rtio::RtioUnixDatagram:Send>
+        })
+    }
+    #[cfg(windows)]
+    fn unix_datagram(&mut self, _path: Option<&CString>)
+                     -> Result<Box<rtio::RtioUnixDatagram:Send>, IoError> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
+
+    fn selector(&mut self) -> Result<Box<rtio::RtioSelector:Send>, IoError> {
+        Ok(SelectorWatcher::new(self) as Box<rtio::RtioSelector:Send>)
+    }
 }
@@ -85,6 +85,11 @@ pub static PROCESS_WINDOWS_VERBATIM_ARGUMENTS: c_int = 1 << 2;
 pub static PROCESS_DETACHED: c_int = 1 << 3;
 pub static PROCESS_WINDOWS_HIDE: c_int = 1 << 4;
 
+pub static UV_READABLE: c_int = 1;
+pub static UV_WRITABLE: c_int = 2;
+
+pub static UV_UDP_REUSEADDR: c_uint = 4;
+
 pub static STDIO_IGNORE: c_int = 0x00;
 pub static STDIO_CREATE_PIPE: c_int = 0x01;
 pub static STDIO_INHERIT_FD: c_int = 0x02;
@@ -157,7 +162,9 @@ pub type uv_process_t = c_void;
 pub type uv_pipe_t = c_void;
 pub type uv_tty_t = c_void;
 pub type uv_signal_t = c_void;
+pub type uv_poll_t = c_void;
 pub type uv_shutdown_t = c_void;
+pub type uv_work_t = c_void;
 
 pub struct uv_timespec_t {
     pub tv_sec: libc::c_long,
@@ -247,11 +254,18 @@ pub type uv_signal_cb = extern "C" fn(handle: *uv_signal_t,
                                       signum: c_int);
 pub type uv_fs_cb = extern "C" fn(req: *uv_fs_t);
 pub type uv_shutdown_cb = extern "C" fn(req: *uv_shutdown_t, status: c_int);
+pub type uv_poll_cb = extern "C" fn(handle: *uv_poll_t,
+                                    status: c_int,
+                                    events: c_int);
+pub type uv_work_cb = extern "C" fn(req: *uv_work_t);
+pub type uv_after_work_cb = extern "C" fn(req: *uv_work_t, status: c_int);
 
 #[cfg(unix)] pub type uv_uid_t = libc::types::os::arch::posix88::uid_t;
 #[cfg(unix)] pub type uv_gid_t = libc::types::os::arch::posix88::gid_t;
 #[cfg(windows)] pub type uv_uid_t = libc::c_uchar;
 #[cfg(windows)] pub type uv_gid_t = libc::c_uchar;
+#[cfg(unix)] pub type uv_os_sock_t = c_int;
+#[cfg(windows)] pub type uv_os_sock_t = libc::SOCKET;
 
 #[repr(C)]
 #[deriving(Eq)]
@@ -555,6 +569,7 @@ extern {
     pub fn uv_tcp_init(l: *uv_loop_t, h: *uv_tcp_t) -> c_int;
     pub fn uv_tcp_connect(c: *uv_connect_t, h: *uv_tcp_t,
                           addr: *sockaddr, cb: uv_connect_cb) -> c_int;
+    pub fn uv_tcp_open(h: *uv_tcp_t, sock: uv_os_sock_t) -> c_int;
     pub fn uv_tcp_bind(t: *uv_tcp_t, addr: *sockaddr) -> c_int;
     pub fn uv_tcp_nodelay(h: *uv_tcp_t, enable: c_int) -> c_int;
     pub fn uv_tcp_keepalive(h: *uv_tcp_t, enable: c_int,
@@ -567,6 +582,7 @@ extern {
 
     // udp bindings
     pub fn uv_udp_init(l: *uv_loop_t, h: *uv_udp_t) -> c_int;
+    pub fn uv_udp_open(h: *uv_udp_t, sock: uv_os_sock_t) -> c_int;
     pub fn uv_udp_bind(h: *uv_udp_t, addr: *sockaddr, flags: c_uint) -> c_int;
     pub fn uv_udp_recv_start(server: *uv_udp_t,
                              on_alloc: uv_alloc_cb,
@@ -637,6 +653,11 @@ extern {
     pub fn uv_fs_lstat(handle: *uv_loop_t, req: *uv_fs_t, file: *c_char,
                        cb: uv_fs_cb) -> c_int;
 
+    // threadpool
+    pub fn uv_queue_work(loop_: *uv_loop_t, req: *uv_work_t,
+                         work_cb: uv_work_cb,
+                         after_work_cb: uv_after_work_cb) -> c_int;
+
     // getaddrinfo
     pub fn uv_getaddrinfo(loop_: *uv_loop_t, req: *uv_getaddrinfo_t,
                           getaddrinfo_cb: uv_getaddrinfo_cb,
@@ -669,6 +690,14 @@ extern {
     pub fn uv_signal_start(h: *uv_signal_t, cb: uv_signal_cb,
                            signum: c_int) -> c_int;
     pub fn uv_signal_stop(handle: *uv_signal_t) -> c_int;
+
+    // polling
+    pub fn uv_poll_init(loop_: *uv_loop_t, handle: *uv_poll_t,
+                        fd: c_int) -> c_int;
+    pub fn uv_poll_start(handle: *uv_poll_t, events: c_int,
+                         cb: uv_poll_cb) -> c_int;
+    pub fn uv_poll_stop(handle: *uv_poll_t) -> c_int;
+    pub fn uv_fileno(handle: *uv_handle_t, fd: *mut c_int) -> c_int;
 }
 
 // libuv requires other native libraries on various platforms. These are all
@@ -284,10 +284,11 @@ use option::{Some, None, Option};
 use owned::Box;
 use result::{Ok, Err, Result};
 use rt::local::Local;
-use rt::task::{Task, BlockedTask};
+use rt::task::{Task, BlockedTask, BlockTimeout, OnTimer};
+use rt::stats;
 use ty::Unsafe;
 
-pub use comm::select::{Select, Handle};
+pub use comm::select::{Select, Handle, SendHandle, SelectableHandle, Selectable};
 
 macro_rules! test (
     { fn $name:ident() $b:block $(#[$a:meta])*} => (
@@ -541,6 +542,12 @@ impl<T: Send> Sender<T> {
     /// assert_eq!(tx.send_opt(1), Err(1));
     /// ```
     pub fn send_opt(&self, t: T) -> Result<(), T> {
+        let ret = self.do_send_opt(t);
+        if ret.is_ok() { stats::message_sent() }
+        ret
+    }
+
+    fn do_send_opt(&self, t: T) -> Result<(), T> {
         // In order to prevent starvation of other tasks in situations where
         // a task sends repeatedly without ever receiving, we occassionally
         // yield instead of doing a send immediately.
@@ -692,7 +699,9 @@ impl<T: Send> SyncSender<T> {
     ///
     /// This function cannot fail.
     pub fn send_opt(&self, t: T) -> Result<(), T> {
-        unsafe { (*self.inner.get()).send(t) }
+        let ret = unsafe { (*self.inner.get()).send(t) };
+        if ret.is_ok() { stats::message_sent() }
+        ret
     }
 
     /// Attempts to send a value on this channel without blocking.
@@ -709,7 +718,9 @@ impl<T: Send> SyncSender<T> {
     ///
     /// This function cannot fail
     pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
-        unsafe { (*self.inner.get()).try_send(t) }
+        let ret = unsafe { (*self.inner.get()).try_send(t) };
+        if ret.is_ok() { stats::message_sent() }
+        ret
     }
 }
 
@@ -773,6 +784,12 @@ impl<T: Send> Receiver<T> {
     ///
     /// This function cannot fail.
     pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let ret = self.do_try_recv();
+        if ret.is_ok() { stats::message_received() }
+        ret
+    }
+
+    fn do_try_recv(&self) -> Result<T, TryRecvError> {
         // If a thread is spinning in try_recv, we should take the opportunity
         // to reschedule things occasionally. See notes above in scheduling on
         // sends for why this doesn't always hit TLS, and also for why this uses
@@ -837,6 +854,12 @@ impl<T: Send> Receiver<T> {
     /// If the channel has hung up, then `Err` is returned. Otherwise `Ok` of
     /// the value found on the receiver is returned.
     pub fn recv_opt(&self) -> Result<T, ()> {
+        let ret = self.do_recv_opt();
+        if ret.is_ok() { stats::message_received() }
+        ret
+    }
+
+    fn do_recv_opt(&self) -> Result<T, ()> {
         loop {
             let new_port = match *unsafe { self.inner() } {
                 Oneshot(ref p) => {
@@ -870,6 +893,54 @@ impl<T: Send> Receiver<T> {
         }
     }
 
+    /// Blocks waiting for a value on this receiver, but for at most `msecs`
+    /// milliseconds.
+    ///
+    /// If the timeout expires before a value is received then `Err(Empty)` is
+    /// returned, and if the channel hangs up then `Err(Disconnected)` is
+    /// returned. A timeout of 0 is the same as calling `try_recv`.
+    ///
+    /// # Failure
+    ///
+    /// This method will fail if the current task's runtime is unable to
+    /// provide a timer.
+    pub fn recv_timeout(&self, msecs: u64) -> Result<T, TryRecvError> {
+        match self.try_recv() {
+            Err(Empty) if msecs > 0 => {}
+            ret => return ret,
+        }
+        let timeout = match BlockTimeout::new(msecs) {
+            Some(timeout) => timeout,
+            None => fail!("no timer is available to time out a receive"),
+        };
+
+        // This is a selection over just this receiver and the timer, so
+        // whichever one of the two gets to the blocked task first wakes it up.
+        let mut contexts = 0;
+        let mut selected = false;
+        // The task is described as waiting on the timer, as that's certain to
+        // wake it up even if the channel never does.
+        let task: Box<Task> = Local::take();
+        task.deschedule_on(OnTimer, 2, |task| {
+            contexts += 1;
+            if contexts == 1 {
+                let ret = self.start_selection(task);
+                selected = ret.is_ok();
+                ret
+            } else {
+                timeout.block(task)
+            }
+        });
+
+        let ready = !selected || self.abort_selection();
+        timeout.reset();
+        if ready {
+            self.recv_opt().map_err(|()| Disconnected)
+        } else {
+            Err(Empty)
+        }
+    }
+
     /// Returns an iterator which will block waiting for messages, but never
     /// `fail!`. It will return `None` when the channel has hung up.
     pub fn iter<'a>(&'a self) -> Messages<'a, T> {
@@ -877,8 +948,8 @@ impl<T: Send> Receiver<T> {
     }
 }
 
-impl<T: Send> select::Packet for Receiver<T> {
-    fn can_recv(&self) -> bool {
+impl<T: Send> select::Selectable for Receiver<T> {
+    fn ready(&self) -> bool {
         loop {
             let new_port = match *unsafe { self.inner() } {
                 Oneshot(ref p) => {
@@ -1514,6 +1585,55 @@ mod test {
         t.join();
         pdone.recv();
     })
+
+    test!(fn recv_timeout_oneshot() {
+        let (tx, rx) = channel::<int>();
+        assert_eq!(rx.recv_timeout(0), Err(Empty));
+        assert_eq!(rx.recv_timeout(10), Err(Empty));
+        spawn(proc() {
+            for _ in range(0, 100) { task::deschedule(); }
+            tx.send(1);
+        });
+        assert_eq!(rx.recv_timeout(100000), Ok(1));
+        assert_eq!(rx.recv_timeout(100000), Err(Disconnected));
+    })
+
+    test!(fn recv_timeout_stream() {
+        let (tx, rx) = channel::<int>();
+        tx.send(1);
+        assert_eq!(rx.recv_timeout(10), Ok(1));
+        assert_eq!(rx.recv_timeout(10), Err(Empty));
+        spawn(proc() {
+            for _ in range(0, 100) { task::deschedule(); }
+            tx.send(2);
+        });
+        assert_eq!(rx.recv_timeout(100000), Ok(2));
+        assert_eq!(rx.recv_timeout(100000), Err(Disconnected));
+    })
+
+    test!(fn recv_timeout_shared() {
+        let (tx, rx) = channel::<int>();
+        let tx2 = tx.clone();
+        assert_eq!(rx.recv_timeout(10), Err(Empty));
+        spawn(proc() { tx.send(1); });
+        spawn(proc() { tx2.send(2); });
+        let a = rx.recv_timeout(100000).unwrap();
+        let b = rx.recv_timeout(100000).unwrap();
+        assert_eq!(a + b, 3);
+        assert_eq!(rx.recv_timeout(100000), Err(Disconnected));
+    })
+
+    test!(fn recv_timeout_then_recv() {
+        // A timed out receive must leave the channel usable afterwards.
+        let (tx, rx) = channel::<int>();
+        for _ in range(0, 10) {
+            assert_eq!(rx.recv_timeout(1), Err(Empty));
+        }
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+        drop(tx);
+        assert_eq!(rx.recv_opt(), Err(()));
+    })
 }
 
 #[cfg(test)]
@@ -2028,4 +2148,17 @@ mod sync_tests {
         });
         assert_eq!(rx.recv(), 1);
     } #[ignore(reason = "flaky on libnative")])
+
+    test!(fn recv_timeout() {
+        let (tx, rx) = sync_channel::<int>(0);
+        assert_eq!(rx.recv_timeout(0), Err(Empty));
+        assert_eq!(rx.recv_timeout(10), Err(Empty));
+        spawn(proc() {
+            tx.send(1);
+            tx.send(2);
+        });
+        assert_eq!(rx.recv_timeout(100000), Ok(1));
+        assert_eq!(rx.recv_timeout(100000), Ok(2));
+        assert_eq!(rx.recv_timeout(100000), Err(Disconnected));
+    })
 }
@@ -40,7 +40,7 @@ use option::{Some, None, Option};
 use owned::Box;
 use result::{Result, Ok, Err};
 use rt::local::Local;
-use rt::task::{Task, BlockedTask};
+use rt::task::{Task, BlockedTask, OnChannel};
 use sync::atomics;
 
 // Various states you can find a port in.
@@ -139,7 +139,7 @@ impl<T: Send> Packet<T> {
         // like we're not empty, then immediately go through to `try_recv`.
         if self.state.load(atomics::SeqCst) == EMPTY {
             let t: Box<Task> = Local::take();
-            t.deschedule(1, |task| {
+            t.deschedule_on(OnChannel, 1, |task| {
                 let n = unsafe { task.cast_to_uint() };
                 match self.state.compare_and_swap(EMPTY, n, atomics::SeqCst) {
                     // Nothing on the channel, we legitimately block
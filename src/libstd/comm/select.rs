@@ -56,12 +56,15 @@ use owned::Box;
 use ptr::RawPtr;
 use result::{Ok, Err, Result};
 use rt::local::Local;
-use rt::task::{Task, BlockedTask};
-use super::Receiver;
+use rt::task::{Task, BlockedTask, BlockTimeout, OnChannel, OnTimer};
+use super::{Receiver, SyncSender, Full, RecvDisconnected};
+use super::sync;
+use ty::Unsafe;
 use uint;
 
 /// The "receiver set" of the select interface. This structure is used to manage
-/// a set of receivers which are being selected over.
+/// a set of receivers (and pending sends on synchronous channels) which are
+/// being selected over.
 pub struct Select {
     head: *mut Handle<'static, ()>,
     tail: *mut Handle<'static, ()>,
@@ -80,20 +83,76 @@ pub struct Handle<'rx, T> {
     next: *mut Handle<'static, ()>,
     prev: *mut Handle<'static, ()>,
     added: bool,
-    packet: &'rx Packet,
+    packet: &'rx Selectable,
 
     // due to our fun transmutes, we be sure to place this at the end. (nothing
     // previous relies on T)
     rx: &'rx Receiver<T>,
 }
 
+/// A handle to a pending send on a `SyncSender` which is currently a member of
+/// a `Select` set. The handle holds on to the value to send, which is sent on
+/// the channel by the call to `wait` which returns this handle's id.
+pub struct SendHandle<'tx, T> {
+    /// The ID of this handle, used to compare against the return value of
+    /// `Select::wait()`
+    id: uint,
+    selector: &'tx Select,
+    next: *mut Handle<'static, ()>,
+    prev: *mut Handle<'static, ()>,
+    added: bool,
+    packet: &'tx Selectable,
+
+    // As with `Handle`, this has to come last. The packet above points into
+    // this allocation, so it doesn't move along with the handle.
+    inner: Box<SendPacket<'tx, T>>,
+}
+
+struct SendPacket<'tx, T> {
+    tx: &'tx SyncSender<T>,
+    data: Unsafe<Option<T>>,
+    result: Unsafe<Option<Result<(), T>>>,
+    node: Unsafe<sync::Node>,
+}
+
 struct Packets { cur: *mut Handle<'static, ()> }
 
-#[doc(hidden)]
-pub trait Packet {
-    fn can_recv(&self) -> bool;
+/// A handle to any other source of events which is currently a member of a
+/// `Select` set, such as the channels found in other libraries.
+pub struct SelectableHandle<'a> {
+    /// The ID of this handle, used to compare against the return value of
+    /// `Select::wait()`
+    id: uint,
+    selector: &'a Select,
+    next: *mut Handle<'static, ()>,
+    prev: *mut Handle<'static, ()>,
+    added: bool,
+    packet: &'a Selectable,
+}
+
+/// A source of events which can be waited on by a `Select` set.
+///
+/// This is implemented by `Receiver`, and it can be implemented by other
+/// channel-like types so that they can be selected over along with the
+/// channels in this module.
+pub trait Selectable {
+    /// Returns whether an event is available right now.
+    fn ready(&self) -> bool;
+
+    /// Registers the blocked task to be woken up once an event is available.
+    /// The task is handed back if an event is already available, in which case
+    /// `abort_selection` is not called.
     fn start_selection(&self, task: BlockedTask) -> Result<(), BlockedTask>;
+
+    /// Unregisters the task once it's running again (the task may have been
+    /// woken up by something else), returning whether an event is available.
     fn abort_selection(&self) -> bool;
+
+    /// Called when `wait` is about to return this source's id, returning
+    /// whether the event still happened. If not (for example if another task
+    /// took the event first) then `wait` goes back to waiting. This can be used
+    /// to carry out the operation that the event was for.
+    fn complete(&self) -> bool { true }
 }
 
 impl Select {
@@ -128,18 +187,122 @@ impl Select {
         }
     }
 
+    /// Creates a new handle into this set for sending `t` on `tx`. As with
+    /// `handle`, the handle must be added to the set with its `add` method.
+    ///
+    /// A send handle is ready once the send can be done without blocking, and
+    /// when `wait` returns its id the value has been sent (or the receiver
+    /// has hung up). The outcome can then be retrieved with `result`.
+    pub fn send_handle<'a, T: Send>(&'a self, tx: &'a SyncSender<T>,
+                                    t: T) -> SendHandle<'a, T> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let inner = box SendPacket {
+            tx: tx,
+            data: Unsafe::new(Some(t)),
+            result: Unsafe::new(None),
+            node: Unsafe::new(sync::Node::new()),
+        };
+        let packet: &'a Selectable = unsafe {
+            mem::transmute(&*inner as &Selectable)
+        };
+        SendHandle {
+            id: id,
+            selector: self,
+            next: 0 as *mut Handle<'static, ()>,
+            prev: 0 as *mut Handle<'static, ()>,
+            added: false,
+            packet: packet,
+            inner: inner,
+        }
+    }
+
+    /// Creates a new handle into this set for some other source of events. As
+    /// with `handle`, the handle must be added to the set with its `add`
+    /// method.
+    pub fn selectable_handle<'a>(&'a self,
+                                 source: &'a Selectable) -> SelectableHandle<'a> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        SelectableHandle {
+            id: id,
+            selector: self,
+            next: 0 as *mut Handle<'static, ()>,
+            prev: 0 as *mut Handle<'static, ()>,
+            added: false,
+            packet: source,
+        }
+    }
+
     /// Waits for an event on this receiver set. The returned value is *not* an
     /// index, but rather an id. This id can be queried against any active
     /// `Handle` structures (each one has an `id` method). The handle with
     /// the matching `id` will have some sort of event available on it. The
     /// event could either be that data is available or the corresponding
     /// channel has been closed.
+    ///
+    /// If the id belongs to a `SendHandle`, then its send has already been
+    /// carried out.
     pub fn wait(&self) -> uint {
         self.wait2(false)
     }
 
+    /// Waits for an event on this receiver set for at most `msecs`
+    /// milliseconds. This behaves like `wait`, except that `None` is returned
+    /// if no receiver became ready before the timeout expired.
+    ///
+    /// # Failure
+    ///
+    /// This method will fail if the current task's runtime is unable to
+    /// provide a timer.
+    pub fn wait_timeout(&self, msecs: u64) -> Option<uint> {
+        self.select(true, Some(msecs))
+    }
+
     /// Helper method for skipping the preflight checks during testing
     fn wait2(&self, do_preflight_checks: bool) -> uint {
+        self.select(do_preflight_checks, None).unwrap()
+    }
+
+    fn select(&self, do_preflight_checks: bool,
+              timeout: Option<u64>) -> Option<uint> {
+        unsafe {
+            let mut amt = 0;
+            for p in self.iter() {
+                amt += 1;
+                if do_preflight_checks && (*p).packet.ready() &&
+                   (*p).packet.complete() {
+                    return Some((*p).id);
+                }
+            }
+            assert!(amt > 0);
+
+            let timeout = match timeout {
+                Some(0) => return None,
+                Some(msecs) => match BlockTimeout::new(msecs) {
+                    Some(timeout) => Some(timeout),
+                    None => fail!("no timer is available to time out a select"),
+                },
+                None => None,
+            };
+
+            // A send handle can be woken up because there's room on its
+            // channel, but another sender may take the room before this
+            // handle gets to it. In that case nothing has happened yet, so we
+            // go back to sleep.
+            loop {
+                let (ready_id, timed_out) = self.block(amt, timeout.as_ref());
+                match ready_id {
+                    Some(id) if self.complete(id) => return Some(id),
+                    _ if timed_out => return None,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    unsafe fn block(&self, amt: uint,
+                    timeout: Option<&BlockTimeout>) -> (Option<uint>, bool) {
         // Note that this is currently an inefficient implementation. We in
         // theory have knowledge about all receivers in the set ahead of time,
         // so this method shouldn't really have to iterate over all of them yet
@@ -152,78 +315,123 @@ impl Select {
         //      fn select(receivers) {
         //          if any receiver ready { return ready index }
         //          deschedule {
-        //              block on all receivers
+        //              block on all receivers (and the timer)
         //          }
-        //          unblock on all receivers
+        //          unblock on all receivers (and the timer)
         //          return ready index
         //      }
         //
         // Most notably, the iterations over all of the receivers shouldn't be
         // necessary.
-        unsafe {
-            let mut amt = 0;
-            for p in self.iter() {
-                amt += 1;
-                if do_preflight_checks && (*p).packet.can_recv() {
-                    return (*p).id;
+        let contexts = if timeout.is_some() {amt + 1} else {amt};
+        let mut ready_index = amt;
+        let mut ready_id = uint::MAX;
+        let mut iter = self.iter().enumerate();
+
+        // Acquire a number of blocking contexts, and block on each one
+        // sequentially until one fails. If one fails, then abort
+        // immediately so we can go unblock on all the other receivers. The
+        // timer is handed the last blocking context.
+        let task: Box<Task> = Local::take();
+        let on = if timeout.is_some() {OnTimer} else {OnChannel};
+        task.deschedule_on(on, contexts, |task| {
+            // Prepare for the block
+            let (i, handle) = match iter.next() {
+                Some(pair) => pair,
+                None => return timeout.unwrap().block(task),
+            };
+            match (*handle).packet.start_selection(task) {
+                Ok(()) => Ok(()),
+                Err(task) => {
+                    ready_index = i;
+                    ready_id = (*handle).id;
+                    Err(task)
                 }
             }
-            assert!(amt > 0);
-
-            let mut ready_index = amt;
-            let mut ready_id = uint::MAX;
-            let mut iter = self.iter().enumerate();
-
-            // Acquire a number of blocking contexts, and block on each one
-            // sequentially until one fails. If one fails, then abort
-            // immediately so we can go unblock on all the other receivers.
-            let task: Box<Task> = Local::take();
-            task.deschedule(amt, |task| {
-                // Prepare for the block
-                let (i, handle) = iter.next().unwrap();
-                match (*handle).packet.start_selection(task) {
-                    Ok(()) => Ok(()),
-                    Err(task) => {
-                        ready_index = i;
-                        ready_id = (*handle).id;
-                        Err(task)
-                    }
-                }
-            });
+        });
 
-            // Abort the selection process on each receiver. If the abort
-            // process returns `true`, then that means that the receiver is
-            // ready to receive some data. Note that this also means that the
-            // receiver may have yet to have fully read the `to_wake` field and
-            // woken us up (although the wakeup is guaranteed to fail).
-            //
-            // This situation happens in the window of where a sender invokes
-            // increment(), sees -1, and then decides to wake up the task. After
-            // all this is done, the sending thread will set `selecting` to
-            // `false`. Until this is done, we cannot return. If we were to
-            // return, then a sender could wake up a receiver which has gone
-            // back to sleep after this call to `select`.
-            //
-            // Note that it is a "fairly small window" in which an increment()
-            // views that it should wake a thread up until the `selecting` bit
-            // is set to false. For now, the implementation currently just spins
-            // in a yield loop. This is very distasteful, but this
-            // implementation is already nowhere near what it should ideally be.
-            // A rewrite should focus on avoiding a yield loop, and for now this
-            // implementation is tying us over to a more efficient "don't
-            // iterate over everything every time" implementation.
-            for handle in self.iter().take(ready_index) {
-                if (*handle).packet.abort_selection() {
-                    ready_id = (*handle).id;
-                }
+        // Abort the selection process on each receiver. If the abort
+        // process returns `true`, then that means that the receiver is
+        // ready to receive some data. Note that this also means that the
+        // receiver may have yet to have fully read the `to_wake` field and
+        // woken us up (although the wakeup is guaranteed to fail).
+        //
+        // This situation happens in the window of where a sender invokes
+        // increment(), sees -1, and then decides to wake up the task. After
+        // all this is done, the sending thread will set `selecting` to
+        // `false`. Until this is done, we cannot return. If we were to
+        // return, then a sender could wake up a receiver which has gone
+        // back to sleep after this call to `select`.
+        //
+        // Note that it is a "fairly small window" in which an increment()
+        // views that it should wake a thread up until the `selecting` bit
+        // is set to false. For now, the implementation currently just spins
+        // in a yield loop. This is very distasteful, but this
+        // implementation is already nowhere near what it should ideally be.
+        // A rewrite should focus on avoiding a yield loop, and for now this
+        // implementation is tying us over to a more efficient "don't
+        // iterate over everything every time" implementation.
+        for handle in self.iter().take(ready_index) {
+            if (*handle).packet.abort_selection() {
+                ready_id = (*handle).id;
             }
+        }
 
-            assert!(ready_id != uint::MAX);
-            return ready_id;
+        // Even if the timer expired, a handle which became ready in the
+        // meantime is still reported.
+        let timed_out = timeout.map_or(false, |t| t.reset());
+        if ready_id == uint::MAX {
+            (None, timed_out)
+        } else {
+            (Some(ready_id), timed_out)
         }
     }
 
+    // Carries out the operation of a handle which has become ready, returning
+    // whether it completed.
+    unsafe fn complete(&self, id: uint) -> bool {
+        for handle in self.iter() {
+            if (*handle).id == id {
+                return (*handle).packet.complete()
+            }
+        }
+        unreachable!()
+    }
+
     fn iter(&self) -> Packets { Packets { cur: self.head } }
+
+    // Appends a handle to the set. The handle must not move while it's linked.
+    unsafe fn link(&self, me: *mut Handle<'static, ()>) {
+        let selector: &mut Select = mem::transmute(self);
+        if selector.head.is_null() {
+            selector.head = me;
+            selector.tail = me;
+        } else {
+            (*me).prev = selector.tail;
+            assert!((*me).next.is_null());
+            (*selector.tail).next = me;
+            selector.tail = me;
+        }
+    }
+
+    unsafe fn unlink(&self, me: *mut Handle<'static, ()>) {
+        let selector: &mut Select = mem::transmute(self);
+        if (*me).prev.is_null() {
+            assert_eq!(selector.head, me);
+            selector.head = (*me).next;
+        } else {
+            (*(*me).prev).next = (*me).next;
+        }
+        if (*me).next.is_null() {
+            assert_eq!(selector.tail, me);
+            selector.tail = (*me).prev;
+        } else {
+            (*(*me).next).prev = (*me).prev;
+        }
+
+        (*me).next = 0 as *mut Handle<'static, ()>;
+        (*me).prev = 0 as *mut Handle<'static, ()>;
+    }
 }
 
 impl<'rx, T: Send> Handle<'rx, T> {
@@ -247,18 +455,7 @@ impl<'rx, T: Send> Handle<'rx, T> {
     /// while it is added to the `Select` set.
     pub unsafe fn add(&mut self) {
         if self.added { return }
-        let selector: &mut Select = mem::transmute(&*self.selector);
-        let me: *mut Handle<'static, ()> = mem::transmute(&*self);
-
-        if selector.head.is_null() {
-            selector.head = me;
-            selector.tail = me;
-        } else {
-            (*me).prev = selector.tail;
-            assert!((*me).next.is_null());
-            (*selector.tail).next = me;
-            selector.tail = me;
-        }
+        self.selector.link(mem::transmute(&*self));
         self.added = true;
     }
 
@@ -267,30 +464,130 @@ impl<'rx, T: Send> Handle<'rx, T> {
     /// called.
     pub unsafe fn remove(&mut self) {
         if !self.added { return }
+        self.selector.unlink(mem::transmute(&*self));
+        self.added = false;
+    }
+}
 
-        let selector: &mut Select = mem::transmute(&*self.selector);
-        let me: *mut Handle<'static, ()> = mem::transmute(&*self);
+impl<'tx, T: Send> SendHandle<'tx, T> {
+    /// Retrieve the id of this handle.
+    #[inline]
+    pub fn id(&self) -> uint { self.id }
 
-        if self.prev.is_null() {
-            assert_eq!(selector.head, me);
-            selector.head = self.next;
-        } else {
-            (*self.prev).next = self.next;
-        }
-        if self.next.is_null() {
-            assert_eq!(selector.tail, me);
-            selector.tail = self.prev;
-        } else {
-            (*self.next).prev = self.prev;
+    /// Takes the outcome of this handle's send. This is `Ok` if the value was
+    /// sent, or `Err` with the value if the receiver hung up. `None` is
+    /// returned if the send hasn't happened yet.
+    pub fn result(&mut self) -> Option<Result<(), T>> {
+        unsafe { (*self.inner.result.get()).take() }
+    }
+
+    /// Gives this handle a new value to send, so it can take part in further
+    /// calls to `wait` after its previous send has completed. Any previous
+    /// outcome is discarded, and a previous value which hasn't been sent yet
+    /// is returned.
+    ///
+    /// A handle with no value to send never becomes ready.
+    pub fn reset(&mut self, t: T) -> Option<T> {
+        unsafe {
+            *self.inner.result.get() = None;
+            mem::replace(&mut *self.inner.data.get(), Some(t))
         }
+    }
 
-        self.next = 0 as *mut Handle<'static, ()>;
-        self.prev = 0 as *mut Handle<'static, ()>;
+    /// Adds this handle to the set that the handle was created from. This
+    /// method can be called multiple times, but it has no effect if `add` was
+    /// called previously.
+    ///
+    /// This method is unsafe because it requires that the `SendHandle` is not
+    /// moved while it is added to the `Select` set.
+    pub unsafe fn add(&mut self) {
+        if self.added { return }
+        self.selector.link(mem::transmute(&*self));
+        self.added = true;
+    }
 
+    /// Removes this handle from the `Select` set. This method is unsafe because
+    /// it has no guarantee that the `SendHandle` was not moved since `add` was
+    /// called.
+    pub unsafe fn remove(&mut self) {
+        if !self.added { return }
+        self.selector.unlink(mem::transmute(&*self));
+        self.added = false;
+    }
+}
+
+impl<'a> SelectableHandle<'a> {
+    /// Retrieve the id of this handle.
+    #[inline]
+    pub fn id(&self) -> uint { self.id }
+
+    /// Adds this handle to the set that the handle was created from. This
+    /// method can be called multiple times, but it has no effect if `add` was
+    /// called previously.
+    ///
+    /// This method is unsafe because it requires that the `SelectableHandle`
+    /// is not moved while it is added to the `Select` set.
+    pub unsafe fn add(&mut self) {
+        if self.added { return }
+        self.selector.link(mem::transmute(&*self));
+        self.added = true;
+    }
+
+    /// Removes this handle from the `Select` set. This method is unsafe because
+    /// it has no guarantee that the `SelectableHandle` was not moved since
+    /// `add` was called.
+    pub unsafe fn remove(&mut self) {
+        if !self.added { return }
+        self.selector.unlink(mem::transmute(&*self));
         self.added = false;
     }
 }
 
+impl<'tx, T: Send> SendPacket<'tx, T> {
+    fn packet<'a>(&'a self) -> &'a sync::Packet<T> {
+        unsafe { &*self.tx.inner.get() }
+    }
+}
+
+impl<'tx, T: Send> Selectable for SendPacket<'tx, T> {
+    fn ready(&self) -> bool {
+        unsafe { (*self.data.get()).is_some() && self.packet().can_send() }
+    }
+
+    fn start_selection(&self, task: BlockedTask) -> Result<(), BlockedTask> {
+        unsafe {
+            // Without anything to send, this handle just never wakes us up.
+            if (*self.data.get()).is_none() { return Ok(()) }
+            self.packet().start_send_selection(&mut *self.node.get(), task)
+        }
+    }
+
+    fn abort_selection(&self) -> bool {
+        unsafe {
+            if (*self.data.get()).is_none() { return false }
+            self.packet().abort_send_selection(&mut *self.node.get())
+        }
+    }
+
+    fn complete(&self) -> bool {
+        unsafe {
+            let t = match (*self.data.get()).take() {
+                Some(t) => t,
+                None => return false,
+            };
+            match self.tx.try_send(t) {
+                Ok(()) => *self.result.get() = Some(Ok(())),
+                Err(RecvDisconnected(t)) => *self.result.get() = Some(Err(t)),
+                Err(Full(t)) => {
+                    *self.data.get() = Some(t);
+                    return false
+                }
+            }
+            true
+        }
+    }
+}
+
 #[unsafe_destructor]
 impl Drop for Select {
     fn drop(&mut self) {
@@ -306,6 +603,20 @@ impl<'rx, T: Send> Drop for Handle<'rx, T> {
     }
 }
 
+#[unsafe_destructor]
+impl<'tx, T: Send> Drop for SendHandle<'tx, T> {
+    fn drop(&mut self) {
+        unsafe { self.remove() }
+    }
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for SelectableHandle<'a> {
+    fn drop(&mut self) {
+        unsafe { self.remove() }
+    }
+}
+
 impl Iterator<*mut Handle<'static, ()>> for Packets {
     fn next(&mut self) -> Option<*mut Handle<'static, ()>> {
         if self.cur.is_null() {
@@ -685,4 +996,155 @@ mod test {
             }
         }
     })
+
+    test!(fn wait_timeout_expires() {
+        let (_tx1, rx1) = channel::<int>();
+        let (_tx2, rx2) = sync_channel::<int>(0);
+        let s = Select::new();
+        let mut h1 = s.handle(&rx1);
+        let mut h2 = s.handle(&rx2);
+        unsafe { h1.add(); h2.add(); }
+        assert_eq!(s.wait_timeout(0), None);
+        assert_eq!(s.wait_timeout(10), None);
+        assert_eq!(s.wait_timeout(10), None);
+    })
+
+    test!(fn wait_timeout_ready() {
+        let (tx1, rx1) = channel::<int>();
+        let (tx2, rx2) = channel::<int>();
+        let s = Select::new();
+        let mut h1 = s.handle(&rx1);
+        let mut h2 = s.handle(&rx2);
+        unsafe { h1.add(); h2.add(); }
+        tx1.send(1);
+        assert_eq!(s.wait_timeout(0), Some(h1.id()));
+        assert_eq!(h1.recv(), 1);
+
+        spawn(proc() {
+            for _ in range(0, 100) { task::deschedule() }
+            tx2.send(2);
+        });
+        assert_eq!(s.wait_timeout(100000), Some(h2.id()));
+        assert_eq!(h2.recv(), 2);
+    })
+
+    test!(fn send_smoke() {
+        let (tx, rx) = sync_channel::<int>(1);
+        let s = Select::new();
+        let mut h = s.send_handle(&tx, 1);
+        unsafe { h.add(); }
+        assert_eq!(s.wait(), h.id());
+        assert_eq!(h.result(), Some(Ok(())));
+        assert_eq!(h.result(), None);
+        assert_eq!(rx.recv(), 1);
+    })
+
+    test!(fn send_waits_for_room() {
+        let (tx1, rx1) = sync_channel::<int>(1);
+        let (_tx2, rx2) = channel::<int>();
+        tx1.send(1);
+        spawn(proc() {
+            for _ in range(0, 100) { task::deschedule() }
+            assert_eq!(rx1.recv(), 1);
+            assert_eq!(rx1.recv(), 2);
+        });
+        let s = Select::new();
+        let mut h1 = s.send_handle(&tx1, 2);
+        let mut h2 = s.handle(&rx2);
+        unsafe { h1.add(); h2.add(); }
+        assert_eq!(s.wait(), h1.id());
+        assert_eq!(h1.result(), Some(Ok(())));
+    })
+
+    test!(fn send_unbuffered() {
+        let (tx, rx) = sync_channel::<int>(0);
+        let (tx2, rx2) = channel();
+        spawn(proc() {
+            for _ in range(0, 100) { task::deschedule() }
+            assert_eq!(rx.recv(), 1);
+            tx2.send(());
+        });
+        let s = Select::new();
+        let mut h = s.send_handle(&tx, 1);
+        unsafe { h.add(); }
+        assert_eq!(s.wait(), h.id());
+        assert_eq!(h.result(), Some(Ok(())));
+        rx2.recv();
+    })
+
+    test!(fn send_or_recv() {
+        let (tx1, rx1) = sync_channel::<int>(0);
+        let (tx2, rx2) = channel::<int>();
+        let s = Select::new();
+        let mut h1 = s.send_handle(&tx1, 1);
+        let mut h2 = s.handle(&rx2);
+        unsafe { h1.add(); h2.add(); }
+
+        // nobody is receiving, so the receive has to win
+        tx2.send(2);
+        assert_eq!(s.wait(), h2.id());
+        assert_eq!(h2.recv(), 2);
+        assert_eq!(h1.result(), None);
+
+        spawn(proc() { assert_eq!(rx1.recv(), 1); });
+        assert_eq!(s.wait(), h1.id());
+        assert_eq!(h1.result(), Some(Ok(())));
+
+        // with nothing left to send, the handle stays out of the way
+        spawn(proc() { tx2.send(3); });
+        assert_eq!(s.wait(), h2.id());
+        assert_eq!(h2.recv(), 3);
+    })
+
+    test!(fn send_disconnected() {
+        let (tx, rx) = sync_channel::<int>(0);
+        let s = Select::new();
+        let mut h = s.send_handle(&tx, 1);
+        unsafe { h.add(); }
+        spawn(proc() {
+            for _ in range(0, 100) { task::deschedule() }
+            drop(rx);
+        });
+        assert_eq!(s.wait(), h.id());
+        assert_eq!(h.result(), Some(Err(1)));
+    })
+
+    test!(fn send_timeout() {
+        let (tx, rx) = sync_channel::<int>(1);
+        tx.send(1);
+        let s = Select::new();
+        let mut h = s.send_handle(&tx, 2);
+        unsafe { h.add(); }
+        assert_eq!(s.wait_timeout(0), None);
+        assert_eq!(s.wait_timeout(10), None);
+        assert_eq!(h.result(), None);
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(s.wait_timeout(0), Some(h.id()));
+        assert_eq!(h.reset(3), None);
+        assert_eq!(s.wait_timeout(10), None);
+        assert_eq!(rx.recv(), 2);
+        assert_eq!(h.reset(4), Some(3));
+    })
+
+    test!(fn send_many_selectors() {
+        // several selecting senders compete for the room on one channel
+        let (tx, rx) = sync_channel::<int>(1);
+        let (done, wait) = channel();
+        for i in range(0, 10) {
+            let tx = tx.clone();
+            let done = done.clone();
+            spawn(proc() {
+                let s = Select::new();
+                let mut h = s.send_handle(&tx, i);
+                unsafe { h.add(); }
+                assert_eq!(s.wait(), h.id());
+                assert_eq!(h.result(), Some(Ok(())));
+                done.send(());
+            });
+        }
+        let mut sum = 0;
+        for _ in range(0, 10) { sum += rx.recv(); }
+        for _ in range(0, 10) { wait.recv(); }
+        assert_eq!(sum, 45);
+    })
 }
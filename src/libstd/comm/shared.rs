@@ -27,7 +27,7 @@ use option::{Some, None, Option};
 use owned::Box;
 use result::{Ok, Err, Result};
 use rt::local::Local;
-use rt::task::{Task, BlockedTask};
+use rt::task::{Task, BlockedTask, OnChannel};
 use rt::thread::Thread;
 use sync::atomics;
 use unstable::mutex::NativeMutex;
@@ -225,7 +225,7 @@ impl<T: Send> Packet<T> {
         }
 
         let task: Box<Task> = Local::take();
-        task.deschedule(1, |task| {
+        task.deschedule_on(OnChannel, 1, |task| {
             self.decrement(task)
         });
 
@@ -27,7 +27,7 @@ use option::{Some, None};
 use owned::Box;
 use result::{Ok, Err, Result};
 use rt::local::Local;
-use rt::task::{Task, BlockedTask};
+use rt::task::{Task, BlockedTask, OnChannel};
 use rt::thread::Thread;
 use spsc = sync::spsc_queue;
 use sync::atomics;
@@ -183,7 +183,7 @@ impl<T: Send> Packet<T> {
         // Welp, our channel has no data. Deschedule the current task and
         // initiate the blocking protocol.
         let task: Box<Task> = Local::take();
-        task.deschedule(1, |task| {
+        task.deschedule_on(OnChannel, 1, |task| {
             self.decrement(task)
         });
 
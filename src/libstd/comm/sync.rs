@@ -43,7 +43,7 @@ use owned::Box;
 use ptr::RawPtr;
 use result::{Result, Ok, Err};
 use rt::local::Local;
-use rt::task::{Task, BlockedTask};
+use rt::task::{Task, BlockedTask, OnChannel};
 use sync::atomics;
 use ty::Unsafe;
 use unstable::mutex::{NativeMutex, LockGuard};
@@ -89,7 +89,7 @@ struct Queue {
     tail: *mut Node,
 }
 
-struct Node {
+pub struct Node {
     task: Option<BlockedTask>,
     next: *mut Node,
 }
@@ -108,16 +108,18 @@ pub enum Failure {
 }
 
 /// Atomically blocks the current task, placing it into `slot`, unlocking `lock`
-/// in the meantime. This re-locks the mutex upon returning.
+/// in the meantime. Once the lock is released, `pending` is woken up. This
+/// re-locks the mutex upon returning.
 fn wait(slot: &mut Blocker, f: fn(BlockedTask) -> Blocker,
-        lock: &NativeMutex) {
+        lock: &NativeMutex, mut pending: Option<BlockedTask>) {
     let me: Box<Task> = Local::take();
-    me.deschedule(1, |task| {
+    me.deschedule_on(OnChannel, 1, |task| {
         match mem::replace(slot, f(task)) {
             NoneBlocked => {}
             _ => unreachable!(),
         }
         unsafe { lock.unlock_noguard(); }
+        pending.take().map(|t| t.wake().map(|t| t.reawaken()));
         Ok(())
     });
     unsafe { lock.lock_noguard(); }
@@ -187,7 +189,7 @@ impl<T: Send> Packet<T> {
                 let mut canceled = false;
                 assert!(state.canceled.is_none());
                 state.canceled = Some(unsafe { mem::transmute(&mut canceled) });
-                wait(&mut state.blocker, BlockedSender, &self.lock);
+                wait(&mut state.blocker, BlockedSender, &self.lock, None);
                 if canceled {Err(state.buf.dequeue())} else {Ok(())}
             }
 
@@ -239,7 +241,8 @@ impl<T: Send> Packet<T> {
         // because we're the only receiver.
         let mut waited = false;
         if !state.disconnected && state.buf.size() == 0 {
-            wait(&mut state.blocker, BlockedReceiver, &self.lock);
+            let sender = state.waiting_sender();
+            wait(&mut state.blocker, BlockedReceiver, &self.lock, sender);
             waited = true;
         }
         if state.disconnected && state.buf.size() == 0 { return Err(()) }
@@ -375,7 +378,7 @@ impl<T: Send> Packet<T> {
     // Attempts to start selection on this port. This can either succeed or fail
     // because there is data waiting.
     pub fn start_selection(&self, task: BlockedTask) -> Result<(), BlockedTask>{
-        let (_g, state) = self.lock();
+        let (guard, state) = self.lock();
         if state.disconnected || state.buf.size() > 0 {
             Err(task)
         } else {
@@ -384,6 +387,9 @@ impl<T: Send> Packet<T> {
                 BlockedSender(..) => unreachable!(),
                 BlockedReceiver(..) => unreachable!(),
             }
+            let sender = state.waiting_sender();
+            mem::drop((state, guard));
+            sender.map(|t| t.wake().map(|t| t.reawaken()));
             Ok(())
         }
     }
@@ -403,6 +409,72 @@ impl<T: Send> Packet<T> {
             BlockedReceiver(task) => { task.trash(); false }
         }
     }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // select implementation for senders
+    ////////////////////////////////////////////////////////////////////////////
+
+    // Whether a send on this channel would complete without blocking, either by
+    // handing off the data or by finding that the receiver has gone away.
+    pub fn can_send(&self) -> bool {
+        let (_g, state) = self.lock();
+        state.disconnected || state.has_room()
+    }
+
+    // Attempts to start selection for a sender, which waits in the same queue
+    // as the senders blocked in `send`. The node must not move until
+    // `abort_send_selection` has been called with it.
+    pub fn start_send_selection(&self, node: &mut Node,
+                                task: BlockedTask) -> Result<(), BlockedTask> {
+        let (_g, state) = self.lock();
+        if state.disconnected || state.has_room() {
+            Err(task)
+        } else {
+            node.task = Some(task);
+            state.queue.push(node);
+            Ok(())
+        }
+    }
+
+    // Removes a selecting sender from this channel, returning whether a send
+    // would now complete without blocking.
+    pub fn abort_send_selection(&self, node: &mut Node) -> bool {
+        let (guard, state) = self.lock();
+        let woken = if state.queue.remove(node) {
+            node.task.take_unwrap().trash();
+            false
+        } else {
+            true
+        };
+        let ready = state.disconnected || state.has_room();
+
+        // If we were dequeued to be woken up, then we may have taken the wakeup
+        // of a sender blocked in `send`. The room may also be taken by another
+        // sender before this one gets around to sending, so pass the wakeup
+        // along (the others will all recheck for room anyway).
+        let next = if woken {state.queue.dequeue()} else {None};
+        mem::drop((state, guard));
+        next.map(|t| t.wake().map(|t| t.reawaken()));
+        ready
+    }
+}
+
+impl<T> State<T> {
+    // Whether a sender could hand off some data right now. Without a buffer,
+    // this also requires a receiver to be waiting for the data.
+    fn has_room(&self) -> bool {
+        self.buf.size() < self.buf.cap() && match self.blocker {
+            BlockedReceiver(..) => true,
+            _ => self.cap != 0,
+        }
+    }
+
+    // Without a buffer, senders selecting on this channel wait for a receiver
+    // to turn up. This returns one of them to be woken once the receiver has
+    // blocked (the senders blocked in `send` recheck for room upon waking).
+    fn waiting_sender(&mut self) -> Option<BlockedTask> {
+        if self.cap == 0 {self.queue.dequeue()} else {None}
+    }
 }
 
 #[unsafe_destructor]
@@ -443,24 +515,19 @@ impl<T> Buffer<T> {
 // Queue, a simple queue to enqueue tasks with (stack-allocated nodes)
 ////////////////////////////////////////////////////////////////////////////////
 
+impl Node {
+    pub fn new() -> Node {
+        Node { task: None, next: 0 as *mut Node }
+    }
+}
+
 impl Queue {
     fn enqueue(&mut self, lock: &NativeMutex) {
         let task: Box<Task> = Local::take();
-        let mut node = Node {
-            task: None,
-            next: 0 as *mut Node,
-        };
-        task.deschedule(1, |task| {
+        let mut node = Node::new();
+        task.deschedule_on(OnChannel, 1, |task| {
             node.task = Some(task);
-            if self.tail.is_null() {
-                self.head = &mut node as *mut Node;
-                self.tail = &mut node as *mut Node;
-            } else {
-                unsafe {
-                    (*self.tail).next = &mut node as *mut Node;
-                    self.tail = &mut node as *mut Node;
-                }
-            }
+            self.push(&mut node);
             unsafe { lock.unlock_noguard(); }
             Ok(())
         });
@@ -468,6 +535,43 @@ impl Queue {
         assert!(node.next.is_null());
     }
 
+    fn push(&mut self, node: &mut Node) {
+        let node = node as *mut Node;
+        if self.tail.is_null() {
+            self.head = node;
+            self.tail = node;
+        } else {
+            unsafe {
+                (*self.tail).next = node;
+                self.tail = node;
+            }
+        }
+    }
+
+    // Unlinks a node from the queue, returning whether it was found.
+    fn remove(&mut self, node: &mut Node) -> bool {
+        let node = node as *mut Node;
+        let mut prev = 0 as *mut Node;
+        let mut cur = self.head;
+        while !cur.is_null() && cur != node {
+            prev = cur;
+            cur = unsafe { (*cur).next };
+        }
+        if cur.is_null() { return false }
+        unsafe {
+            if prev.is_null() {
+                self.head = (*node).next;
+            } else {
+                (*prev).next = (*node).next;
+            }
+            if self.tail == node {
+                self.tail = prev;
+            }
+            (*node).next = 0 as *mut Node;
+        }
+        true
+    }
+
     fn dequeue(&mut self) -> Option<BlockedTask> {
         if self.head.is_null() {
             return None
@@ -51,13 +51,15 @@ fs::unlink(&path);
 
 use c_str::ToCStr;
 use clone::Clone;
+use cmp::TotalOrd;
 use container::Container;
-use iter::Iterator;
+use iter::{Iterator, DoubleEndedIterator};
 use kinds::Send;
 use super::{Reader, Writer, Seek};
 use super::{SeekStyle, Read, Write, Open, IoError, Truncate};
 use super::{FileMode, FileAccess, FileStat, IoResult, FilePermission};
-use rt::rtio::{RtioFileStream, IoFactory, LocalIo};
+use super::FileType;
+use rt::rtio::{RtioFileStream, RtioDirectory, IoFactory, LocalIo};
 use io;
 use option::{Some, None, Option};
 use owned::Box;
@@ -219,6 +221,42 @@ impl File {
     pub fn stat(&mut self) -> IoResult<FileStat> {
         self.fd.fstat()
     }
+
+    /// Acquires a shared advisory lock on this file, blocking the current
+    /// task until it is available.
+    ///
+    /// Any number of shared locks may be held on a file at once, but none may
+    /// be held while an exclusive lock is. Locks are advisory: they only
+    /// exclude others who also take locks, not plain reads or writes. This is
+    /// `flock` on unix and `LockFileEx` on windows.
+    ///
+    /// Locks belong to this `File` and are released by `unlock` or when it is
+    /// closed. Opening the same path again yields a separate `File` whose
+    /// locks conflict with these, even within a single process.
+    pub fn lock_shared(&mut self) -> IoResult<()> {
+        self.fd.lock(false, true).map(|_| ())
+    }
+
+    /// Acquires an exclusive advisory lock on this file, blocking the current
+    /// task until no other lock is held on it.
+    ///
+    /// See `lock_shared` for how locks behave.
+    pub fn lock_exclusive(&mut self) -> IoResult<()> {
+        self.fd.lock(true, true).map(|_| ())
+    }
+
+    /// Attempts to acquire an advisory lock on this file without blocking,
+    /// exclusive if `exclusive` is true and shared otherwise.
+    ///
+    /// Returns `Ok(false)` if a conflicting lock is held elsewhere.
+    pub fn try_lock(&mut self, exclusive: bool) -> IoResult<bool> {
+        self.fd.lock(exclusive, false)
+    }
+
+    /// Releases the lock held on this file, if any.
+    pub fn unlock(&mut self) -> IoResult<()> {
+        self.fd.unlock()
+    }
 }
 
 /// Unlink a file from the underlying filesystem.
@@ -521,6 +559,307 @@ impl Iterator<Path> for Directories {
     }
 }
 
+/// Returns an iterator over the entries of the directory at `path`, which are
+/// read lazily as the iterator advances.
+///
+/// Unlike `readdir`, each entry carries the type and inode number which the
+/// operating system reported along with its name, so a walk over a large tree
+/// often needs no `stat` at all. The `.` and `..` entries are skipped.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::io::fs;
+///
+/// for entry in fs::dir_entries(&Path::new(".")).unwrap() {
+///     let entry = entry.unwrap();
+///     if entry.file_type() == Some(io::TypeDirectory) {
+///         println!("{}/", entry.path().display());
+///     }
+/// }
+/// ```
+///
+/// # Error
+///
+/// This function fails for the same reasons as `readdir`. An error reading
+/// the directory part way through is yielded by the iterator, which then
+/// stops.
+pub fn dir_entries(path: &Path) -> IoResult<DirEntries> {
+    let dir = try!(LocalIo::maybe_raise(|io| {
+        io.fs_opendir(&path.to_c_str())
+    }));
+    Ok(DirEntries { dir: dir, path: path.clone(), done: false })
+}
+
+/// An iterator over the entries of a single directory, see `dir_entries`.
+pub struct DirEntries {
+    dir: Box<RtioDirectory:Send>,
+    path: Path,
+    done: bool,
+}
+
+impl Iterator<IoResult<DirEntry>> for DirEntries {
+    fn next(&mut self) -> Option<IoResult<DirEntry>> {
+        if self.done { return None }
+        match self.dir.next_entry() {
+            Ok(Some(entry)) => Some(Ok(DirEntry {
+                path: self.path.join(entry.name.as_slice()),
+                kind: entry.kind,
+                inode: entry.inode,
+            })),
+            Ok(None) => { self.done = true; None }
+            Err(e) => { self.done = true; Some(Err(e)) }
+        }
+    }
+}
+
+/// An entry in a directory, as yielded by `dir_entries` and `Walk`.
+pub struct DirEntry {
+    path: Path,
+    kind: Option<FileType>,
+    inode: u64,
+}
+
+impl DirEntry {
+    /// Returns the full path of this entry, which is the directory being
+    /// read joined with the entry's name.
+    pub fn path<'a>(&'a self) -> &'a Path { &self.path }
+
+    /// Returns the name of this entry within its directory.
+    pub fn filename<'a>(&'a self) -> &'a [u8] {
+        self.path.filename().unwrap()
+    }
+
+    /// Returns the type of this entry as reported while reading the
+    /// directory, or `None` if the platform or file system didn't say.
+    ///
+    /// Symlinks are reported as `TypeSymlink` rather than as the type of
+    /// whatever they point at.
+    pub fn file_type(&self) -> Option<FileType> { self.kind }
+
+    /// Returns the inode number of this entry. This is always 0 on windows.
+    pub fn inode(&self) -> u64 { self.inode }
+
+    /// Queries information about this entry, without following symlinks.
+    pub fn stat(&self) -> IoResult<FileStat> { lstat(&self.path) }
+}
+
+/// Configures a recursive walk over a directory tree.
+///
+/// The walk is depth first, and yields every entry below the root (but not
+/// the root itself) before the contents of that entry if it's a directory.
+/// Errors are yielded in place of the entries they concern and the walk then
+/// carries on, so one unreadable directory doesn't end it.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::fs::Walker;
+///
+/// let mut walk = Walker::new(&Path::new(".")).max_depth(3).sort(true).walk();
+/// loop {
+///     let entry = match walk.next() {
+///         Some(Ok(entry)) => entry,
+///         Some(Err(e)) => { println!("skipping: {}", e); continue }
+///         None => break,
+///     };
+///     if entry.filename() == bytes!(".git") {
+///         walk.prune();
+///         continue
+///     }
+///     println!("{}", entry.path().display());
+/// }
+/// ```
+pub struct Walker {
+    root: Path,
+    max_depth: Option<uint>,
+    follow_links: bool,
+    sort: bool,
+}
+
+impl Walker {
+    /// Creates a walk of the tree rooted at `root` which has no depth limit,
+    /// doesn't follow symlinks and yields entries in directory order.
+    pub fn new(root: &Path) -> Walker {
+        Walker {
+            root: root.clone(),
+            max_depth: None,
+            follow_links: false,
+            sort: false,
+        }
+    }
+
+    /// Only yields entries at most `depth` levels below the root, where the
+    /// root's own entries are at depth 1.
+    pub fn max_depth<'a>(&'a mut self, depth: uint) -> &'a mut Walker {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Descends into symlinks which point at directories. A symlink back to
+    /// one of its own ancestors is reported as an error rather than followed.
+    pub fn follow_links<'a>(&'a mut self, follow: bool) -> &'a mut Walker {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Yields the entries of each directory sorted by name. This means
+    /// reading each directory in full before yielding any of its entries.
+    pub fn sort<'a>(&'a mut self, sort: bool) -> &'a mut Walker {
+        self.sort = sort;
+        self
+    }
+
+    /// Starts the walk. Nothing is read until the returned iterator is first
+    /// advanced.
+    pub fn walk(&self) -> Walk {
+        Walk {
+            stack: Vec::new(),
+            pending: if self.max_depth == Some(0) {
+                None
+            } else {
+                Some(self.root.clone())
+            },
+            depth: 0,
+            max_depth: self.max_depth,
+            follow_links: self.follow_links,
+            sort: self.sort,
+        }
+    }
+}
+
+/// An iterator over a directory tree, see `Walker`.
+pub struct Walk {
+    stack: Vec<WalkLevel>,
+    // the directory to descend into before yielding anything else
+    pending: Option<Path>,
+    depth: uint,
+    max_depth: Option<uint>,
+    follow_links: bool,
+    sort: bool,
+}
+
+struct WalkLevel {
+    listing: Listing,
+    // (device, inode) of the directory, only known when following symlinks
+    id: Option<(u64, u64)>,
+}
+
+enum Listing {
+    Lazy(DirEntries),
+    // in reverse order
+    Sorted(Vec<IoResult<DirEntry>>),
+}
+
+impl Walk {
+    /// Skips the contents of the entry most recently yielded, if it's a
+    /// directory.
+    pub fn prune(&mut self) {
+        self.pending = None;
+    }
+
+    /// Returns how many levels below the root the entry most recently yielded
+    /// was found, where the root's own entries are at depth 1.
+    pub fn depth(&self) -> uint { self.depth }
+
+    fn descend(&mut self, path: Path) -> IoResult<()> {
+        let id = if self.follow_links {
+            let stat = try!(stat(&path));
+            let id = (stat.unstable.device, stat.unstable.inode);
+            let looped = stat.unstable.inode != 0 &&
+                         self.stack.iter().any(|level| level.id == Some(id));
+            if looped {
+                return Err(IoError {
+                    kind: io::OtherIoError,
+                    desc: "symlink leads back to one of its ancestors",
+                    detail: Some(format!("{}", path.display())),
+                })
+            }
+            Some(id)
+        } else {
+            None
+        };
+
+        let entries = try!(dir_entries(&path));
+        let listing = if self.sort {
+            let mut found = Vec::new();
+            let mut errors = Vec::new();
+            for entry in entries {
+                match entry {
+                    Ok(entry) => found.push(entry),
+                    Err(e) => errors.push(Err(e)),
+                }
+            }
+            found.sort_by(|a, b| a.filename().cmp(&b.filename()));
+            // Popped from the back, so errors come out first.
+            let mut listing: Vec<IoResult<DirEntry>> =
+                found.move_iter().rev().map(|entry| Ok(entry)).collect();
+            listing.push_all_move(errors);
+            Sorted(listing)
+        } else {
+            Lazy(entries)
+        };
+        self.stack.push(WalkLevel { listing: listing, id: id });
+        Ok(())
+    }
+
+    fn is_dir(&self, entry: &DirEntry) -> IoResult<bool> {
+        let kind = match entry.kind {
+            Some(kind) => kind,
+            None => try!(lstat(&entry.path)).kind,
+        };
+        Ok(match kind {
+            io::TypeDirectory => true,
+            // a dangling symlink is just another leaf
+            io::TypeSymlink if self.follow_links => {
+                match stat(&entry.path) {
+                    Ok(stat) => stat.kind == io::TypeDirectory,
+                    Err(..) => false,
+                }
+            }
+            _ => false,
+        })
+    }
+}
+
+impl Iterator<IoResult<DirEntry>> for Walk {
+    fn next(&mut self) -> Option<IoResult<DirEntry>> {
+        match self.pending.take() {
+            Some(path) => match self.descend(path) {
+                Ok(()) => {}
+                Err(e) => return Some(Err(e)),
+            },
+            None => {}
+        }
+
+        loop {
+            let next = match self.stack.mut_last() {
+                Some(level) => match level.listing {
+                    Lazy(ref mut entries) => entries.next(),
+                    Sorted(ref mut entries) => entries.pop(),
+                },
+                None => return None,
+            };
+            let entry = match next {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e)),
+                None => { self.stack.pop(); continue }
+            };
+
+            self.depth = self.stack.len();
+            if self.max_depth.map_or(true, |max| self.depth < max) {
+                match self.is_dir(&entry) {
+                    Ok(true) => self.pending = Some(entry.path.clone()),
+                    Ok(false) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            return Some(Ok(entry))
+        }
+    }
+}
+
 /// Recursively create a directory and all of its parent components if they
 /// are missing.
 ///
@@ -1002,6 +1341,130 @@ mod test {
         check!(rmdir_recursive(dir));
     })
 
+    iotest!(fn file_test_dir_entries() {
+        let tmpdir = tmpdir();
+        check!(mkdir(&tmpdir.join("d"), io::UserRWX));
+        check!(File::create(&tmpdir.join("f")));
+
+        let mut entries = check!(dir_entries(tmpdir.path()))
+                            .map(|e| check!(e)).collect::<Vec<DirEntry>>();
+        entries.sort_by(|a, b| a.filename().cmp(&b.filename()));
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries.get(0).filename(), bytes!("d"));
+        assert!(entries.get(0).path() == &tmpdir.join("d"));
+        match entries.get(0).file_type() {
+            Some(kind) => assert_eq!(kind, io::TypeDirectory),
+            None => {}
+        }
+        assert_eq!(check!(entries.get(0).stat()).kind, io::TypeDirectory);
+
+        assert_eq!(entries.get(1).filename(), bytes!("f"));
+        match entries.get(1).file_type() {
+            Some(kind) => assert_eq!(kind, io::TypeFile),
+            None => {}
+        }
+        if cfg!(unix) {
+            let inode = check!(entries.get(1).stat()).unstable.inode;
+            assert_eq!(entries.get(1).inode(), inode);
+        }
+    })
+
+    iotest!(fn file_test_dir_entries_missing() {
+        let tmpdir = tmpdir();
+        assert!(dir_entries(&tmpdir.join("missing")).is_err());
+    })
+
+    iotest!(fn file_test_walker() {
+        let tmpdir = tmpdir();
+        check!(mkdir_recursive(&tmpdir.join("a/b/c"), io::UserRWX));
+        check!(mkdir_recursive(&tmpdir.join("d"), io::UserRWX));
+        check!(File::create(&tmpdir.join("a/b/c/f")));
+        check!(File::create(&tmpdir.join("a/e")));
+
+        let names = |walker: &Walker| {
+            let mut walk = walker.walk();
+            let mut names = Vec::new();
+            loop {
+                match walk.next() {
+                    Some(entry) => {
+                        let entry = check!(entry);
+                        let rel = entry.path().path_relative_from(tmpdir.path());
+                        names.push((rel.unwrap().as_str().unwrap().to_owned(),
+                                    walk.depth()));
+                    }
+                    None => break,
+                }
+            }
+            names
+        };
+
+        let all = names(Walker::new(tmpdir.path()).sort(true));
+        let expected = [("a", 1), ("a/b", 2), ("a/b/c", 3), ("a/b/c/f", 4),
+                        ("a/e", 2), ("d", 1)];
+        assert_eq!(all.len(), expected.len());
+        for (&(ref name, depth), &(ename, edepth)) in
+                all.iter().zip(expected.iter()) {
+            assert_eq!(name.as_slice(), ename);
+            assert_eq!(depth, edepth);
+        }
+
+        let shallow = names(Walker::new(tmpdir.path()).sort(true).max_depth(2));
+        assert_eq!(shallow.len(), 4);
+        assert!(shallow.iter().all(|&(_, depth)| depth <= 2));
+        assert_eq!(names(Walker::new(tmpdir.path()).max_depth(0)).len(), 0);
+
+        // Unsorted walks see the same entries.
+        let mut unsorted = names(&Walker::new(tmpdir.path()));
+        unsorted.sort_by(|a, b| a.ref0().cmp(b.ref0()));
+        assert_eq!(unsorted, all);
+    })
+
+    iotest!(fn file_test_walker_prune() {
+        let tmpdir = tmpdir();
+        check!(mkdir_recursive(&tmpdir.join("skip/inner"), io::UserRWX));
+        check!(mkdir_recursive(&tmpdir.join("keep/inner"), io::UserRWX));
+
+        let mut walk = Walker::new(tmpdir.path()).sort(true).walk();
+        let mut seen = Vec::new();
+        loop {
+            let entry = match walk.next() {
+                Some(entry) => check!(entry),
+                None => break,
+            };
+            if entry.filename() == bytes!("skip") { walk.prune() }
+            seen.push(entry.path().clone());
+        }
+        assert!(seen.contains(&tmpdir.join("skip")));
+        assert!(!seen.contains(&tmpdir.join("skip/inner")));
+        assert!(seen.contains(&tmpdir.join("keep/inner")));
+    })
+
+    iotest!(fn file_test_walker_reports_errors() {
+        let tmpdir = tmpdir();
+        let mut walk = Walker::new(&tmpdir.join("missing")).walk();
+        assert!(walk.next().unwrap().is_err());
+        assert!(walk.next().is_none());
+    })
+
+    iotest!(fn file_test_walker_symlinks() {
+        let tmpdir = tmpdir();
+        check!(mkdir_recursive(&tmpdir.join("a/b"), io::UserRWX));
+        check!(symlink(&tmpdir.join("a"), &tmpdir.join("a/b/up")));
+
+        // Not followed, the link is just a leaf.
+        let count = Walker::new(tmpdir.path()).walk()
+                           .map(|e| check!(e)).count(|_| true);
+        assert_eq!(count, 3);
+
+        // Followed, the loop back to `a` is reported instead of descended.
+        let mut errors = 0;
+        for entry in Walker::new(tmpdir.path()).follow_links(true).walk() {
+            if entry.is_err() { errors += 1 }
+        }
+        assert_eq!(errors, 1);
+    } #[cfg(not(windows))])
+
     iotest!(fn recursive_mkdir() {
         let tmpdir = tmpdir();
         let dir = tmpdir.join("d1/d2");
@@ -1351,4 +1814,60 @@ mod test {
         let actual = check!(File::open(&tmpdir.join("test")).read_to_end());
         assert!(actual.as_slice() == bytes);
     })
+
+    iotest!(fn file_lock_exclusive() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("lock");
+        let mut a = check!(File::create(&path));
+        let mut b = check!(File::open_mode(&path, io::Open, io::ReadWrite));
+
+        check!(a.lock_exclusive());
+        assert!(!check!(b.try_lock(true)));
+        assert!(!check!(b.try_lock(false)));
+        check!(a.unlock());
+        assert!(check!(b.try_lock(true)));
+        assert!(!check!(a.try_lock(false)));
+    })
+
+    iotest!(fn file_lock_shared() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("lock");
+        let mut a = check!(File::create(&path));
+        let mut b = check!(File::open(&path));
+
+        check!(a.lock_shared());
+        check!(b.lock_shared());
+        assert!(!check!(a.try_lock(true)));
+    })
+
+    iotest!(fn file_lock_released_on_close() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("lock");
+        {
+            let mut a = check!(File::create(&path));
+            check!(a.lock_exclusive());
+        }
+        let mut b = check!(File::open(&path));
+        assert!(check!(b.try_lock(true)));
+    })
+
+    iotest!(fn file_lock_blocks() {
+        let tmpdir = tmpdir();
+        let path = tmpdir.join("lock");
+        let mut a = check!(File::create(&path));
+        check!(a.lock_exclusive());
+
+        let (tx, rx) = channel();
+        let path2 = path.clone();
+        spawn(proc() {
+            let mut b = File::open(&path2).unwrap();
+            b.lock_exclusive().unwrap();
+            tx.send(());
+        });
+
+        timer::sleep(50);
+        assert!(rx.try_recv().is_err());
+        check!(a.unlock());
+        rx.recv();
+    })
 }
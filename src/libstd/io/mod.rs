@@ -245,7 +245,7 @@ pub use self::net::tcp::TcpListener;
 pub use self::net::tcp::TcpStream;
 pub use self::net::udp::UdpStream;
 pub use self::pipe::PipeStream;
-pub use self::process::{Process, Command};
+pub use self::process::{Process, Command, Pipeline};
 pub use self::tempfile::TempDir;
 
 pub use self::mem::{MemReader, BufReader, MemWriter, BufWriter};
@@ -265,6 +265,7 @@ pub mod extensions;
 pub mod fs;
 pub mod net;
 pub mod pipe;
+pub mod poll;
 pub mod process;
 pub mod signal;
 pub mod stdio;
@@ -26,12 +26,14 @@ use io::net::addrinfo::get_host_addresses;
 use io::net::ip::SocketAddr;
 use io::{IoError, ConnectionFailed, InvalidInput};
 use io::{Reader, Writer, Listener, Acceptor};
+use io::poll::Evented;
 use from_str::FromStr;
 use kinds::Send;
 use option::{None, Some, Option};
 use owned::Box;
 use rt::rtio::{IoFactory, LocalIo, RtioSocket, RtioTcpListener};
-use rt::rtio::{RtioTcpAcceptor, RtioTcpStream};
+use rt::rtio::{RtioTcpAcceptor, RtioTcpStream, EventSource, SocketConfig};
+use default::Default;
 
 /// A structure which represents a TCP stream between a local socket and a
 /// remote socket.
@@ -75,7 +77,8 @@ impl TcpStream {
         for address in addresses.iter() {
             let socket_addr = SocketAddr{ip: *address, port: port};
             let result = LocalIo::maybe_raise(|io| {
-                io.tcp_connect(socket_addr, None).map(TcpStream::new)
+                io.tcp_connect(socket_addr, &Default::default(), None)
+                  .map(TcpStream::new)
             });
             match result {
                 Ok(stream) => {
@@ -102,7 +105,8 @@ impl TcpStream {
     pub fn connect_timeout(addr: SocketAddr,
                            timeout_ms: u64) -> IoResult<TcpStream> {
         LocalIo::maybe_raise(|io| {
-            io.tcp_connect(addr, Some(timeout_ms)).map(TcpStream::new)
+            io.tcp_connect(addr, &Default::default(), Some(timeout_ms))
+              .map(TcpStream::new)
         })
     }
 
@@ -268,6 +272,12 @@ impl Writer for TcpStream {
     fn write(&mut self, buf: &[u8]) -> IoResult<()> { self.obj.write(buf) }
 }
 
+impl Evented for TcpStream {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
 /// A structure representing a socket server. This listener is used to create a
 /// `TcpAcceptor` which can be used to accept sockets on a local port.
 ///
@@ -319,10 +329,11 @@ impl TcpListener {
     pub fn bind(addr: &str, port: u16) -> IoResult<TcpListener> {
         match FromStr::from_str(addr) {
             Some(ip) => {
-                let socket_addr = SocketAddr{ip: ip, port: port};
-                LocalIo::maybe_raise(|io| {
-                    io.tcp_bind(socket_addr).map(|l| TcpListener { obj: l })
-                })
+                // On platforms with Berkeley-derived sockets, this allows
+                // to quickly rebind a socket, without needing to wait for
+                // the OS to clean up the previous one.
+                TcpBuilder::new().reuse_address(cfg!(unix))
+                                 .bind(SocketAddr{ip: ip, port: port})
             }
             None => {
                 Err(IoError{
@@ -346,6 +357,12 @@ impl Listener<TcpStream, TcpAcceptor> for TcpListener {
     }
 }
 
+impl Evented for TcpListener {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
 /// The accepting half of a TCP socket server. This structure is created through
 /// a `TcpListener`'s `listen` method, and this object can be used to accept new
 /// `TcpStream` instances.
@@ -407,6 +424,130 @@ impl Acceptor<TcpStream> for TcpAcceptor {
     }
 }
 
+impl Evented for TcpAcceptor {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
+/// A builder for TCP sockets, giving control over the socket options which
+/// have to be set before the socket is bound or connected.
+///
+/// # Example
+///
+/// ```no_run
+/// # #![allow(unused_must_use)]
+/// use std::io::net::tcp::TcpBuilder;
+/// use std::io::net::ip::{Ipv4Addr, SocketAddr};
+/// use std::io::Acceptor;
+///
+/// // Several processes can accept on the same port, so a new server can be
+/// // started before the old one is shut down.
+/// let addr = SocketAddr { ip: Ipv4Addr(0, 0, 0, 0), port: 8080 };
+/// let mut acceptor = TcpBuilder::new().reuse_address(true)
+///                                     .reuse_port(true)
+///                                     .backlog(1024)
+///                                     .listen(addr).unwrap();
+/// let stream = acceptor.accept();
+/// ```
+pub struct TcpBuilder {
+    cfg: SocketConfig,
+}
+
+impl TcpBuilder {
+    /// Creates a new builder, which uses the system defaults for every option
+    /// and a listen backlog of 128.
+    pub fn new() -> TcpBuilder {
+        TcpBuilder { cfg: Default::default() }
+    }
+
+    /// Sets SO_REUSEADDR, which allows binding to an address which is still
+    /// in the TIME_WAIT state. Defaults to off, although listeners created
+    /// with `TcpListener::bind` always set it on unix.
+    pub fn reuse_address<'a>(&'a mut self, on: bool) -> &'a mut TcpBuilder {
+        self.cfg.reuse_address = on;
+        self
+    }
+
+    /// Sets SO_REUSEPORT, which allows several sockets to bind to the same
+    /// address and port. This is unavailable on windows.
+    pub fn reuse_port<'a>(&'a mut self, on: bool) -> &'a mut TcpBuilder {
+        self.cfg.reuse_port = on;
+        self
+    }
+
+    /// Sets IPV6_V6ONLY for IPv6 sockets, which decides whether they also
+    /// accept IPv4 connections. Ignored for IPv4 sockets.
+    pub fn only_v6<'a>(&'a mut self, on: bool) -> &'a mut TcpBuilder {
+        self.cfg.only_v6 = Some(on);
+        self
+    }
+
+    /// Sets the size of the socket's send buffer.
+    pub fn send_buffer_size<'a>(&'a mut self, size: uint) -> &'a mut TcpBuilder {
+        self.cfg.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the size of the socket's receive buffer.
+    pub fn recv_buffer_size<'a>(&'a mut self, size: uint) -> &'a mut TcpBuilder {
+        self.cfg.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets SO_LINGER, so closing the socket blocks for up to `seconds`
+    /// seconds while unsent data is delivered. With a timeout of zero, the
+    /// connection is reset when it's closed instead.
+    pub fn linger<'a>(&'a mut self, seconds: uint) -> &'a mut TcpBuilder {
+        self.cfg.linger = Some(seconds);
+        self
+    }
+
+    /// Sets the maximum number of pending connections of listening sockets.
+    pub fn backlog<'a>(&'a mut self, backlog: uint) -> &'a mut TcpBuilder {
+        self.cfg.backlog = backlog;
+        self
+    }
+
+    /// Binds connecting sockets to `addr` before they connect, choosing the
+    /// interface and source port connections are made from.
+    pub fn local_addr<'a>(&'a mut self, addr: SocketAddr) -> &'a mut TcpBuilder {
+        self.cfg.local_addr = Some(addr);
+        self
+    }
+
+    /// Creates a socket with the configured options and connects it to
+    /// `addr`.
+    pub fn connect(&self, addr: SocketAddr) -> IoResult<TcpStream> {
+        LocalIo::maybe_raise(|io| {
+            io.tcp_connect(addr, &self.cfg, None).map(TcpStream::new)
+        })
+    }
+
+    /// Same as `connect`, but fails with a `TimedOut` error if the connection
+    /// isn't made within `timeout_ms` milliseconds.
+    #[experimental = "the timeout argument may eventually change types"]
+    pub fn connect_timeout(&self, addr: SocketAddr,
+                           timeout_ms: u64) -> IoResult<TcpStream> {
+        LocalIo::maybe_raise(|io| {
+            io.tcp_connect(addr, &self.cfg, Some(timeout_ms)).map(TcpStream::new)
+        })
+    }
+
+    /// Creates a socket with the configured options and binds it to `addr`.
+    /// The configured backlog is used when the listener starts listening.
+    pub fn bind(&self, addr: SocketAddr) -> IoResult<TcpListener> {
+        LocalIo::maybe_raise(|io| {
+            io.tcp_bind(addr, &self.cfg).map(|l| TcpListener { obj: l })
+        })
+    }
+
+    /// Shorthand for `bind` followed by `listen`.
+    pub fn listen(&self, addr: SocketAddr) -> IoResult<TcpAcceptor> {
+        self.bind(addr).and_then(|l| l.listen())
+    }
+}
+
 #[cfg(test)]
 #[allow(experimental)]
 mod test {
@@ -1322,4 +1463,23 @@ mod test {
 
         rx2.recv();
     })
+
+    iotest!(fn builder_local_addr() {
+        let addr = next_test_ip4();
+        let local = next_test_ip4();
+        let mut a = TcpBuilder::new().backlog(1)
+                                     .recv_buffer_size(64 * 1024)
+                                     .listen(addr).unwrap();
+        spawn(proc() {
+            let mut s = TcpBuilder::new().local_addr(local)
+                                         .send_buffer_size(64 * 1024)
+                                         .connect(addr).unwrap();
+            assert_eq!(s.socket_name(), Ok(local));
+            assert_eq!(s.write([1]), Ok(()));
+        });
+
+        let mut s = a.accept().unwrap();
+        assert_eq!(s.peer_name(), Ok(local));
+        assert_eq!(s.read_byte(), Ok(1));
+    })
 }
@@ -18,11 +18,14 @@
 use clone::Clone;
 use io::net::ip::{SocketAddr, IpAddr};
 use io::{Reader, Writer, IoResult};
+use io::poll::Evented;
 use kinds::Send;
 use owned::Box;
 use option::Option;
 use result::{Ok, Err};
-use rt::rtio::{RtioSocket, RtioUdpSocket, IoFactory, LocalIo};
+use rt::rtio::{RtioSocket, RtioUdpSocket, IoFactory, LocalIo, EventSource};
+use rt::rtio::SocketConfig;
+use default::Default;
 
 /// A User Datagram Protocol socket.
 ///
@@ -62,9 +65,7 @@ pub struct UdpSocket {
 impl UdpSocket {
     /// Creates a UDP socket from the given socket address.
     pub fn bind(addr: SocketAddr) -> IoResult<UdpSocket> {
-        LocalIo::maybe_raise(|io| {
-            io.udp_bind(addr).map(|s| UdpSocket { obj: s })
-        })
+        UdpBuilder::new().bind(addr)
     }
 
     /// Receives data from the socket. On success, returns the number of bytes
@@ -184,6 +185,79 @@ impl Clone for UdpSocket {
     }
 }
 
+impl Evented for UdpSocket {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
+/// A builder for UDP sockets, giving control over the socket options which
+/// have to be set before the socket is bound.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::net::udp::UdpBuilder;
+/// use std::io::net::ip::{Ipv6Addr, SocketAddr};
+///
+/// let addr = SocketAddr { ip: Ipv6Addr(0, 0, 0, 0, 0, 0, 0, 0), port: 5353 };
+/// let socket = UdpBuilder::new().reuse_address(true)
+///                               .only_v6(false)
+///                               .recv_buffer_size(1 << 20)
+///                               .bind(addr);
+/// ```
+pub struct UdpBuilder {
+    cfg: SocketConfig,
+}
+
+impl UdpBuilder {
+    /// Creates a new builder, which uses the system defaults for every
+    /// option.
+    pub fn new() -> UdpBuilder {
+        UdpBuilder { cfg: Default::default() }
+    }
+
+    /// Sets SO_REUSEADDR, which (for multicast in particular) allows several
+    /// sockets to bind to the same address.
+    pub fn reuse_address<'a>(&'a mut self, on: bool) -> &'a mut UdpBuilder {
+        self.cfg.reuse_address = on;
+        self
+    }
+
+    /// Sets SO_REUSEPORT, which allows several sockets to bind to the same
+    /// address and port. This is unavailable on windows.
+    pub fn reuse_port<'a>(&'a mut self, on: bool) -> &'a mut UdpBuilder {
+        self.cfg.reuse_port = on;
+        self
+    }
+
+    /// Sets IPV6_V6ONLY for IPv6 sockets, which decides whether they also
+    /// receive IPv4 datagrams. Ignored for IPv4 sockets.
+    pub fn only_v6<'a>(&'a mut self, on: bool) -> &'a mut UdpBuilder {
+        self.cfg.only_v6 = Some(on);
+        self
+    }
+
+    /// Sets the size of the socket's send buffer.
+    pub fn send_buffer_size<'a>(&'a mut self, size: uint) -> &'a mut UdpBuilder {
+        self.cfg.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the size of the socket's receive buffer.
+    pub fn recv_buffer_size<'a>(&'a mut self, size: uint) -> &'a mut UdpBuilder {
+        self.cfg.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Creates a socket with the configured options and binds it to `addr`.
+    pub fn bind(&self, addr: SocketAddr) -> IoResult<UdpSocket> {
+        LocalIo::maybe_raise(|io| {
+            io.udp_bind(addr, &self.cfg).map(|s| UdpSocket { obj: s })
+        })
+    }
+}
+
 /// A type that allows convenient usage of a UDP stream connected to one
 /// address via the `Reader` and `Writer` traits.
 pub struct UdpStream {
@@ -561,4 +635,10 @@ mod test {
             }
         }
     })
+
+    iotest!(fn builder_reuse_address() {
+        let addr = next_test_ip4();
+        let _a = UdpBuilder::new().reuse_address(true).bind(addr).unwrap();
+        let _b = UdpBuilder::new().reuse_address(true).bind(addr).unwrap();
+    } #[ignore(cfg(windows))])
 }
@@ -20,6 +20,10 @@ These pipes are similar to TCP in the sense that you can have both a stream to a
 server and a server itself. The server provided accepts other `UnixStream`
 instances as clients.
 
+On Unix, a `UnixStream` can also pass open file descriptors to the process at
+the other end, and report the credentials of that process. `UnixDatagram`
+provides connectionless Unix domain sockets, similar to UDP.
+
 */
 
 #![allow(missing_doc)]
@@ -28,11 +32,25 @@ use prelude::*;
 
 use c_str::ToCStr;
 use clone::Clone;
-use io::{Listener, Acceptor, Reader, Writer, IoResult};
+use io::{Listener, Acceptor, Reader, Writer, IoResult, IoError, InvalidInput};
+use io::poll::Evented;
 use kinds::Send;
+use libc;
 use owned::Box;
 use rt::rtio::{IoFactory, LocalIo, RtioUnixListener};
-use rt::rtio::{RtioUnixAcceptor, RtioPipe};
+use rt::rtio::{RtioUnixAcceptor, RtioPipe, RtioUnixDatagram, EventSource};
+
+/// The identity of the process at the other end of a Unix socket, as recorded
+/// by the system when the connection was made.
+#[deriving(Clone, Eq, Show)]
+pub struct Credentials {
+    /// The process id of the peer. Only Linux reports this.
+    pub pid: Option<libc::pid_t>,
+    /// The effective user id of the peer.
+    pub uid: uint,
+    /// The effective group id of the peer.
+    pub gid: uint,
+}
 
 /// A stream which communicates over a named pipe.
 pub struct UnixStream {
@@ -117,6 +135,62 @@ impl UnixStream {
     pub fn set_write_timeout(&mut self, timeout_ms: Option<u64>) {
         self.obj.set_write_timeout(timeout_ms)
     }
+
+    /// Writes `buf` to the stream along with copies of the file descriptors
+    /// `fds`, which the receiver gets with `recv_fds` (SCM_RIGHTS).
+    ///
+    /// The descriptors are attached to the first byte of `buf`, which must
+    /// not be empty. They remain open in this process, and the caller keeps
+    /// ownership of them. The stream's timeouts don't apply to this call.
+    ///
+    /// This is only available on Unix.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # #![allow(unused_must_use)]
+    /// extern crate libc;
+    /// use std::io::net::unix::UnixStream;
+    ///
+    /// # fn main() {
+    /// // hand our standard output over to a logging daemon
+    /// let mut stream = UnixStream::connect(&Path::new("/tmp/logger.sock"));
+    /// stream.send_fds([0], [libc::STDOUT_FILENO]);
+    /// # }
+    /// ```
+    pub fn send_fds(&mut self, buf: &[u8],
+                    fds: &[libc::c_int]) -> IoResult<()> {
+        if buf.len() == 0 {
+            return Err(IoError {
+                kind: InvalidInput,
+                desc: "file descriptors must be sent with some data",
+                detail: None,
+            })
+        }
+        self.obj.send_fds(buf, fds)
+    }
+
+    /// Reads data from the stream along with any file descriptors which were
+    /// sent with it, returning the number of bytes read and the descriptors.
+    ///
+    /// At most `max_fds` descriptors are received, any others which were sent
+    /// with the data are closed. The received descriptors are owned by the
+    /// caller, who is responsible for closing them, for example by wrapping
+    /// them in a `PipeStream`. The stream's timeouts don't apply to this call.
+    ///
+    /// This is only available on Unix.
+    pub fn recv_fds(&mut self, buf: &mut [u8],
+                    max_fds: uint) -> IoResult<(uint, Vec<libc::c_int>)> {
+        self.obj.recv_fds(buf, max_fds)
+    }
+
+    /// Returns the credentials of the process at the other end of this
+    /// stream (SO_PEERCRED, or getpeereid on the BSDs).
+    ///
+    /// This is only available on Unix.
+    pub fn peer_cred(&mut self) -> IoResult<Credentials> {
+        self.obj.peer_cred()
+    }
 }
 
 impl Clone for UnixStream {
@@ -133,6 +207,12 @@ impl Writer for UnixStream {
     fn write(&mut self, buf: &[u8]) -> IoResult<()> { self.obj.write(buf) }
 }
 
+impl Evented for UnixStream {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
 /// A value that can listen for incoming named pipe connection requests.
 pub struct UnixListener {
     /// The internal, opaque runtime Unix listener.
@@ -206,6 +286,76 @@ impl Acceptor<UnixStream> for UnixAcceptor {
     }
 }
 
+/// A connectionless Unix domain socket, sending datagrams to other sockets
+/// by path.
+///
+/// This is only available on Unix.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #![allow(unused_must_use)]
+/// use std::io::net::unix::UnixDatagram;
+///
+/// let mut socket = UnixDatagram::bind(&Path::new("/tmp/server.sock")).unwrap();
+/// let mut buf = [0, ..1024];
+/// match socket.recv_from(buf) {
+///     Ok((amt, Some(src))) => { socket.send_to(buf.slice_to(amt), &src); }
+///     Ok((_, None)) => { /* the sender has no name to reply to */ }
+///     Err(e) => println!("couldn't receive a datagram: {}", e),
+/// }
+/// ```
+pub struct UnixDatagram {
+    obj: Box<RtioUnixDatagram:Send>,
+}
+
+impl UnixDatagram {
+    /// Creates a socket bound to `path`, which other sockets can send
+    /// datagrams to. The path is removed when the socket (and all of its
+    /// clones) have been dropped.
+    pub fn bind<P: ToCStr>(path: &P) -> IoResult<UnixDatagram> {
+        LocalIo::maybe_raise(|io| {
+            io.unix_datagram(Some(&path.to_c_str()))
+              .map(|s| UnixDatagram { obj: s })
+        })
+    }
+
+    /// Creates a socket which isn't bound to a path. It can send datagrams,
+    /// but their receivers can't reply.
+    pub fn unbound() -> IoResult<UnixDatagram> {
+        LocalIo::maybe_raise(|io| {
+            io.unix_datagram(None).map(|s| UnixDatagram { obj: s })
+        })
+    }
+
+    /// Receives a datagram, returning the number of bytes read and the path
+    /// of the socket which sent it, if that socket is bound. A datagram which
+    /// doesn't fit in `buf` is truncated.
+    pub fn recv_from(&mut self, buf: &mut [u8])
+                     -> IoResult<(uint, Option<Path>)> {
+        self.obj.recv_from(buf).map(|(amt, src)| {
+            (amt, src.map(|src| Path::new(src)))
+        })
+    }
+
+    /// Sends `buf` as a single datagram to the socket bound to `dst`.
+    pub fn send_to<P: ToCStr>(&mut self, buf: &[u8], dst: &P) -> IoResult<()> {
+        self.obj.send_to(buf, &dst.to_c_str())
+    }
+}
+
+impl Clone for UnixDatagram {
+    fn clone(&self) -> UnixDatagram {
+        UnixDatagram { obj: self.obj.clone() }
+    }
+}
+
+impl Evented for UnixDatagram {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
 #[cfg(test)]
 #[allow(experimental)]
 mod tests {
@@ -674,4 +824,81 @@ mod tests {
 
         rx2.recv();
     })
+
+    iotest!(fn pass_fds() {
+        smalltest(proc(mut server) {
+            let mut buf = [0, 0];
+            let (amt, fds) = server.recv_fds(buf, 2).unwrap();
+            assert_eq!(amt, 2);
+            assert_eq!(buf.as_slice(), &[1, 2]);
+            assert_eq!(fds.len(), 1);
+            let mut reader = PipeStream::open(*fds.get(0)).unwrap();
+            assert_eq!(reader.read_byte(), Ok(3));
+        }, proc(mut client) {
+            let pipe = ::os::pipe();
+            {
+                let mut writer = PipeStream::open(pipe.out).unwrap();
+                writer.write([3]).unwrap();
+            }
+            client.send_fds([1, 2], [pipe.input]).unwrap();
+            unsafe { ::libc::close(pipe.input); }
+        })
+    } #[cfg(not(windows))])
+
+    iotest!(fn send_fds_needs_data() {
+        smalltest(proc(_server) {
+        }, proc(mut client) {
+            let err = client.send_fds([], [0]).err().unwrap();
+            assert_eq!(err.kind, InvalidInput);
+        })
+    } #[cfg(not(windows))])
+
+    iotest!(fn peer_cred() {
+        smalltest(proc(mut server) {
+            let cred = server.peer_cred().unwrap();
+            assert_eq!(cred.uid, unsafe { ::libc::getuid() } as uint);
+            assert_eq!(cred.gid, unsafe { ::libc::getgid() } as uint);
+            if cfg!(target_os = "linux") {
+                assert_eq!(cred.pid, Some(unsafe { ::libc::getpid() }));
+            }
+        }, proc(_client) {
+        })
+    } #[cfg(not(windows))])
+
+    iotest!(fn datagram_smoke() {
+        let path1 = next_test_unix();
+        let path2 = next_test_unix();
+        let mut a = UnixDatagram::bind(&path1).unwrap();
+        let mut b = UnixDatagram::bind(&path2).unwrap();
+        let mut c = UnixDatagram::unbound().unwrap();
+
+        a.send_to([1, 2, 3], &path2).unwrap();
+        let mut buf = [0, ..10];
+        let (amt, src) = b.recv_from(buf).unwrap();
+        assert_eq!(amt, 3);
+        assert_eq!(buf.slice_to(3), &[1, 2, 3]);
+        assert_eq!(src, Some(path1.clone()));
+
+        c.send_to([4], &path2).unwrap();
+        assert_eq!(b.recv_from(buf).unwrap(), (1, None));
+
+        drop(a);
+        assert!(!path1.exists());
+    } #[cfg(not(windows))])
+
+    iotest!(fn datagram_clone() {
+        let path = next_test_unix();
+        let mut a = UnixDatagram::bind(&path).unwrap();
+        let b = a.clone();
+        let path2 = path.clone();
+        spawn(proc() {
+            let mut c = UnixDatagram::unbound().unwrap();
+            c.send_to([5], &path2).unwrap();
+        });
+        assert_eq!(a.recv_from([0]).unwrap(), (1, None));
+        drop(a);
+        assert!(path.exists());
+        drop(b);
+        assert!(!path.exists());
+    } #[cfg(not(windows))])
 }
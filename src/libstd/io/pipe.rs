@@ -17,9 +17,10 @@
 
 use prelude::*;
 use io::IoResult;
+use io::poll::Evented;
 use libc;
 use owned::Box;
-use rt::rtio::{RtioPipe, LocalIo};
+use rt::rtio::{RtioPipe, LocalIo, EventSource};
 
 /// A synchronous, in-memory pipe.
 pub struct PipeStream {
@@ -74,6 +75,12 @@ impl Writer for PipeStream {
     fn write(&mut self, buf: &[u8]) -> IoResult<()> { self.obj.write(buf) }
 }
 
+impl Evented for PipeStream {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
 #[cfg(test)]
 mod test {
     iotest!(fn partial_read() {
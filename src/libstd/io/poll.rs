@@ -0,0 +1,212 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+
+Readiness-based I/O multiplexing
+
+A `Selector` waits on many I/O objects at once from a single task, instead of
+dedicating a task (and with the native runtime, a thread) to each one. Objects
+are registered with a token and the kinds of readiness they're interested in,
+and `select` blocks until at least one of them is ready, returning the tokens
+of those which are.
+
+Readiness is level-triggered: a socket stays readable for as long as there is
+data to read, so a ready object will be reported by each call to `select`
+until it has been drained. A `Timer` is readable once each time it fires.
+
+Selectors are currently only implemented by the green runtime (on top of
+libuv) and by the native runtime on Linux (on top of epoll). Elsewhere,
+creating one fails with `IoUnavailable`. With the green runtime, libuv is
+always accepting connections on a listening socket itself, so a `TcpAcceptor`
+can't be registered either.
+
+# Example
+
+```rust
+# fn main() {}
+# fn foo() {
+use std::io::net::tcp::TcpStream;
+use std::io::poll::{Selector, Readable};
+use std::io::Timer;
+
+let mut stream = TcpStream::connect("127.0.0.1", 8080).unwrap();
+let mut timer = Timer::new().unwrap();
+let heartbeat = timer.periodic(1000);
+
+let mut selector = Selector::new().unwrap();
+selector.register(&mut stream, 0, Readable).unwrap();
+selector.register(&mut timer, 1, Readable).unwrap();
+
+let mut buf = [0, ..1024];
+loop {
+    for event in selector.select(None).unwrap().iter() {
+        match event.token {
+            0 => { let _ = stream.read(buf); /* handle the data */ }
+            _ => { heartbeat.recv(); /* send a heartbeat */ }
+        }
+    }
+}
+# }
+```
+
+*/
+
+use io::IoResult;
+use kinds::Send;
+use option::{Option, Some};
+use owned::Box;
+use result::{Ok, Err};
+use rt::rtio::{EventSource, IoFactory, LocalIo, RtioSelector};
+use vec::Vec;
+
+bitflags!(
+    #[doc="The kinds of readiness which can be waited for, and which are
+reported by `select`."]
+    #[deriving(Show)]
+    flags Interest: u32 {
+        static Readable = 0x1,
+        static Writable = 0x2
+    }
+)
+
+/// A registered object which has become ready.
+#[deriving(Clone, Eq, Show)]
+pub struct Event {
+    /// The token the object was registered with.
+    pub token: uint,
+    /// How the object is ready, a subset of what it was registered for.
+    pub readiness: Interest,
+}
+
+/// An I/O object which can be registered with a `Selector`.
+pub trait Evented {
+    /// Returns what the underlying I/O implementation should wait on.
+    fn event_source(&mut self) -> IoResult<EventSource>;
+}
+
+/// A set of I/O objects to wait on. See the module documentation for details.
+pub struct Selector {
+    obj: Box<RtioSelector:Send>,
+}
+
+impl Selector {
+    /// Creates a new selector with nothing registered.
+    pub fn new() -> IoResult<Selector> {
+        LocalIo::maybe_raise(|io| {
+            io.selector().map(|s| Selector { obj: s })
+        })
+    }
+
+    /// Starts waiting for `interest` on `io`, which will be reported by
+    /// `select` with `token`. Registering an already registered token replaces
+    /// its previous registration.
+    ///
+    /// The selector does not keep `io` alive: it must be deregistered before
+    /// it is dropped, otherwise a later object may be reported with its token.
+    pub fn register<E: Evented>(&mut self, io: &mut E, token: uint,
+                                interest: Interest) -> IoResult<()> {
+        let source = try!(io.event_source());
+        self.obj.register(source, token, interest)
+    }
+
+    /// Stops waiting on the object registered with `token`.
+    pub fn deregister(&mut self, token: uint) -> IoResult<()> {
+        self.obj.deregister(token)
+    }
+
+    /// Blocks the current task until at least one registered object is ready,
+    /// returning an event for each of them.
+    ///
+    /// If `timeout_ms` is given and nothing becomes ready within that many
+    /// milliseconds, an empty list is returned.
+    pub fn select(&mut self, timeout_ms: Option<u64>) -> IoResult<Vec<Event>> {
+        self.obj.select(timeout_ms)
+    }
+
+    /// Returns the events for the registered objects which are ready now,
+    /// without blocking.
+    pub fn poll(&mut self) -> IoResult<Vec<Event>> {
+        self.obj.select(Some(0))
+    }
+}
+
+#[cfg(test, target_os = "linux")]
+mod test {
+    use super::*;
+
+    iotest!(fn nothing_ready_times_out() {
+        let mut selector = Selector::new().unwrap();
+        assert_eq!(selector.select(Some(10)).unwrap(), vec![]);
+        assert_eq!(selector.poll().unwrap(), vec![]);
+    })
+
+    iotest!(fn tcp_readable() {
+        let addr = next_test_ip4();
+        let ip_str = addr.ip.to_str();
+        let port = addr.port;
+        let mut acceptor = TcpListener::bind(ip_str, port).listen().unwrap();
+        let (tx, rx) = channel();
+        spawn(proc() {
+            let mut s = TcpStream::connect(ip_str, port).unwrap();
+            rx.recv();
+            s.write([1]).unwrap();
+            rx.recv();
+        });
+        let mut stream = acceptor.accept().unwrap();
+
+        let mut selector = Selector::new().unwrap();
+        selector.register(&mut stream, 2, Readable | Writable).unwrap();
+        let events = selector.select(None).unwrap();
+        assert_eq!(events, vec![Event { token: 2, readiness: Writable }]);
+
+        selector.register(&mut stream, 2, Readable).unwrap();
+        tx.send(());
+        let events = selector.select(None).unwrap();
+        assert_eq!(events, vec![Event { token: 2, readiness: Readable }]);
+
+        // level triggered: still readable until the byte is read
+        assert_eq!(selector.poll().unwrap().len(), 1);
+        assert_eq!(stream.read_byte().unwrap(), 1);
+        assert_eq!(selector.poll().unwrap(), vec![]);
+        selector.deregister(2).unwrap();
+        tx.send(());
+    })
+
+    iotest!(fn udp_readable() {
+        let addr1 = next_test_ip4();
+        let addr2 = next_test_ip4();
+        let mut a = UdpSocket::bind(addr1).unwrap();
+        let mut b = UdpSocket::bind(addr2).unwrap();
+        let mut selector = Selector::new().unwrap();
+        selector.register(&mut b, 7, Readable).unwrap();
+        a.sendto([9], addr2).unwrap();
+        let events = selector.select(None).unwrap();
+        assert_eq!(events, vec![Event { token: 7, readiness: Readable }]);
+        selector.deregister(7).unwrap();
+    })
+
+    iotest!(fn timer_ready_once_per_fire() {
+        let mut timer = Timer::new().unwrap();
+        let mut selector = Selector::new().unwrap();
+        selector.register(&mut timer, 3, Readable).unwrap();
+        let rx = timer.oneshot(1);
+        let events = selector.select(None).unwrap();
+        assert_eq!(events, vec![Event { token: 3, readiness: Readable }]);
+        rx.recv();
+        assert_eq!(selector.select(Some(10)).unwrap(), vec![]);
+        selector.deregister(3).unwrap();
+    })
+
+    iotest!(fn deregister_unknown() {
+        let mut selector = Selector::new().unwrap();
+        assert!(selector.deregister(100).is_err());
+    })
+}
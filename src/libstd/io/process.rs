@@ -20,6 +20,7 @@ use io::IoResult;
 use io;
 use libc;
 use mem;
+use os;
 use owned::Box;
 use rt::rtio::{RtioProcess, ProcessConfig, IoFactory, LocalIo};
 use c_str::CString;
@@ -74,6 +75,11 @@ pub struct Process {
     /// Extra I/O handles as configured by the original `ProcessConfig` when
     /// this process was created. This is by default empty.
     pub extra_io: Vec<Option<io::PipeStream>>,
+
+    /// The master side of the child's pseudo-terminal, if it was spawned with
+    /// `Command::pty`. Reading it yields what the child writes to its terminal
+    /// and writing it is typing at the terminal.
+    pub pty: Option<io::PipeStream>,
 }
 
 /// The `Command` type acts as a process builder, providing fine-grained control
@@ -92,6 +98,7 @@ pub struct Process {
 ///
 /// let output = process.stdout.get_mut_ref().read_to_end();
 /// ```
+#[deriving(Clone)]
 pub struct Command {
     // The internal data for the builder. Documented by the builder
     // methods below, and serialized into rt::rtio::ProcessConfig.
@@ -106,6 +113,9 @@ pub struct Command {
     uid: Option<uint>,
     gid: Option<uint>,
     detach: bool,
+    pgroup: Option<libc::pid_t>,
+    setsid: bool,
+    pty: bool,
 }
 
 // FIXME (#12938): Until DST lands, we cannot decompose &str into & and str, so
@@ -140,6 +150,9 @@ impl Command {
             uid: None,
             gid: None,
             detach: false,
+            pgroup: None,
+            setsid: false,
+            pty: false,
         }
     }
 
@@ -230,21 +243,66 @@ impl Command {
         self
     }
 
+    /// Puts the child in the process group `pgid`, or in a new group which it
+    /// leads if `pgid` is 0. A whole group can then be signalled at once by
+    /// passing the negated group id to `Process::kill`.
+    ///
+    /// This is ignored if the child starts a new session, which gives it a new
+    /// group anyway. Spawning fails on windows, and for tasks running on
+    /// libuv's event loop (libgreen), if this is set.
+    pub fn pgroup<'a>(&'a mut self, pgid: libc::pid_t) -> &'a mut Command {
+        self.pgroup = Some(pgid);
+        self
+    }
+
+    /// Makes the child the leader of a new session (and of a new process
+    /// group within it), which detaches it from this process's controlling
+    /// terminal. Spawning fails on windows if this is set.
+    pub fn setsid<'a>(&'a mut self) -> &'a mut Command {
+        self.setsid = true;
+        self
+    }
+
+    /// Runs the child on a newly allocated pseudo-terminal, available from
+    /// the `pty` field of the returned `Process`.
+    ///
+    /// The terminal becomes the child's stdin, stdout and stderr, replacing
+    /// whatever they were configured as, and its controlling terminal. The
+    /// child leads a new session, as with `setsid`. Spawning fails on
+    /// windows, and for tasks running on libuv's event loop (libgreen), if
+    /// this is set.
+    pub fn pty<'a>(&'a mut self) -> &'a mut Command {
+        self.pty = true;
+        self
+    }
+
     /// Executes the command as a child process, which is returned.
     pub fn spawn(&self) -> IoResult<Process> {
-        LocalIo::maybe_raise(|io| {
+        let pty = if self.pty { Some(try!(Pty::open())) } else { None };
+        let (stdin, stdout, stderr) = match pty {
+            Some(ref pty) => {
+                (InheritFd(pty.slave), InheritFd(pty.slave),
+                 InheritFd(pty.slave))
+            }
+            None => (self.stdin, self.stdout, self.stderr),
+        };
+
+        let mut process = try!(LocalIo::maybe_raise(|io| {
             let cfg = ProcessConfig {
                 program: &self.program,
                 args: self.args.as_slice(),
                 env: self.env.as_ref().map(|env| env.as_slice()),
                 cwd: self.cwd.as_ref(),
-                stdin: self.stdin,
-                stdout: self.stdout,
-                stderr: self.stderr,
+                stdin: stdin,
+                stdout: stdout,
+                stderr: stderr,
                 extra_io: self.extra_io.as_slice(),
                 uid: self.uid,
                 gid: self.gid,
                 detach: self.detach,
+                pgroup: self.pgroup,
+                setsid: self.setsid || self.pty,
+                controlling_tty: self.pty,
             };
             io.spawn(cfg).map(|(p, io)| {
                 let mut io = io.move_iter().map(|p| {
@@ -256,9 +314,19 @@ impl Command {
                     stdout: io.next().unwrap(),
                     stderr: io.next().unwrap(),
                     extra_io: io.collect(),
+                    pty: None,
                 }
             })
-        })
+        }));
+
+        match pty {
+            Some(mut pty) => {
+                let master = mem::replace(&mut pty.master, -1);
+                process.pty = Some(try!(io::PipeStream::open(master)));
+            }
+            None => {}
+        }
+        Ok(process)
     }
 
     /// Executes the command as a child process, waiting for it to finish and
@@ -328,6 +396,7 @@ pub struct ProcessOutput {
 }
 
 /// Describes what to do with a standard io stream for a child process.
+#[deriving(Clone)]
 pub enum StdioContainer {
     /// This stream will be ignored. This is the equivalent of attaching the
     /// stream to `/dev/null`
@@ -526,6 +595,164 @@ impl Process {
     }
 }
 
+/// A pipeline of commands, in which each command's stdout is connected
+/// directly to the next one's stdin by an OS pipe, like `a | b | c` in a
+/// shell.
+///
+/// The first command's stdin and the last command's stdout, along with every
+/// command's stderr, are configured as usual by their `Command`s.
+///
+/// # Example
+///
+/// ```
+/// use std::io::{Command, Pipeline};
+///
+/// let mut children = Pipeline::new()
+///     .push(Command::new("echo").arg("hello"))
+///     .push(Command::new("tr").arg("a-z").arg("A-Z"))
+///     .spawn().unwrap();
+///
+/// let last = children.mut_last().unwrap();
+/// let output = last.stdout.get_mut_ref().read_to_str().unwrap();
+/// assert_eq!(output.as_slice(), "HELLO\n");
+/// ```
+pub struct Pipeline {
+    commands: Vec<Command>,
+    pgroup: bool,
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline.
+    pub fn new() -> Pipeline {
+        Pipeline { commands: Vec::new(), pgroup: false }
+    }
+
+    /// Appends a copy of `cmd` to the end of the pipeline.
+    pub fn push<'a>(&'a mut self, cmd: &Command) -> &'a mut Pipeline {
+        self.commands.push(cmd.clone());
+        self
+    }
+
+    /// Puts every command in a new process group led by the first, so that
+    /// the whole pipeline can be signalled at once. See `Command::pgroup`.
+    pub fn pgroup<'a>(&'a mut self) -> &'a mut Pipeline {
+        self.pgroup = true;
+        self
+    }
+
+    /// Spawns every command, returning the children in pipeline order.
+    ///
+    /// If a command fails to spawn, the ones already spawned are waited for
+    /// (their pipes having been closed) and the error is returned.
+    pub fn spawn(&self) -> IoResult<Vec<Process>> {
+        let mut children: Vec<Process> = Vec::new();
+        let mut next_stdin: Option<PipeEnd> = None;
+        for (i, cmd) in self.commands.iter().enumerate() {
+            let mut cmd = cmd.clone();
+
+            // Our copies of the ends given to this child are closed once it's
+            // spawned, so that only the children hold them.
+            let stdin = next_stdin.take();
+            match stdin {
+                Some(ref reader) => { cmd.stdin(InheritFd(reader.fd)); }
+                None => {}
+            }
+            let stdout = if i + 1 < self.commands.len() {
+                let (reader, writer) = try!(PipeEnd::pipe());
+                cmd.stdout(InheritFd(writer.fd));
+                next_stdin = Some(reader);
+                Some(writer)
+            } else {
+                None
+            };
+            if self.pgroup {
+                cmd.pgroup(children.iter().next().map_or(0, |p| p.id()));
+            }
+
+            children.push(try!(cmd.spawn()));
+            drop(stdin);
+            drop(stdout);
+        }
+        Ok(children)
+    }
+}
+
+/// One end of an OS pipe, closed on drop.
+struct PipeEnd {
+    fd: libc::c_int,
+}
+
+impl PipeEnd {
+    fn pipe() -> IoResult<(PipeEnd, PipeEnd)> {
+        let pipe = os::pipe();
+        let ends = (PipeEnd { fd: pipe.input }, PipeEnd { fd: pipe.out });
+        // Other children spawned meanwhile mustn't inherit either end, or the
+        // reader would never see EOF.
+        try!(set_cloexec(pipe.input));
+        try!(set_cloexec(pipe.out));
+        Ok(ends)
+    }
+}
+
+impl Drop for PipeEnd {
+    fn drop(&mut self) {
+        unsafe { let _ = libc::close(self.fd); }
+    }
+}
+
+/// Both sides of a pseudo-terminal, closed on drop unless taken.
+struct Pty {
+    master: libc::c_int,
+    slave: libc::c_int,
+}
+
+impl Pty {
+    #[cfg(unix)]
+    fn open() -> IoResult<Pty> {
+        let mut pty = Pty { master: -1, slave: -1 };
+        match unsafe { rustrt::rust_openpty(&mut pty.master, &mut pty.slave) } {
+            0 => Ok(pty),
+            _ => Err(io::IoError::last_error()),
+        }
+    }
+
+    #[cfg(windows)]
+    fn open() -> IoResult<Pty> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        unsafe {
+            if self.master != -1 { let _ = libc::close(self.master); }
+            if self.slave != -1 { let _ = libc::close(self.slave); }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_cloexec(fd: libc::c_int) -> IoResult<()> {
+    match unsafe { rustrt::rust_set_cloexec(fd) } {
+        0 => Ok(()),
+        _ => Err(io::IoError::last_error()),
+    }
+}
+
+// Windows pipes from `os::pipe` are already uninheritable.
+#[cfg(windows)]
+fn set_cloexec(_fd: libc::c_int) -> IoResult<()> { Ok(()) }
+
+#[cfg(unix)]
+mod rustrt {
+    use libc::c_int;
+
+    extern {
+        pub fn rust_openpty(master: *mut c_int, slave: *mut c_int) -> c_int;
+        pub fn rust_set_cloexec(fd: c_int) -> c_int;
+    }
+}
+
 impl Drop for Process {
     fn drop(&mut self) {
         // Close all I/O before exiting to ensure that the child doesn't wait
@@ -533,6 +760,7 @@ impl Drop for Process {
         drop(self.stdin.take());
         drop(self.stdout.take());
         drop(self.stderr.take());
+        drop(self.pty.take());
         drop(mem::replace(&mut self.extra_io, Vec::new()));
 
         self.set_timeout(None);
@@ -909,4 +1137,80 @@ mod tests {
         rx.recv();
         rx.recv();
     })
+
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn test_pipeline() {
+        let mut children = Pipeline::new()
+            .push(Command::new("echo").arg("hello"))
+            .push(Command::new("tr").arg("a-z").arg("A-Z"))
+            .spawn().unwrap();
+        assert_eq!(children.len(), 2);
+        let output = children.mut_last().unwrap().stdout.get_mut_ref()
+                             .read_to_str().unwrap();
+        assert_eq!(output.as_slice(), "HELLO\n");
+        for child in children.mut_iter() {
+            assert!(child.wait().unwrap().success());
+        }
+    })
+
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn test_pipeline_three_stages() {
+        let mut first = Command::new("sh");
+        first.arg("-c").arg("echo b; echo a; echo c");
+        let mut children = Pipeline::new()
+            .push(&first)
+            .push(&Command::new("sort"))
+            .push(Command::new("head").arg("-n").arg("2"))
+            .spawn().unwrap();
+        let output = children.mut_last().unwrap().stdout.get_mut_ref()
+                             .read_to_str().unwrap();
+        assert_eq!(output.as_slice(), "a\nb\n");
+    })
+
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn test_pgroup() {
+        // libuv can't do this, in which case the spawn fails cleanly
+        match Command::new("sleep").arg("1000").pgroup(0).spawn() {
+            Ok(mut p) => {
+                assert!(Process::kill(-p.id(), 0).is_ok());
+                p.signal_kill().unwrap();
+            }
+            Err(e) => assert_eq!(e.kind, IoUnavailable),
+        }
+
+        let mut p = Command::new("sleep").arg("1000").spawn().unwrap();
+        assert!(Process::kill(-p.id(), 0).is_err());
+        p.signal_kill().unwrap();
+    })
+
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn test_setsid() {
+        let mut p = Command::new("sleep").arg("1000").setsid().spawn().unwrap();
+        assert!(Process::kill(-p.id(), 0).is_ok());
+        p.signal_kill().unwrap();
+    })
+
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn test_pty() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("test -t 0 && test -t 1 && echo tty");
+        let mut p = match cmd.pty().spawn() {
+            Ok(p) => p,
+            Err(e) => { assert_eq!(e.kind, IoUnavailable); return }
+        };
+        assert!(p.stdin.is_none() && p.stdout.is_none());
+
+        // Once the child has gone, reading the master fails (with EIO on
+        // linux) instead of reaching EOF.
+        let mut output = Vec::new();
+        let mut buf = [0u8, ..64];
+        loop {
+            match p.pty.get_mut_ref().read(buf) {
+                Ok(n) => output.push_all(buf.slice_to(n)),
+                Err(..) => break,
+            }
+        }
+        assert!(p.wait().unwrap().success());
+        assert!(output.as_slice().starts_with(bytes!("tty")));
+    })
 }
@@ -19,10 +19,11 @@ and create receivers which will receive notifications after a period of time.
 
 use comm::Receiver;
 use io::IoResult;
+use io::poll::Evented;
 use kinds::Send;
 use owned::Box;
 use option::Expect;
-use rt::rtio::{IoFactory, LocalIo, RtioTimer};
+use rt::rtio::{IoFactory, LocalIo, RtioTimer, EventSource};
 
 /// A synchronous timer object
 ///
@@ -117,6 +118,12 @@ impl Timer {
     }
 }
 
+impl Evented for Timer {
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        self.obj.event_source()
+    }
+}
+
 #[cfg(test)]
 mod test {
     iotest!(fn test_io_timer_sleep_simple() {
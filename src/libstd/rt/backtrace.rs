@@ -8,22 +8,45 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! Simple backtrace functionality (to print on failure)
+//! Capturing, symbolizing and printing backtraces
 
 #![allow(non_camel_case_types)]
 
 use char::Char;
 use container::Container;
+use fmt;
 use from_str::from_str;
-use io::{IoResult, Writer};
+use io::{IoResult, MemWriter, Writer};
 use iter::Iterator;
-use option::{Some, None};
+use option::{Option, Some, None};
 use os;
 use result::{Ok, Err};
-use str::StrSlice;
+use slice::Vector;
+use str::{Str, StrSlice};
+use strbuf::StrBuf;
 use sync::atomics;
+use vec::Vec;
+
+/// A backtrace of a task, as captured by `Backtrace::capture`
+#[deriving(Clone)]
+pub struct Backtrace {
+    frames: Vec<Frame>,
+    // whether there were more frames than the ones which were captured
+    truncated: bool,
+}
 
-pub use self::imp::write;
+/// A single frame of a `Backtrace`
+#[deriving(Clone)]
+pub struct Frame {
+    /// The instruction pointer of the frame
+    pub ip: uint,
+    /// The mangled name of the function containing `ip`, if it was found
+    pub symbol: Option<StrBuf>,
+    /// The source file of `ip`, if the executable has debug info
+    pub file: Option<StrBuf>,
+    /// The line of `file` which `ip` belongs to
+    pub line: Option<uint>,
+}
 
 // For now logging is turned off by default, and this function checks to see
 // whether the magical environment variable is present to see if it's turned on.
@@ -45,6 +68,75 @@ pub fn log_enabled() -> bool {
     val == 2
 }
 
+/// Prints a backtrace of the current task to `w`.
+#[inline(never)] // so that this is always a frame of its own
+pub fn write(w: &mut Writer) -> IoResult<()> {
+    write!(w, "{}", Backtrace::capture())
+}
+
+impl Backtrace {
+    /// Captures a backtrace of the current task, resolving the symbol names
+    /// and source locations of its frames.
+    ///
+    /// The symbols are looked up in the debug info of the executable, and are
+    /// missing from the frames if it has none. Backtraces are captured one at
+    /// a time across the whole process, so this should only be used when
+    /// something has gone wrong.
+    #[inline(never)]
+    pub fn capture() -> Backtrace {
+        let (frames, truncated) = imp::capture();
+        Backtrace { frames: frames, truncated: truncated }
+    }
+
+    /// Returns the frames of the backtrace, starting from the innermost one.
+    pub fn frames<'a>(&'a self) -> &'a [Frame] {
+        self.frames.as_slice()
+    }
+}
+
+impl fmt::Show for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "stack backtrace:\n"));
+        for (i, frame) in self.frames.iter().enumerate() {
+            try!(write!(f, "  {:2}: {}\n", i + 1, *frame));
+            match (&frame.file, frame.line) {
+                (&Some(ref file), Some(line)) => {
+                    // lined up with the name of the frame
+                    try!(write!(f, "{:3$}at {}:{}\n", "", file, line,
+                                HEX_WIDTH + 9));
+                }
+                _ => {}
+            }
+        }
+        if self.truncated {
+            try!(write!(f, " ... <frames omitted>\n"));
+        }
+        Ok(())
+    }
+}
+
+impl Frame {
+    /// Returns the demangled name of the function containing this frame, if
+    /// it was found.
+    pub fn name(&self) -> Option<StrBuf> {
+        self.symbol.as_ref().map(|symbol| {
+            let mut w = MemWriter::new();
+            // writing to memory can't fail, and only strs are written
+            demangle(&mut w, symbol.as_slice()).unwrap();
+            StrBuf::from_utf8(w.unwrap()).unwrap()
+        })
+    }
+}
+
+impl fmt::Show for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{:#2$x} - {}", self.ip, name, HEX_WIDTH),
+            None => write!(f, "{:#1$x} - <unknown>", self.ip, HEX_WIDTH),
+        }
+    }
+}
+
 #[cfg(target_word_size = "64")] static HEX_WIDTH: uint = 18;
 #[cfg(target_word_size = "32")] static HEX_WIDTH: uint = 10;
 
@@ -65,7 +157,10 @@ pub fn log_enabled() -> bool {
 // Note that this demangler isn't quite as fancy as it could be. We have lots
 // of other information in our symbols like hashes, version, type information,
 // etc. Additionally, this doesn't handle glue symbols at all.
-fn demangle(writer: &mut Writer, s: &str) -> IoResult<()> {
+
+/// Writes the demangled form of the symbol `s` to `writer`, or `s` itself if
+/// it isn't a Rust symbol.
+pub fn demangle(writer: &mut Writer, s: &str) -> IoResult<()> {
     // First validate the symbol. If it doesn't look like anything we're
     // expecting, we just print it literally. Note that we must handle non-rust
     // symbols because we could have any function in the backtrace.
@@ -237,94 +332,83 @@ fn demangle(writer: &mut Writer, s: &str) -> IoResult<()> {
 #[cfg(unix)]
 mod imp {
     use c_str::CString;
-    use io::{IoResult, IoError, Writer};
+    use iter::Iterator;
     use libc;
     use mem;
     use option::{Some, None, Option};
-    use result::{Ok, Err};
+    use ptr::RawPtr;
+    use strbuf::StrBuf;
     use unstable::mutex::{StaticNativeMutex, NATIVE_MUTEX_INIT};
     use uw = rt::libunwind;
+    use vec::Vec;
 
-    struct Context<'a> {
-        idx: int,
-        writer: &'a mut Writer,
-        last_error: Option<IoError>,
+    use super::Frame;
+
+    struct Context {
+        ips: Vec<uint>,
+        truncated: bool,
     }
 
+    // Don't capture ginormous backtraces
+    static MAX_FRAMES: uint = 100;
+
     #[inline(never)] // if we know this is a function call, we can skip it when
                      // tracing
-    pub fn write(w: &mut Writer) -> IoResult<()> {
+    pub fn capture() -> (Vec<Frame>, bool) {
         // When using libbacktrace, we use some necessary global state, so we
         // need to prevent more than one thread from entering this block. This
-        // is semi-reasonable in terms of printing anyway, and we know that all
-        // I/O done here is blocking I/O, not green I/O, so we don't have to
-        // worry about this being a native vs green mutex.
+        // is semi-reasonable as backtraces are only meant to be taken when
+        // something has gone wrong, and we know that nothing here does green
+        // I/O, so we don't have to worry about this being a native vs green
+        // mutex.
         static mut LOCK: StaticNativeMutex = NATIVE_MUTEX_INIT;
         let _g = unsafe { LOCK.lock() };
 
-        try!(writeln!(w, "stack backtrace:"));
-
-        let mut cx = Context { writer: w, last_error: None, idx: 0 };
-        return match unsafe {
+        let mut cx = Context { ips: Vec::new(), truncated: false };
+        unsafe {
             uw::_Unwind_Backtrace(trace_fn,
-                                  &mut cx as *mut Context as *libc::c_void)
-        } {
-            uw::_URC_NO_REASON => {
-                match cx.last_error {
-                    Some(err) => Err(err),
-                    None => Ok(())
-                }
-            }
-            _ => Ok(()),
-        };
+                                  &mut cx as *mut Context as *libc::c_void);
+        }
+        let frames = cx.ips.iter().map(|&ip| resolve(ip)).collect();
+        return (frames, cx.truncated);
 
         extern fn trace_fn(ctx: *uw::_Unwind_Context,
                            arg: *libc::c_void) -> uw::_Unwind_Reason_Code {
             let cx: &mut Context = unsafe { mem::transmute(arg) };
-            let ip = unsafe { uw::_Unwind_GetIP(ctx) as *libc::c_void };
-            // dladdr() on osx gets whiny when we use FindEnclosingFunction, and
-            // it appears to work fine without it, so we only use
-            // FindEnclosingFunction on non-osx platforms. In doing so, we get a
-            // slightly more accurate stack trace in the process.
-            //
-            // This is often because failure involves the last instruction of a
-            // function being "call std::rt::begin_unwind", with no ret
-            // instructions after it. This means that the return instruction
-            // pointer points *outside* of the calling function, and by
-            // unwinding it we go back to the original function.
-            let ip = if cfg!(target_os = "macos") {
-                ip
-            } else {
-                unsafe { uw::_Unwind_FindEnclosingFunction(ip) }
-            };
-
-            // Don't print out the first few frames (they're not user frames)
-            cx.idx += 1;
-            if cx.idx <= 0 { return uw::_URC_NO_REASON }
-            // Don't print ginormous backtraces
-            if cx.idx > 100 {
-                match write!(cx.writer, " ... <frames omitted>\n") {
-                    Ok(()) => {}
-                    Err(e) => { cx.last_error = Some(e); }
-                }
+            if cx.ips.len() == MAX_FRAMES {
+                cx.truncated = true;
                 return uw::_URC_FAILURE
             }
-
-            // Once we hit an error, stop trying to print more frames
-            if cx.last_error.is_some() { return uw::_URC_FAILURE }
-
-            match print(cx.writer, cx.idx, ip) {
-                Ok(()) => {}
-                Err(e) => { cx.last_error = Some(e); }
-            }
+            cx.ips.push(unsafe { uw::_Unwind_GetIP(ctx) as uint });
 
             // keep going
             return uw::_URC_NO_REASON
         }
     }
 
+    fn resolve(ip: uint) -> Frame {
+        // dladdr() on osx gets whiny when we use FindEnclosingFunction, and
+        // it appears to work fine without it, so we only use
+        // FindEnclosingFunction on non-osx platforms. In doing so, we get a
+        // slightly more accurate stack trace in the process.
+        //
+        // This is often because failure involves the last instruction of a
+        // function being "call std::rt::begin_unwind", with no ret
+        // instructions after it. This means that the return instruction
+        // pointer points *outside* of the calling function, and by
+        // unwinding it we go back to the original function.
+        let addr = if cfg!(target_os = "macos") {
+            ip as *libc::c_void
+        } else {
+            unsafe { uw::_Unwind_FindEnclosingFunction(ip as *libc::c_void) }
+        };
+        let mut frame = Frame { ip: ip, symbol: None, file: None, line: None };
+        symbolize(&mut frame, addr);
+        frame
+    }
+
     #[cfg(target_os = "macos")]
-    fn print(w: &mut Writer, idx: int, addr: *libc::c_void) -> IoResult<()> {
+    fn symbolize(frame: &mut Frame, addr: *libc::c_void) {
         use intrinsics;
         struct Dl_info {
             dli_fname: *libc::c_char,
@@ -338,22 +422,16 @@ mod imp {
         }
 
         let mut info: Dl_info = unsafe { intrinsics::init() };
-        if unsafe { dladdr(addr, &mut info) == 0 } {
-            output(w, idx,addr, None)
-        } else {
-            output(w, idx, addr, Some(unsafe {
-                CString::new(info.dli_sname, false)
-            }))
+        if unsafe { dladdr(addr, &mut info) != 0 } {
+            frame.symbol = to_strbuf(info.dli_sname);
         }
     }
 
     #[cfg(not(target_os = "macos"))]
-    fn print(w: &mut Writer, idx: int, addr: *libc::c_void) -> IoResult<()> {
+    fn symbolize(frame: &mut Frame, addr: *libc::c_void) {
         use container::Container;
-        use iter::Iterator;
         use os;
         use path::GenericPath;
-        use ptr::RawPtr;
         use ptr;
         use slice::{ImmutableVector, MutableVector};
 
@@ -366,6 +444,12 @@ mod imp {
                           symname: *libc::c_char,
                           symval: libc::uintptr_t,
                           symsize: libc::uintptr_t);
+        type backtrace_full_callback =
+            extern "C" fn(data: *mut libc::c_void,
+                          pc: libc::uintptr_t,
+                          filename: *libc::c_char,
+                          lineno: libc::c_int,
+                          function: *libc::c_char) -> libc::c_int;
         type backtrace_error_callback =
             extern "C" fn(data: *mut libc::c_void,
                           msg: *libc::c_char,
@@ -383,12 +467,22 @@ mod imp {
                                  cb: backtrace_syminfo_callback,
                                  error: backtrace_error_callback,
                                  data: *mut libc::c_void) -> libc::c_int;
+            fn backtrace_pcinfo(state: *mut backtrace_state,
+                                addr: libc::uintptr_t,
+                                cb: backtrace_full_callback,
+                                error: backtrace_error_callback,
+                                data: *mut libc::c_void) -> libc::c_int;
         }
 
         ////////////////////////////////////////////////////////////////////////
         // helper callbacks
         ////////////////////////////////////////////////////////////////////////
 
+        struct FileLine {
+            file: *libc::c_char,
+            line: libc::c_int,
+        }
+
         extern fn error_cb(_data: *mut libc::c_void, _msg: *libc::c_char,
                            _errnum: libc::c_int) {
             // do nothing for now
@@ -401,6 +495,20 @@ mod imp {
             let slot = data as *mut *libc::c_char;
             unsafe { *slot = symname; }
         }
+        extern fn pcinfo_cb(data: *mut libc::c_void,
+                            _pc: libc::uintptr_t,
+                            filename: *libc::c_char,
+                            lineno: libc::c_int,
+                            _function: *libc::c_char) -> libc::c_int {
+            let slot = data as *mut FileLine;
+            unsafe {
+                (*slot).file = filename;
+                (*slot).line = lineno;
+            }
+            // The innermost location comes first if the code was inlined, and
+            // that's the only one we're interested in.
+            1
+        }
 
         // The libbacktrace API supports creating a state, but it does not
         // support destroying a state. I personally take this to mean that a
@@ -454,12 +562,11 @@ mod imp {
         // translation
         ////////////////////////////////////////////////////////////////////////
 
-        // backtrace errors are currently swept under the rug, only I/O
-        // errors are reported
+        // backtrace errors are currently swept under the rug, the frame is
+        // just left without the information which couldn't be found
         let state = unsafe { init_state() };
-        if state.is_null() {
-            return output(w, idx, addr, None)
-        }
+        if state.is_null() { return }
+
         let mut data = 0 as *libc::c_char;
         let data_addr = &mut data as *mut *libc::c_char;
         let ret = unsafe {
@@ -467,22 +574,30 @@ mod imp {
                               syminfo_cb, error_cb,
                               data_addr as *mut libc::c_void)
         };
-        if ret == 0 || data.is_null() {
-            output(w, idx, addr, None)
-        } else {
-            output(w, idx, addr, Some(unsafe { CString::new(data, false) }))
+        if ret != 0 {
+            frame.symbol = to_strbuf(data);
         }
-    }
 
-    // Finally, after all that work above, we can emit a symbol.
-    fn output(w: &mut Writer, idx: int, addr: *libc::c_void,
-              s: Option<CString>) -> IoResult<()> {
-        try!(write!(w, "  {:2}: {:2$} - ", idx, addr, super::HEX_WIDTH));
-        match s.as_ref().and_then(|c| c.as_str()) {
-            Some(string) => try!(super::demangle(w, string)),
-            None => try!(write!(w, "<unknown>")),
+        // The instruction pointer is the return address, which may belong to
+        // the line after the call (or even to another function), so the
+        // location of the call is looked up instead.
+        if frame.ip == 0 { return }
+        let mut fileline = FileLine { file: ptr::null(), line: 0 };
+        unsafe {
+            backtrace_pcinfo(state, (frame.ip - 1) as libc::uintptr_t,
+                             pcinfo_cb, error_cb,
+                             &mut fileline as *mut FileLine as *mut libc::c_void);
         }
-        w.write(['\n' as u8])
+        frame.file = to_strbuf(fileline.file);
+        if frame.file.is_some() && fileline.line > 0 {
+            frame.line = Some(fileline.line as uint);
+        }
+    }
+
+    fn to_strbuf(s: *libc::c_char) -> Option<StrBuf> {
+        if s.is_null() { return None }
+        let s = unsafe { CString::new(s, false) };
+        s.as_str().map(|s| StrBuf::from_str(s))
     }
 }
 
@@ -504,7 +619,6 @@ mod imp {
 mod imp {
     use c_str::CString;
     use container::Container;
-    use io::{IoResult, Writer};
     use iter::Iterator;
     use libc;
     use mem;
@@ -513,10 +627,14 @@ mod imp {
     use path::Path;
     use result::{Ok, Err};
     use str::StrSlice;
+    use strbuf::StrBuf;
     use unstable::dynamic_lib::DynamicLibrary;
     use intrinsics;
     use unstable::mutex::{StaticNativeMutex, NATIVE_MUTEX_INIT};
     use slice::ImmutableVector;
+    use vec::Vec;
+
+    use super::Frame;
 
     extern "system" {
         fn GetCurrentProcess() -> libc::HANDLE;
@@ -759,7 +877,7 @@ mod imp {
         fn drop(&mut self) { (self.SymCleanup)(self.handle); }
     }
 
-    pub fn write(w: &mut Writer) -> IoResult<()> {
+    pub fn capture() -> (Vec<Frame>, bool) {
         // According to windows documentation, all dbghelp functions are
         // single-threaded.
         static mut LOCK: StaticNativeMutex = NATIVE_MUTEX_INIT;
@@ -770,13 +888,13 @@ mod imp {
         let path = Path::new("dbghelp.dll");
         let lib = match DynamicLibrary::open(Some(&path)) {
             Ok(lib) => lib,
-            Err(..) => return Ok(()),
+            Err(..) => return (Vec::new(), false),
         };
 
         macro_rules! sym( ($e:expr, $t:ident) => (
             match unsafe { lib.symbol::<$t>($e) } {
                 Ok(f) => f,
-                Err(..) => return Ok(())
+                Err(..) => return (Vec::new(), false)
             }
         ) )
 
@@ -796,12 +914,11 @@ mod imp {
 
         // Initialize this process's symbols
         let ret = SymInitialize(process, 0 as *libc::c_void, libc::TRUE);
-        if ret != libc::TRUE { return Ok(()) }
+        if ret != libc::TRUE { return (Vec::new(), false) }
         let _c = Cleanup { handle: process, SymCleanup: SymCleanup };
 
         // And now that we're done with all the setup, do the stack walking!
-        let mut i = 0;
-        try!(write!(w, "stack backtrace:\n"));
+        let mut frames = Vec::new();
         while StackWalk64(image, process, thread, &mut frame, &mut context,
                           0 as *libc::c_void, 0 as *libc::c_void,
                           0 as *libc::c_void, 0 as *libc::c_void) == libc::TRUE{
@@ -809,8 +926,6 @@ mod imp {
             if addr == frame.AddrReturn.Offset || addr == 0 ||
                frame.AddrReturn.Offset == 0 { break }
 
-            i += 1;
-            try!(write!(w, "  {:2}: {:#2$x}", i, addr, super::HEX_WIDTH));
             let mut info: SYMBOL_INFO = unsafe { intrinsics::init() };
             info.MaxNameLen = MAX_SYM_NAME as libc::c_ulong;
             info.SizeOfStruct = (mem::size_of::<SYMBOL_INFO>() -
@@ -820,19 +935,21 @@ mod imp {
             let ret = SymFromAddr(process, addr as u64, &mut displacement,
                                   &mut info);
 
-            if ret == libc::TRUE {
-                try!(write!(w, " - "));
+            let symbol = if ret == libc::TRUE {
                 let cstr = unsafe { CString::new(info.Name.as_ptr(), false) };
-                let bytes = cstr.as_bytes();
-                match cstr.as_str() {
-                    Some(s) => try!(super::demangle(w, s)),
-                    None => try!(w.write(bytes.slice_to(bytes.len() - 1))),
-                }
-            }
-            try!(w.write(['\n' as u8]));
+                cstr.as_str().map(|s| StrBuf::from_str(s))
+            } else {
+                None
+            };
+            frames.push(Frame {
+                ip: addr as uint,
+                symbol: symbol,
+                file: None,
+                line: None,
+            });
         }
 
-        Ok(())
+        (frames, false)
     }
 }
 
@@ -869,4 +986,29 @@ mod test {
         t!("_ZN12test$x20test4foobE", "test test::foob");
         t!("_ZN12test$UP$test4foobE", "testBoxtest::foob");
     }
+
+    #[test]
+    fn frame_name() {
+        let mut frame = super::Frame {
+            ip: 0x1234,
+            symbol: Some("_ZN4test1a2bcE".to_strbuf()),
+            file: None,
+            line: None,
+        };
+        assert_eq!(frame.name(), Some("test::a::bc".to_strbuf()));
+        assert!(format!("{}", frame).ends_with(" - test::a::bc"));
+
+        frame.symbol = None;
+        assert_eq!(frame.name(), None);
+        assert!(format!("{}", frame).ends_with(" - <unknown>"));
+    }
+
+    #[test]
+    fn capture() {
+        let bt = super::Backtrace::capture();
+        assert!(format!("{}", bt).starts_with("stack backtrace:\n"));
+        if cfg!(unix) {
+            assert!(bt.frames().len() > 0);
+        }
+    }
 }
@@ -21,6 +21,7 @@ use ptr;
 use raw;
 use rt::libc_heap;
 use rt::local::Local;
+use rt::stats;
 use rt::task::Task;
 use slice::{ImmutableVector, Vector};
 use vec::Vec;
@@ -36,6 +37,9 @@ pub type Box = raw::Box<()>;
 pub struct MemoryRegion {
     allocations: Vec<*AllocHeader>,
     live_allocations: uint,
+    // Whether each allocation is preceded by its size, which is only the case
+    // for the heaps of tasks keeping statistics.
+    sized: bool,
 }
 
 pub struct LocalHeap {
@@ -50,6 +54,7 @@ impl LocalHeap {
         let region = MemoryRegion {
             allocations: Vec::new(),
             live_allocations: 0,
+            sized: false,
         };
         LocalHeap {
             memory_region: region,
@@ -57,10 +62,23 @@ impl LocalHeap {
         }
     }
 
+    /// Records the size of every allocation from now on, so that they can be
+    /// counted in the statistics of the task. This is only possible while the
+    /// heap is empty, and returns whether sizes are being recorded.
+    pub fn record_sizes(&mut self) -> bool {
+        if self.memory_region.live_allocations == 0 {
+            self.memory_region.sized = true;
+        }
+        self.memory_region.sized
+    }
+
     #[inline]
     pub fn alloc(&mut self, drop_glue: fn(*mut u8), size: uint, align: uint) -> *mut Box {
         let total_size = util::get_box_size(size, align);
         let alloc = self.memory_region.malloc(total_size);
+        if self.memory_region.sized {
+            stats::local_allocated(total_size, 0);
+        }
         {
             // Make sure that we can't use `mybox` outside of this scope
             let mybox: &mut Box = unsafe { mem::transmute(alloc) };
@@ -82,7 +100,12 @@ impl LocalHeap {
     pub fn realloc(&mut self, ptr: *mut Box, size: uint) -> *mut Box {
         // Make sure that we can't use `mybox` outside of this scope
         let total_size = size + mem::size_of::<Box>();
+        let old_size = self.memory_region.size_of(ptr);
         let new_box = self.memory_region.realloc(ptr, total_size);
+        match old_size {
+            Some(old_size) => stats::local_allocated(total_size, old_size),
+            None => {}
+        }
         {
             // Fix links because we could have moved around
             let mybox: &mut Box = unsafe { mem::transmute(new_box) };
@@ -117,6 +140,10 @@ impl LocalHeap {
             }
         }
 
+        match self.memory_region.size_of(alloc) {
+            Some(size) => stats::local_allocated(0, size),
+            None => {}
+        }
         self.memory_region.free(alloc);
     }
 }
@@ -184,11 +211,28 @@ impl AllocHeader {
 }
 
 impl MemoryRegion {
+    // The space in front of the header of each allocation, which holds its
+    // size in a sized region. It's padded like the header.
+    #[inline]
+    fn prefix_size(&self) -> uint {
+        if self.sized { 16 } else { 0 }
+    }
+
+    #[inline]
+    fn size_of(&self, alloc: *mut Box) -> Option<uint> {
+        if !self.sized { return None }
+        let prefix = AllocHeader::from(alloc) as uint - self.prefix_size();
+        Some(unsafe { *(prefix as *uint) })
+    }
+
     #[inline]
     fn malloc(&mut self, size: uint) -> *mut Box {
-        let total_size = size + AllocHeader::size();
+        let prefix = self.prefix_size();
+        let total_size = prefix + size + AllocHeader::size();
         let alloc: *AllocHeader = unsafe {
-            libc_heap::malloc_raw(total_size) as *AllocHeader
+            let base = libc_heap::malloc_raw(total_size);
+            if self.sized { *(base as *mut uint) = size; }
+            base.offset(prefix as int) as *AllocHeader
         };
 
         let alloc: &mut AllocHeader = unsafe { mem::transmute(alloc) };
@@ -205,9 +249,13 @@ impl MemoryRegion {
         let orig_alloc = AllocHeader::from(alloc);
         unsafe { (*orig_alloc).assert_sane(); }
 
-        let total_size = size + AllocHeader::size();
+        let prefix = self.prefix_size();
+        let total_size = prefix + size + AllocHeader::size();
         let alloc: *AllocHeader = unsafe {
-            libc_heap::realloc_raw(orig_alloc as *mut u8, total_size) as *AllocHeader
+            let base = (orig_alloc as *mut u8).offset(-(prefix as int));
+            let base = libc_heap::realloc_raw(base, total_size);
+            if self.sized { *(base as *mut uint) = size; }
+            base.offset(prefix as int) as *AllocHeader
         };
 
         let alloc: &mut AllocHeader = unsafe { mem::transmute(alloc) };
@@ -226,7 +274,8 @@ impl MemoryRegion {
             self.release(mem::transmute(alloc));
             rtassert!(self.live_allocations > 0);
             self.live_allocations -= 1;
-            free(alloc as *mut c_void)
+            let base = (alloc as *mut u8).offset(-(self.prefix_size() as int));
+            free(base as *mut c_void)
         }
     }
 
@@ -119,7 +119,7 @@ pub mod unwind;
 // The interface to libunwind that rust is using.
 mod libunwind;
 
-// Simple backtrace functionality (to print on failure)
+// Capturing and printing backtraces
 pub mod backtrace;
 
 // Just stuff
@@ -137,6 +137,9 @@ pub mod bookkeeping;
 // Stack overflow protection
 pub mod stack;
 
+// Opt-in statistics about the resources used by tasks
+pub mod stats;
+
 /// The default error code of the rust runtime if the main task fails instead
 /// of exiting cleanly.
 pub static DEFAULT_ERROR_CODE: int = 101;
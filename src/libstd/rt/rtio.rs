@@ -12,6 +12,7 @@
 
 use c_str::CString;
 use comm::{Sender, Receiver};
+use default::Default;
 use kinds::Send;
 use libc::c_int;
 use libc;
@@ -29,9 +30,11 @@ use ai = io::net::addrinfo;
 use io;
 use io::IoResult;
 use io::net::ip::{IpAddr, SocketAddr};
+use io::net::unix::Credentials;
+use io::poll::{Event, Interest};
 use io::process::{StdioContainer, ProcessExit};
 use io::signal::Signum;
-use io::{FileMode, FileAccess, FileStat, FilePermission};
+use io::{FileMode, FileAccess, FileStat, FilePermission, FileType};
 use io::{SeekStyle};
 
 pub trait Callback {
@@ -140,6 +143,70 @@ pub struct ProcessConfig<'a> {
     /// If true, the child process is spawned in a detached state. On unix, this
     /// means that the child is the leader of a new process group.
     pub detach: bool,
+
+    /// Moves the child into a process group with `setpgid`, where 0 means a
+    /// new group led by the child. Not available on windows.
+    pub pgroup: Option<libc::pid_t>,
+
+    /// If true, the child calls `setsid` to lead a new session (and process
+    /// group). Not available on windows.
+    pub setsid: bool,
+
+    /// If true, the child's stdin is made its controlling terminal once it has
+    /// started a new session. Requires `setsid`, and stdin to be a terminal.
+    pub controlling_tty: bool,
+}
+
+/// Options for creating a socket. Serializes the `std::io::net::tcp::TcpBuilder`
+/// and `std::io::net::udp::UdpBuilder` builders.
+pub struct SocketConfig {
+    /// For a connecting socket, the local address to bind to before
+    /// connecting. Ignored by listening and datagram sockets, which are bound
+    /// to the address they are created with.
+    pub local_addr: Option<SocketAddr>,
+
+    /// Whether to set SO_REUSEADDR before binding.
+    pub reuse_address: bool,
+
+    /// Whether to set SO_REUSEPORT before binding. This is not available on
+    /// windows, where setting it causes the socket creation to fail.
+    pub reuse_port: bool,
+
+    /// The value of IPV6_V6ONLY for IPv6 sockets, or the system's default if
+    /// this is None.
+    pub only_v6: Option<bool>,
+
+    /// The size of the send buffer (SO_SNDBUF), or the system's default if
+    /// this is None.
+    pub send_buffer_size: Option<uint>,
+
+    /// The size of the receive buffer (SO_RCVBUF), or the system's default
+    /// if this is None.
+    pub recv_buffer_size: Option<uint>,
+
+    /// If set, closing the socket blocks for up to this many seconds while
+    /// unsent data is delivered (SO_LINGER). A timeout of zero resets the
+    /// connection on close instead.
+    pub linger: Option<uint>,
+
+    /// The maximum length of the queue of pending connections of a listening
+    /// socket.
+    pub backlog: uint,
+}
+
+impl Default for SocketConfig {
+    fn default() -> SocketConfig {
+        SocketConfig {
+            local_addr: None,
+            reuse_address: false,
+            reuse_port: false,
+            only_v6: None,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            linger: None,
+            backlog: 128,
+        }
+    }
 }
 
 pub struct LocalIo<'a> {
@@ -205,16 +272,18 @@ impl<'a> LocalIo<'a> {
 
 pub trait IoFactory {
     // networking
-    fn tcp_connect(&mut self, addr: SocketAddr,
+    fn tcp_connect(&mut self, addr: SocketAddr, cfg: &SocketConfig,
                    timeout: Option<u64>) -> IoResult<Box<RtioTcpStream:Send>>;
-    fn tcp_bind(&mut self, addr: SocketAddr)
+    fn tcp_bind(&mut self, addr: SocketAddr, cfg: &SocketConfig)
                 -> IoResult<Box<RtioTcpListener:Send>>;
-    fn udp_bind(&mut self, addr: SocketAddr)
+    fn udp_bind(&mut self, addr: SocketAddr, cfg: &SocketConfig)
                 -> IoResult<Box<RtioUdpSocket:Send>>;
     fn unix_bind(&mut self, path: &CString)
                  -> IoResult<Box<RtioUnixListener:Send>>;
     fn unix_connect(&mut self, path: &CString,
                     timeout: Option<u64>) -> IoResult<Box<RtioPipe:Send>>;
+    fn unix_datagram(&mut self, path: Option<&CString>)
+                     -> IoResult<Box<RtioUnixDatagram:Send>>;
     fn get_host_addresses(&mut self, host: Option<&str>, servname: Option<&str>,
                           hint: Option<ai::Hint>) -> IoResult<Vec<ai::Info>>;
 
@@ -233,6 +302,8 @@ pub trait IoFactory {
     fn fs_rename(&mut self, path: &CString, to: &CString) -> IoResult<()>;
     fn fs_readdir(&mut self, path: &CString, flags: c_int) ->
         IoResult<Vec<Path>>;
+    fn fs_opendir(&mut self, path: &CString)
+                  -> IoResult<Box<RtioDirectory:Send>>;
     fn fs_lstat(&mut self, path: &CString) -> IoResult<FileStat>;
     fn fs_chown(&mut self, path: &CString, uid: int, gid: int) ->
         IoResult<()>;
@@ -253,6 +324,7 @@ pub trait IoFactory {
             -> IoResult<Box<RtioTTY:Send>>;
     fn signal(&mut self, signal: Signum, channel: Sender<Signum>)
         -> IoResult<Box<RtioSignal:Send>>;
+    fn selector(&mut self) -> IoResult<Box<RtioSelector:Send>>;
 }
 
 pub trait RtioTcpListener : RtioSocket {
@@ -284,6 +356,9 @@ pub trait RtioTcpStream : RtioSocket {
 
 pub trait RtioSocket {
     fn socket_name(&mut self) -> IoResult<SocketAddr>;
+    fn event_source(&self) -> IoResult<EventSource> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
 }
 
 pub trait RtioUdpSocket : RtioSocket {
@@ -312,6 +387,12 @@ pub trait RtioTimer {
     fn sleep(&mut self, msecs: u64);
     fn oneshot(&mut self, msecs: u64) -> Receiver<()>;
     fn period(&mut self, msecs: u64) -> Receiver<()>;
+    /// Invokes `cb` once `msecs` have elapsed, on whatever thread the timer
+    /// fires on. As with `oneshot`, any previous request is cancelled.
+    fn oneshot_callback(&mut self, msecs: u64, cb: Box<Callback:Send>);
+    fn event_source(&mut self) -> IoResult<EventSource> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
 }
 
 pub trait RtioFileStream {
@@ -325,6 +406,26 @@ pub trait RtioFileStream {
     fn datasync(&mut self) -> IoResult<()>;
     fn truncate(&mut self, offset: i64) -> IoResult<()>;
     fn fstat(&mut self) -> IoResult<FileStat>;
+    /// Takes an advisory lock on the whole file. If `wait` is false and the
+    /// lock is held elsewhere, this returns `Ok(false)` instead of blocking.
+    fn lock(&mut self, exclusive: bool, wait: bool) -> IoResult<bool>;
+    fn unlock(&mut self) -> IoResult<()>;
+}
+
+/// A single entry read from a directory, without any extra `stat` call.
+pub struct DirEntry {
+    /// The name of the entry, relative to its directory.
+    pub name: Vec<u8>,
+    /// The type of the entry, if the platform reported it.
+    pub kind: Option<FileType>,
+    /// The inode number of the entry, or 0 where there is none.
+    pub inode: u64,
+}
+
+pub trait RtioDirectory {
+    /// Reads the next entry, skipping `.` and `..`. Returns `None` once the
+    /// directory has been exhausted.
+    fn next_entry(&mut self) -> IoResult<Option<DirEntry>>;
 }
 
 pub trait RtioProcess {
@@ -344,6 +445,30 @@ pub trait RtioPipe {
     fn set_timeout(&mut self, timeout_ms: Option<u64>);
     fn set_read_timeout(&mut self, timeout_ms: Option<u64>);
     fn set_write_timeout(&mut self, timeout_ms: Option<u64>);
+    fn event_source(&self) -> IoResult<EventSource> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
+
+    // Only implemented by unix domain sockets.
+    fn send_fds(&mut self, _buf: &[u8], _fds: &[c_int]) -> IoResult<()> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
+    fn recv_fds(&mut self, _buf: &mut [u8],
+                _max_fds: uint) -> IoResult<(uint, Vec<c_int>)> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
+    fn peer_cred(&mut self) -> IoResult<Credentials> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
+}
+
+pub trait RtioUnixDatagram {
+    fn recv_from(&mut self, buf: &mut [u8]) -> IoResult<(uint, Option<CString>)>;
+    fn send_to(&mut self, buf: &[u8], dst: &CString) -> IoResult<()>;
+    fn clone(&self) -> Box<RtioUnixDatagram:Send>;
+    fn event_source(&self) -> IoResult<EventSource> {
+        Err(io::standard_error(io::IoUnavailable))
+    }
 }
 
 pub trait RtioUnixListener {
@@ -369,3 +494,24 @@ pub trait PausableIdleCallback {
 }
 
 pub trait RtioSignal {}
+
+/// Something which an `RtioSelector` can wait on.
+pub enum EventSource {
+    /// A file descriptor (or socket), ready whenever the OS says it is.
+    FdSource(c_int),
+    /// The read end of a timer's notification pipe, which receives a byte
+    /// each time the timer fires. Selectors drain the pipe when they report
+    /// it as readable, so a timer is ready once per firing.
+    TimerSource(c_int),
+}
+
+pub trait RtioSelector {
+    /// Starts waiting for `interest` on `source`, reporting it as `token`.
+    /// Registering a token again replaces its previous registration.
+    fn register(&mut self, source: EventSource, token: uint,
+                interest: Interest) -> IoResult<()>;
+    fn deregister(&mut self, token: uint) -> IoResult<()>;
+    /// Blocks until at least one registered source is ready or the timeout
+    /// expires, in which case no events are returned.
+    fn select(&mut self, timeout_ms: Option<u64>) -> IoResult<Vec<Event>>;
+}
@@ -0,0 +1,364 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Opt-in statistics about the resources used by tasks
+//!
+//! A task only keeps statistics once it has asked for them with
+//! `task::enable_stats`, or was spawned with `TaskBuilder::with_stats`, as
+//! keeping them adds some work to every allocation, channel message and
+//! context switch of the task. As long as no task keeps statistics, the cost
+//! for everyone else is a single atomic load per allocation and message.
+//!
+//! The statistics of a task are only ever updated by one thread at a time: the
+//! task itself, or its scheduler while switching to or away from it. They are
+//! kept in atomic words along with a sequence number which is odd during
+//! updates, so that snapshots of other tasks taken through `all` are
+//! consistent, although they may be slightly out of date.
+
+use alloc::arc::{Arc, Weak};
+use clone::Clone;
+use iter::Iterator;
+use mem;
+use ops::Drop;
+use option::{Option, Some, None};
+use owned::Box;
+use ptr::RawPtr;
+use rt::heap;
+use rt::local::Local;
+use rt::task::Task;
+use str::SendStr;
+use sync::atomics;
+use unstable::mutex::{StaticNativeMutex, NATIVE_MUTEX_INIT};
+use vec::Vec;
+
+/// A snapshot of the statistics of a task
+#[deriving(Clone, Eq, Show)]
+pub struct TaskStats {
+    /// The name of the task, if it had one when it started keeping statistics
+    pub name: Option<SendStr>,
+    /// The number of nanoseconds for which the task has been running
+    pub scheduled_ns: u64,
+    /// The number of times the task has been switched to by its scheduler. For
+    /// native tasks, this is the number of times they've been woken up.
+    pub context_switches: u64,
+    /// The number of bytes allocated on the exchange and local heaps
+    pub bytes_allocated: u64,
+    /// The number of bytes freed on the exchange and local heaps
+    pub bytes_freed: u64,
+    /// The number of messages sent on channels
+    pub messages_sent: u64,
+    /// The number of messages received from channels
+    pub messages_received: u64,
+}
+
+/// The statistics kept by a task, which are shared with the registry of all
+/// of the tasks keeping statistics.
+pub struct Counters {
+    name: Option<SendStr>,
+    // Incremented before and after each update of `inner`.
+    seq: atomics::AtomicUint,
+    inner: Inner,
+}
+
+struct Inner {
+    // when the task was last switched to, or 0 if it isn't running
+    running_since: Counter,
+    scheduled_ns: Counter,
+    context_switches: Counter,
+    bytes_allocated: Counter,
+    bytes_freed: Counter,
+    messages_sent: Counter,
+    messages_received: Counter,
+}
+
+// A 64-bit counter, kept in two words which are at least 32 bits wide as there
+// are no 64-bit atomics on every platform. Only one thread may modify it at a
+// time.
+struct Counter {
+    lo: atomics::AtomicUint,
+    hi: atomics::AtomicUint,
+}
+
+// The number of tasks which are keeping statistics.
+static mut ENABLED: atomics::AtomicUint = atomics::INIT_ATOMIC_UINT;
+
+// All of the tasks which have kept statistics, protected by LOCK. The dead
+// ones are pruned whenever a new one is added or the registry is listed.
+static mut LOCK: StaticNativeMutex = NATIVE_MUTEX_INIT;
+static mut REGISTRY: *mut Vec<Weak<Counters>> = 0 as *mut Vec<Weak<Counters>>;
+
+/// Starts keeping statistics for `task`, which isn't running yet.
+///
+/// Allocations on the local heap are only counted if the task has no managed
+/// boxes yet, as their sizes aren't recorded otherwise.
+pub fn enable(task: &mut Task) {
+    if task.stats.is_some() { return }
+    task.heap.record_sizes();
+    let counters = Arc::new(Counters {
+        name: task.name.clone(),
+        seq: atomics::AtomicUint::new(0),
+        inner: Inner {
+            running_since: Counter::new(),
+            scheduled_ns: Counter::new(),
+            context_switches: Counter::new(),
+            bytes_allocated: Counter::new(),
+            bytes_freed: Counter::new(),
+            messages_sent: Counter::new(),
+            messages_received: Counter::new(),
+        },
+    });
+    let _live = unsafe {
+        let _g = LOCK.lock();
+        if ENABLED.fetch_add(1, atomics::SeqCst) == 0 {
+            heap::set_hook(Some(allocated));
+        }
+        if REGISTRY.is_null() {
+            REGISTRY = mem::transmute(box Vec::<Weak<Counters>>::new());
+        }
+        let live = prune();
+        (*REGISTRY).push(counters.downgrade());
+        live
+    };
+    task.stats = Some(counters);
+}
+
+/// Starts keeping statistics for the current task.
+pub fn enable_current() {
+    let mut task = Local::borrow(None::<Task>);
+    if task.stats.is_some() { return }
+    enable(&mut *task);
+    match task.stats {
+        Some(ref c) => c.update(|inner| inner.running_since.set(now())),
+        None => {}
+    }
+}
+
+/// Returns the statistics of the current task, if it keeps any.
+pub fn current() -> Option<TaskStats> {
+    let task = Local::borrow(None::<Task>);
+    task.stats.as_ref().map(|c| c.snapshot())
+}
+
+/// Returns the statistics of all of the live tasks which keep any.
+pub fn all() -> Vec<TaskStats> {
+    let live = unsafe {
+        let _g = LOCK.lock();
+        if REGISTRY.is_null() { return Vec::new() }
+        prune()
+    };
+    live.iter().map(|c| c.snapshot()).collect()
+}
+
+// Removes the dead tasks from the registry, returning the live ones. These may
+// only be dropped once LOCK is released, as dropping the last reference to the
+// counters of a task takes it.
+unsafe fn prune() -> Vec<Arc<Counters>> {
+    let live: Vec<Arc<Counters>> = (*REGISTRY).iter().filter_map(|c| {
+        c.upgrade()
+    }).collect();
+    *REGISTRY = live.iter().map(|c| c.downgrade()).collect();
+    live
+}
+
+/// Called by schedulers right before `task` starts running.
+pub fn resume(task: &Task) {
+    match task.stats {
+        Some(ref c) => c.update(|inner| {
+            inner.running_since.set(now());
+            inner.context_switches.add(1);
+        }),
+        None => {}
+    }
+}
+
+/// Called by schedulers right after `task` stops running.
+pub fn suspend(task: &Task) {
+    match task.stats {
+        Some(ref c) => c.update(|inner| {
+            let since = inner.running_since.get();
+            if since != 0 {
+                inner.scheduled_ns.add(now() - since);
+                inner.running_since.set(0);
+            }
+        }),
+        None => {}
+    }
+}
+
+/// Records that a message was sent by the current task.
+pub fn message_sent() {
+    with_current(|inner| inner.messages_sent.add(1))
+}
+
+/// Records that a message was received by the current task.
+pub fn message_received() {
+    with_current(|inner| inner.messages_received.add(1))
+}
+
+/// Records an allocation on the local heap of the current task.
+pub fn local_allocated(allocated: uint, freed: uint) {
+    with_current(|inner| {
+        inner.bytes_allocated.add(allocated as u64);
+        inner.bytes_freed.add(freed as u64);
+    })
+}
+
+// The hook for the exchange heap, which must not allocate.
+fn allocated(allocated: uint, freed: uint) {
+    with_current(|inner| {
+        inner.bytes_allocated.add(allocated as u64);
+        inner.bytes_freed.add(freed as u64);
+    })
+}
+
+#[inline]
+fn with_current(f: |&Inner|) {
+    if unsafe { ENABLED.load(atomics::Relaxed) } == 0 { return }
+    // The task is borrowed unsafely as this may run anywhere, including in
+    // the middle of the runtime mutating the task.
+    let task: Option<*mut Task> = unsafe { Local::try_unsafe_borrow() };
+    match task {
+        Some(task) => match unsafe { &(*task).stats } {
+            &Some(ref c) => c.update(f),
+            &None => {}
+        },
+        None => {}
+    }
+}
+
+impl Counters {
+    // Runs `f` to update the counters, which only one thread may do at a time.
+    #[inline]
+    fn update(&self, f: |&Inner|) {
+        self.seq.fetch_add(1, atomics::SeqCst);
+        f(&self.inner);
+        self.seq.fetch_add(1, atomics::SeqCst);
+    }
+
+    fn snapshot(&self) -> TaskStats {
+        let inner = &self.inner;
+        loop {
+            let seq = self.seq.load(atomics::SeqCst);
+            // An update is in progress.
+            if seq & 1 == 1 { continue }
+            let since = inner.running_since.get();
+            let stats = TaskStats {
+                name: None,
+                scheduled_ns: inner.scheduled_ns.get(),
+                context_switches: inner.context_switches.get(),
+                bytes_allocated: inner.bytes_allocated.get(),
+                bytes_freed: inner.bytes_freed.get(),
+                messages_sent: inner.messages_sent.get(),
+                messages_received: inner.messages_received.get(),
+            };
+            if self.seq.load(atomics::SeqCst) != seq { continue }
+
+            let running = if since == 0 { 0 } else { now() - since };
+            return TaskStats {
+                name: self.name.clone(),
+                scheduled_ns: stats.scheduled_ns + running,
+                ..stats
+            }
+        }
+    }
+}
+
+impl Drop for Counters {
+    fn drop(&mut self) {
+        unsafe {
+            let _g = LOCK.lock();
+            if ENABLED.fetch_sub(1, atomics::SeqCst) == 1 {
+                heap::set_hook(None);
+            }
+        }
+    }
+}
+
+impl Counter {
+    fn new() -> Counter {
+        Counter {
+            lo: atomics::AtomicUint::new(0),
+            hi: atomics::AtomicUint::new(0),
+        }
+    }
+
+    fn get(&self) -> u64 {
+        let lo = self.lo.load(atomics::SeqCst) as u64;
+        let hi = self.hi.load(atomics::SeqCst) as u64;
+        hi << 32 | lo
+    }
+
+    fn set(&self, n: u64) {
+        self.lo.store((n & 0xffffffff) as uint, atomics::SeqCst);
+        self.hi.store((n >> 32) as uint, atomics::SeqCst);
+    }
+
+    fn add(&self, n: u64) {
+        self.set(self.get() + n)
+    }
+}
+
+// A monotonic clock in nanoseconds, which never returns 0.
+fn now() -> u64 {
+    return os_precise_time_ns() | 1;
+
+    #[cfg(windows)]
+    fn os_precise_time_ns() -> u64 {
+        use libc;
+        let mut ticks_per_s = 0;
+        let mut ticks = 0;
+        unsafe {
+            libc::QueryPerformanceFrequency(&mut ticks_per_s);
+            libc::QueryPerformanceCounter(&mut ticks);
+        }
+        let ticks_per_s = if ticks_per_s == 0 {1} else {ticks_per_s};
+        (ticks as u64 * 1000000000) / (ticks_per_s as u64)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn os_precise_time_ns() -> u64 {
+        use libc;
+        extern {
+            fn mach_absolute_time() -> u64;
+            fn mach_timebase_info(info: *mut libc::mach_timebase_info)
+                                  -> libc::c_int;
+        }
+        static mut TIMEBASE: libc::mach_timebase_info =
+            libc::mach_timebase_info { numer: 0, denom: 0 };
+        unsafe {
+            // Racing to fill in the timebase is harmless, as everyone fills
+            // in the same values.
+            if TIMEBASE.denom == 0 {
+                mach_timebase_info(&mut TIMEBASE);
+            }
+            let time = mach_absolute_time();
+            time * TIMEBASE.numer as u64 / TIMEBASE.denom as u64
+        }
+    }
+
+    #[cfg(not(windows), not(target_os = "macos"))]
+    fn os_precise_time_ns() -> u64 {
+        use libc;
+
+        // Apparently android provides this in some other library?
+        #[cfg(not(target_os = "android"))]
+        #[link(name = "rt")]
+        extern {}
+
+        extern {
+            fn clock_gettime(clk_id: libc::c_int,
+                             tp: *mut libc::timespec) -> libc::c_int;
+        }
+
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        unsafe { clock_gettime(libc::CLOCK_MONOTONIC, &mut ts); }
+        (ts.tv_sec as u64) * 1000000000 + (ts.tv_nsec as u64)
+    }
+}
@@ -31,11 +31,12 @@ use result::{Result, Ok, Err};
 use rt::Runtime;
 use rt::local::Local;
 use rt::local_heap::LocalHeap;
-use rt::rtio::LocalIo;
+use rt::rtio::{LocalIo, RtioTimer, Callback};
+use rt::stats;
 use rt::unwind::Unwinder;
 use str::SendStr;
 use sync::atomics::{AtomicUint, SeqCst};
-use task::{TaskResult, TaskOpts};
+use task::{TaskResult, TaskOpts, Failure};
 use unstable::finally::Finally;
 
 /// The Task struct represents all state associated with a rust
@@ -54,6 +55,9 @@ pub struct Task {
 
     pub stdout: Option<Box<Writer:Send>>,
     pub stderr: Option<Box<Writer:Send>>,
+    pub failure_hook: Option<proc(&Failure):Send>,
+    pub stats: Option<Arc<stats::Counters>>,
+    pub blocked_on: BlockedOn,
 
     imp: Option<Box<Runtime:Send>>,
 }
@@ -69,6 +73,23 @@ pub enum BlockedTask {
     Shared(Arc<AtomicUint>),
 }
 
+/// What a task was waiting for when it was last descheduled, as told by the
+/// primitive which descheduled it. Runtimes may use this to describe their
+/// blocked tasks.
+#[deriving(Clone, Eq, Show)]
+pub enum BlockedOn {
+    /// Sending or receiving on a channel
+    OnChannel,
+    /// Sleeping until a timer fires
+    OnTimer,
+    /// Waiting for I/O to complete
+    OnIo,
+    /// Acquiring a mutex or waiting on a condition
+    OnMutex,
+    /// Anything else
+    OnUnknown,
+}
+
 pub enum DeathAction {
     /// Action to be done with the exit code. If set, also makes the task wait
     /// until all its watched children exit before collecting the status.
@@ -86,6 +107,22 @@ pub struct BlockedTasks {
     inner: Arc<AtomicUint>,
 }
 
+/// A timer which races to reawaken a blocked task. The task is blocked on one
+/// extra handle (see `make_selectable`) which is given to `block`, and if the
+/// timer expires before anything else wakes the task then it wakes it instead.
+pub struct BlockTimeout {
+    state: Arc<AtomicUint>,
+    // only held on to so that the timer is cancelled when this is dropped
+    timer: Box<RtioTimer:Send>,
+}
+
+struct TimeoutCallback {
+    state: Arc<AtomicUint>,
+}
+
+static TIMEOUT_EMPTY: uint = 0;
+static TIMEOUT_FIRED: uint = 1;
+
 impl Task {
     pub fn new() -> Task {
         Task {
@@ -98,6 +135,9 @@ impl Task {
             name: None,
             stdout: None,
             stderr: None,
+            failure_hook: None,
+            stats: None,
+            blocked_on: OnUnknown,
             imp: None,
         }
     }
@@ -246,8 +286,16 @@ impl Task {
     /// Deschedules the current task, invoking `f` `amt` times. It is not
     /// recommended to use this function directly, but rather communication
     /// primitives in `std::comm` should be used.
-    pub fn deschedule(mut ~self, amt: uint,
+    pub fn deschedule(~self, amt: uint,
                       f: |BlockedTask| -> Result<(), BlockedTask>) {
+        self.deschedule_on(OnUnknown, amt, f)
+    }
+
+    /// Deschedules the current task like `deschedule`, recording that it's
+    /// blocked on `on` for as long as it isn't running.
+    pub fn deschedule_on(mut ~self, on: BlockedOn, amt: uint,
+                         f: |BlockedTask| -> Result<(), BlockedTask>) {
+        self.blocked_on = on;
         let ops = self.imp.take_unwrap();
         ops.deschedule(amt, self, f)
     }
@@ -383,6 +431,62 @@ impl BlockedTask {
     }
 }
 
+impl BlockTimeout {
+    /// Starts a timer which will fire after `msecs` milliseconds. Returns
+    /// `None` if the local runtime has no timers to offer.
+    pub fn new(msecs: u64) -> Option<BlockTimeout> {
+        let mut timer = match LocalIo::maybe_raise(|io| io.timer_init()) {
+            Ok(timer) => timer,
+            Err(..) => return None,
+        };
+        let state = Arc::new(AtomicUint::new(TIMEOUT_EMPTY));
+        timer.oneshot_callback(msecs, box TimeoutCallback {
+            state: state.clone(),
+        } as Box<Callback:Send>);
+        Some(BlockTimeout { state: state, timer: timer })
+    }
+
+    /// Hands a blocked task over to the timer. If the timer has already
+    /// expired then the task is handed back.
+    pub fn block(&self, task: BlockedTask) -> Result<(), BlockedTask> {
+        let ptr = unsafe { task.cast_to_uint() };
+        match self.state.compare_and_swap(TIMEOUT_EMPTY, ptr, SeqCst) {
+            TIMEOUT_EMPTY => Ok(()),
+            _ => Err(unsafe { BlockedTask::cast_from_uint(ptr) }),
+        }
+    }
+
+    /// Takes the task back from the timer once it's running again, returning
+    /// whether the timer has expired. The timer can be blocked on again
+    /// afterwards, and it is cancelled when dropped.
+    pub fn reset(&self) -> bool {
+        loop {
+            match self.state.load(SeqCst) {
+                TIMEOUT_EMPTY => return false,
+                TIMEOUT_FIRED => return true,
+                n => {
+                    // If this fails then the timer has just fired, and it now
+                    // owns the handle to the task.
+                    if self.state.compare_and_swap(n, TIMEOUT_EMPTY,
+                                                   SeqCst) == n {
+                        unsafe { BlockedTask::cast_from_uint(n) }.trash();
+                        return false
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Callback for TimeoutCallback {
+    fn call(&mut self) {
+        match self.state.swap(TIMEOUT_FIRED, SeqCst) {
+            TIMEOUT_EMPTY => {}
+            n => unsafe { BlockedTask::cast_from_uint(n) }.reawaken(),
+        }
+    }
+}
+
 impl Death {
     pub fn new() -> Death {
         Death { on_exit: None, }
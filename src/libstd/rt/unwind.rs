@@ -57,9 +57,10 @@
 //
 // Currently Rust uses unwind runtime provided by libgcc.
 
-use any::{Any, AnyRefExt};
+use any::Any;
 use fmt;
 use intrinsics;
+use io::Writer;
 use kinds::Send;
 use mem;
 use option::{Some, None, Option};
@@ -68,23 +69,27 @@ use prelude::drop;
 use ptr::RawPtr;
 use result::{Err, Ok};
 use rt::backtrace;
+use rt::backtrace::Backtrace;
 use rt::local::Local;
 use rt::task::Task;
-use str::Str;
-use task::TaskResult;
+use sync::atomics;
+use task::{TaskResult, Failure};
 
 use uw = rt::libunwind;
 
 pub struct Unwinder {
     unwinding: bool,
-    cause: Option<Box<Any:Send>>
+    failure: Option<Failure>,
 }
 
+// The global failure hook, as set by `task::set_failure_hook`, or 0.
+static mut FAILURE_HOOK: atomics::AtomicUint = atomics::INIT_ATOMIC_UINT;
+
 impl Unwinder {
     pub fn new() -> Unwinder {
         Unwinder {
             unwinding: false,
-            cause: None,
+            failure: None,
         }
     }
 
@@ -127,11 +132,11 @@ impl Unwinder {
         }
     }
 
-    pub fn begin_unwind(&mut self, cause: Box<Any:Send>) -> ! {
+    pub fn begin_unwind(&mut self, failure: Failure) -> ! {
         rtdebug!("begin_unwind()");
 
         self.unwinding = true;
-        self.cause = Some(cause);
+        self.failure = Some(failure);
 
         rust_fail();
 
@@ -162,7 +167,7 @@ impl Unwinder {
 
     pub fn result(&mut self) -> TaskResult {
         if self.unwinding {
-            Err(self.cause.take().unwrap())
+            Err(self.failure.take().unwrap())
         } else {
             Ok(())
         }
@@ -384,95 +389,81 @@ pub fn begin_unwind<M: Any + Send>(msg: M, file: &'static str, line: uint) -> !
 fn begin_unwind_inner(msg: Box<Any:Send>,
                       file: &'static str,
                       line: uint) -> ! {
-    let mut task;
-    {
-        let msg_s = match msg.as_ref::<&'static str>() {
-            Some(s) => *s,
-            None => match msg.as_ref::<~str>() {
-                Some(s) => s.as_slice(),
-                None => "Box<Any>",
-            }
-        };
-
-        // It is assumed that all reasonable rust code will have a local task at
-        // all times. This means that this `try_take` will succeed almost all of
-        // the time. There are border cases, however, when the runtime has
-        // *almost* set up the local task, but hasn't quite gotten there yet. In
-        // order to get some better diagnostics, we print on failure and
-        // immediately abort the whole process if there is no local task
-        // available.
-        let opt_task: Option<Box<Task>> = Local::try_take();
-        task = match opt_task {
-            Some(t) => t,
-            None => {
-                rterrln!("failed at '{}', {}:{}", msg_s, file, line);
-                if backtrace::log_enabled() {
-                    let mut err = ::rt::util::Stderr;
-                    let _err = backtrace::write(&mut err);
-                } else {
-                    rterrln!("run with `RUST_BACKTRACE=1` to see a backtrace");
-                }
-                unsafe { intrinsics::abort() }
-            }
-        };
-
-        // See comments in io::stdio::with_task_stdout as to why we have to be
-        // careful when using an arbitrary I/O handle from the task. We
-        // essentially need to dance to make sure when a task is in TLS when
-        // running user code.
-        let name = task.name.take();
-        {
-            let n = name.as_ref().map(|n| n.as_slice()).unwrap_or("<unnamed>");
-
-            match task.stderr.take() {
-                Some(mut stderr) => {
-                    Local::put(task);
-                    // FIXME: what to do when the task printing fails?
-                    let _err = write!(stderr,
-                                      "task '{}' failed at '{}', {}:{}\n",
-                                      n, msg_s, file, line);
-                    if backtrace::log_enabled() {
-                        let _err = backtrace::write(stderr);
-                    }
-                    task = Local::take();
-
-                    match mem::replace(&mut task.stderr, Some(stderr)) {
-                        Some(prev) => {
-                            Local::put(task);
-                            drop(prev);
-                            task = Local::take();
-                        }
-                        None => {}
-                    }
-                }
-                None => {
-                    rterrln!("task '{}' failed at '{}', {}:{}", n, msg_s,
-                             file, line);
-                    if backtrace::log_enabled() {
-                        let mut err = ::rt::util::Stderr;
-                        let _err = backtrace::write(&mut err);
-                    }
-                }
-            }
-        }
-        task.name = name;
-
-        if task.unwinder.unwinding {
-            // If a task fails while it's already unwinding then we
-            // have limited options. Currently our preference is to
-            // just abort. In the future we may consider resuming
-            // unwinding or otherwise exiting the task cleanly.
-            rterrln!("task failed during unwinding (double-failure - total drag!)")
-            rterrln!("rust must abort now. so sorry.");
-
-            // Don't print the backtrace twice (it would have already been
-            // printed if logging was enabled).
-            if !backtrace::log_enabled() {
+    let mut failure = Failure {
+        name: None,
+        cause: msg,
+        file: file,
+        line: line,
+        backtrace: None,
+    };
+
+    // It is assumed that all reasonable rust code will have a local task at
+    // all times. This means that this `try_take` will succeed almost all of
+    // the time. There are border cases, however, when the runtime has
+    // *almost* set up the local task, but hasn't quite gotten there yet. In
+    // order to get some better diagnostics, we print on failure and
+    // immediately abort the whole process if there is no local task
+    // available.
+    let opt_task: Option<Box<Task>> = Local::try_take();
+    let mut task = match opt_task {
+        Some(t) => t,
+        None => {
+            rterrln!("failed at '{}', {}:{}",
+                     failure.message().unwrap_or("Box<Any>"), file, line);
+            if backtrace::log_enabled() {
                 let mut err = ::rt::util::Stderr;
                 let _err = backtrace::write(&mut err);
+            } else {
+                rterrln!("run with `RUST_BACKTRACE=1` to see a backtrace");
             }
             unsafe { intrinsics::abort() }
         }
+    };
+    failure.name = task.name.clone();
+
+    // Hooks aren't run for a failure during unwinding, which is always fatal.
+    let double_failure = task.unwinder.unwinding;
+    let local_hook = task.failure_hook.take();
+    let global_hook = failure_hook();
+    let hooked = !double_failure &&
+                 (local_hook.is_some() || global_hook.is_some());
+
+    if hooked || backtrace::log_enabled() {
+        Local::put(task);
+        failure.backtrace = Some(Backtrace::capture());
+        task = Local::take();
+    }
+
+    if hooked {
+        // The hook is user code, so it runs with the task in TLS, and it's
+        // treated as part of the unwinding so that failing inside of it is a
+        // double failure.
+        task.unwinder.unwinding = true;
+        Local::put(task);
+        match local_hook {
+            Some(hook) => hook(&failure),
+            None => (global_hook.unwrap())(&failure),
+        }
+        task = Local::take();
+    } else {
+        task = print_failure(task, &failure);
+    }
+
+    if double_failure {
+        // If a task fails while it's already unwinding then we
+        // have limited options. Currently our preference is to
+        // just abort. In the future we may consider resuming
+        // unwinding or otherwise exiting the task cleanly.
+        rterrln!("task failed during unwinding (double-failure - total drag!)")
+        rterrln!("rust must abort now. so sorry.");
+
+        // Don't print the backtrace twice (it would have already been
+        // printed if logging was enabled).
+        if !backtrace::log_enabled() {
+            let mut err = ::rt::util::Stderr;
+            let _err = backtrace::write(&mut err);
+        }
+        unsafe { intrinsics::abort() }
     }
 
     // The unwinder won't actually use the task at all, so we put the task back
@@ -481,6 +472,67 @@ fn begin_unwind_inner(msg: Box<Any:Send>,
     Local::put(task);
     unsafe {
         let task: *mut Task = Local::unsafe_borrow();
-        (*task).unwinder.begin_unwind(msg);
+        (*task).unwinder.begin_unwind(failure);
+    }
+}
+
+/// Prints the default failure message, along with the backtrace if one was
+/// captured, to the task's stderr.
+fn print_failure(mut task: Box<Task>, failure: &Failure) -> Box<Task> {
+    // See comments in io::stdio::with_task_stdout as to why we have to be
+    // careful when using an arbitrary I/O handle from the task. We
+    // essentially need to dance to make sure when a task is in TLS when
+    // running user code.
+    match task.stderr.take() {
+        Some(mut stderr) => {
+            Local::put(task);
+            // FIXME: what to do when the task printing fails?
+            let _err = write!(stderr, "{}\n", *failure);
+            match failure.backtrace {
+                Some(ref bt) => { let _err = write!(stderr, "{}", *bt); }
+                None => {}
+            }
+            task = Local::take();
+
+            match mem::replace(&mut task.stderr, Some(stderr)) {
+                Some(prev) => {
+                    Local::put(task);
+                    drop(prev);
+                    task = Local::take();
+                }
+                None => {}
+            }
+        }
+        None => {
+            rterrln!("{}", *failure);
+            match failure.backtrace {
+                Some(ref bt) => {
+                    let mut err = ::rt::util::Stderr;
+                    let _err = write!(err, "{}", *bt);
+                }
+                None => {}
+            }
+        }
+    }
+    task
+}
+
+fn failure_hook() -> Option<fn(&Failure)> {
+    match unsafe { FAILURE_HOOK.load(atomics::SeqCst) } {
+        0 => None,
+        hook => Some(unsafe { mem::transmute(hook) }),
+    }
+}
+
+/// Sets the hook which is run when a task without a hook of its own fails,
+/// returning the previous one. See `task::set_failure_hook`.
+pub fn set_failure_hook(hook: Option<fn(&Failure)>) -> Option<fn(&Failure)> {
+    let hook = match hook {
+        Some(hook) => unsafe { mem::transmute::<fn(&Failure), uint>(hook) },
+        None => 0,
+    };
+    match unsafe { FAILURE_HOOK.swap(hook, atomics::SeqCst) } {
+        0 => None,
+        prev => Some(unsafe { mem::transmute(prev) }),
     }
 }
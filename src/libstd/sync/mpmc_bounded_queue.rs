@@ -35,6 +35,7 @@ use clone::Clone;
 use kinds::Send;
 use num::next_power_of_two;
 use option::{Option, Some, None};
+use result::{Result, Ok, Err};
 use sync::atomics::{AtomicUint,Relaxed,Release,Acquire};
 use vec::Vec;
 use ty::Unsafe;
@@ -86,7 +87,7 @@ impl<T: Send> State<T> {
         }
     }
 
-    fn push(&self, value: T) -> bool {
+    fn push(&self, value: T) -> Result<(), T> {
         let mask = self.mask;
         let mut pos = self.enqueue_pos.load(Relaxed);
         loop {
@@ -106,12 +107,12 @@ impl<T: Send> State<T> {
                     pos = enqueue_pos;
                 }
             } else if diff < 0 {
-                return false
+                return Err(value)
             } else {
                 pos = self.enqueue_pos.load(Relaxed);
             }
         }
-        true
+        Ok(())
     }
 
     fn pop(&self) -> Option<T> {
@@ -149,6 +150,11 @@ impl<T: Send> Queue<T> {
     }
 
     pub fn push(&self, value: T) -> bool {
+        self.state.push(value).is_ok()
+    }
+
+    /// Like `push`, except that the value is handed back if the queue is full.
+    pub fn try_push(&self, value: T) -> Result<(), T> {
         self.state.push(value)
     }
 
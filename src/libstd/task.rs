@@ -36,18 +36,26 @@
  * ```
  */
 
-use any::Any;
+use any::{Any, AnyRefExt};
 use comm::{Sender, Receiver, channel};
+use fmt;
 use io::Writer;
 use kinds::{Send, marker};
+use mem;
 use option::{None, Some, Option};
 use owned::Box;
 use result::{Result, Ok, Err};
 use rt::local::Local;
+use rt::backtrace::Backtrace;
+use rt::stats;
 use rt::task::Task;
+use rt::unwind;
 use str::{Str, SendStr, IntoMaybeOwned};
+use vec::Vec;
 
-#[cfg(test)] use any::AnyRefExt;
+pub use rt::stats::TaskStats;
+
+#[cfg(test)] use iter::Iterator;
 #[cfg(test)] use owned::AnyOwnExt;
 #[cfg(test)] use result;
 #[cfg(test)] use str::StrAllocating;
@@ -58,7 +66,23 @@ use str::{Str, SendStr, IntoMaybeOwned};
 ///
 /// If you wish for this result's delivery to block until all
 /// children tasks complete, recommend using a result future.
-pub type TaskResult = Result<(), Box<Any:Send>>;
+pub type TaskResult = Result<(), Failure>;
+
+/// A report of why and where a task failed.
+pub struct Failure {
+    /// The name of the task, if it had one
+    pub name: Option<SendStr>,
+    /// The value given to `fail!`, which is usually a string
+    pub cause: Box<Any:Send>,
+    /// The source file in which the task failed
+    pub file: &'static str,
+    /// The line of `file` at which the task failed
+    pub line: uint,
+    /// The backtrace of the task at the point of failure. This is only
+    /// captured if the `RUST_BACKTRACE` environment variable is set or a
+    /// failure hook is going to be run.
+    pub backtrace: Option<Backtrace>,
+}
 
 /// Task configuration options
 pub struct TaskOpts {
@@ -72,6 +96,10 @@ pub struct TaskOpts {
     pub stdout: Option<Box<Writer:Send>>,
     /// Task-local stderr
     pub stderr: Option<Box<Writer:Send>>,
+    /// A hook to run instead of printing a message if the task fails
+    pub failure_hook: Option<proc(&Failure):Send>,
+    /// Keep statistics about the resources used by the task
+    pub stats: bool,
 }
 
 /**
@@ -139,6 +167,21 @@ impl TaskBuilder {
         self
     }
 
+    /// Run `hook` if the task-to-be fails, instead of printing a failure
+    /// message to its stderr. This takes precedence over the global hook set
+    /// with `set_failure_hook`.
+    pub fn on_failure(mut self, hook: proc(&Failure):Send) -> TaskBuilder {
+        self.opts.failure_hook = Some(hook);
+        self
+    }
+
+    /// Keep statistics about the resources used by the task-to-be, which can
+    /// be read with `stats` and `all_stats`.
+    pub fn with_stats(mut self) -> TaskBuilder {
+        self.opts.stats = true;
+        self
+    }
+
     /**
      * Add a wrapper to the body of the spawned task.
      *
@@ -203,8 +246,8 @@ impl TaskBuilder {
         });
 
         match result.recv() {
-            Ok(())     => Ok(rx.recv()),
-            Err(cause) => Err(cause)
+            Ok(())       => Ok(rx.recv()),
+            Err(failure) => Err(failure.cause)
         }
     }
 }
@@ -223,10 +266,34 @@ impl TaskOpts {
             stack_size: None,
             stdout: None,
             stderr: None,
+            failure_hook: None,
+            stats: false,
+        }
+    }
+}
+
+impl Failure {
+    /// Returns the message the task failed with, if `cause` is a string.
+    pub fn message<'a>(&'a self) -> Option<&'a str> {
+        match self.cause.as_ref::<&'static str>() {
+            Some(s) => Some(*s),
+            None => self.cause.as_ref::<~str>().map(|s| s.as_slice()),
         }
     }
 }
 
+impl fmt::Show for Failure {
+    /// Formats the failure the way that it's reported by default, without
+    /// the backtrace.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.name.as_ref().map(|n| n.as_slice());
+        write!(f, "task '{}' failed at '{}', {}:{}",
+               name.unwrap_or("<unnamed>"),
+               self.message().unwrap_or("Box<Any>"),
+               self.file, self.line)
+    }
+}
+
 /* Spawn convenience functions */
 
 /// Creates and executes a new child task
@@ -277,6 +344,45 @@ pub fn failing() -> bool {
     Local::borrow(None::<Task>).unwinder.unwinding()
 }
 
+/// Run `hook` if the current task fails, instead of printing a failure
+/// message to its stderr. This replaces any hook which was set before, and
+/// takes precedence over the global hook.
+pub fn on_failure(hook: proc(&Failure):Send) {
+    // The previous hook is dropped once the task is no longer borrowed.
+    let _prev = mem::replace(&mut Local::borrow(None::<Task>).failure_hook,
+                             Some(hook));
+}
+
+/// Sets the hook to run when a task without a hook of its own fails, returning
+/// the previous one. Failure messages are printed to the task's stderr when no
+/// hook is set, which is the default.
+///
+/// The hook runs in the failing task before it starts to unwind. If the hook
+/// itself fails then the whole process is aborted.
+pub fn set_failure_hook(hook: Option<fn(&Failure)>) -> Option<fn(&Failure)> {
+    unwind::set_failure_hook(hook)
+}
+
+/// Starts keeping statistics about the resources used by the current task,
+/// from now on. This does nothing if the task already keeps them.
+///
+/// Allocations on the local heap are only counted if the task has no managed
+/// boxes yet.
+pub fn enable_stats() {
+    stats::enable_current()
+}
+
+/// Returns the statistics of the current task, or `None` if it doesn't keep
+/// any.
+pub fn stats() -> Option<TaskStats> {
+    stats::current()
+}
+
+/// Returns the statistics of all of the live tasks which keep any.
+pub fn all_stats() -> Vec<TaskStats> {
+    stats::all()
+}
+
 // The following 8 tests test the following 2^3 combinations:
 // {un,}linked {un,}supervised failure propagation {up,down}wards.
 
@@ -356,6 +462,44 @@ fn test_future_result() {
     assert!(result.recv().is_err());
 }
 
+#[test]
+fn test_future_result_failure() {
+    let mut builder = TaskBuilder::new().named("doomed");
+    let result = builder.future_result();
+    builder.spawn(proc() {
+        fail!("oh no");
+    });
+    let failure = result.recv().unwrap_err();
+    assert_eq!(failure.name.unwrap().as_slice(), "doomed");
+    assert_eq!(failure.message(), Some("oh no"));
+    assert_eq!(failure.file, file!());
+}
+
+#[test]
+fn test_on_failure() {
+    let (tx, rx) = channel();
+    let mut builder = TaskBuilder::new().on_failure(proc(failure) {
+        tx.send(failure.message().unwrap().to_owned());
+    });
+    let result = builder.future_result();
+    builder.spawn(proc() {
+        fail!("{}", "hooked");
+    });
+    assert_eq!(rx.recv(), "hooked".to_owned());
+    assert!(result.recv().is_err());
+}
+
+#[test]
+fn test_on_failure_current_task() {
+    let (tx, rx) = channel();
+    let result = try(proc() {
+        on_failure(proc(failure) { tx.send(failure.line) });
+        fail!()
+    });
+    assert!(result.is_err());
+    assert!(rx.recv() > 0);
+}
+
 #[test] #[should_fail]
 fn test_back_to_the_future_result() {
     let mut builder = TaskBuilder::new();
@@ -534,3 +678,59 @@ fn test_try_fail_message_unit_struct() {
         Err(_) | Ok(()) => fail!()
     }
 }
+
+#[test]
+fn test_stats_disabled() {
+    let (tx, rx) = channel();
+    spawn(proc() tx.send(stats()));
+    assert!(rx.recv().is_none());
+}
+
+#[test]
+fn test_stats() {
+    let (tx, rx) = channel();
+    TaskBuilder::new().with_stats().spawn(proc() {
+        let (tx2, rx2) = channel();
+        let v = vec!(1u8, 2, 3);
+        tx2.send(v);
+        let _v = rx2.recv();
+        tx.send(stats().unwrap());
+    });
+    let s = rx.recv();
+    assert!(s.bytes_allocated >= 3);
+    assert!(s.messages_sent >= 1);
+    assert_eq!(s.messages_received, 1);
+    assert!(s.context_switches >= 1);
+}
+
+#[test]
+fn test_stats_local_heap() {
+    let (tx, rx) = channel();
+    TaskBuilder::new().with_stats().spawn(proc() {
+        let before = stats().unwrap();
+        {
+            let _b = @10;
+        }
+        let after = stats().unwrap();
+        tx.send((after.bytes_allocated - before.bytes_allocated,
+                 after.bytes_freed - before.bytes_freed));
+    });
+    let (allocated, freed) = rx.recv();
+    assert!(freed > 0);
+    assert!(allocated >= freed);
+}
+
+#[test]
+fn test_all_stats() {
+    let (tx, rx) = channel();
+    let (done_tx, done_rx) = channel::<()>();
+    TaskBuilder::new().named("counted").with_stats().spawn(proc() {
+        tx.send(());
+        done_rx.recv();
+    });
+    rx.recv();
+    assert!(all_stats().iter().any(|s| {
+        s.name.as_ref().map(|n| n.as_slice()) == Some("counted")
+    }));
+    done_tx.send(());
+}
@@ -0,0 +1,679 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Composable asynchronous results
+//!
+//! An `Async<T>` is a value of type `T` which will become available at some
+//! point, usually because another task fulfils the `Promise<T>` it was created
+//! alongside of. Unlike `sync::Future`, asyncs can be combined with `map`,
+//! `and_then`, `join_all` and `select_first` into larger computations without
+//! spawning a task for each step: the steps are run by whichever task ends up
+//! waiting for the result, as soon as their inputs are available.
+//!
+//! Any `Async` may also be waited on as part of a `std::comm::Select`, along
+//! with channels and other asyncs, through `Select::selectable_handle`.
+//!
+//! An `Async` resolves to an `Err` if its promise was dropped without being
+//! fulfilled (for example because the task holding it failed), or if it was
+//! given a timeout which expired. Dropping an `Async` cancels it, which the
+//! task working on the result can find out with `Promise::is_cancelled`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use sync::async::{Async, join_all};
+//!
+//! # fn fetch(i: uint) -> uint { i }
+//! let requests = Vec::from_fn(4, |i| Async::spawn(proc() fetch(i)));
+//! let total = join_all(requests).map(proc(v) v.iter().fold(0, |a, &b| a + b))
+//!                               .timeout(5000);
+//! assert_eq!(total.get(), Ok(6));
+//! ```
+
+use std::comm::{Selectable, Empty, Disconnected};
+use std::io::Timer;
+use std::kinds::marker;
+use std::mem;
+use std::rt::local::Local;
+use std::rt::task::{BlockedTask, Task, OnChannel};
+use std::sync::atomics;
+use std::ty::Unsafe;
+
+use alloc::arc::Arc;
+
+/// The reasons for which an `Async` may never produce a value.
+#[deriving(Clone, Eq, Show)]
+pub enum Error {
+    /// The promise was dropped without being fulfilled.
+    Abandoned,
+    /// The timeout given to `Async::timeout` expired first.
+    TimedOut,
+}
+
+/// A value which will become available at some point.
+pub struct Async<T> {
+    // The computation producing the value, which is `None` once it's done.
+    node: Unsafe<Option<Box<Node<T>:Send>>>,
+    // The result of the computation, until it's taken.
+    result: Unsafe<Option<Result<T, Error>>>,
+    marker: marker::NoShare,
+}
+
+/// The producing end of an `Async`.
+pub struct Promise<T> {
+    tx: Sender<T>,
+    cancelled: Arc<atomics::AtomicBool>,
+}
+
+// One step of a computation, which makes progress whenever it's polled by the
+// task waiting for the result.
+trait Node<T> {
+    // Makes as much progress as possible without blocking, returning the
+    // result once there is one. This isn't called again afterwards.
+    fn poll(&mut self) -> Option<Result<T, Error>>;
+
+    // Registers the task to be woken up once polling may make progress,
+    // handing it back if it already can.
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask>;
+
+    // Unregisters the task after a successful `block`, returning whether
+    // polling may make progress.
+    fn unblock(&mut self) -> bool;
+
+    // Lets whoever is working on the result know that it's no longer wanted.
+    fn cancel(&mut self);
+}
+
+/// Creates a new promise, along with the `Async` which its value is delivered
+/// to.
+pub fn promise<T: Send>() -> (Promise<T>, Async<T>) {
+    let (tx, rx) = channel();
+    let cancelled = Arc::new(atomics::AtomicBool::new(false));
+    let node = box Recv { rx: rx, cancelled: Some(cancelled.clone()) };
+    (Promise { tx: tx, cancelled: cancelled }, Async::new(node))
+}
+
+/// Combines many asyncs into one which resolves to all of their values, in
+/// order. If any of them fails to produce a value then so does the result,
+/// and the others are cancelled.
+pub fn join_all<T: Send>(asyncs: Vec<Async<T>>) -> Async<Vec<T>> {
+    let results = Vec::from_fn(asyncs.len(), |_| None);
+    Async::new(box JoinAll {
+        nodes: asyncs.move_iter().map(|a| Some(a.into_node())).collect(),
+        results: results,
+        blocked: None,
+    })
+}
+
+/// Combines many asyncs into one which resolves to the first of them to
+/// finish, along with its index. The others are then cancelled.
+///
+/// # Failure
+///
+/// Fails if `asyncs` is empty.
+pub fn select_first<T: Send>(asyncs: Vec<Async<T>>) -> Async<(uint, T)> {
+    assert!(asyncs.len() > 0, "select_first needs something to select");
+    Async::new(box First {
+        nodes: asyncs.move_iter().map(|a| a.into_node()).collect(),
+        registered: 0,
+    })
+}
+
+impl<T: Send> Async<T> {
+    fn new(node: Box<Node<T>:Send>) -> Async<T> {
+        Async {
+            node: Unsafe::new(Some(node)),
+            result: Unsafe::new(None),
+            marker: marker::NoShare,
+        }
+    }
+
+    /// Creates an async whose value is available right away.
+    pub fn from_value(value: T) -> Async<T> {
+        Async::new(box Value { result: Some(Ok(value)) })
+    }
+
+    /// Creates an async which resolves to the first value received on `rx`.
+    pub fn from_receiver(rx: Receiver<T>) -> Async<T> {
+        Async::new(box Recv { rx: rx, cancelled: None })
+    }
+
+    /// Creates an async which resolves to the result of running `blk` in a
+    /// new task.
+    pub fn spawn(blk: proc():Send -> T) -> Async<T> {
+        let (promise, async) = promise();
+        spawn(proc() {
+            promise.fulfil(blk());
+        });
+        async
+    }
+
+    /// Creates an async which resolves to this one's value passed through
+    /// `f`. The function is run by the task which waits for the result.
+    pub fn map<U: Send>(self, f: proc(T):Send -> U) -> Async<U> {
+        Async::new(box Map { node: self.into_node(), f: Some(f) })
+    }
+
+    /// Creates an async which resolves to the value of the async returned by
+    /// `f`, once this one's value has been passed to it.
+    pub fn and_then<U: Send>(self, f: proc(T):Send -> Async<U>) -> Async<U> {
+        Async::new(box AndThen {
+            first: Some(self.into_node()),
+            f: Some(f),
+            second: None,
+        })
+    }
+
+    /// Creates an async which resolves to `Err(TimedOut)` if this one hasn't
+    /// resolved within `ms` milliseconds. This one is cancelled when that
+    /// happens.
+    ///
+    /// # Failure
+    ///
+    /// Fails if the runtime is unable to provide a timer.
+    pub fn timeout(self, ms: u64) -> Async<T> {
+        let mut timer = match Timer::new() {
+            Ok(timer) => timer,
+            Err(e) => fail!("no timer is available for a timeout: {}", e),
+        };
+        let rx = timer.oneshot(ms);
+        let expire = Async::new(box Expire { timer: timer, rx: rx });
+        select_first(vec!(self, expire)).map(proc((_, t)) t)
+    }
+
+    /// Cancels this async, which is the same as dropping it.
+    pub fn cancel(self) {}
+
+    /// Returns the result if it's available, without blocking.
+    ///
+    /// # Failure
+    ///
+    /// Fails if the result has already been taken.
+    pub fn try_get(&mut self) -> Option<Result<T, Error>> {
+        if self.poll() {
+            unsafe { (*self.result.get()).take() }
+        } else {
+            None
+        }
+    }
+
+    /// Blocks until the result is available, running any steps which are
+    /// needed to produce it.
+    ///
+    /// # Failure
+    ///
+    /// Fails if the result has already been taken.
+    pub fn get(mut self) -> Result<T, Error> {
+        loop {
+            match self.try_get() {
+                Some(result) => return result,
+                None => {}
+            }
+            let node = unsafe { (*self.node.get()).get_mut_ref() };
+            let mut blocked = false;
+            let task: Box<Task> = Local::take();
+            task.deschedule_on(OnChannel, 1, |task| {
+                let ret = node.block(task);
+                blocked = ret.is_ok();
+                ret
+            });
+            if blocked {
+                node.unblock();
+            }
+        }
+    }
+
+    // Moves the result along as far as it can, returning whether it's ready.
+    fn poll(&self) -> bool {
+        unsafe {
+            if (*self.result.get()).is_some() { return true }
+            let result = match *self.node.get() {
+                Some(ref mut node) => node.poll(),
+                None => fail!("the result of this Async has already been taken"),
+            };
+            match result {
+                Some(result) => {
+                    *self.result.get() = Some(result);
+                    *self.node.get() = None;
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    fn into_node(self) -> Box<Node<T>:Send> {
+        unsafe {
+            match (*self.result.get()).take() {
+                Some(result) => box Value { result: Some(result) },
+                None => match (*self.node.get()).take() {
+                    Some(node) => node,
+                    None => fail!("the result of this Async has already been taken"),
+                },
+            }
+        }
+    }
+}
+
+#[unsafe_destructor]
+impl<T: Send> Drop for Async<T> {
+    fn drop(&mut self) {
+        match unsafe { (*self.node.get()).take() } {
+            Some(mut node) => node.cancel(),
+            None => {}
+        }
+    }
+}
+
+impl<T: Send> Selectable for Async<T> {
+    fn ready(&self) -> bool { self.poll() }
+
+    fn start_selection(&self, task: BlockedTask) -> Result<(), BlockedTask> {
+        unsafe { (*self.node.get()).get_mut_ref().block(task) }
+    }
+
+    fn abort_selection(&self) -> bool {
+        unsafe { (*self.node.get()).get_mut_ref().unblock() }
+    }
+
+    // Waking up only means that a step can be taken, and there may be more
+    // steps left to go.
+    fn complete(&self) -> bool { self.poll() }
+}
+
+impl<T: Send> Promise<T> {
+    /// Delivers a value to the async of this promise. The value is simply
+    /// dropped if the async has gone away.
+    pub fn fulfil(self, value: T) {
+        let _ = self.tx.send_opt(value);
+    }
+
+    /// Returns whether the async of this promise has been cancelled, in which
+    /// case whatever work is left to fulfil it may be skipped.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(atomics::SeqCst)
+    }
+}
+
+/****************************************************************************
+ * Computation steps
+ ****************************************************************************/
+
+struct Value<T> {
+    result: Option<Result<T, Error>>,
+}
+
+impl<T: Send> Node<T> for Value<T> {
+    fn poll(&mut self) -> Option<Result<T, Error>> { self.result.take() }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        Err(task)
+    }
+    fn unblock(&mut self) -> bool { true }
+    fn cancel(&mut self) {}
+}
+
+struct Recv<T> {
+    rx: Receiver<T>,
+    cancelled: Option<Arc<atomics::AtomicBool>>,
+}
+
+impl<T: Send> Node<T> for Recv<T> {
+    fn poll(&mut self) -> Option<Result<T, Error>> {
+        match self.rx.try_recv() {
+            Ok(t) => Some(Ok(t)),
+            Err(Empty) => None,
+            Err(Disconnected) => Some(Err(Abandoned)),
+        }
+    }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        self.rx.start_selection(task)
+    }
+    fn unblock(&mut self) -> bool { self.rx.abort_selection() }
+    fn cancel(&mut self) {
+        match self.cancelled {
+            Some(ref flag) => flag.store(true, atomics::SeqCst),
+            None => {}
+        }
+    }
+}
+
+struct Expire {
+    // Only held on to so that the timer keeps running.
+    timer: Timer,
+    rx: Receiver<()>,
+}
+
+impl<T: Send> Node<T> for Expire {
+    fn poll(&mut self) -> Option<Result<T, Error>> {
+        match self.rx.try_recv() {
+            Err(Empty) => None,
+            Ok(()) | Err(Disconnected) => Some(Err(TimedOut)),
+        }
+    }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        self.rx.start_selection(task)
+    }
+    fn unblock(&mut self) -> bool { self.rx.abort_selection() }
+    fn cancel(&mut self) {}
+}
+
+struct Map<A, T> {
+    node: Box<Node<A>:Send>,
+    f: Option<proc(A):Send -> T>,
+}
+
+impl<A: Send, T: Send> Node<T> for Map<A, T> {
+    fn poll(&mut self) -> Option<Result<T, Error>> {
+        match self.node.poll() {
+            Some(Ok(a)) => Some(Ok((self.f.take_unwrap())(a))),
+            Some(Err(e)) => Some(Err(e)),
+            None => None,
+        }
+    }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        self.node.block(task)
+    }
+    fn unblock(&mut self) -> bool { self.node.unblock() }
+    fn cancel(&mut self) { self.node.cancel() }
+}
+
+struct AndThen<A, T> {
+    first: Option<Box<Node<A>:Send>>,
+    f: Option<proc(A):Send -> Async<T>>,
+    second: Option<Box<Node<T>:Send>>,
+}
+
+impl<A: Send, T: Send> Node<T> for AndThen<A, T> {
+    fn poll(&mut self) -> Option<Result<T, Error>> {
+        let a = match self.first {
+            Some(ref mut node) => match node.poll() {
+                Some(Ok(a)) => Some(a),
+                Some(Err(e)) => return Some(Err(e)),
+                None => return None,
+            },
+            None => None,
+        };
+        match a {
+            Some(a) => {
+                self.first = None;
+                self.second = Some((self.f.take_unwrap())(a).into_node());
+            }
+            None => {}
+        }
+        self.second.get_mut_ref().poll()
+    }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        match self.first {
+            Some(ref mut node) => node.block(task),
+            None => self.second.get_mut_ref().block(task),
+        }
+    }
+    fn unblock(&mut self) -> bool {
+        match self.first {
+            Some(ref mut node) => node.unblock(),
+            None => self.second.get_mut_ref().unblock(),
+        }
+    }
+    fn cancel(&mut self) {
+        match self.first {
+            Some(ref mut node) => node.cancel(),
+            None => self.second.get_mut_ref().cancel(),
+        }
+    }
+}
+
+struct JoinAll<T> {
+    // Each of these is taken out once its result is in.
+    nodes: Vec<Option<Box<Node<T>:Send>>>,
+    results: Vec<Option<T>>,
+    // Only one of the nodes is blocked on at a time, as all of them are
+    // needed anyway.
+    blocked: Option<uint>,
+}
+
+impl<T: Send> Node<Vec<T>> for JoinAll<T> {
+    fn poll(&mut self) -> Option<Result<Vec<T>, Error>> {
+        let mut done = true;
+        for i in range(0, self.nodes.len()) {
+            let result = match *self.nodes.get_mut(i) {
+                Some(ref mut node) => node.poll(),
+                None => continue,
+            };
+            match result {
+                Some(Ok(t)) => {
+                    *self.nodes.get_mut(i) = None;
+                    *self.results.get_mut(i) = Some(t);
+                }
+                Some(Err(e)) => {
+                    *self.nodes.get_mut(i) = None;
+                    self.cancel();
+                    return Some(Err(e))
+                }
+                None => done = false,
+            }
+        }
+        if !done { return None }
+        let results = mem::replace(&mut self.results, Vec::new());
+        Some(Ok(results.move_iter().map(|t| t.unwrap()).collect()))
+    }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        for (i, node) in self.nodes.mut_iter().enumerate() {
+            match *node {
+                Some(ref mut node) => {
+                    try!(node.block(task));
+                    self.blocked = Some(i);
+                    return Ok(())
+                }
+                None => {}
+            }
+        }
+        Err(task)
+    }
+    fn unblock(&mut self) -> bool {
+        match self.blocked.take() {
+            Some(i) => self.nodes.get_mut(i).get_mut_ref().unblock(),
+            None => true,
+        }
+    }
+    fn cancel(&mut self) {
+        for node in self.nodes.mut_iter() {
+            match node.take() {
+                Some(mut node) => node.cancel(),
+                None => {}
+            }
+        }
+    }
+}
+
+struct First<T> {
+    nodes: Vec<Box<Node<T>:Send>>,
+    // How many of the nodes have been blocked on, from the front.
+    registered: uint,
+}
+
+impl<T: Send> Node<(uint, T)> for First<T> {
+    fn poll(&mut self) -> Option<Result<(uint, T), Error>> {
+        let mut result = None;
+        for (i, node) in self.nodes.mut_iter().enumerate() {
+            match node.poll() {
+                Some(r) => { result = Some(r.map(|t| (i, t))); break }
+                None => {}
+            }
+        }
+        if result.is_some() {
+            self.cancel();
+        }
+        result
+    }
+    fn block(&mut self, task: BlockedTask) -> Result<(), BlockedTask> {
+        // All of the nodes share the task, and whichever of them gets to it
+        // first wakes it up.
+        let mut tasks = task.make_selectable(self.nodes.len());
+        self.registered = 0;
+        for node in self.nodes.mut_iter() {
+            match node.block(tasks.next().unwrap()) {
+                Ok(()) => self.registered += 1,
+                Err(task) => {
+                    self.unblock();
+                    // The task can only be handed back if none of the nodes
+                    // which were just unblocked got to it first. If one did,
+                    // then it is already being woken up.
+                    return match task.wake() {
+                        Some(task) => Err(BlockedTask::block(task)),
+                        None => Ok(()),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    fn unblock(&mut self) -> bool {
+        let mut ready = false;
+        for node in self.nodes.mut_iter().take(self.registered) {
+            ready = node.unblock() || ready;
+        }
+        self.registered = 0;
+        ready
+    }
+    fn cancel(&mut self) {
+        for node in self.nodes.mut_iter() {
+            node.cancel();
+        }
+        self.nodes.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::comm::Select;
+    use std::task;
+
+    use super::{Async, promise, join_all, select_first, Abandoned, TimedOut};
+
+    #[test]
+    fn smoke() {
+        assert_eq!(Async::from_value(1).get(), Ok(1));
+        assert_eq!(Async::spawn(proc() 2).get(), Ok(2));
+
+        let (tx, rx) = channel();
+        tx.send(3);
+        assert_eq!(Async::from_receiver(rx).get(), Ok(3));
+    }
+
+    #[test]
+    fn try_get() {
+        let (p, mut a) = promise();
+        assert_eq!(a.try_get(), None);
+        p.fulfil(1);
+        assert_eq!(a.try_get(), Some(Ok(1)));
+    }
+
+    #[test]
+    fn abandoned() {
+        let (p, a) = promise::<int>();
+        drop(p);
+        assert_eq!(a.get(), Err(Abandoned));
+
+        let a = Async::spawn(proc() -> int { fail!() });
+        assert_eq!(a.get(), Err(Abandoned));
+    }
+
+    #[test]
+    fn cancel() {
+        let (p, a) = promise::<int>();
+        assert!(!p.is_cancelled());
+        a.map(proc(i) i + 1).cancel();
+        assert!(p.is_cancelled());
+    }
+
+    #[test]
+    fn map_and_then() {
+        let (p, a) = promise();
+        let (p2, a2) = promise();
+        let a = a.map(proc(i) i * 2)
+                 .and_then(proc(i) a2.map(proc(j) i + j));
+        spawn(proc() {
+            p.fulfil(10);
+            p2.fulfil(1);
+        });
+        assert_eq!(a.get(), Ok(21));
+    }
+
+    #[test]
+    fn and_then_error() {
+        let (p, a) = promise::<int>();
+        drop(p);
+        let a = a.and_then(proc(_) -> Async<int> { fail!() });
+        assert_eq!(a.get(), Err(Abandoned));
+    }
+
+    #[test]
+    fn join_all_values() {
+        let asyncs = Vec::from_fn(10, |i| Async::spawn(proc() i));
+        assert_eq!(join_all(asyncs).get(), Ok(Vec::from_fn(10, |i| i)));
+        assert_eq!(join_all::<int>(Vec::new()).get(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn join_all_error_cancels_rest() {
+        let (p1, a1) = promise::<int>();
+        let (p2, a2) = promise::<int>();
+        drop(p2);
+        assert_eq!(join_all(vec!(a1, a2)).get(), Err(Abandoned));
+        assert!(p1.is_cancelled());
+    }
+
+    #[test]
+    fn select_first_wins() {
+        let (p1, a1) = promise::<int>();
+        let (p2, a2) = promise::<int>();
+        spawn(proc() {
+            p2.fulfil(2);
+        });
+        assert_eq!(select_first(vec!(a1, a2)).get(), Ok((1, 2)));
+        assert!(p1.is_cancelled());
+    }
+
+    #[test]
+    fn timeout() {
+        let (p, a) = promise::<int>();
+        assert_eq!(a.timeout(10).get(), Err(TimedOut));
+        assert!(p.is_cancelled());
+
+        let a = Async::spawn(proc() 1).timeout(1000 * 60);
+        assert_eq!(a.get(), Ok(1));
+    }
+
+    #[test]
+    fn select() {
+        let (p1, mut a1) = promise::<int>();
+        let (p2, a2) = promise::<int>();
+        let mut a2 = a2.and_then(proc(i) Async::spawn(proc() i + 1));
+        spawn(proc() {
+            p2.fulfil(1);
+            task::deschedule();
+            drop(p1);
+        });
+
+        {
+            let sel = Select::new();
+            let mut h1 = sel.selectable_handle(&a1);
+            let mut h2 = sel.selectable_handle(&a2);
+            unsafe { h1.add(); h2.add(); }
+            let first = sel.wait();
+            unsafe { if first == h1.id() { h1.remove() } else { h2.remove() } }
+            let second = sel.wait();
+            assert!(first != second);
+            unsafe { h1.remove(); h2.remove(); }
+        }
+        assert_eq!(a1.try_get(), Some(Err(Abandoned)));
+        assert_eq!(a2.try_get(), Some(Ok(2)));
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A broadcast channel, delivering every message to every subscriber
+//!
+//! The channel keeps the last `lag` messages which were sent on it, and each
+//! receiver walks through them at its own pace. Sending never blocks: a
+//! receiver which falls more than `lag` messages behind skips ahead to the
+//! oldest message which is still around, and the number of messages it never
+//! saw is available from `missed`. Receivers only see messages sent after
+//! they subscribed.
+//!
+//! Receiving blocks while a receiver has seen everything, on both green and
+//! native tasks, and receivers may be waited on with `std::comm::Select`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use sync::broadcast;
+//!
+//! let (tx, rx) = broadcast::channel(8);
+//! let rx2 = tx.subscribe();
+//! tx.send(1);
+//! assert_eq!(rx.recv(), 1);
+//! assert_eq!(rx2.recv(), 1);
+//! ```
+
+use std::cell::Cell;
+use std::comm::{TryRecvError, Empty, Disconnected};
+use std::comm::Selectable;
+use std::kinds::marker;
+use std::rt::task::{BlockedTask, OnChannel};
+use std::sync::atomics;
+use std::ty::Unsafe;
+use std::unstable::mutex::NativeMutex;
+
+use alloc::arc::Arc;
+
+use waitqueue;
+use waitqueue::{Node, WaitQueue};
+
+struct Shared<T> {
+    senders: atomics::AtomicUint,
+    lock: NativeMutex,
+    state: Unsafe<State<T>>,
+}
+
+// Everything in here is protected by the lock.
+struct State<T> {
+    // Message `n` lives in slot `n % buf.len()` until it is overwritten.
+    buf: Vec<Option<T>>,
+    // The sequence number of the next message to be sent.
+    next: u64,
+    disconnected: bool,
+    waiters: WaitQueue,
+}
+
+/// The sending half of a broadcast channel, which may be cloned to send from
+/// several tasks.
+pub struct Sender<T> {
+    inner: Arc<Shared<T>>,
+    marker: marker::NoShare,
+}
+
+/// A subscription to a broadcast channel, which receives every message sent
+/// after it was created.
+pub struct Receiver<T> {
+    inner: Arc<Shared<T>>,
+    // The sequence number of the next message this receiver will see.
+    pos: Cell<u64>,
+    missed: Cell<u64>,
+    // Where this receiver is queued while it is being selected over.
+    node: Unsafe<Node>,
+    marker: marker::NoShare,
+}
+
+/// An iterator over the messages on a receiver, which blocks while there is
+/// nothing new and ends once every sender is gone.
+pub struct Messages<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+/// Creates a new broadcast channel which keeps the last `lag` messages around
+/// for receivers which have yet to see them.
+///
+/// # Failure
+///
+/// Fails if `lag` is zero.
+pub fn channel<T: Send + Clone>(lag: uint) -> (Sender<T>, Receiver<T>) {
+    assert!(lag > 0, "a broadcast channel needs room for a message");
+    let inner = Arc::new(Shared {
+        senders: atomics::AtomicUint::new(1),
+        lock: unsafe { NativeMutex::new() },
+        state: Unsafe::new(State {
+            buf: Vec::from_fn(lag, |_| None),
+            next: 0,
+            disconnected: false,
+            waiters: WaitQueue::new(),
+        }),
+    });
+    (Sender { inner: inner.clone(), marker: marker::NoShare },
+     Receiver::new(inner, 0))
+}
+
+impl<T: Send + Clone> State<T> {
+    // Skips `rx` past any messages which have been overwritten, returning
+    // whether it has anything left to receive.
+    fn catch_up(&self, rx: &Receiver<T>) -> bool {
+        let lag = self.buf.len() as u64;
+        let oldest = if self.next > lag { self.next - lag } else { 0 };
+        if rx.pos.get() < oldest {
+            rx.missed.set(rx.missed.get() + oldest - rx.pos.get());
+            rx.pos.set(oldest);
+        }
+        rx.pos.get() < self.next
+    }
+
+    fn ready(&self, rx: &Receiver<T>) -> bool {
+        self.catch_up(rx) || self.disconnected
+    }
+}
+
+impl<T: Send + Clone> Sender<T> {
+    /// Sends a value to every receiver, without blocking.
+    ///
+    /// The oldest message is overwritten if the channel is full, and the value
+    /// is simply dropped if nothing is subscribed to the channel.
+    pub fn send(&self, t: T) {
+        let tasks = unsafe {
+            let _g = self.inner.lock.lock();
+            let state = &mut *self.inner.state.get();
+            let slot = (state.next % state.buf.len() as u64) as uint;
+            *state.buf.get_mut(slot) = Some(t);
+            state.next += 1;
+            state.waiters.drain()
+        };
+        for task in tasks.move_iter() {
+            waitqueue::wake(task);
+        }
+    }
+
+    /// Creates a new receiver, which will see every message sent from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let next = unsafe {
+            let _g = self.inner.lock.lock();
+            (*self.inner.state.get()).next
+        };
+        Receiver::new(self.inner.clone(), next)
+    }
+}
+
+impl<T: Send + Clone> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.inner.senders.fetch_add(1, atomics::SeqCst);
+        Sender { inner: self.inner.clone(), marker: marker::NoShare }
+    }
+}
+
+#[unsafe_destructor]
+impl<T: Send + Clone> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.inner.senders.fetch_sub(1, atomics::SeqCst) != 1 { return }
+        let tasks = unsafe {
+            let _g = self.inner.lock.lock();
+            let state = &mut *self.inner.state.get();
+            state.disconnected = true;
+            state.waiters.drain()
+        };
+        for task in tasks.move_iter() {
+            waitqueue::wake(task);
+        }
+    }
+}
+
+impl<T: Send + Clone> Receiver<T> {
+    fn new(inner: Arc<Shared<T>>, pos: u64) -> Receiver<T> {
+        Receiver {
+            inner: inner,
+            pos: Cell::new(pos),
+            missed: Cell::new(0),
+            node: Unsafe::new(Node::new()),
+            marker: marker::NoShare,
+        }
+    }
+
+    /// Receives the next message, blocking until one is sent.
+    ///
+    /// # Failure
+    ///
+    /// Fails if every sender has been dropped and this receiver has seen all
+    /// of the messages which are left.
+    pub fn recv(&self) -> T {
+        match self.recv_opt() {
+            Ok(t) => t,
+            Err(()) => fail!("receiving on a closed channel"),
+        }
+    }
+
+    /// Receives the next message, blocking until one is sent.
+    ///
+    /// Returns `Err` once every sender has been dropped and this receiver has
+    /// seen all of the messages which are left.
+    pub fn recv_opt(&self) -> Result<T, ()> {
+        loop {
+            match self.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(Disconnected) => return Err(()),
+                Err(Empty) => {}
+            }
+            let inner = &*self.inner;
+            let waiters = unsafe { &mut (*inner.state.get()).waiters };
+            waitqueue::block(&inner.lock, waiters, OnChannel, || {
+                unsafe { (*inner.state.get()).ready(self) }
+            });
+        }
+    }
+
+    /// Attempts to receive the next message without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        unsafe {
+            let _g = self.inner.lock.lock();
+            let state = &*self.inner.state.get();
+            if state.catch_up(self) {
+                let pos = self.pos.get();
+                self.pos.set(pos + 1);
+                let slot = (pos % state.buf.len() as u64) as uint;
+                Ok(state.buf.get(slot).get_ref().clone())
+            } else if state.disconnected {
+                Err(Disconnected)
+            } else {
+                Err(Empty)
+            }
+        }
+    }
+
+    /// Returns the number of messages this receiver has skipped over because
+    /// it fell too far behind.
+    pub fn missed(&self) -> u64 { self.missed.get() }
+
+    /// Returns an iterator which blocks waiting for messages, ending once
+    /// every sender has been dropped.
+    pub fn iter<'a>(&'a self) -> Messages<'a, T> {
+        Messages { rx: self }
+    }
+}
+
+impl<'a, T: Send + Clone> Iterator<T> for Messages<'a, T> {
+    fn next(&mut self) -> Option<T> { self.rx.recv_opt().ok() }
+}
+
+impl<T: Send + Clone> Clone for Receiver<T> {
+    /// Creates another receiver which starts out at the same message as this
+    /// one.
+    fn clone(&self) -> Receiver<T> {
+        Receiver::new(self.inner.clone(), self.pos.get())
+    }
+}
+
+impl<T: Send + Clone> Selectable for Receiver<T> {
+    fn ready(&self) -> bool {
+        unsafe {
+            let _g = self.inner.lock.lock();
+            (*self.inner.state.get()).ready(self)
+        }
+    }
+
+    fn start_selection(&self, task: BlockedTask) -> Result<(), BlockedTask> {
+        unsafe {
+            let _g = self.inner.lock.lock();
+            let state = &mut *self.inner.state.get();
+            let node = &mut *self.node.get();
+            state.waiters.push(node, task);
+            if state.ready(self) {
+                Err(state.waiters.remove(node).unwrap())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn abort_selection(&self) -> bool {
+        unsafe {
+            let _g = self.inner.lock.lock();
+            let state = &mut *self.inner.state.get();
+            match state.waiters.remove(&mut *self.node.get()) {
+                Some(task) => task.trash(),
+                None => {}
+            }
+            state.ready(self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate native;
+
+    use std::comm::{Empty, Disconnected};
+    use std::comm::Select;
+
+    use super::channel;
+
+    #[test]
+    fn smoke() {
+        let (tx, rx) = channel(4);
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.try_recv(), Err(Empty));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(Disconnected));
+        assert_eq!(rx.recv_opt(), Err(()));
+    }
+
+    #[test]
+    fn every_subscriber_sees_every_message() {
+        let (tx, rx1) = channel(4);
+        let rx2 = tx.subscribe();
+        tx.send(1);
+        tx.send(2);
+        drop(tx);
+        assert_eq!(rx1.iter().collect::<Vec<int>>(), vec!(1, 2));
+        assert_eq!(rx2.iter().collect::<Vec<int>>(), vec!(1, 2));
+    }
+
+    #[test]
+    fn subscribers_start_at_the_end() {
+        let (tx, rx1) = channel(4);
+        tx.send(1);
+        let rx2 = tx.subscribe();
+        tx.send(2);
+        assert_eq!(rx1.recv(), 1);
+        assert_eq!(rx2.recv(), 2);
+        assert_eq!(rx2.try_recv(), Err(Empty));
+    }
+
+    #[test]
+    fn lagging_receiver_skips_ahead() {
+        let (tx, rx) = channel(2);
+        for i in range(0, 5) { tx.send(i); }
+        assert_eq!(rx.recv(), 3);
+        assert_eq!(rx.missed(), 3);
+        assert_eq!(rx.recv(), 4);
+        assert_eq!(rx.missed(), 3);
+    }
+
+    #[test]
+    fn clone_keeps_position() {
+        let (tx, rx1) = channel(4);
+        tx.send(1);
+        tx.send(2);
+        assert_eq!(rx1.recv(), 1);
+        let rx2 = rx1.clone();
+        assert_eq!(rx1.recv(), 2);
+        assert_eq!(rx2.recv(), 2);
+    }
+
+    #[test]
+    fn blocking_subscribers() {
+        let (tx, rx) = channel(16);
+        let (done_tx, done_rx) = ::std::comm::channel();
+        for _ in range(0, 3) {
+            let rx = rx.clone();
+            let done_tx = done_tx.clone();
+            spawn(proc() {
+                done_tx.send(rx.iter().fold(0, |a, b| a + b));
+            });
+        }
+        for _ in range(0, 2) {
+            let rx = rx.clone();
+            let done_tx = done_tx.clone();
+            native::task::spawn(proc() {
+                done_tx.send(rx.iter().fold(0, |a, b| a + b));
+            });
+        }
+        drop(rx);
+        for i in range(0, 10) { tx.send(i); }
+        drop(tx);
+        for _ in range(0, 5) { assert_eq!(done_rx.recv(), 45); }
+    }
+
+    #[test]
+    fn select() {
+        let (tx1, rx1) = channel::<int>(2);
+        let (tx2, rx2) = channel::<int>(2);
+        spawn(proc() {
+            tx2.send(2);
+        });
+        let sel = Select::new();
+        let mut h1 = sel.selectable_handle(&rx1);
+        let mut h2 = sel.selectable_handle(&rx2);
+        unsafe { h1.add(); h2.add(); }
+        assert_eq!(sel.wait(), h2.id());
+        assert_eq!(rx2.recv(), 2);
+        drop(tx1);
+        assert_eq!(sel.wait(), h1.id());
+        assert_eq!(rx1.recv_opt(), Err(()));
+    }
+}
@@ -44,7 +44,12 @@ mod future;
 mod lock;
 mod mpsc_intrusive;
 mod task_pool;
+mod waitqueue;
 
 pub mod raw;
 pub mod mutex;
 pub mod one;
+pub mod mpmc;
+pub mod broadcast;
+pub mod pool;
+pub mod async;
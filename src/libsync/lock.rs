@@ -113,6 +113,24 @@ impl<'a> Condvar<'a> {
         PoisonOnFail::check(*self.poison.flag, self.name);
     }
 
+    /// Atomically exit the associated lock and block until a signal is sent
+    /// or `ms` milliseconds have passed, returning whether a signal was
+    /// received. The lock is held again when this returns either way.
+    ///
+    /// wait_timeout(ms) is equivalent to wait_timeout_on(0, ms).
+    #[inline]
+    pub fn wait_timeout(&self, ms: u64) -> bool { self.wait_timeout_on(0, ms) }
+
+    /// As wait_timeout, but blocking on a specified condvar (as
+    /// sync::cond.wait_timeout_on).
+    #[inline]
+    pub fn wait_timeout_on(&self, condvar_id: uint, ms: u64) -> bool {
+        assert!(!*self.poison.flag);
+        let signalled = self.inner.cond().wait_timeout_on(condvar_id, ms);
+        PoisonOnFail::check(*self.poison.flag, self.name);
+        signalled
+    }
+
     /// Wake up a blocked task. Returns false if there was no blocked task.
     #[inline]
     pub fn signal(&self) -> bool { self.signal_on(0) }
@@ -311,8 +329,22 @@ impl<T: Send + Share> RWLock<T> {
     /// the lock, so subsequent readers and writers will both also fail.
     #[inline]
     pub fn write<'a>(&'a self) -> RWLockWriteGuard<'a, T> {
-        let guard = self.lock.write();
+        self.write_guard(self.lock.write())
+    }
 
+    /// Access the underlying data mutably if nobody else is accessing it,
+    /// without blocking. Returns `None` if the lock is held.
+    ///
+    /// # Failure
+    ///
+    /// As write, this fails if the lock has been poisoned.
+    #[inline]
+    pub fn try_write<'a>(&'a self) -> Option<RWLockWriteGuard<'a, T>> {
+        self.lock.try_write().map(|guard| self.write_guard(guard))
+    }
+
+    fn write_guard<'a>(&'a self,
+                       guard: raw::RWLockWriteGuard<'a>) -> RWLockWriteGuard<'a, T> {
         // These two accesses are safe because we're guranteed at this point
         // that we have exclusive access to this rwlock. We are indeed able to
         // promote ourselves from &RWLock to `&mut T`
@@ -337,7 +369,22 @@ impl<T: Send + Share> RWLock<T> {
     /// Failing will unlock the lock while unwinding. However, unlike all other
     /// access modes, this will not poison the lock.
     pub fn read<'a>(&'a self) -> RWLockReadGuard<'a, T> {
-        let guard = self.lock.read();
+        self.read_guard(self.lock.read())
+    }
+
+    /// Access the underlying data immutably if no writer is accessing it,
+    /// without blocking. Returns `None` if a writer holds or is waiting for
+    /// the lock.
+    ///
+    /// # Failure
+    ///
+    /// As read, this fails if the lock has been poisoned.
+    pub fn try_read<'a>(&'a self) -> Option<RWLockReadGuard<'a, T>> {
+        self.lock.try_read().map(|guard| self.read_guard(guard))
+    }
+
+    fn read_guard<'a>(&'a self,
+                      guard: raw::RWLockReadGuard<'a>) -> RWLockReadGuard<'a, T> {
         PoisonOnFail::check(unsafe { *self.failed.get() }, "RWLock");
         RWLockReadGuard {
             guard: guard,
@@ -471,6 +518,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mutex_arc_condvar_timeout() {
+        let arc = Arc::new(Mutex::new(false));
+        let arc2 = arc.clone();
+
+        let mut lock = arc.lock();
+        assert!(!lock.cond.wait_timeout(10));
+        *lock = true;
+        drop(lock);
+
+        let lock = arc.lock();
+        task::spawn(proc() {
+            let mut lock = arc2.lock();
+            *lock = false;
+            lock.cond.signal();
+        });
+        while *lock {
+            assert!(lock.cond.wait_timeout(1000 * 60));
+        }
+    }
+
+    #[test]
+    fn test_rw_arc_try() {
+        let arc = RWLock::new(1);
+        {
+            let _r = arc.read();
+            assert_eq!(*arc.try_read().unwrap(), 1);
+            assert!(arc.try_write().is_none());
+        }
+        {
+            let mut w = arc.try_write().unwrap();
+            *w = 2;
+            assert!(arc.try_read().is_none());
+        }
+        assert_eq!(*arc.try_read().unwrap(), 2);
+    }
+
     #[test] #[should_fail]
     fn test_arc_condvar_poison() {
         let arc = Arc::new(Mutex::new(1));
@@ -0,0 +1,666 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A bounded multi-producer multi-consumer channel
+//!
+//! Unlike the channels in `std::comm`, the receiving half of this channel can
+//! be cloned, and every message is delivered to exactly one of the receivers.
+//! This is the shape of a pool of workers pulling jobs off of a shared queue.
+//!
+//! The messages themselves are stored in a lock-free
+//! `std::sync::mpmc_bounded_queue`, so sending and receiving never take a lock
+//! unless there is a task blocked on the other side. Senders block while the
+//! channel is full and receivers block while it's empty, on both green and
+//! native tasks.
+//!
+//! # Example
+//!
+//! ```rust
+//! use sync::mpmc;
+//!
+//! let (tx, rx) = mpmc::channel(16);
+//! for _ in range(0, 4) {
+//!     let rx = rx.clone();
+//!     spawn(proc() {
+//!         for job in rx.iter() {
+//!             println!("working on {}", job);
+//!         }
+//!     });
+//! }
+//! for i in range(0, 100) {
+//!     tx.send(i);
+//! }
+//! ```
+
+use std::comm::{TryRecvError, Empty, Disconnected};
+use std::comm::Selectable;
+use std::kinds::marker;
+use std::rt::task::{BlockedTask, OnChannel};
+use std::sync::atomics;
+use std::sync::mpmc_bounded_queue::Queue;
+use std::ty::Unsafe;
+use std::unstable::mutex::NativeMutex;
+
+use alloc::arc::Arc;
+
+use waitqueue;
+use waitqueue::{Node, WaitQueue};
+
+struct Shared<T> {
+    queue: Queue<T>,
+    senders: atomics::AtomicUint,
+    receivers: atomics::AtomicUint,
+
+    // Tasks blocked on either side of the channel. Both queues are protected by
+    // `lock`. The message queue doesn't need the lock, but it is looked at
+    // once more with the lock held right after a task has been queued, so
+    // that a message or a disconnection arriving meanwhile isn't missed.
+    lock: NativeMutex,
+    blocked_senders: Unsafe<WaitQueue>,
+    blocked_receivers: Unsafe<WaitQueue>,
+
+    // Messages handed back by dropped receivers while the channel was full,
+    // which are received ahead of the queued ones. The list is protected by
+    // `lock`, and its length is kept outside of it so that receiving doesn't
+    // need to take the lock while the list is empty.
+    returned: Unsafe<Vec<T>>,
+    nreturned: atomics::AtomicUint,
+}
+
+/// The sending half of a multi-consumer channel, which may be cloned to send
+/// from several tasks.
+pub struct Sender<T> {
+    inner: Arc<Shared<T>>,
+    marker: marker::NoShare,
+}
+
+/// The receiving half of a multi-consumer channel. Each clone of a receiver
+/// takes its own messages off of the channel.
+pub struct Receiver<T> {
+    inner: Arc<Shared<T>>,
+    // A message taken off of the channel by a `Select` on this receiver, which
+    // is handed out by the next call to receive.
+    stash: Unsafe<Option<T>>,
+    // Where this receiver is queued while it is being selected over.
+    node: Unsafe<Node>,
+    marker: marker::NoShare,
+}
+
+/// An iterator over the messages on a receiver, which blocks when the channel
+/// is empty and ends once every sender is gone.
+pub struct Messages<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+/// Creates a new multi-consumer channel which can buffer `bound` messages
+/// before senders block.
+///
+/// The bound is rounded up to the next power of two, and is at least two.
+pub fn channel<T: Send>(bound: uint) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Shared {
+        queue: Queue::with_capacity(bound),
+        senders: atomics::AtomicUint::new(1),
+        receivers: atomics::AtomicUint::new(1),
+        lock: unsafe { NativeMutex::new() },
+        blocked_senders: Unsafe::new(WaitQueue::new()),
+        blocked_receivers: Unsafe::new(WaitQueue::new()),
+        returned: Unsafe::new(Vec::new()),
+        nreturned: atomics::AtomicUint::new(0),
+    });
+    (Sender { inner: inner.clone(), marker: marker::NoShare },
+     Receiver::new(inner))
+}
+
+impl<T: Send> Shared<T> {
+    fn disconnected(&self) -> bool {
+        self.senders.load(atomics::SeqCst) == 0
+    }
+
+    fn orphaned(&self) -> bool {
+        self.receivers.load(atomics::SeqCst) == 0
+    }
+
+    // Wakes up one task blocked in `queue`, if there is one. The fence pairs
+    // with the one in `block` so that either the blocked task sees what was
+    // just done to the message queue or we see the blocked task.
+    fn wake_one(&self, queue: &Unsafe<WaitQueue>) {
+        atomics::fence(atomics::SeqCst);
+        if unsafe { (*queue.get()).len() } == 0 { return }
+        let task = unsafe {
+            let _g = self.lock.lock();
+            (*queue.get()).pop()
+        };
+        task.map(waitqueue::wake);
+    }
+
+    fn wake_all(&self, queue: &Unsafe<WaitQueue>) {
+        let tasks = unsafe {
+            let _g = self.lock.lock();
+            (*queue.get()).drain()
+        };
+        for task in tasks.move_iter() {
+            waitqueue::wake(task);
+        }
+    }
+
+    // Blocks on `queue` unless `abort` says otherwise once the task is queued.
+    fn block(&self, queue: &Unsafe<WaitQueue>, abort: || -> bool) {
+        waitqueue::block(&self.lock, queue.get(), OnChannel, || {
+            atomics::fence(atomics::SeqCst);
+            abort()
+        });
+    }
+
+    // Pushes a message, blocking while the channel is full. The message is
+    // handed back if every receiver has been dropped.
+    fn push(&self, t: T) -> Result<(), T> {
+        let mut data = Some(t);
+        loop {
+            let t = data.take_unwrap();
+            if self.orphaned() { return Err(t) }
+            match self.queue.try_push(t) {
+                Ok(()) => break,
+                Err(t) => data = Some(t),
+            }
+
+            self.block(&self.blocked_senders, || {
+                match self.queue.try_push(data.take_unwrap()) {
+                    Ok(()) => true,
+                    Err(t) => { data = Some(t); self.orphaned() }
+                }
+            });
+            if data.is_none() { break }
+        }
+        self.wake_one(&self.blocked_receivers);
+        Ok(())
+    }
+
+    fn pop(&self) -> Option<T> {
+        if self.nreturned.load(atomics::SeqCst) > 0 {
+            let ret = unsafe {
+                let _g = self.lock.lock();
+                self.pop_returned()
+            };
+            if ret.is_some() { return ret }
+        }
+        let ret = self.queue.pop();
+        if ret.is_some() {
+            self.wake_one(&self.blocked_senders);
+        }
+        ret
+    }
+
+    // Like `pop`, but with the lock already held and without waking anyone
+    // up. Returns whether the message made room in the queue.
+    unsafe fn pop_locked(&self) -> (Option<T>, bool) {
+        match self.pop_returned() {
+            Some(t) => (Some(t), false),
+            None => {
+                let ret = self.queue.pop();
+                let made_room = ret.is_some();
+                (ret, made_room)
+            }
+        }
+    }
+
+    // Takes a message handed back by a dropped receiver. The lock must be held.
+    unsafe fn pop_returned(&self) -> Option<T> {
+        let ret = (*self.returned.get()).shift();
+        if ret.is_some() {
+            self.nreturned.fetch_sub(1, atomics::SeqCst);
+        }
+        ret
+    }
+
+    // Hands back a message taken off of the channel by a receiver which is
+    // going away. Destructors shouldn't block, so if the channel has filled up
+    // in the meantime the message is put aside instead of waiting for room.
+    fn give_back(&self, t: T) {
+        match self.queue.try_push(t) {
+            Ok(()) => {}
+            Err(t) => unsafe {
+                let _g = self.lock.lock();
+                (*self.returned.get()).push(t);
+                self.nreturned.fetch_add(1, atomics::SeqCst);
+            }
+        }
+        self.wake_one(&self.blocked_receivers);
+    }
+}
+
+impl<T: Send> Sender<T> {
+    /// Sends a value on the channel, blocking while the channel is full.
+    ///
+    /// # Failure
+    ///
+    /// Fails if every receiver has been dropped, as the value can never be
+    /// received.
+    pub fn send(&self, t: T) {
+        if self.send_opt(t).is_err() {
+            fail!("sending on a closed channel");
+        }
+    }
+
+    /// Sends a value on the channel, blocking while the channel is full.
+    ///
+    /// The value is handed back if every receiver has been dropped. Note that
+    /// a successful send does not guarantee that the value will be received.
+    pub fn send_opt(&self, t: T) -> Result<(), T> {
+        self.inner.push(t)
+    }
+
+    /// Attempts to send a value without blocking. The value is handed back if
+    /// the channel is full or every receiver has been dropped.
+    pub fn try_send(&self, t: T) -> Result<(), T> {
+        let inner = &*self.inner;
+        if inner.orphaned() { return Err(t) }
+        try!(inner.queue.try_push(t));
+        inner.wake_one(&inner.blocked_receivers);
+        Ok(())
+    }
+}
+
+impl<T: Send> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.inner.senders.fetch_add(1, atomics::SeqCst);
+        Sender { inner: self.inner.clone(), marker: marker::NoShare }
+    }
+}
+
+#[unsafe_destructor]
+impl<T: Send> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.inner.senders.fetch_sub(1, atomics::SeqCst) == 1 {
+            self.inner.wake_all(&self.inner.blocked_receivers);
+        }
+    }
+}
+
+impl<T: Send> Receiver<T> {
+    fn new(inner: Arc<Shared<T>>) -> Receiver<T> {
+        Receiver {
+            inner: inner,
+            stash: Unsafe::new(None),
+            node: Unsafe::new(Node::new()),
+            marker: marker::NoShare,
+        }
+    }
+
+    /// Receives a value from the channel, blocking while it is empty.
+    ///
+    /// # Failure
+    ///
+    /// Fails if every sender has been dropped and the channel is empty.
+    pub fn recv(&self) -> T {
+        match self.recv_opt() {
+            Ok(t) => t,
+            Err(()) => fail!("receiving on a closed channel"),
+        }
+    }
+
+    /// Receives a value from the channel, blocking while it is empty.
+    ///
+    /// Returns `Err` once every sender has been dropped and the channel is
+    /// empty.
+    pub fn recv_opt(&self) -> Result<T, ()> {
+        let inner = &*self.inner;
+        loop {
+            match self.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(Disconnected) => return Err(()),
+                Err(Empty) => {}
+            }
+
+            let mut data = None;
+            let mut made_room = false;
+            inner.block(&inner.blocked_receivers, || {
+                let (t, room) = unsafe { inner.pop_locked() };
+                data = t;
+                made_room = room;
+                data.is_some() || inner.disconnected()
+            });
+            if made_room {
+                inner.wake_one(&inner.blocked_senders);
+            }
+            match data {
+                Some(t) => return Ok(t),
+                None => {}
+            }
+        }
+    }
+
+    /// Attempts to receive a value without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let inner = &*self.inner;
+        match unsafe { (*self.stash.get()).take() } {
+            Some(t) => return Ok(t),
+            None => {}
+        }
+        match inner.pop() {
+            Some(t) => return Ok(t),
+            None => {}
+        }
+        // The senders may have pushed their last messages right before
+        // leaving, so look once more after seeing them gone.
+        if !inner.disconnected() { return Err(Empty) }
+        match inner.pop() {
+            Some(t) => Ok(t),
+            None => Err(Disconnected),
+        }
+    }
+
+    /// Returns an iterator which blocks waiting for messages, ending once
+    /// every sender has been dropped.
+    pub fn iter<'a>(&'a self) -> Messages<'a, T> {
+        Messages { rx: self }
+    }
+
+    // Makes sure that a message is in the stash if one can be had, returning
+    // whether a receive would now complete immediately.
+    fn fill_stash(&self) -> bool {
+        unsafe {
+            let stash = &mut *self.stash.get();
+            if stash.is_none() {
+                *stash = self.inner.pop();
+            }
+            stash.is_some() || self.inner.disconnected()
+        }
+    }
+}
+
+impl<'a, T: Send> Iterator<T> for Messages<'a, T> {
+    fn next(&mut self) -> Option<T> { self.rx.recv_opt().ok() }
+}
+
+impl<T: Send> Clone for Receiver<T> {
+    /// Creates another receiver on the same channel, which competes with this
+    /// one for messages.
+    fn clone(&self) -> Receiver<T> {
+        self.inner.receivers.fetch_add(1, atomics::SeqCst);
+        Receiver::new(self.inner.clone())
+    }
+}
+
+#[unsafe_destructor]
+impl<T: Send> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if self.inner.receivers.fetch_sub(1, atomics::SeqCst) == 1 {
+            self.inner.wake_all(&self.inner.blocked_senders);
+            return
+        }
+        // A message taken off of the channel by `Select` belongs to the other
+        // receivers now.
+        match unsafe { (*self.stash.get()).take() } {
+            Some(t) => self.inner.give_back(t),
+            None => {}
+        }
+    }
+}
+
+// A receiver being selected over is queued alongside the receivers blocked in
+// `recv`. Whichever message wakes it up is only taken off of the channel once
+// `Select` has decided to return this receiver, so it may have been taken by
+// another receiver by then.
+impl<T: Send> Selectable for Receiver<T> {
+    fn ready(&self) -> bool { self.fill_stash() }
+
+    fn start_selection(&self, task: BlockedTask) -> Result<(), BlockedTask> {
+        let inner = &*self.inner;
+        let (ret, made_room) = unsafe {
+            let _g = inner.lock.lock();
+            let queue = &mut *inner.blocked_receivers.get();
+            let node = &mut *self.node.get();
+            queue.push(node, task);
+            atomics::fence(atomics::SeqCst);
+            // `ready` has already come up empty, so the stash is too.
+            let stash = &mut *self.stash.get();
+            let (t, made_room) = inner.pop_locked();
+            *stash = t;
+            if stash.is_some() || inner.disconnected() {
+                (Err(queue.remove(node).unwrap()), made_room)
+            } else {
+                (Ok(()), made_room)
+            }
+        };
+        // A sender may be waiting for the room we just made, but it can only
+        // be woken up once the lock has been released.
+        if made_room {
+            inner.wake_one(&inner.blocked_senders);
+        }
+        ret
+    }
+
+    fn abort_selection(&self) -> bool {
+        let inner = &*self.inner;
+        let (woken, pass_on) = unsafe {
+            let _g = inner.lock.lock();
+            let queue = &mut *inner.blocked_receivers.get();
+            match queue.remove(&mut *self.node.get()) {
+                // Still queued, so nobody has tried to wake us up.
+                Some(task) => { task.trash(); (false, None) }
+                // We were woken up for a message which `Select` may not take
+                // from us, so someone else needs to have a look as well.
+                None => (true, queue.pop()),
+            }
+        };
+        pass_on.map(waitqueue::wake);
+        woken || unsafe { (*self.stash.get()).is_some() } ||
+            inner.disconnected()
+    }
+
+    fn complete(&self) -> bool { self.fill_stash() }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate native;
+
+    use std::comm::{Empty, Disconnected};
+    use std::comm::Select;
+
+    use super::channel;
+
+    #[test]
+    fn smoke() {
+        let (tx, rx) = channel(4);
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.try_recv(), Err(Empty));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(Disconnected));
+        assert_eq!(rx.recv_opt(), Err(()));
+    }
+
+    #[test]
+    fn try_send_full() {
+        let (tx, rx) = channel(2);
+        assert_eq!(tx.try_send(1), Ok(()));
+        assert_eq!(tx.try_send(2), Ok(()));
+        assert_eq!(tx.try_send(3), Err(3));
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(tx.try_send(3), Ok(()));
+        drop(rx);
+        assert_eq!(tx.try_send(4), Err(4));
+        assert_eq!(tx.send_opt(4), Err(4));
+    }
+
+    #[test]
+    fn messages_outlive_senders() {
+        let (tx, rx) = channel(4);
+        tx.send(1);
+        tx.send(2);
+        drop(tx);
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.recv(), 2);
+        assert_eq!(rx.recv_opt(), Err(()));
+    }
+
+    #[test]
+    fn each_message_received_once() {
+        static N: uint = 1000;
+        static WORKERS: uint = 4;
+        let (tx, rx) = channel(8);
+        let (done_tx, done_rx) = ::std::comm::channel();
+        for _ in range(0, WORKERS) {
+            let rx = rx.clone();
+            let done_tx = done_tx.clone();
+            spawn(proc() {
+                let mut sum = 0;
+                for i in rx.iter() { sum += i; }
+                done_tx.send(sum);
+            });
+        }
+        drop(rx);
+        for i in range(0, N) { tx.send(i); }
+        drop(tx);
+        let mut sum = 0;
+        for _ in range(0, WORKERS) { sum += done_rx.recv(); }
+        assert_eq!(sum, N * (N - 1) / 2);
+    }
+
+    #[test]
+    fn native_workers() {
+        static N: uint = 1000;
+        let (tx, rx) = channel(2);
+        let (done_tx, done_rx) = ::std::comm::channel();
+        for _ in range(0, 3) {
+            let rx = rx.clone();
+            let done_tx = done_tx.clone();
+            native::task::spawn(proc() {
+                done_tx.send(rx.iter().count(|_| true));
+            });
+        }
+        for _ in range(0, 2) {
+            let tx = tx.clone();
+            native::task::spawn(proc() {
+                for i in range(0, N) { tx.send(i); }
+            });
+        }
+        drop((tx, rx));
+        let mut total = 0;
+        for _ in range(0, 3) { total += done_rx.recv(); }
+        assert_eq!(total, 2 * N);
+    }
+
+    #[test]
+    fn sender_blocks_while_full() {
+        let (tx, rx) = channel(2);
+        let (done_tx, done_rx) = ::std::comm::channel();
+        spawn(proc() {
+            for i in range(0, 3) { tx.send(i); }
+            done_tx.send(());
+        });
+        assert_eq!(rx.recv(), 0);
+        done_rx.recv();
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.recv(), 2);
+    }
+
+    #[test]
+    fn sender_woken_by_last_receiver() {
+        let (tx, rx) = channel(2);
+        tx.send(1);
+        tx.send(2);
+        let (done_tx, done_rx) = ::std::comm::channel();
+        spawn(proc() {
+            done_tx.send(tx.send_opt(3));
+        });
+        drop(rx);
+        assert_eq!(done_rx.recv(), Err(3));
+    }
+
+    #[test]
+    fn select() {
+        let (tx1, rx1) = channel::<int>(2);
+        let (tx2, rx2) = channel::<int>(2);
+        spawn(proc() {
+            tx2.send(2);
+        });
+        let sel = Select::new();
+        let mut h1 = sel.selectable_handle(&rx1);
+        let mut h2 = sel.selectable_handle(&rx2);
+        unsafe { h1.add(); h2.add(); }
+        let id = sel.wait();
+        assert_eq!(id, h2.id());
+        assert_eq!(rx2.recv(), 2);
+        drop(tx1);
+        assert_eq!(sel.wait(), h1.id());
+        assert_eq!(rx1.recv_opt(), Err(()));
+    }
+
+    #[test]
+    fn stash_returned_on_drop() {
+        let (tx, rx) = channel::<int>(2);
+        let rx2 = rx.clone();
+        tx.send(1);
+        {
+            let sel = Select::new();
+            let mut h = sel.selectable_handle(&rx2);
+            unsafe { h.add(); }
+            assert_eq!(sel.wait(), h.id());
+        }
+        // The message is now in the stash of `rx2`
+        assert_eq!(rx.try_recv(), Err(Empty));
+        drop(rx2);
+        assert_eq!(rx.recv(), 1);
+    }
+
+    #[test]
+    fn stash_returned_on_drop_while_full() {
+        let (tx, rx) = channel::<int>(2);
+        let rx2 = rx.clone();
+        tx.send(1);
+        {
+            let sel = Select::new();
+            let mut h = sel.selectable_handle(&rx2);
+            unsafe { h.add(); }
+            assert_eq!(sel.wait(), h.id());
+        }
+        tx.send(2);
+        tx.send(3);
+        // The channel is full, which mustn't keep `rx2` from going away.
+        drop(rx2);
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.recv(), 2);
+        assert_eq!(rx.recv(), 3);
+        assert_eq!(rx.try_recv(), Err(Empty));
+    }
+
+    #[test]
+    fn select_shared_receiver() {
+        static N: uint = 100;
+        let (tx, rx) = channel::<uint>(4);
+        let (done_tx, done_rx) = ::std::comm::channel();
+        for _ in range(0, 2) {
+            let rx = rx.clone();
+            let done_tx = done_tx.clone();
+            spawn(proc() {
+                let (_tx, other) = ::std::comm::channel::<()>();
+                let sel = Select::new();
+                let mut h1 = sel.handle(&other);
+                let mut h2 = sel.selectable_handle(&rx);
+                unsafe { h1.add(); h2.add(); }
+                let mut count = 0;
+                loop {
+                    assert_eq!(sel.wait(), h2.id());
+                    match rx.recv_opt() {
+                        Ok(..) => count += 1,
+                        Err(()) => break,
+                    }
+                }
+                done_tx.send(count);
+            });
+        }
+        drop(rx);
+        for i in range(0, N) { tx.send(i); }
+        drop(tx);
+        assert_eq!(done_rx.recv() + done_rx.recv(), N);
+    }
+}
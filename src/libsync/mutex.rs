@@ -60,7 +60,7 @@
 use std::kinds::marker;
 use std::mem;
 use std::rt::local::Local;
-use std::rt::task::{BlockedTask, Task};
+use std::rt::task::{BlockedTask, Task, OnMutex};
 use std::rt::thread::Thread;
 use std::sync::atomics;
 use std::ty::Unsafe;
@@ -245,7 +245,7 @@ impl StaticMutex {
         // of lock stealing the lock, it's also possible for native/native
         // contention to hit this location, but as less common.
         let t: Box<Task> = Local::take();
-        t.deschedule(1, |task| {
+        t.deschedule_on(OnMutex, 1, |task| {
             let task = unsafe { task.cast_to_uint() };
 
             // These accesses are protected by the respective native/green
@@ -330,7 +330,7 @@ impl StaticMutex {
         }
 
         let mut node = q::Node::new(0);
-        t.deschedule(1, |task| {
+        t.deschedule_on(OnMutex, 1, |task| {
             unsafe {
                 node.data = task.cast_to_uint();
                 self.q.push(&mut node);
@@ -0,0 +1,753 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A work-stealing pool of tasks for data parallelism
+//!
+//! Each task in a `Pool` owns a Chase-Lev deque (`std::sync::deque`) of jobs.
+//! New jobs are pushed onto the deque of the task which created them and are
+//! taken back off of it in LIFO order, while idle tasks steal the oldest jobs
+//! from the other end of their peers' deques. Jobs from outside of the pool
+//! are injected through a shared deque which every task steals from.
+//!
+//! There are two ways of running jobs in a pool:
+//!
+//! * `join` runs two closures, potentially in parallel, and returns both of
+//!   their results. This is meant for divide-and-conquer recursion, where the
+//!   closures call `join` again themselves.
+//!
+//! * `Pool::scope` runs a closure which may spawn any number of jobs, and only
+//!   returns once all of them have finished. Until then the jobs may borrow
+//!   anything from the stack frame which created the scope.
+//!
+//! The closures given to `join` aren't allocated anywhere: they live on the
+//! stack of the task waiting for them. A job which fails causes whoever is
+//! waiting on it to fail as well, after which the pool carries on as before.
+//!
+//! # Example
+//!
+//! ```rust
+//! use sync::pool::{Pool, join};
+//!
+//! fn sum(v: &[uint]) -> uint {
+//!     if v.len() <= 1024 { return v.iter().fold(0, |a, &b| a + b) }
+//!     let mid = v.len() / 2;
+//!     let (l, r) = join(|| sum(v.slice_to(mid)), || sum(v.slice_from(mid)));
+//!     l + r
+//! }
+//!
+//! let pool = Pool::new(4);
+//! let v = Vec::from_fn(100000, |i| i);
+//! let total = pool.join(|| sum(v.slice_to(50000)),
+//!                       || sum(v.slice_from(50000)));
+//!
+//! let mut squares = Vec::from_elem(100, 0u);
+//! pool.scope(|s| {
+//!     for (i, chunk) in squares.as_mut_slice().mut_chunks(10).enumerate() {
+//!         s.spawn(proc(_) {
+//!             for (j, x) in chunk.mut_iter().enumerate() {
+//!                 let n = i * 10 + j;
+//!                 *x = n * n;
+//!             }
+//!         });
+//!     }
+//! });
+//! ```
+
+use std::kinds::marker;
+use std::mem;
+use std::rt::local::Local;
+use std::rt::task::{BlockedTask, Task, OnUnknown};
+use std::sync::atomics;
+use std::sync::deque::{BufferPool, Worker, Stealer, Data, Empty, Abort};
+use std::task;
+use std::task::TaskBuilder;
+use std::ty::Unsafe;
+use std::unstable::mutex::NativeMutex;
+
+use alloc::arc::Arc;
+
+use waitqueue;
+use waitqueue::WaitQueue;
+
+/// A pool of tasks which run jobs created by `join` and `Scope::spawn`.
+///
+/// The tasks in the pool are spawned like any other task, so whether they run
+/// in parallel depends on the runtime. On native threads they always do, while
+/// green tasks need a scheduler pool with enough schedulers.
+///
+/// Dropping the pool lets its tasks exit once they are idle.
+pub struct Pool {
+    registry: Arc<Registry>,
+}
+
+/// A scope created by `Pool::scope`, in which jobs may be spawned which borrow
+/// anything outliving the lifetime `'a` from the enclosing stack frame.
+pub struct Scope<'a> {
+    registry: Arc<Registry>,
+    // One for every job which hasn't finished yet, plus one for the closure
+    // passed to `Pool::scope`.
+    pending: atomics::AtomicUint,
+    failed: atomics::AtomicBool,
+    done: Latch,
+    // The lifetime may not be shortened, or jobs could borrow from the
+    // closure passed to `Pool::scope`, which returns before they finish.
+    marker: marker::InvariantLifetime<'a>,
+}
+
+// The state shared by all of the tasks in a pool.
+struct Registry {
+    threads: uint,
+
+    // Jobs from outside of the pool are pushed onto this deque while holding
+    // `inject_lock`, as a deque only has one pushing end.
+    inject_lock: NativeMutex,
+    injector: Unsafe<Worker<JobRef>>,
+
+    // Idle tasks sleep in `sleepers` until `epoch` changes, which it does
+    // whenever a new job is made available.
+    sleep_lock: NativeMutex,
+    sleepers: Unsafe<WaitQueue>,
+    epoch: atomics::AtomicUint,
+    terminating: atomics::AtomicBool,
+}
+
+// The state of one of the tasks in a pool.
+struct WorkerThread {
+    index: uint,
+    deque: Worker<JobRef>,
+    stealers: Vec<Stealer<JobRef>>,
+    injector: Stealer<JobRef>,
+    registry: Arc<Registry>,
+}
+
+// A type-erased pointer to a job, which lives either on the stack of a task
+// waiting for it or in an allocation owned by the job itself.
+struct JobRef {
+    data: *(),
+    run: unsafe fn(*()),
+}
+
+// A job running a closure created by `join`, which lives on the stack of the
+// task which will wait for it.
+struct StackJob<'a, R> {
+    func: Unsafe<Option<||:'a + Share -> R>>,
+    result: Unsafe<Option<R>>,
+    done: Latch,
+}
+
+// A job spawned in a scope.
+struct HeapJob<'a> {
+    func: proc(&Scope<'a>):'a + Share,
+    scope: *Scope<'a>,
+}
+
+// A flag which may be waited on by a single task.
+struct Latch {
+    state: atomics::AtomicUint,
+}
+
+static UNSET: uint = 0;
+static SET: uint = 1;
+
+local_data_key!(WORKER_THREAD: *WorkerThread)
+
+impl Pool {
+    /// Spawns a new pool of `threads` tasks.
+    ///
+    /// # Failure
+    ///
+    /// Fails if `threads` is zero.
+    pub fn new(threads: uint) -> Pool {
+        assert!(threads >= 1);
+        let buffers = BufferPool::new();
+        let (injector, inject_stealer) = buffers.deque();
+        let registry = Arc::new(Registry {
+            threads: threads,
+            inject_lock: unsafe { NativeMutex::new() },
+            injector: Unsafe::new(injector),
+            sleep_lock: unsafe { NativeMutex::new() },
+            sleepers: Unsafe::new(WaitQueue::new()),
+            epoch: atomics::AtomicUint::new(0),
+            terminating: atomics::AtomicBool::new(false),
+        });
+
+        let mut deques = Vec::new();
+        let mut stealers = Vec::new();
+        for _ in range(0, threads) {
+            let (deque, stealer) = buffers.deque();
+            deques.push(deque);
+            stealers.push(stealer);
+        }
+        for (i, deque) in deques.move_iter().enumerate() {
+            spawn_worker(box WorkerThread {
+                index: i,
+                deque: deque,
+                stealers: stealers.clone(),
+                injector: inject_stealer.clone(),
+                registry: registry.clone(),
+            });
+        }
+        Pool { registry: registry }
+    }
+
+    /// Returns the number of tasks in this pool.
+    pub fn threads(&self) -> uint { self.registry.threads }
+
+    /// Runs `a` and `b` in this pool, potentially in parallel, and returns
+    /// both of their results. The calling task blocks until both are done.
+    ///
+    /// # Failure
+    ///
+    /// Fails if either of the closures fails, once both have finished.
+    pub fn join<A: Send, B: Send>(&self, a: ||:Share -> A,
+                                  b: ||:Share -> B) -> (A, B) {
+        let mut a = Some(a);
+        let mut b = Some(b);
+        self.in_worker(|worker| worker.join(a.take_unwrap(), b.take_unwrap()))
+    }
+
+    /// Runs `f` in this pool, returning only once every job spawned in the
+    /// given scope has finished. The jobs may therefore borrow from the stack
+    /// frame of the caller.
+    ///
+    /// # Failure
+    ///
+    /// Fails if `f` or any of the jobs spawned in the scope fails, once all of
+    /// them have finished.
+    pub fn scope<'a, R: Send>(&self, f: |&Scope<'a>|:Share -> R) -> R {
+        let mut f = Some(f);
+        self.in_worker(|worker| worker.scope(f.take_unwrap()))
+    }
+
+    // Runs `op` on one of the tasks of this pool, which is the current task if
+    // it belongs to the pool.
+    fn in_worker<R: Send>(&self, op: |&WorkerThread|:Share -> R) -> R {
+        unsafe {
+            match WorkerThread::current() {
+                Some(worker) if (*worker).belongs_to(&*self.registry) => {
+                    return op(&*worker)
+                }
+                _ => {}
+            }
+            let job = StackJob::new(|| {
+                op(&*WorkerThread::current().unwrap())
+            });
+            self.registry.inject(job.as_job_ref());
+            job.done.wait();
+            job.into_result()
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.registry.terminate();
+    }
+}
+
+/// Runs `a` and `b`, potentially in parallel, and returns both of their
+/// results.
+///
+/// When called from a job running in a `Pool`, `b` is made available for
+/// another task in the pool to steal while `a` runs. Otherwise `a` and `b` are
+/// simply run one after the other.
+///
+/// # Failure
+///
+/// Fails if either of the closures fails, once both have finished.
+pub fn join<A: Send, B: Send>(a: ||:Share -> A, b: ||:Share -> B) -> (A, B) {
+    match WorkerThread::current() {
+        Some(worker) => unsafe { (*worker).join(a, b) },
+        None => (a(), b()),
+    }
+}
+
+impl<'a> Scope<'a> {
+    /// Spawns a job in this scope, which will run on some task in the pool
+    /// before the scope ends.
+    ///
+    /// The job is given the scope, through which it may spawn more jobs.
+    pub fn spawn(&self, f: proc(&Scope<'a>):'a + Share) {
+        self.pending.fetch_add(1, atomics::SeqCst);
+        let job = box HeapJob { func: f, scope: self as *Scope<'a> };
+        self.registry.push(JobRef {
+            data: unsafe { mem::transmute(job) },
+            run: run_heap_job,
+        });
+    }
+
+    fn job_done(&self) {
+        if self.pending.fetch_sub(1, atomics::SeqCst) == 1 {
+            self.done.set();
+        }
+    }
+}
+
+impl Registry {
+    // Makes a job available to the tasks of the pool, pushing it onto the
+    // deque of the current task if it's one of them.
+    fn push(&self, job: JobRef) {
+        match WorkerThread::current() {
+            Some(worker) if unsafe { (*worker).belongs_to(self) } => {
+                unsafe { (*worker).deque.push(job) }
+                self.notify();
+            }
+            _ => self.inject(job),
+        }
+    }
+
+    fn inject(&self, job: JobRef) {
+        unsafe {
+            let _g = self.inject_lock.lock();
+            (*self.injector.get()).push(job);
+        }
+        self.notify();
+    }
+
+    // Wakes up an idle task to go look for the job which was just pushed. The
+    // job is visible before the epoch changes, so a task which goes to sleep
+    // on an older epoch will have seen it or will be woken up.
+    fn notify(&self) {
+        self.epoch.fetch_add(1, atomics::SeqCst);
+        if unsafe { (*self.sleepers.get()).len() } == 0 { return }
+        let task = unsafe {
+            let _g = self.sleep_lock.lock();
+            (*self.sleepers.get()).pop()
+        };
+        task.map(waitqueue::wake);
+    }
+
+    fn sleep(&self, epoch: uint) {
+        waitqueue::block(&self.sleep_lock, self.sleepers.get(), OnUnknown, || {
+            atomics::fence(atomics::SeqCst);
+            self.epoch.load(atomics::SeqCst) != epoch || self.terminating()
+        });
+    }
+
+    fn terminating(&self) -> bool {
+        self.terminating.load(atomics::SeqCst)
+    }
+
+    fn terminate(&self) {
+        self.terminating.store(true, atomics::SeqCst);
+        let tasks = unsafe {
+            let _g = self.sleep_lock.lock();
+            (*self.sleepers.get()).drain()
+        };
+        for task in tasks.move_iter() {
+            waitqueue::wake(task);
+        }
+    }
+}
+
+fn spawn_worker(worker: Box<WorkerThread>) {
+    let name = format!("<pool worker {}>", worker.index);
+    TaskBuilder::new().named(name).spawn(proc() {
+        let guard = Respawn { worker: Some(worker) };
+        guard.worker.get_ref().run();
+    });
+}
+
+// Replaces a task in the pool which failed while running a job. Its deque may
+// still have jobs in it which other tasks are waiting for, so the new task
+// takes it over.
+struct Respawn {
+    worker: Option<Box<WorkerThread>>,
+}
+
+impl Drop for Respawn {
+    fn drop(&mut self) {
+        WORKER_THREAD.replace(None);
+        let worker = self.worker.take_unwrap();
+        if task::failing() && !worker.registry.terminating() {
+            spawn_worker(worker);
+        }
+    }
+}
+
+impl WorkerThread {
+    // Returns the pool task which is currently running, if any.
+    fn current() -> Option<*WorkerThread> {
+        WORKER_THREAD.get().map(|worker| *worker)
+    }
+
+    fn belongs_to(&self, registry: &Registry) -> bool {
+        &*self.registry as *Registry == registry as *Registry
+    }
+
+    fn run(&self) {
+        WORKER_THREAD.replace(Some(self as *WorkerThread));
+        loop {
+            let epoch = self.registry.epoch.load(atomics::SeqCst);
+            match self.find_work() {
+                Some(job) => unsafe { job.execute() },
+                None if self.registry.terminating() => break,
+                None => self.registry.sleep(epoch),
+            }
+        }
+    }
+
+    fn find_work(&self) -> Option<JobRef> {
+        match self.deque.pop() {
+            Some(job) => return Some(job),
+            None => {}
+        }
+        let n = self.stealers.len();
+        for i in range(1, n) {
+            match steal(self.stealers.get((self.index + i) % n)) {
+                Some(job) => return Some(job),
+                None => {}
+            }
+        }
+        steal(&self.injector)
+    }
+
+    // Runs other jobs until `latch` is set, only blocking when there are none.
+    fn wait_until(&self, latch: &Latch) {
+        let _g = KeepWaiting { worker: self, latch: latch };
+        while !latch.probe() {
+            match self.find_work() {
+                Some(job) => unsafe { job.execute() },
+                None => latch.wait(),
+            }
+        }
+    }
+
+    fn join<A: Send, B: Send>(&self, a: ||:Share -> A,
+                              b: ||:Share -> B) -> (A, B) {
+        let job_b = StackJob::new(b);
+        let job_ref = unsafe { job_b.as_job_ref() };
+        self.deque.push(job_ref);
+        self.registry.notify();
+
+        let ra = {
+            let _g = JoinGuard { worker: self, job: &job_b, job_ref: job_ref };
+            a()
+        };
+
+        // Everything `a` pushed has been popped again by now, so `b` is on the
+        // top of the deque unless it was stolen.
+        match self.deque.pop() {
+            Some(job) if job == job_ref => return (ra, job_b.run_inline()),
+            Some(job) => unsafe { job.execute() },
+            None => {}
+        }
+        self.wait_until(&job_b.done);
+        (ra, job_b.into_result())
+    }
+
+    fn scope<'a, R>(&self, f: |&Scope<'a>|:Share -> R) -> R {
+        let scope = Scope {
+            registry: self.registry.clone(),
+            pending: atomics::AtomicUint::new(1),
+            failed: atomics::AtomicBool::new(false),
+            done: Latch::new(),
+            marker: marker::InvariantLifetime,
+        };
+        let ret = {
+            let _g = ScopeGuard { worker: self, scope: &scope };
+            f(&scope)
+        };
+        if scope.failed.load(atomics::SeqCst) {
+            fail!("a job spawned in the scope failed");
+        }
+        ret
+    }
+}
+
+// A job which fails while a task is waiting on a latch unwinds the stack of
+// the waiting task, which may not happen until the latch is set: it may be
+// guarding jobs which borrow from that stack. Any jobs still in the deque are
+// run as well, so a second failure aborts like in any other destructor.
+struct KeepWaiting<'a> {
+    worker: &'a WorkerThread,
+    latch: &'a Latch,
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for KeepWaiting<'a> {
+    fn drop(&mut self) {
+        if task::failing() {
+            self.worker.wait_until(self.latch);
+        }
+    }
+}
+
+// If the first closure of a `join` fails, then the second one can't be allowed
+// to outlive the stack frame it's borrowing from.
+struct JoinGuard<'a, 'b, R> {
+    worker: &'a WorkerThread,
+    job: &'a StackJob<'b, R>,
+    job_ref: JobRef,
+}
+
+#[unsafe_destructor]
+impl<'a, 'b, R> Drop for JoinGuard<'a, 'b, R> {
+    fn drop(&mut self) {
+        if !task::failing() { return }
+        match self.worker.deque.pop() {
+            Some(job) if job == self.job_ref => return,
+            Some(job) => self.worker.deque.push(job),
+            None => {}
+        }
+        self.worker.wait_until(&self.job.done);
+    }
+}
+
+// Waits for all of the jobs in a scope once its closure is done, even if it
+// failed.
+struct ScopeGuard<'a, 'b> {
+    worker: &'a WorkerThread,
+    scope: &'a Scope<'b>,
+}
+
+#[unsafe_destructor]
+impl<'a, 'b> Drop for ScopeGuard<'a, 'b> {
+    fn drop(&mut self) {
+        if task::failing() {
+            self.scope.failed.store(true, atomics::SeqCst);
+        }
+        self.scope.job_done();
+        self.worker.wait_until(&self.scope.done);
+    }
+}
+
+fn steal(stealer: &Stealer<JobRef>) -> Option<JobRef> {
+    loop {
+        match stealer.steal() {
+            Data(job) => return Some(job),
+            Empty => return None,
+            Abort => {}
+        }
+    }
+}
+
+impl JobRef {
+    unsafe fn execute(self) { (self.run)(self.data) }
+}
+
+impl Eq for JobRef {
+    fn eq(&self, other: &JobRef) -> bool { self.data == other.data }
+}
+
+impl<'a, R> StackJob<'a, R> {
+    fn new(func: ||:'a + Share -> R) -> StackJob<'a, R> {
+        StackJob {
+            func: Unsafe::new(Some(func)),
+            result: Unsafe::new(None),
+            done: Latch::new(),
+        }
+    }
+
+    // The job must not move or go away until it has been run.
+    unsafe fn as_job_ref(&self) -> JobRef {
+        JobRef {
+            data: self as *StackJob<'a, R> as *(),
+            run: run_stack_job::<R>,
+        }
+    }
+
+    fn run_inline(self) -> R {
+        let func = unsafe { (*self.func.get()).take_unwrap() };
+        func()
+    }
+
+    fn into_result(self) -> R {
+        match unsafe { (*self.result.get()).take() } {
+            Some(r) => r,
+            None => fail!("a job run by the pool failed"),
+        }
+    }
+}
+
+unsafe fn run_stack_job<R>(data: *()) {
+    let job = &*(data as *StackJob<R>);
+    let _done = SetOnDrop { latch: &job.done };
+    let func = (*job.func.get()).take_unwrap();
+    *job.result.get() = Some(func());
+}
+
+unsafe fn run_heap_job(data: *()) {
+    let job: Box<HeapJob> = mem::transmute(data);
+    let HeapJob { func, scope } = *job;
+    let _done = JobDone { scope: &*scope };
+    func(&*scope);
+}
+
+// Sets a latch once a job is done running, which is the last time the job is
+// touched: the waiting task may free it right away.
+struct SetOnDrop<'a> {
+    latch: &'a Latch,
+}
+
+#[unsafe_destructor]
+impl<'a> Drop for SetOnDrop<'a> {
+    fn drop(&mut self) { self.latch.set() }
+}
+
+struct JobDone<'a, 'b> {
+    scope: &'a Scope<'b>,
+}
+
+#[unsafe_destructor]
+impl<'a, 'b> Drop for JobDone<'a, 'b> {
+    fn drop(&mut self) {
+        if task::failing() {
+            self.scope.failed.store(true, atomics::SeqCst);
+        }
+        self.scope.job_done();
+    }
+}
+
+impl Latch {
+    fn new() -> Latch {
+        Latch { state: atomics::AtomicUint::new(UNSET) }
+    }
+
+    fn probe(&self) -> bool {
+        self.state.load(atomics::SeqCst) == SET
+    }
+
+    fn set(&self) {
+        match self.state.swap(SET, atomics::SeqCst) {
+            UNSET | SET => {}
+            n => waitqueue::wake(unsafe { BlockedTask::cast_from_uint(n) }),
+        }
+    }
+
+    fn wait(&self) {
+        if self.probe() { return }
+        let task: Box<Task> = Local::take();
+        task.deschedule(1, |task| {
+            let n = unsafe { task.cast_to_uint() };
+            match self.state.compare_and_swap(UNSET, n, atomics::SeqCst) {
+                UNSET => Ok(()),
+                _ => Err(unsafe { BlockedTask::cast_from_uint(n) }),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::task;
+
+    use alloc::arc::Arc;
+
+    use super::{Pool, join};
+
+    fn fib(n: uint) -> uint {
+        if n < 2 { return n }
+        let (a, b) = join(|| fib(n - 1), || fib(n - 2));
+        a + b
+    }
+
+    #[test]
+    fn join_smoke() {
+        let pool = Pool::new(2);
+        assert_eq!(pool.join(|| 1, || 2), (1, 2));
+    }
+
+    #[test]
+    fn join_recursive() {
+        let pool = Pool::new(4);
+        assert_eq!(pool.join(|| fib(15), || fib(16)), (610, 987));
+    }
+
+    #[test]
+    fn join_outside_pool() {
+        assert_eq!(join(|| 1, || 2), (1, 2));
+        assert_eq!(fib(10), 55);
+    }
+
+    #[test]
+    fn join_borrows() {
+        let pool = Pool::new(2);
+        let v = Vec::from_fn(100, |i| i);
+        let (a, b) = pool.join(|| v.slice_to(50).iter().fold(0, |a, &b| a + b),
+                               || v.slice_from(50).iter().fold(0, |a, &b| a + b));
+        assert_eq!(a + b, 4950);
+    }
+
+    #[test]
+    fn scope_borrows() {
+        let pool = Pool::new(4);
+        let mut v = Vec::from_elem(100, 0u);
+        pool.scope(|s| {
+            for (i, chunk) in v.as_mut_slice().mut_chunks(10).enumerate() {
+                s.spawn(proc(_) {
+                    for x in chunk.mut_iter() { *x = i; }
+                });
+            }
+        });
+        for (i, x) in v.iter().enumerate() {
+            assert_eq!(*x, i / 10);
+        }
+    }
+
+    #[test]
+    fn scope_nested_spawns() {
+        let pool = Pool::new(3);
+        let mut v = Vec::from_elem(8, 0u);
+        pool.scope(|s| {
+            for (i, chunk) in v.as_mut_slice().mut_chunks(2).enumerate() {
+                s.spawn(proc(s) {
+                    let (l, r) = chunk.mut_split_at(1);
+                    s.spawn(proc(_) { l[0] = i; });
+                    s.spawn(proc(_) { r[0] = i; });
+                });
+            }
+        });
+        assert_eq!(v, vec!(0, 0, 1, 1, 2, 2, 3, 3));
+    }
+
+    #[test]
+    fn scope_returns_value() {
+        let pool = Pool::new(1);
+        assert_eq!(pool.scope(|_| 3), 3);
+        assert_eq!(pool.threads(), 1);
+    }
+
+    #[test]
+    fn scope_from_many_tasks() {
+        let pool = Arc::new(Pool::new(2));
+        let (tx, rx) = channel();
+        for i in range(0u, 4) {
+            let pool = pool.clone();
+            let tx = tx.clone();
+            spawn(proc() {
+                let (a, b) = pool.join(|| fib(i + 10), || fib(i + 11));
+                tx.send(a + b == fib(i + 12));
+            });
+        }
+        for _ in range(0, 4) { assert!(rx.recv()); }
+    }
+
+    #[test]
+    fn failure_propagates() {
+        let pool = Arc::new(Pool::new(2));
+        let pool2 = pool.clone();
+        let res = task::try(proc() {
+            pool2.scope(|s| {
+                s.spawn(proc(_) { fail!() });
+            });
+        });
+        assert!(res.is_err());
+
+        let pool2 = pool.clone();
+        let res = task::try(proc() {
+            pool2.join(|| 1, || -> int { fail!() });
+        });
+        assert!(res.is_err());
+
+        // The tasks which failed have been replaced.
+        assert_eq!(pool.join(|| fib(12), || fib(13)), (144, 233));
+    }
+}
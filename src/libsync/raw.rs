@@ -15,8 +15,11 @@
 //! `sync` crate which wrap values directly and provide safer abstractions for
 //! containing data.
 
+use std::comm::{Empty, Disconnected, Selectable};
 use std::kinds::marker;
 use std::mem;
+use std::rt::local::Local;
+use std::rt::task::{Task, OnMutex};
 use std::sync::atomics;
 use std::unstable::finally::Finally;
 
@@ -79,6 +82,23 @@ impl WaitQueue {
     }
 }
 
+// Blocks until `wait_end` is signalled. The signal happens to be delivered over
+// a channel, but the task is waiting for a lock or a condition, so that's what
+// it is described as being blocked on.
+fn wait(wait_end: WaitEnd) {
+    let mut selected = false;
+    let task: Box<Task> = Local::take();
+    task.deschedule_on(OnMutex, 1, |task| {
+        let ret = wait_end.start_selection(task);
+        selected = ret.is_ok();
+        ret
+    });
+    if selected {
+        wait_end.abort_selection();
+    }
+    let _ = wait_end.recv_opt();
+}
+
 // The building-block used to make semaphores, mutexes, and rwlocks.
 struct Sem<Q> {
     lock: mutex::Mutex,
@@ -141,11 +161,66 @@ impl<Q: Send> Sem<Q> {
             /* for _ in range(0, 1000) { task::deschedule(); } */
             // Need to wait outside the exclusive.
             if waiter_nobe.is_some() {
-                let _ = waiter_nobe.unwrap().recv();
+                wait(waiter_nobe.unwrap());
             }
         }
     }
 
+    pub fn try_acquire(&self) -> bool {
+        let mut acquired = false;
+        unsafe {
+            self.with(|state| {
+                if state.count > 0 {
+                    state.count -= 1;
+                    acquired = true;
+                }
+            })
+        }
+        acquired
+    }
+
+    pub fn acquire_timeout(&self, ms: u64) -> bool {
+        unsafe {
+            let mut waiter_nobe = None;
+            self.with(|state| {
+                state.count -= 1;
+                if state.count < 0 {
+                    waiter_nobe = Some(state.waiters.wait_end());
+                }
+            });
+            match waiter_nobe {
+                None => true,
+                Some(wait_end) => {
+                    self.wait_timeout(wait_end, ms, |state| state.count += 1)
+                }
+            }
+        }
+    }
+
+    // Waits at most `ms` milliseconds for a signal on `wait_end`, returning
+    // whether one was received. On timeout `cancel` is run with the lock held,
+    // unless a signal slipped in after the timer went off.
+    //
+    // Signals are sent with the lock held as well, and the wait end is dropped
+    // before it's released, so a signal can't be lost on a task which has
+    // given up: `signal()` skips over it as it does for killed tasks.
+    unsafe fn wait_timeout(&self, wait_end: WaitEnd, ms: u64,
+                           cancel: |&mut SemInner<Q>|) -> bool {
+        match wait_end.recv_timeout(ms) {
+            Ok(()) | Err(Disconnected) => return true,
+            Err(Empty) => {}
+        }
+        let mut wait_end = Some(wait_end);
+        let mut signalled = false;
+        self.with(|state| {
+            signalled = wait_end.take_unwrap().try_recv().is_ok();
+            if !signalled {
+                cancel(state);
+            }
+        });
+        signalled
+    }
+
     pub fn release(&self) {
         unsafe {
             self.with(|state| {
@@ -240,6 +315,26 @@ impl<'a> Condvar<'a> {
     ///
     /// wait() is equivalent to wait_on(0).
     pub fn wait_on(&self, condvar_id: uint) {
+        self.wait_inner(condvar_id, None, "cond.wait_on()");
+    }
+
+    /// As wait(), but gives up once `ms` milliseconds have passed without a
+    /// signal. The lock is reacquired either way, and the return value is
+    /// whether a signal was received.
+    ///
+    /// A `broadcast` which races with the timeout may count this task as
+    /// woken up even though it reports that it timed out.
+    pub fn wait_timeout(&self, ms: u64) -> bool {
+        self.wait_timeout_on(0, ms)
+    }
+
+    /// As wait_timeout(), but with a specified condvar_id. See wait_on.
+    pub fn wait_timeout_on(&self, condvar_id: uint, ms: u64) -> bool {
+        self.wait_inner(condvar_id, Some(ms), "cond.wait_timeout_on()")
+    }
+
+    fn wait_inner(&self, condvar_id: uint, timeout: Option<u64>,
+                  act: &str) -> bool {
         let mut wait_end = None;
         let mut out_of_bounds = None;
         // Release lock, 'atomically' enqueuing ourselves in so doing.
@@ -262,12 +357,18 @@ impl<'a> Condvar<'a> {
 
         // If deschedule checks start getting inserted anywhere, we can be
         // killed before or after enqueueing.
-        check_cvar_bounds(out_of_bounds, condvar_id, "cond.wait_on()", || {
+        check_cvar_bounds(out_of_bounds, condvar_id, act, || {
             // Unconditionally "block". (Might not actually block if a
             // signaller already sent -- I mean 'unconditionally' in contrast
             // with acquire().)
             (|| {
-                let _ = wait_end.take_unwrap().recv();
+                let wait_end = wait_end.take_unwrap();
+                match timeout {
+                    None => { wait(wait_end); true }
+                    Some(ms) => unsafe {
+                        self.sem.wait_timeout(wait_end, ms, |_| {})
+                    },
+                }
             }).finally(|| {
                 // Reacquire the condvar.
                 match self.order {
@@ -382,6 +483,16 @@ impl Semaphore {
     /// until resource(s) become available.
     pub fn acquire(&self) { self.sem.acquire() }
 
+    /// Acquire a resource represented by the semaphore if one is available
+    /// right away. Returns whether the resource was acquired.
+    pub fn try_acquire(&self) -> bool { self.sem.try_acquire() }
+
+    /// As acquire(), but gives up once `ms` milliseconds have passed. Returns
+    /// whether the resource was acquired.
+    pub fn acquire_timeout(&self, ms: u64) -> bool {
+        self.sem.acquire_timeout(ms)
+    }
+
     /// Release a held resource represented by the semaphore. Wakes a blocked
     /// contending task, if any exist. Won't block the caller.
     pub fn release(&self) { self.sem.release() }
@@ -510,6 +621,26 @@ impl RWLock {
         RWLockReadGuard { lock: self }
     }
 
+    /// Attempts to acquire a read-lock without blocking, returning `None` if
+    /// a writer holds or is waiting for the lock.
+    pub fn try_read<'a>(&'a self) -> Option<RWLockReadGuard<'a>> {
+        if !self.order_lock.try_acquire() { return None }
+        let old_count = self.read_count.fetch_add(1, atomics::Acquire);
+        let acquired = if old_count != 0 || self.access_lock.try_acquire() {
+            true
+        } else if self.read_count.compare_and_swap(1, 0,
+                                                   atomics::Release) != 1 {
+            // A downgrading writer saw us in the reader count, and is handing
+            // off the access lock to us. It's ours as soon as it's released.
+            self.access_lock.acquire();
+            true
+        } else {
+            false
+        };
+        self.order_lock.release();
+        if acquired { Some(RWLockReadGuard { lock: self }) } else { None }
+    }
+
     /// Acquire a write-lock, returning an RAII guard that will unlock the lock
     /// when dropped. No calls to 'read' or 'write' from other tasks will run
     /// concurrently with this one.
@@ -560,6 +691,19 @@ impl RWLock {
         // which can't happen until T2 finishes the downgrade-read entirely.
         // The astute reader will also note that making waking writers use the
         // order_lock is better for not starving readers.
+        self.write_guard()
+    }
+
+    /// Attempts to acquire a write-lock without blocking, returning `None` if
+    /// the lock is held by anyone else.
+    pub fn try_write<'a>(&'a self) -> Option<RWLockWriteGuard<'a>> {
+        if !self.order_lock.try_acquire() { return None }
+        let acquired = self.access_lock.try_acquire();
+        self.order_lock.release();
+        if acquired { Some(self.write_guard()) } else { None }
+    }
+
+    fn write_guard<'a>(&'a self) -> RWLockWriteGuard<'a> {
         RWLockWriteGuard {
             lock: self,
             cond: Condvar {
@@ -717,6 +861,34 @@ mod tests {
         }
         rx.recv(); // wait for child to be done
     }
+    #[test]
+    fn test_sem_try_acquire() {
+        let s = Semaphore::new(1);
+        assert!(s.try_acquire());
+        assert!(!s.try_acquire());
+        s.release();
+        assert!(s.try_acquire());
+    }
+    #[test]
+    fn test_sem_acquire_timeout() {
+        let s = Arc::new(Semaphore::new(1));
+        assert!(s.acquire_timeout(10));
+        assert!(!s.acquire_timeout(10));
+        // The task which gave up no longer counts as waiting.
+        s.release();
+        assert!(s.try_acquire());
+        s.release();
+
+        let s2 = s.clone();
+        let (tx, rx) = channel();
+        s.acquire();
+        task::spawn(proc() {
+            tx.send(s2.acquire_timeout(1000 * 60));
+        });
+        for _ in range(0, 5) { task::deschedule(); }
+        s.release();
+        assert!(rx.recv());
+    }
     /************************************************************************
      * Mutex tests
      ************************************************************************/
@@ -829,6 +1001,24 @@ mod tests {
         assert!(!lock.cond.signal());
     }
     #[test]
+    fn test_mutex_cond_wait_timeout() {
+        let m = Arc::new(Mutex::new());
+        {
+            let lock = m.lock();
+            assert!(!lock.cond.wait_timeout(10));
+            // The waiter which timed out isn't there to be signalled.
+            assert!(!lock.cond.signal());
+        }
+
+        let m2 = m.clone();
+        let lock = m.lock();
+        task::spawn(proc() {
+            let lock = m2.lock();
+            lock.cond.signal();
+        });
+        assert!(lock.cond.wait_timeout(1000 * 60));
+    }
+    #[test]
     fn test_mutex_killed_simple() {
         use std::any::Any;
 
@@ -998,6 +1188,24 @@ mod tests {
         let _g2 = x.read();
     }
     #[test]
+    fn test_rwlock_try_read_write() {
+        let x = RWLock::new();
+        {
+            let _r = x.read();
+            assert!(x.try_read().is_some());
+            assert!(x.try_write().is_none());
+        }
+        {
+            let _w = x.try_write().unwrap();
+            assert!(x.try_read().is_none());
+            assert!(x.try_write().is_none());
+        }
+        let w = x.write();
+        let _r = w.downgrade();
+        assert!(x.try_read().is_some());
+        assert!(x.try_write().is_none());
+    }
+    #[test]
     fn test_rwlock_cond_wait() {
         // As test_mutex_cond_wait above.
         let x = Arc::new(RWLock::new());
@@ -0,0 +1,229 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A queue of blocked tasks, shared by the channels in this crate
+//!
+//! The nodes of the queue belong to the blocked tasks, living either on their
+//! stacks or inside of the receivers which are being selected over, so this
+//! structure is not safe at all. It must only be modified while holding the
+//! lock which protects it. The length may be read without the lock in order
+//! to find out whether the lock needs to be taken at all.
+
+use std::rt::local::Local;
+use std::rt::task::{BlockedTask, BlockedOn, Task};
+use std::sync::atomics;
+use std::unstable::mutex::NativeMutex;
+
+pub struct Node {
+    task: Option<BlockedTask>,
+    next: *mut Node,
+}
+
+pub struct WaitQueue {
+    head: *mut Node,
+    tail: *mut Node,
+    len: atomics::AtomicUint,
+}
+
+impl Node {
+    pub fn new() -> Node {
+        Node { task: None, next: 0 as *mut Node }
+    }
+}
+
+impl WaitQueue {
+    pub fn new() -> WaitQueue {
+        WaitQueue {
+            head: 0 as *mut Node,
+            tail: 0 as *mut Node,
+            len: atomics::AtomicUint::new(0),
+        }
+    }
+
+    /// The number of blocked tasks, which is always up to date once the lock
+    /// has been acquired.
+    pub fn len(&self) -> uint { self.len.load(atomics::SeqCst) }
+
+    pub fn push(&mut self, node: &mut Node, task: BlockedTask) {
+        node.task = Some(task);
+        let node = node as *mut Node;
+        if self.tail.is_null() {
+            self.head = node;
+        } else {
+            unsafe { (*self.tail).next = node; }
+        }
+        self.tail = node;
+        self.len.fetch_add(1, atomics::SeqCst);
+    }
+
+    pub fn pop(&mut self) -> Option<BlockedTask> {
+        if self.head.is_null() { return None }
+        let node = self.head;
+        unsafe {
+            self.head = (*node).next;
+            if self.head.is_null() {
+                self.tail = 0 as *mut Node;
+            }
+            self.len.fetch_sub(1, atomics::SeqCst);
+            (*node).next = 0 as *mut Node;
+            (*node).task.take()
+        }
+    }
+
+    /// Unlinks a node from the queue, returning its task if it was still
+    /// queued. If it wasn't, then the task has been woken up by someone else.
+    pub fn remove(&mut self, node: &mut Node) -> Option<BlockedTask> {
+        let node = node as *mut Node;
+        let mut prev = 0 as *mut Node;
+        let mut cur = self.head;
+        while !cur.is_null() && cur != node {
+            prev = cur;
+            cur = unsafe { (*cur).next };
+        }
+        if cur.is_null() { return None }
+        unsafe {
+            if prev.is_null() {
+                self.head = (*node).next;
+            } else {
+                (*prev).next = (*node).next;
+            }
+            if self.tail == node {
+                self.tail = prev;
+            }
+            self.len.fetch_sub(1, atomics::SeqCst);
+            (*node).next = 0 as *mut Node;
+            (*node).task.take()
+        }
+    }
+
+    /// Takes all of the tasks out of the queue, to be woken up once the lock
+    /// has been released.
+    pub fn drain(&mut self) -> Vec<BlockedTask> {
+        let mut ret = Vec::new();
+        loop {
+            match self.pop() {
+                Some(task) => ret.push(task),
+                None => return ret,
+            }
+        }
+    }
+}
+
+/// Blocks the current task in `queue` (which is protected by `lock`) until it
+/// is woken up. Once the task is visible in the queue `abort` is called with
+/// the lock still held, and if it returns true then the task doesn't block
+/// after all. This is where the condition being waited for is checked one
+/// last time, as anyone who changes it afterwards will find this task queued.
+/// The task is recorded as blocked `on` while it waits.
+pub fn block(lock: &NativeMutex, queue: *mut WaitQueue, on: BlockedOn,
+             abort: || -> bool) {
+    let mut node = Node::new();
+    let task: Box<Task> = Local::take();
+    unsafe { lock.lock_noguard(); }
+    task.deschedule_on(on, 1, |task| {
+        let queue = unsafe { &mut *queue };
+        queue.push(&mut node, task);
+        let ret = if abort() {
+            Err(queue.remove(&mut node).unwrap())
+        } else {
+            Ok(())
+        };
+        unsafe { lock.unlock_noguard(); }
+        ret
+    });
+}
+
+/// Wakes up a task taken out of a queue, which should be done without holding
+/// the lock in case it results in a context switch.
+pub fn wake(task: BlockedTask) {
+    task.wake().map(|t| t.reawaken());
+}
+
+#[cfg(test)]
+mod test {
+    use std::rt::task::{BlockedTask, OnChannel, Task};
+    use std::unstable::mutex::NativeMutex;
+
+    use super::{block, Node, WaitQueue};
+
+    fn blocked(name: &'static str) -> BlockedTask {
+        let mut task = box Task::new();
+        task.name = Some(name.into_maybe_owned());
+        BlockedTask::block(task)
+    }
+
+    // Wakes up a task which never ran, returning its name.
+    fn name(task: BlockedTask) -> StrBuf {
+        let mut task = task.wake().unwrap();
+        task.destroyed = true;
+        task.name.get_ref().as_slice().to_strbuf()
+    }
+
+    #[test]
+    fn fifo() {
+        let mut queue = WaitQueue::new();
+        let (mut a, mut b) = (Node::new(), Node::new());
+        assert!(queue.pop().is_none());
+        queue.push(&mut a, blocked("a"));
+        queue.push(&mut b, blocked("b"));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(name(queue.pop().unwrap()), "a".to_strbuf());
+        assert_eq!(name(queue.pop().unwrap()), "b".to_strbuf());
+        assert_eq!(queue.len(), 0);
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn remove() {
+        let mut queue = WaitQueue::new();
+        let (mut a, mut b, mut c) = (Node::new(), Node::new(), Node::new());
+        queue.push(&mut a, blocked("a"));
+        queue.push(&mut b, blocked("b"));
+        queue.push(&mut c, blocked("c"));
+        assert_eq!(name(queue.remove(&mut b).unwrap()), "b".to_strbuf());
+        assert!(queue.remove(&mut b).is_none());
+        assert_eq!(name(queue.remove(&mut c).unwrap()), "c".to_strbuf());
+        assert_eq!(queue.len(), 1);
+
+        // The tail was unlinked, so pushing has to go after `a`
+        queue.push(&mut b, blocked("d"));
+        assert_eq!(name(queue.remove(&mut a).unwrap()), "a".to_strbuf());
+        assert_eq!(name(queue.pop().unwrap()), "d".to_strbuf());
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn drain() {
+        let mut queue = WaitQueue::new();
+        let (mut a, mut b) = (Node::new(), Node::new());
+        queue.push(&mut a, blocked("a"));
+        queue.push(&mut b, blocked("b"));
+        let names: Vec<StrBuf> = queue.drain().move_iter().map(name).collect();
+        assert_eq!(names, vec!("a".to_strbuf(), "b".to_strbuf()));
+        assert_eq!(queue.len(), 0);
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn block_aborted() {
+        let lock = unsafe { NativeMutex::new() };
+        let mut queue = WaitQueue::new();
+        let ptr = &mut queue as *mut WaitQueue;
+        let mut called = false;
+        block(&lock, ptr, OnChannel, || {
+            // The task is queued by the time it's asked whether to block
+            assert_eq!(unsafe { (*ptr).len() }, 1);
+            called = true;
+            true
+        });
+        assert!(called);
+        assert_eq!(queue.len(), 0);
+    }
+}
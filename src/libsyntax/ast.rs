@@ -526,7 +526,12 @@ pub enum Expr_ {
     ExprRepeat(@Expr /* element */, @Expr /* count */),
 
     // No-op: used solely so we can pretty-print faithfully
-    ExprParen(@Expr)
+    ExprParen(@Expr),
+
+    // Placeholder for an expression that failed to parse. Only produced by
+    // a parser that is recovering from syntax errors; such a crate is never
+    // handed on to type checking.
+    ExprError
 }
 
 // When the main rust parser encounters a syntax-extension invocation, it
@@ -118,6 +118,8 @@ impl SpanHandler {
 // others log errors for later reporting.
 pub struct Handler {
     err_count: Cell<uint>,
+    // errors set aside by `defer_errors`, see there
+    deferred_err_count: Cell<uint>,
     emit: RefCell<Box<Emitter:Send>>,
 }
 
@@ -141,16 +143,31 @@ impl Handler {
     }
     pub fn abort_if_errors(&self) {
         let s;
-        match self.err_count.get() {
-          0u => return,
+        if self.err_count.get() == 0u {
+            return;
+        }
+        match self.err_count.get() + self.deferred_err_count.get() {
           1u => s = "aborting due to previous error".to_owned(),
-          _  => {
-            s = format!("aborting due to {} previous errors",
-                     self.err_count.get());
+          n  => {
+            s = format!("aborting due to {} previous errors", n);
           }
         }
         self.fatal(s);
     }
+    /// Set aside the errors reported so far, so that `abort_if_errors`
+    /// only fires for errors reported after this point. This lets passes
+    /// that stop on their own errors run over input that already had
+    /// (recovered) errors, until `restore_deferred_errors` is called.
+    pub fn defer_errors(&self) {
+        self.deferred_err_count.set(self.deferred_err_count.get() +
+                                    self.err_count.get());
+        self.err_count.set(0u);
+    }
+    pub fn restore_deferred_errors(&self) {
+        self.err_count.set(self.err_count.get() +
+                           self.deferred_err_count.get());
+        self.deferred_err_count.set(0u);
+    }
     pub fn warn(&self, msg: &str) {
         self.emit.borrow_mut().emit(None, msg, Warning);
     }
@@ -190,6 +207,7 @@ pub fn default_handler(color_config: ColorConfig) -> Handler {
 pub fn mk_handler(e: Box<Emitter:Send>) -> Handler {
     Handler {
         err_count: Cell::new(0),
+        deferred_err_count: Cell::new(0),
         emit: RefCell::new(e),
     }
 }
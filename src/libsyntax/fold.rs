@@ -883,7 +883,8 @@ pub fn noop_fold_expr<T: Folder>(e: @Expr, folder: &mut T) -> @Expr {
                        fields.iter().map(|x| fold_field_(*x, folder)).collect(),
                        maybe_expr.map(|x| folder.fold_expr(x)))
         },
-        ExprParen(ex) => ExprParen(folder.fold_expr(ex))
+        ExprParen(ex) => ExprParen(folder.fold_expr(ex)),
+        ExprError => ExprError
     };
 
     @Expr {
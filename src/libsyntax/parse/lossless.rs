@@ -0,0 +1,474 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A lossless view of a source file, for tools that edit source code.
+//!
+//! The lexer only hands the parser significant tokens and the AST keeps
+//! nothing but spans, so neither can be used to reproduce a file. A
+//! `SourceFile` holds every token of a file together with the whitespace
+//! and comments in front of it (its "trivia"), nested by delimiters. The
+//! tokens and trivia cover the file without gaps, so it can be written back
+//! out byte for byte.
+//!
+//! AST nodes parsed from the same `FileMap` share its positions, so a tool
+//! can go from an AST node to the tokens it was parsed from with
+//! `SourceFile::tokens_in`, describe its changes as `Edit`s on spans, and
+//! get the new text of the file from `SourceFile::apply_edits`. Everything
+//! outside of the edits comes out exactly as it went in.
+//!
+//! ```ignore
+//! let sess = parse::new_parse_sess();
+//! let (krate, file) = parse_crate_lossless(&path, Vec::new(), &sess);
+//! let item = *krate.module.items.get(0);
+//! let ident = file.tokens_in(item.span).move_iter()
+//!                 .find(|t| token::is_ident(&t.tok)).unwrap();
+//! let new_src = file.apply_edits([Edit::replace(ident.sp, "renamed")]);
+//! ```
+
+use ast;
+use codemap::{BytePos, FileMap, Pos, Span, mk_sp};
+use parse::lexer::{Reader, TokenAndSpan, new_string_reader};
+use parse::token;
+use parse::{ParseSess, filemap_to_parser, string_to_filemap};
+
+use std::io::File;
+use std::rc::Rc;
+use std::str;
+use std::strbuf::StrBuf;
+
+/// The kinds of source text that the lexer skips over between tokens.
+#[deriving(Clone, Eq, Show)]
+pub enum TriviaKind {
+    Whitespace,
+    /// A `//` comment, not including the newline that ends it. Doc
+    /// comments are tokens, not trivia.
+    LineComment,
+    /// A `/* */` comment, which may contain nested block comments.
+    BlockComment,
+    /// A `#!` line at the very start of the file.
+    Shebang,
+}
+
+#[deriving(Clone, Eq, Show)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub sp: Span,
+}
+
+/// A token along with the trivia between it and the previous token.
+#[deriving(Clone, Eq, Show)]
+pub struct LosslessToken {
+    pub leading: Vec<Trivia>,
+    pub tok: token::Token,
+    pub sp: Span,
+}
+
+#[deriving(Clone, Eq, Show)]
+pub enum SyntaxNode {
+    Leaf(LosslessToken),
+    Group(Delimited),
+}
+
+/// A `(...)`, `[...]` or `{...}` group. `close` is `None` if the file ends
+/// before the group is closed.
+#[deriving(Clone, Eq, Show)]
+pub struct Delimited {
+    pub open: LosslessToken,
+    pub children: Vec<SyntaxNode>,
+    pub close: Option<LosslessToken>,
+}
+
+/// A change to a source file: the text in `sp` is replaced by
+/// `replacement`. Insertions use an empty span.
+#[deriving(Clone, Eq, Show)]
+pub struct Edit {
+    pub sp: Span,
+    pub replacement: StrBuf,
+}
+
+impl Edit {
+    pub fn replace(sp: Span, replacement: &str) -> Edit {
+        Edit { sp: sp, replacement: replacement.to_strbuf() }
+    }
+
+    pub fn insert(pos: BytePos, text: &str) -> Edit {
+        Edit { sp: mk_sp(pos, pos), replacement: text.to_strbuf() }
+    }
+
+    pub fn delete(sp: Span) -> Edit {
+        Edit { sp: sp, replacement: StrBuf::new() }
+    }
+}
+
+pub struct SourceFile {
+    pub filemap: Rc<FileMap>,
+    /// The top level tokens and groups of the file, in order.
+    pub nodes: Vec<SyntaxNode>,
+    /// Trivia after the last token of the file.
+    pub trailing: Vec<Trivia>,
+    // The codemap drops a byte order mark from the start of a file and
+    // appends a newline to files that don't end in one; both have to be
+    // undone to get the original text back.
+    bom: bool,
+    added_newline: bool,
+}
+
+impl SourceFile {
+    /// Every token of the file in source order, ignoring the nesting.
+    pub fn tokens<'a>(&'a self) -> Vec<&'a LosslessToken> {
+        let mut tokens = Vec::new();
+        flatten(self.nodes.as_slice(), &mut tokens);
+        tokens
+    }
+
+    /// The tokens lying entirely within `sp`, e.g. the span of an AST node
+    /// parsed from this file.
+    pub fn tokens_in<'a>(&'a self, sp: Span) -> Vec<&'a LosslessToken> {
+        self.tokens().move_iter().filter(|t| {
+            t.sp.lo >= sp.lo && t.sp.hi <= sp.hi
+        }).collect()
+    }
+
+    /// The token containing `pos`, if `pos` is not in trivia.
+    pub fn token_at<'a>(&'a self, pos: BytePos) -> Option<&'a LosslessToken> {
+        self.tokens().move_iter().find(|t| t.sp.lo <= pos && pos < t.sp.hi)
+    }
+
+    /// Does `sp` refer to text in this file? AST nodes from other files of
+    /// the same crate (e.g. out-of-line modules) don't.
+    pub fn contains(&self, sp: Span) -> bool {
+        sp.lo >= self.filemap.start_pos && sp.hi <= self.end_pos()
+    }
+
+    /// The source text covered by `sp`.
+    pub fn text<'a>(&'a self, sp: Span) -> &'a str {
+        let base = self.filemap.start_pos.to_uint();
+        self.filemap.src.as_slice().slice(sp.lo.to_uint() - base,
+                                          sp.hi.to_uint() - base)
+    }
+
+    /// The complete text of the file, exactly as it was read.
+    pub fn to_source(&self) -> StrBuf {
+        self.apply_edits([]).unwrap()
+    }
+
+    /// The text of the file with `edits` applied. Edits may be given in any
+    /// order but must not overlap, and must lie within the file.
+    pub fn apply_edits(&self, edits: &[Edit]) -> Result<StrBuf, StrBuf> {
+        let base = self.filemap.start_pos.to_uint();
+        let src = self.filemap.src.as_slice();
+        let end = if self.added_newline { src.len() - 1 } else { src.len() };
+
+        let mut edits = Vec::from_slice(edits);
+        edits.sort_by(|a, b| a.sp.lo.cmp(&b.sp.lo));
+
+        let mut out = StrBuf::with_capacity(src.len());
+        if self.bom {
+            out.push_str("\ufeff");
+        }
+        let mut cursor = 0u;
+        for edit in edits.iter() {
+            if edit.sp.lo.to_uint() < base || edit.sp.hi < edit.sp.lo ||
+               edit.sp.hi.to_uint() - base > end {
+                return Err(format_strbuf!("edit {} does not lie within {}",
+                                          edit.sp, self.filemap.name));
+            }
+            let (lo, hi) = (edit.sp.lo.to_uint() - base,
+                            edit.sp.hi.to_uint() - base);
+            if lo < cursor {
+                return Err(format_strbuf!("edit {} overlaps the previous one",
+                                          edit.sp));
+            }
+            out.push_str(src.slice(cursor, lo));
+            out.push_str(edit.replacement.as_slice());
+            cursor = hi;
+        }
+        out.push_str(src.slice(cursor, end));
+        Ok(out)
+    }
+
+    fn end_pos(&self) -> BytePos {
+        Pos::from_uint(self.filemap.start_pos.to_uint() +
+                       self.filemap.src.len())
+    }
+}
+
+fn flatten<'a>(nodes: &'a [SyntaxNode], out: &mut Vec<&'a LosslessToken>) {
+    for node in nodes.iter() {
+        match *node {
+            Leaf(ref tok) => out.push(tok),
+            Group(ref group) => {
+                out.push(&group.open);
+                flatten(group.children.as_slice(), out);
+                match group.close {
+                    Some(ref tok) => out.push(tok),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+/// Parse the crate rooted at `path`, also returning a lossless view of
+/// that file which the spans in the crate refer to.
+pub fn parse_crate_lossless(path: &Path,
+                            cfg: ast::CrateConfig,
+                            sess: &ParseSess)
+                            -> (ast::Crate, SourceFile) {
+    let file = lex_file(path, sess);
+    let mut p = filemap_to_parser(sess, file.filemap.clone(), cfg);
+    let krate = p.parse_crate_mod();
+    p.abort_if_errors();
+    (krate, file)
+}
+
+/// Add the file at `path` to the session's codemap and lex it.
+pub fn lex_file(path: &Path, sess: &ParseSess) -> SourceFile {
+    let bytes = match File::open(path).read_to_end() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            sess.span_diagnostic.handler()
+                .fatal(format!("couldn't read {}: {}", path.display(), e))
+        }
+    };
+    match str::from_utf8(bytes.as_slice()) {
+        Some(s) => {
+            lex_source_str(format_strbuf!("{}", path.display()),
+                           s.to_strbuf(),
+                           sess)
+        }
+        None => {
+            sess.span_diagnostic.handler()
+                .fatal(format!("{} is not UTF-8 encoded", path.display()))
+        }
+    }
+}
+
+/// Add `source` to the session's codemap under `name` and lex it.
+pub fn lex_source_str(name: StrBuf, source: StrBuf, sess: &ParseSess)
+                      -> SourceFile {
+    let bom = source.as_slice().starts_with("\ufeff");
+    // The codemap strips the BOM before deciding whether to add a newline.
+    let added_newline = {
+        let text = source.as_slice();
+        let text = if bom { text.slice_from(3) } else { text };
+        text.len() > 0 && !text.ends_with("\n")
+    };
+    let filemap = string_to_filemap(sess, source, name);
+    lex_filemap(filemap, bom, added_newline, sess)
+}
+
+struct OpenGroup {
+    open: LosslessToken,
+    children: Vec<SyntaxNode>,
+}
+
+fn lex_filemap(filemap: Rc<FileMap>, bom: bool, added_newline: bool,
+               sess: &ParseSess) -> SourceFile {
+    let mut rdr = new_string_reader(&sess.span_diagnostic, filemap.clone());
+    let mut stack: Vec<OpenGroup> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut last = filemap.start_pos;
+
+    loop {
+        let TokenAndSpan { tok: tok, sp: sp } = rdr.next_token();
+        if tok == token::EOF {
+            break;
+        }
+        let tok = LosslessToken {
+            leading: split_trivia(&*filemap, last, sp.lo),
+            tok: tok,
+            sp: sp,
+        };
+        last = sp.hi;
+
+        let closes_group = match stack.last() {
+            Some(group) => {
+                token::close_delimiter_for(&group.open.tok) == Some(tok.tok.clone())
+            }
+            None => false,
+        };
+        if closes_group {
+            let OpenGroup { open: open, children: children } = stack.pop().unwrap();
+            push_node(&mut stack, &mut nodes, Group(Delimited {
+                open: open,
+                children: children,
+                close: Some(tok),
+            }));
+        } else if token::close_delimiter_for(&tok.tok).is_some() {
+            stack.push(OpenGroup { open: tok, children: Vec::new() });
+        } else {
+            push_node(&mut stack, &mut nodes, Leaf(tok));
+        }
+    }
+
+    // Whatever is still open at the end of the file stays unclosed.
+    loop {
+        match stack.pop() {
+            Some(OpenGroup { open: open, children: children }) => {
+                push_node(&mut stack, &mut nodes, Group(Delimited {
+                    open: open,
+                    children: children,
+                    close: None,
+                }));
+            }
+            None => break,
+        }
+    }
+
+    let end = Pos::from_uint(filemap.start_pos.to_uint() + filemap.src.len());
+    let trailing = split_trivia(&*filemap, last, end);
+    SourceFile {
+        filemap: filemap,
+        nodes: nodes,
+        trailing: trailing,
+        bom: bom,
+        added_newline: added_newline,
+    }
+}
+
+fn push_node(stack: &mut Vec<OpenGroup>, nodes: &mut Vec<SyntaxNode>,
+             node: SyntaxNode) {
+    match stack.mut_last() {
+        Some(group) => group.children.push(node),
+        None => nodes.push(node),
+    }
+}
+
+// Classify the text between two tokens.
+fn split_trivia(fm: &FileMap, lo: BytePos, hi: BytePos) -> Vec<Trivia> {
+    let base = fm.start_pos.to_uint();
+    let src = fm.src.as_slice().slice(lo.to_uint() - base, hi.to_uint() - base);
+    let mut trivia = Vec::new();
+    let mut i = 0u;
+    while i < src.len() {
+        let rest = src.slice_from(i);
+        let (kind, len) = if rest.starts_with("//") {
+            (LineComment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (BlockComment, block_comment_len(rest))
+        } else if rest.starts_with("#!") {
+            (Shebang, rest.find('\n').unwrap_or(rest.len()))
+        } else {
+            (Whitespace, rest.find(|c: char| !c.is_whitespace())
+                             .unwrap_or(rest.len()))
+        };
+        // The lexer skips nothing else, but never loop on anything odd.
+        let len = if len == 0 { rest.char_range_at(0).next } else { len };
+        let start = lo.to_uint() + i;
+        trivia.push(Trivia {
+            kind: kind,
+            sp: mk_sp(Pos::from_uint(start), Pos::from_uint(start + len)),
+        });
+        i += len;
+    }
+    trivia
+}
+
+// The length of the (possibly nested) block comment `s` starts with.
+fn block_comment_len(s: &str) -> uint {
+    let bytes = s.as_bytes();
+    let mut depth = 0u;
+    let mut i = 0u;
+    while i + 1 < bytes.len() {
+        if bytes[i] == '/' as u8 && bytes[i + 1] == '*' as u8 {
+            depth += 1;
+            i += 2;
+        } else if bytes[i] == '*' as u8 && bytes[i + 1] == '/' as u8 {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use codemap::Pos;
+    use parse::new_parse_sess;
+    use parse::filemap_to_parser;
+    use parse::token;
+
+    fn lex(src: &str) -> SourceFile {
+        lex_source_str("bogofile".to_strbuf(), src.to_strbuf(),
+                       &new_parse_sess())
+    }
+
+    #[test] fn round_trip() {
+        let src = "#!/usr/bin/env rustx\n\
+                   // line comment\n\
+                   /* block /* nested */ */ fn main() {\n\
+                   \t/// doc\n    let x = (1, [2]);   \n}";
+        assert_eq!(lex(src).to_source().as_slice(), src);
+        let bom = "\ufeff fn f() {} /* trailing */\n\n";
+        assert_eq!(lex(bom).to_source().as_slice(), bom);
+        assert_eq!(lex("").to_source().as_slice(), "");
+        assert_eq!(lex("\ufeff").to_source().as_slice(), "\ufeff");
+        assert_eq!(lex("\ufefffn").to_source().as_slice(), "\ufefffn");
+    }
+
+    #[test] fn trivia_kinds() {
+        let file = lex("  // a\n/* b */fn");
+        let toks = file.tokens();
+        assert_eq!(toks.len(), 1);
+        let kinds: Vec<TriviaKind> = toks.get(0).leading.iter()
+                                          .map(|t| t.kind).collect();
+        assert_eq!(kinds, vec!(Whitespace, LineComment, Whitespace,
+                               BlockComment));
+    }
+
+    #[test] fn groups() {
+        let file = lex("f(a, [b]) { c");
+        assert_eq!(file.nodes.len(), 3);
+        match *file.nodes.get(1) {
+            Group(ref g) => {
+                assert_eq!(g.open.tok, token::LPAREN);
+                assert_eq!(g.children.len(), 3);
+                assert!(g.close.is_some());
+            }
+            _ => fail!("expected a group"),
+        }
+        match *file.nodes.get(2) {
+            Group(ref g) => assert!(g.close.is_none()),
+            _ => fail!("expected a group"),
+        }
+    }
+
+    #[test] fn edit_through_ast_spans() {
+        let sess = new_parse_sess();
+        let file = lex_source_str("bogofile".to_strbuf(),
+                                  "use a;\n\n// keep me\nfn foo() {}\n".to_strbuf(),
+                                  &sess);
+        let krate = filemap_to_parser(&sess, file.filemap.clone(), Vec::new())
+                        .parse_crate_mod();
+        let item = *krate.module.items.get(0);
+        assert!(file.contains(item.span));
+        let ident = *file.tokens_in(item.span).iter()
+                         .find(|t| token::is_ident(&t.tok)).unwrap();
+        assert_eq!(file.text(ident.sp), "foo");
+
+        let start = file.filemap.start_pos;
+        let new_src = file.apply_edits([Edit::replace(ident.sp, "bar"),
+                                        Edit::insert(start, "use b;\n")]);
+        assert_eq!(new_src.unwrap().as_slice(),
+                   "use b;\nuse a;\n\n// keep me\nfn bar() {}\n");
+
+        assert!(file.apply_edits([Edit::delete(item.span),
+                                  Edit::delete(ident.sp)]).is_err());
+        let past_end = Pos::from_uint(start.to_uint() + 100);
+        assert!(file.apply_edits([Edit::insert(past_end, "x")]).is_err());
+    }
+}
@@ -17,7 +17,7 @@ use diagnostic::{SpanHandler, mk_span_handler, default_handler, Auto};
 use parse::attr::ParserAttr;
 use parse::parser::Parser;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io::File;
 use std::rc::Rc;
 use std::str;
@@ -26,6 +26,7 @@ pub mod lexer;
 pub mod parser;
 pub mod token;
 pub mod comments;
+pub mod lossless;
 pub mod attr;
 
 pub mod common;
@@ -37,12 +38,16 @@ pub struct ParseSess {
     pub span_diagnostic: SpanHandler, // better be the same as the one in the reader!
     /// Used to determine and report recursive mod inclusions
     included_mod_stack: RefCell<Vec<Path>>,
+    /// If set, parsers report syntax errors and resynchronise instead of
+    /// aborting on the first one
+    pub error_recovery: Cell<bool>,
 }
 
 pub fn new_parse_sess() -> ParseSess {
     ParseSess {
         span_diagnostic: mk_span_handler(default_handler(Auto), CodeMap::new()),
         included_mod_stack: RefCell::new(Vec::new()),
+        error_recovery: Cell::new(false),
     }
 }
 
@@ -50,6 +55,7 @@ pub fn new_parse_sess_special_handler(sh: SpanHandler) -> ParseSess {
     ParseSess {
         span_diagnostic: sh,
         included_mod_stack: RefCell::new(Vec::new()),
+        error_recovery: Cell::new(false),
     }
 }
 
@@ -21,7 +21,7 @@ use ast::{Crate, CrateConfig, Decl, DeclItem};
 use ast::{DeclLocal, DefaultBlock, UnDeref, BiDiv, EMPTY_CTXT, EnumDef, ExplicitSelf};
 use ast::{Expr, Expr_, ExprAddrOf, ExprMatch, ExprAgain};
 use ast::{ExprAssign, ExprAssignOp, ExprBinary, ExprBlock, ExprBox};
-use ast::{ExprBreak, ExprCall, ExprCast};
+use ast::{ExprBreak, ExprCall, ExprCast, ExprError};
 use ast::{ExprField, ExprFnBlock, ExprIf, ExprIndex};
 use ast::{ExprLit, ExprLoop, ExprMac};
 use ast::{ExprMethodCall, ExprParen, ExprPath, ExprProc};
@@ -81,6 +81,10 @@ use std::mem::replace;
 use std::rc::Rc;
 use std::strbuf::StrBuf;
 
+/// How many syntax errors a recovering parser will swallow without
+/// consuming a single token before it concludes it is stuck and aborts.
+static MAX_ERRORS_WITHOUT_PROGRESS: uint = 16;
+
 #[allow(non_camel_case_types)]
 #[deriving(Eq)]
 pub enum restriction {
@@ -315,6 +319,8 @@ pub fn Parser<'a>(
         open_braces: Vec::new(),
         owns_directory: true,
         root_module_name: None,
+        last_error_pos: None,
+        errors_at_pos: 0,
     }
 }
 
@@ -351,6 +357,10 @@ pub struct Parser<'a> {
     /// name is not known. This does not change while the parser is descending
     /// into modules, and sub-parsers have new values for this name.
     pub root_module_name: Option<StrBuf>,
+    /// Position of the last syntax error reported while recovering, and how
+    /// many more errors have been reported at that same position since.
+    pub last_error_pos: Option<BytePos>,
+    pub errors_at_pos: uint,
 }
 
 fn is_plain_ident_or_underscore(t: &token::Token) -> bool {
@@ -387,9 +397,15 @@ impl<'a> Parser<'a> {
         } else {
             let token_str = Parser::token_to_str(t);
             let this_token_str = self.this_token_to_str();
-            self.fatal(format!("expected `{}` but found `{}`",
-                               token_str,
-                               this_token_str))
+            self.recoverable_err(format!("expected `{}` but found `{}`",
+                                         token_str,
+                                         this_token_str));
+            // If the expected token is only one stray token away, drop the
+            // stray one; otherwise carry on as though `t` had been there.
+            if self.look_ahead(1, |next| *next == *t) {
+                self.bump();
+                self.bump();
+            }
         }
     }
 
@@ -419,7 +435,7 @@ impl<'a> Parser<'a> {
             let expected = edible.iter().map(|x| (*x).clone()).collect::<Vec<_>>().append(inedible);
             let expect = tokens_to_str(expected.as_slice());
             let actual = self.this_token_to_str();
-            self.fatal(
+            self.recoverable_err(
                 if expected.len() != 1 {
                     format!("expected one of `{}` but found `{}`", expect, actual)
                 } else {
@@ -501,7 +517,9 @@ impl<'a> Parser<'a> {
             }
             _ => {
                 let token_str = self.this_token_to_str();
-                self.fatal(format!( "expected ident, found `{}`", token_str))
+                self.recoverable_err(format!("expected ident, found `{}`",
+                                             token_str));
+                special_idents::invalid
             }
         }
     }
@@ -901,6 +919,81 @@ impl<'a> Parser<'a> {
         self.sess.span_diagnostic.handler().abort_if_errors();
     }
 
+    // are syntax errors to be recovered from rather than aborting?
+    pub fn recovering(&self) -> bool {
+        self.sess.error_recovery.get()
+    }
+
+    // Report a syntax error at the current token. This is fatal unless the
+    // parser is recovering, in which case the caller is expected to patch
+    // things up and carry on. Errors at the same position as the previous
+    // one are usually fallout from it and are not reported; if the parser
+    // keeps erring without consuming any input, it gives up.
+    pub fn recoverable_err(&mut self, m: &str) {
+        if !self.recovering() {
+            self.fatal(m);
+        }
+        let pos = self.span.lo;
+        if self.last_error_pos == Some(pos) {
+            self.errors_at_pos += 1;
+            if self.errors_at_pos >= MAX_ERRORS_WITHOUT_PROGRESS {
+                self.abort_if_errors();
+            }
+            return;
+        }
+        self.last_error_pos = Some(pos);
+        self.errors_at_pos = 0;
+        let sp = self.span;
+        self.span_err(sp, m);
+    }
+
+    // true if the last error the parser recovered from was reported at the
+    // current token, i.e. nothing has been consumed since.
+    pub fn stuck_at_error(&self) -> bool {
+        self.last_error_pos == Some(self.span.lo)
+    }
+
+    // is the current token one that can start an item (or a `let`), and so
+    // a good place to resume parsing after an error?
+    fn token_is_sync_keyword(&mut self) -> bool {
+        [keywords::Fn, keywords::Struct, keywords::Enum, keywords::Impl,
+         keywords::Trait, keywords::Mod, keywords::Static, keywords::Type,
+         keywords::Use, keywords::Extern, keywords::Pub, keywords::Let]
+            .iter().any(|&kw| token::is_keyword(kw, &self.token))
+    }
+
+    // After a syntax error, skip ahead to the next point where parsing can
+    // sensibly resume: just past a `;`, or at a `}` closing the enclosing
+    // block, or at a keyword starting an item, whichever comes first.
+    // Delimited groups are skipped as a whole.
+    pub fn recover_to_sync_point(&mut self) {
+        let mut depth = 0u;
+        loop {
+            match self.token {
+                token::EOF => return,
+                token::LBRACE | token::LPAREN | token::LBRACKET => {
+                    depth += 1;
+                }
+                token::RBRACE if depth == 0 => return,
+                token::RBRACE | token::RPAREN | token::RBRACKET => {
+                    if depth > 0 {
+                        depth -= 1;
+                    }
+                }
+                token::SEMI if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                _ => {
+                    if depth == 0 && self.token_is_sync_keyword() {
+                        return;
+                    }
+                }
+            }
+            self.bump();
+        }
+    }
+
     pub fn id_to_interned_str(&mut self, id: Ident) -> InternedString {
         token::get_ident(id)
     }
@@ -1968,6 +2061,20 @@ impl<'a> Parser<'a> {
 
             hi = pth.span.hi;
             ex = ExprPath(pth);
+        } else if self.recovering() && !token::is_lit(&self.token) &&
+                  !self.is_keyword(keywords::True) &&
+                  !self.is_keyword(keywords::False) {
+            let token_str = self.this_token_to_str();
+            self.recoverable_err(format!("expected expression, found `{}`",
+                                         token_str));
+            // Leave closing delimiters and separators to whoever is
+            // waiting for them.
+            match self.token {
+                token::RPAREN | token::RBRACKET | token::RBRACE |
+                token::SEMI | token::COMMA | token::EOF => {}
+                _ => self.bump()
+            }
+            return self.mk_expr(lo, hi, ExprError);
         } else {
             // other literal expression
             let lit = self.parse_lit();
@@ -3335,9 +3442,20 @@ impl<'a> Parser<'a> {
                 token::RBRACE => {
                     // fall through and out.
                 }
+                token::EOF if self.recovering() => {
+                    self.recoverable_err("expected `}` but found end of file");
+                    break;
+                }
                 _ => {
                     let stmt = self.parse_stmt(attributes_box);
                     attributes_box = Vec::new();
+                    if self.stuck_at_error() {
+                        // the statement ended at a syntax error; skip past
+                        // the rest of it and keep what we could make out.
+                        stmts.push(stmt);
+                        self.recover_to_sync_point();
+                        continue;
+                    }
                     match stmt.node {
                         StmtExpr(e, stmt_id) => {
                             // expression without semicolon
@@ -4140,14 +4258,26 @@ impl<'a> Parser<'a> {
                                                true /* macros allowed */) {
               IoviItem(item) => items.push(item),
               IoviViewItem(view_item) => {
-                self.span_fatal(view_item.span,
-                                "view items must be declared at the top of \
-                                 the module");
+                if !self.recovering() {
+                    self.span_fatal(view_item.span,
+                                    "view items must be declared at the top \
+                                     of the module");
+                }
+                self.span_err(view_item.span,
+                              "view items must be declared at the top of \
+                               the module");
               }
               _ => {
                   let token_str = self.this_token_to_str();
-                  self.fatal(format!("expected item but found `{}`",
-                                     token_str))
+                  self.recoverable_err(format!("expected item but found `{}`",
+                                               token_str));
+                  if self.token == token::EOF {
+                      break;
+                  }
+                  // always make progress, even if we are stopped at
+                  // something that looks like a synchronisation point.
+                  self.bump();
+                  self.recover_to_sync_point();
               }
             }
         }
@@ -1526,6 +1526,7 @@ impl<'a> State<'a> {
                 try!(self.print_expr(e));
                 try!(self.pclose());
             }
+            ast::ExprError => try!(word(&mut self.s, "/* error */")),
         }
         try!(self.ann.post(self, NodeExpr(expr)));
         self.end()
@@ -10,6 +10,11 @@
 
 /* Foreign builtins. */
 
+#if defined(__linux__) || defined(__ANDROID__)
+// for struct ucred
+#define _GNU_SOURCE
+#endif
+
 #include "valgrind/valgrind.h"
 
 #include <stdint.h>
@@ -21,10 +26,16 @@
 #if !defined(__WIN32__)
 #include <sys/time.h>
 #include <sys/types.h>
+#include <sys/socket.h>
+#include <sys/uio.h>
+#include <errno.h>
 #include <dirent.h>
 #include <signal.h>
 #include <unistd.h>
 #include <pthread.h>
+#include <fcntl.h>
+#include <termios.h>
+#include <sys/ioctl.h>
 #else
 #include <windows.h>
 #include <wincrypt.h>
@@ -127,6 +138,17 @@ rust_list_dir_wfd_fp_buf(void* wfd) {
 }
 #endif
 
+uint32_t
+#if defined(__WIN32__)
+rust_list_dir_wfd_attributes(WIN32_FIND_DATAW* wfd) {
+    return wfd->dwFileAttributes;
+}
+#else
+rust_list_dir_wfd_attributes(void* wfd) {
+    return 0;
+}
+#endif
+
 typedef struct
 {
     size_t fill;    // in bytes; if zero, heapified
@@ -289,6 +311,22 @@ rust_dirent_t_size() {
     return sizeof(struct dirent);
 }
 
+// One of the DT_* constants, or 0 (DT_UNKNOWN) where dirent has no d_type.
+int
+rust_dirent_type(struct dirent* entry_ptr) {
+#if defined(_DIRENT_HAVE_D_TYPE) || defined(__APPLE__) || \
+    defined(__FreeBSD__) || defined(__ANDROID__)
+    return entry_ptr->d_type;
+#else
+    return 0;
+#endif
+}
+
+uint64_t
+rust_dirent_ino(struct dirent* entry_ptr) {
+    return entry_ptr->d_ino;
+}
+
 #else
 
 void
@@ -367,6 +405,137 @@ rust_valgrind_stack_deregister(unsigned int id) {
   VALGRIND_STACK_DEREGISTER(id);
 }
 
+#ifndef _WIN32
+
+// The layout of control messages is only available through the CMSG_* macros,
+// so passing descriptors over unix sockets is done here rather than in rust.
+
+ssize_t
+rust_send_fds(int sock, const void *buf, size_t len,
+              const int *fds, size_t nfds, int flags) {
+    struct msghdr msg;
+    struct iovec iov;
+    struct cmsghdr *cmsg;
+    char *control = NULL;
+    ssize_t ret;
+
+    memset(&msg, 0, sizeof(msg));
+    iov.iov_base = (void*) buf;
+    iov.iov_len = len;
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+
+    if (nfds > 0) {
+        size_t space = CMSG_SPACE(nfds * sizeof(int));
+        control = calloc(1, space);
+        if (control == NULL) {
+            errno = ENOMEM;
+            return -1;
+        }
+        msg.msg_control = control;
+        msg.msg_controllen = space;
+        cmsg = CMSG_FIRSTHDR(&msg);
+        cmsg->cmsg_level = SOL_SOCKET;
+        cmsg->cmsg_type = SCM_RIGHTS;
+        cmsg->cmsg_len = CMSG_LEN(nfds * sizeof(int));
+        memcpy(CMSG_DATA(cmsg), fds, nfds * sizeof(int));
+    }
+
+    ret = sendmsg(sock, &msg, flags);
+    free(control);
+    return ret;
+}
+
+// Receives up to `*nfds` descriptors into `fds`, setting `*nfds` to the number
+// which were received. Any descriptors beyond that are closed.
+ssize_t
+rust_recv_fds(int sock, void *buf, size_t len,
+              int *fds, size_t *nfds, int flags) {
+    struct msghdr msg;
+    struct iovec iov;
+    struct cmsghdr *cmsg;
+    char *control;
+    size_t space = CMSG_SPACE(*nfds * sizeof(int));
+    size_t n = 0;
+    ssize_t ret;
+
+    control = calloc(1, space);
+    if (control == NULL) {
+        errno = ENOMEM;
+        return -1;
+    }
+    memset(&msg, 0, sizeof(msg));
+    iov.iov_base = buf;
+    iov.iov_len = len;
+    msg.msg_iov = &iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control;
+    msg.msg_controllen = space;
+
+#ifdef MSG_CMSG_CLOEXEC
+    flags |= MSG_CMSG_CLOEXEC;
+#endif
+    ret = recvmsg(sock, &msg, flags);
+    if (ret >= 0) {
+        for (cmsg = CMSG_FIRSTHDR(&msg); cmsg != NULL;
+             cmsg = CMSG_NXTHDR(&msg, cmsg)) {
+            size_t i, count;
+            int *data;
+            if (cmsg->cmsg_level != SOL_SOCKET ||
+                cmsg->cmsg_type != SCM_RIGHTS) {
+                continue;
+            }
+            count = (cmsg->cmsg_len - CMSG_LEN(0)) / sizeof(int);
+            data = (int*) CMSG_DATA(cmsg);
+            for (i = 0; i < count; i++) {
+                if (n < *nfds) {
+                    fds[n++] = data[i];
+                } else {
+                    close(data[i]);
+                }
+            }
+        }
+    }
+    *nfds = n;
+    free(control);
+    return ret;
+}
+
+// The pid is set to -1 on platforms which don't report it.
+int
+rust_peer_cred(int sock, pid_t *pid, uid_t *uid, gid_t *gid) {
+#if defined(__linux__) || defined(__ANDROID__)
+    struct ucred cred;
+    socklen_t len = sizeof(cred);
+    if (getsockopt(sock, SOL_SOCKET, SO_PEERCRED, &cred, &len) != 0) {
+        return -1;
+    }
+    *pid = cred.pid;
+    *uid = cred.uid;
+    *gid = cred.gid;
+    return 0;
+#else
+    *pid = -1;
+    return getpeereid(sock, uid, gid);
+#endif
+}
+
+#else
+
+void
+rust_send_fds() {
+}
+
+void
+rust_recv_fds() {
+}
+
+void
+rust_peer_cred() {
+}
+
+#endif
+
 #if defined(__WIN32__)
 
 void
@@ -387,6 +556,89 @@ rust_unset_sigprocmask() {
 
 #endif
 
+#if defined(__WIN32__)
+
+void
+rust_openpty() {
+}
+
+void
+rust_set_controlling_tty() {
+}
+
+void
+rust_set_cloexec() {
+}
+
+#else
+
+int
+rust_set_cloexec(int fd) {
+    int flags = fcntl(fd, F_GETFD);
+    if (flags == -1) {
+        return -1;
+    }
+    return fcntl(fd, F_SETFD, flags | FD_CLOEXEC);
+}
+
+// Opens the slave side of the pseudo-terminal whose master is `m`. Where it's
+// available ptsname_r is used, as ptsname returns a static buffer which other
+// threads may be overwriting at the same time.
+static int
+open_pty_slave(int m) {
+#if defined(__linux__) || defined(__ANDROID__)
+    char name[128];
+    int err = ptsname_r(m, name, sizeof(name));
+    if (err != 0) {
+        errno = err;
+        return -1;
+    }
+#else
+    char *name = ptsname(m);
+    if (name == NULL) {
+        return -1;
+    }
+#endif
+    return open(name, O_RDWR | O_NOCTTY);
+}
+
+// Allocates a pseudo-terminal pair, both ends close-on-exec. Returns 0, or -1
+// with errno set.
+int
+rust_openpty(int *master, int *slave) {
+    int m, s;
+
+    m = posix_openpt(O_RDWR | O_NOCTTY);
+    if (m == -1) {
+        return -1;
+    }
+    if (grantpt(m) == -1 || unlockpt(m) == -1 ||
+        (s = open_pty_slave(m)) == -1) {
+        int err = errno;
+        close(m);
+        errno = err;
+        return -1;
+    }
+    if (rust_set_cloexec(m) == -1 || rust_set_cloexec(s) == -1) {
+        int err = errno;
+        close(m);
+        close(s);
+        errno = err;
+        return -1;
+    }
+    *master = m;
+    *slave = s;
+    return 0;
+}
+
+// Called in a freshly forked child which has just become a session leader.
+int
+rust_set_controlling_tty(int fd) {
+    return ioctl(fd, TIOCSCTTY, 0);
+}
+
+#endif
+
 //
 // Local Variables:
 // mode: C++
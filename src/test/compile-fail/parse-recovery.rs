@@ -0,0 +1,29 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z parse-recovery
+
+// Check that every syntax error in the crate is reported, and that name
+// resolution still runs over whatever could be parsed.
+
+fn missing_semi() {
+    let x = 1
+    let y = 2; //~ ERROR expected `;` but found `let`
+}
+
+fn bad_expr() {
+    let z = ); //~ ERROR expected expression, found `)`
+}
+
+42 //~ ERROR expected item but found `42`
+
+fn main() {
+    foo(); //~ ERROR unresolved name `foo`.
+}
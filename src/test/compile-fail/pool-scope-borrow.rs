@@ -0,0 +1,29 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Jobs spawned in a scope may not borrow from the closure passed to
+// `Pool::scope`, which returns before the jobs are waited for.
+
+extern crate sync;
+
+use sync::pool::Pool;
+
+fn main() {
+    let pool = Pool::new(2);
+    let outer = vec!(1, 2, 3);
+    pool.scope(|s| {
+        let inner = vec!(4, 5, 6);
+        let a = &outer;
+        let b = &inner; //~ ERROR `inner` does not live long enough
+        s.spawn(proc(_) {
+            println!("{} {}", a, b);
+        });
+    });
+}
@@ -0,0 +1,36 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![crate_type = "lib"]
+
+// The first example is a correct compile_fail example, the others have to make
+// the tests fail: one compiles, one only triggers a lint and one fails with
+// another error than the expected one.
+
+/// ```compile_fail
+/// let x = 5;
+/// x = 6; //~ ERROR re-assignment of immutable variable
+/// ```
+pub fn immutable() {}
+
+/// ```compile_fail
+/// let x = 5;
+/// ```
+pub fn compiles() {}
+
+/// ```compile_fail
+/// use std::mem;
+/// ```
+pub fn lint_only() {}
+
+/// ```compile_fail
+/// let x: int = "not an int"; //~ ERROR re-assignment of immutable variable
+/// ```
+pub fn wrong_error() {}
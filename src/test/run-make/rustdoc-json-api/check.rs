@@ -0,0 +1,107 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Checks the "api" part of rustdoc's JSON output for foo.rs.
+
+extern crate serialize;
+
+use std::io::File;
+use std::os;
+use serialize::json;
+use serialize::json::Json;
+
+fn key(s: &str) -> StrBuf { s.to_strbuf() }
+
+fn get<'a>(j: &'a Json, k: &str) -> &'a Json {
+    match j.find(&key(k)) {
+        Some(v) => v,
+        None => fail!("no field `{}` in {}", k, j.to_str()),
+    }
+}
+
+fn string<'a>(j: &'a Json, k: &str) -> &'a str {
+    get(j, k).as_string().expect(format!("`{}` isn't a string", k))
+}
+
+fn item<'a>(index: &'a json::Object, name: &str, kind: &str) -> &'a Json {
+    let found = index.values().find(|i| {
+        i.find(&key("name")).and_then(|n| n.as_string()) == Some(name) &&
+        i.find(&key("kind")).and_then(|n| n.as_string()) == Some(kind)
+    });
+    found.expect(format!("no {} named `{}`", kind, name))
+}
+
+fn path(paths: &Json, item: &Json) -> Vec<StrBuf> {
+    let p = get(paths, string(item, "id"));
+    get(p, "path").as_list().unwrap().iter().map(|s| {
+        s.as_string().unwrap().to_strbuf()
+    }).collect()
+}
+
+fn main() {
+    let args = os::args();
+    let mut file = File::open(&Path::new(args.get(1).as_slice())).unwrap();
+    let json = json::from_reader(&mut file).unwrap();
+    let api = get(&json, "api");
+
+    assert_eq!(get(api, "format_version").as_number(), Some(1.0));
+    assert_eq!(string(api, "name"), "foo");
+    let index = get(api, "index").as_object().unwrap();
+    let paths = get(api, "paths");
+
+    let root = get(get(api, "index"), string(api, "root"));
+    assert_eq!(string(root, "kind"), "module");
+    assert_eq!(string(root, "docs").trim(), "Shapes and points");
+
+    // Each item has the right docs and path
+    for &(name, kind, docs, ref fqp) in [
+        ("bar", "module", None, Some(vec!("foo", "bar"))),
+        ("Point", "struct", Some("A point"), Some(vec!("foo", "bar", "Point"))),
+        ("x", "struct_field", Some("The x coordinate"), None),
+        ("Shape", "trait", Some("Something with an area"),
+         Some(vec!("foo", "bar", "Shape"))),
+        ("area", "ty_method", Some("The area of the shape"), None),
+        ("area", "method", None, None),
+        ("add_one", "function", Some("Adds one to `x`"),
+         Some(vec!("foo", "bar", "add_one"))),
+    ].iter() {
+        let i = item(index, name, kind);
+        assert_eq!(get(i, "docs").as_string().map(|d| d.trim()), docs);
+        match *fqp {
+            Some(ref fqp) => {
+                let expected: Vec<StrBuf> = fqp.iter().map(|s| s.to_strbuf())
+                                               .collect();
+                assert_eq!(path(paths, i), expected);
+            }
+            None => {}
+        }
+    }
+
+    // Signatures
+    assert_eq!(string(item(index, "add_one", "function"), "visibility"), "public");
+    let decl = get(get(item(index, "add_one", "function"), "inner"), "decl");
+    let inputs = get(decl, "inputs").as_list().unwrap();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(string(inputs.get(0), "name"), "x");
+    assert_eq!(string(get(inputs.get(0), "type"), "kind"), "primitive");
+    assert_eq!(string(get(inputs.get(0), "type"), "name"), "int");
+    assert_eq!(string(get(decl, "output"), "name"), "int");
+
+    // The impl of Shape for Point is listed both ways
+    let shape = string(item(index, "Shape", "trait"), "id");
+    let point = string(item(index, "Point", "struct"), "id");
+    let implementors = get(get(api, "implementors"), shape).as_list().unwrap();
+    let impls = get(get(api, "impls"), point).as_list().unwrap();
+    assert_eq!(implementors.len(), 1);
+    assert_eq!(implementors, impls);
+    let imp = get(get(api, "index"), implementors.get(0).as_string().unwrap());
+    assert_eq!(string(imp, "kind"), "impl");
+    assert_eq!(string(get(get(imp, "inner"), "for"), "id"), point);
+}
@@ -0,0 +1,34 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![crate_id = "foo#0.1"]
+
+//! Shapes and points
+
+pub mod bar {
+    /// A point
+    pub struct Point {
+        /// The x coordinate
+        pub x: int,
+    }
+
+    /// Something with an area
+    pub trait Shape {
+        /// The area of the shape
+        fn area(&self) -> f64;
+    }
+
+    impl Shape for Point {
+        fn area(&self) -> f64 { 0.0 }
+    }
+
+    /// Adds one to `x`
+    pub fn add_one(x: int) -> int { x + 1 }
+}
@@ -0,0 +1,36 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![crate_id = "foo#0.1"]
+#![crate_type = "lib"]
+
+/// ```
+/// struct Two;
+/// assert_eq!(1 + 1, 2);
+/// ```
+pub fn add() {}
+
+/// ```
+/// assert_eq!(foo::double(2), 4);
+/// ```
+pub fn double(x: int) -> int { x * 2 }
+
+/// ```should_fail
+/// struct Two;
+/// fail!("this example fails");
+/// ```
+pub fn fails() {}
+
+/// ```
+/// fn main() {
+///     assert!(true);
+/// }
+/// ```
+pub fn with_main() {}
@@ -0,0 +1,37 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::task;
+use std::task::{Failure, TaskBuilder};
+use std::sync::atomics::{AtomicUint, INIT_ATOMIC_UINT, SeqCst};
+
+static mut HOOKED: AtomicUint = INIT_ATOMIC_UINT;
+
+fn hook(failure: &Failure) {
+    assert_eq!(failure.message(), Some("hook me"));
+    unsafe { HOOKED.fetch_add(1, SeqCst); }
+}
+
+pub fn main() {
+    assert!(task::set_failure_hook(Some(hook)).is_none());
+    assert!(task::try(proc() { fail!("hook me") }).is_err());
+    assert_eq!(unsafe { HOOKED.load(SeqCst) }, 1);
+
+    // A task's own hook takes precedence over the global one.
+    let (tx, rx) = channel();
+    let mut builder = TaskBuilder::new().on_failure(proc(_) tx.send(()));
+    let result = builder.future_result();
+    builder.spawn(proc() { fail!("hook me") });
+    assert!(result.recv().is_err());
+    rx.recv();
+    assert_eq!(unsafe { HOOKED.load(SeqCst) }, 1);
+
+    assert!(task::set_failure_hook(None).is_some());
+}